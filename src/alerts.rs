@@ -0,0 +1,232 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    str::FromStr,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use thiserror::Error;
+
+use crate::output::json_string;
+
+/// What has to happen to an LED for an alert rule to fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertCondition {
+    TurnsOn,
+    TurnsOff,
+    Changes,
+}
+
+/// What Glimpse does when an alert rule fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertAction {
+    /// Ring the terminal bell
+    Bell,
+    /// Show a popup that has to be dismissed
+    Modal,
+    /// Run a shell command
+    Hook(String),
+    /// POST a JSON description of the change to a URL
+    Webhook(String),
+}
+
+/// A rule such as "if `enclosure:fault` turns on, ring the bell".
+///
+/// Rules are written as `<led>[@on|off|change]=<action>`, e.g. `enclosure:fault@on=modal`,
+/// where the action is `bell`, `modal`, `hook:<command>` or `webhook:<url>`. The condition
+/// defaults to `on`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlertRule {
+    /// File name of the LED the rule watches
+    pub led: String,
+    pub condition: AlertCondition,
+    pub action: AlertAction,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseAlertError {
+    #[error("expected <led>[@condition]=<action>")]
+    MissingAction,
+    #[error("LED name is empty")]
+    EmptyLed,
+    #[error("unknown condition \"{0}\" (expected on, off or change)")]
+    UnknownCondition(String),
+    #[error("unknown action \"{0}\" (expected bell, modal, hook:<command> or webhook:<url>)")]
+    UnknownAction(String),
+}
+
+impl FromStr for AlertRule {
+    type Err = ParseAlertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, action) = s.split_once('=').ok_or(ParseAlertError::MissingAction)?;
+        let (led, condition) = match target.rsplit_once('@') {
+            Some((led, condition)) => (led, condition.parse()?),
+            None => (target, AlertCondition::TurnsOn),
+        };
+        if led.is_empty() {
+            return Err(ParseAlertError::EmptyLed);
+        }
+        Ok(Self {
+            led: led.to_string(),
+            condition,
+            action: action.parse()?,
+        })
+    }
+}
+
+impl FromStr for AlertCondition {
+    type Err = ParseAlertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on" => Ok(Self::TurnsOn),
+            "off" => Ok(Self::TurnsOff),
+            "change" => Ok(Self::Changes),
+            _ => Err(ParseAlertError::UnknownCondition(s.to_string())),
+        }
+    }
+}
+
+impl FromStr for AlertAction {
+    type Err = ParseAlertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bell" => Ok(Self::Bell),
+            "modal" => Ok(Self::Modal),
+            _ => {
+                if let Some(command) = s.strip_prefix("hook:").filter(|c| !c.is_empty()) {
+                    Ok(Self::Hook(command.to_string()))
+                } else if let Some(url) = s
+                    .strip_prefix("webhook:")
+                    .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+                {
+                    Ok(Self::Webhook(url.to_string()))
+                } else {
+                    Err(ParseAlertError::UnknownAction(s.to_string()))
+                }
+            }
+        }
+    }
+}
+
+impl AlertRule {
+    /// Checks whether a change of the LED's on/off state should fire this rule.
+    pub fn matches(&self, file_name: &str, was_on: bool, is_on: bool) -> bool {
+        if self.led != file_name || was_on == is_on {
+            return false;
+        }
+        match self.condition {
+            AlertCondition::TurnsOn => is_on,
+            AlertCondition::TurnsOff => !is_on,
+            AlertCondition::Changes => true,
+        }
+    }
+}
+
+/// Runs a hook command in the background, passing the LED state in environment variables.
+pub fn run_hook(command: &str, file_name: &str, is_on: bool) -> std::io::Result<()> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("GLIMPSE_LED", file_name)
+        .env("GLIMPSE_STATE", if is_on { "on" } else { "off" })
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reap the child once it exits so it doesn't linger as a zombie
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Sends a JSON description of the LED change to a webhook in the background.
+///
+/// The request is made with `curl`, so that Glimpse doesn't need its own HTTP and TLS stack.
+pub fn send_webhook(url: &str, file_name: &str, was_on: bool, is_on: bool) -> std::io::Result<()> {
+    let state = |on: bool| if on { "on" } else { "off" };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let payload = format!(
+        "{{\"led\":{},\"from\":\"{}\",\"to\":\"{}\",\"timestamp\":{}}}",
+        json_string(file_name),
+        state(was_on),
+        state(is_on),
+        timestamp
+    );
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--max-time", "10"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(payload.as_bytes()),
+        None => Ok(()),
+    };
+    // Reaped even if the payload couldn't be written, e.g. because curl exited early
+    thread::spawn(move || child.wait());
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!(
+            "enclosure:fault=bell".parse(),
+            Ok(AlertRule {
+                led: "enclosure:fault".to_string(),
+                condition: AlertCondition::TurnsOn,
+                action: AlertAction::Bell,
+            })
+        );
+        let rule: AlertRule = "input3::capslock@change=hook:notify-send caps"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.condition, AlertCondition::Changes);
+        assert_eq!(
+            rule.action,
+            AlertAction::Hook("notify-send caps".to_string())
+        );
+        let rule: AlertRule = "disk@off=webhook:https://example.com/a=b".parse().unwrap();
+        assert_eq!(
+            rule.action,
+            AlertAction::Webhook("https://example.com/a=b".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let parse = |rule: &str| rule.parse::<AlertRule>().unwrap_err();
+        assert_eq!(parse("disk"), ParseAlertError::MissingAction);
+        assert_eq!(parse("@on=bell"), ParseAlertError::EmptyLed);
+        assert_eq!(
+            parse("disk@blink=bell"),
+            ParseAlertError::UnknownCondition("blink".to_string())
+        );
+        for action in ["beep", "hook:", "webhook:ftp://example.com"] {
+            assert_eq!(
+                parse(&format!("disk={}", action)),
+                ParseAlertError::UnknownAction(action.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn matches_changes() {
+        let rule: AlertRule = "disk@off=modal".parse().unwrap();
+        assert!(rule.matches("disk", true, false));
+        assert!(!rule.matches("disk", false, true));
+        assert!(!rule.matches("disk", false, false));
+        assert!(!rule.matches("other", true, false));
+    }
+}
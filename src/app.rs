@@ -0,0 +1,3828 @@
+//! The TUI: its state, how it reacts to keys and ticks, and how it's drawn.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    LED, NewLEDError, Triggers,
+    alerts::{self, AlertAction, AlertRule},
+    attributes::{self, Attributes},
+    battery::{self, BatteryGauge},
+    brightnessctl::Amount,
+    calibration::Calibration,
+    chart::ChartView,
+    cli::Args,
+    clipboard::{self, CopyMethod},
+    compare,
+    confirm::Confirmation,
+    countdown::{self, Countdown},
+    daemon::{self, DaemonStatus},
+    external::ExternalTool,
+    filter::{Filter, FilterMode},
+    helper::{self, HelperRequest},
+    history::{BrightnessHistory, Sample},
+    keymap::{Action, Keymap},
+    led::{
+        FailedLED, LedScan, SYS_CLASS_LEDS, backlight_file_names, format_brightness,
+        led_file_names, scan_leds_and_backlights,
+    },
+    log::{self, Level, Log, LogEntry},
+    mapping::{EventMapping, MappingState, battery_percentage},
+    multicolor::{
+        self, COLOR_TEMPERATURE_STEP, ChannelEditor, MAX_COLOR_TEMPERATURE, MIN_COLOR_TEMPERATURE,
+        Rgb,
+    },
+    mute,
+    pattern::PatternEditor,
+    pomodoro::{self, Pomodoro},
+    presets::{PresetValue, Presets},
+    profiles::{self, Profile},
+    quiet::{self, Presentation},
+    recording::Recorder,
+    remote, resolve,
+    session::{SESSION_FILE, Session},
+    settings::{GroupBy, SETTINGS_FILE, Settings},
+    sysfs, terminal,
+    text_input::TextInput,
+    theme::{self, Role, Theme},
+    trigger_settings::{self, SettingsForm},
+    undo::{Edit, UndoHistory},
+    widgets::{
+        LedDetailState, LedDetailWidget, LedListState, LedListWidget, SidebarRow, centered_rect,
+    },
+};
+use color_eyre::Result;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::{
+    DefaultTerminal, Frame, Terminal,
+    backend::TestBackend,
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    text::{Line, Span},
+    widgets::{
+        Block, Clear, List, ListState, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+        Wrap,
+    },
+};
+
+/// How often the brightness of every LED is sampled and timed features are updated, unless set
+/// with `--tick-rate`.
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+/// The tick interval once nothing has happened for [`IDLE_AFTER`], to save power, unless the
+/// configured interval is even longer.
+const IDLE_TICK_INTERVAL: Duration = Duration::from_secs(1);
+/// How long after the last key press or LED change ticking slows down.
+const IDLE_AFTER: Duration = Duration::from_secs(10);
+/// How far the wall clock may run ahead of the monotonic clock between two ticks before it's
+/// assumed that the system was suspended (the monotonic clock stops during suspend).
+const RESUME_DETECTION_THRESHOLD: Duration = Duration::from_secs(5);
+/// How often `/sys/class/leds` is checked for LEDs that were added or removed.
+const HOTPLUG_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+/// How much the brightness keys change the brightness, in percent of the maximum.
+const BRIGHTNESS_STEP_PERCENT: i32 = 10;
+/// Height of the log at the bottom of the TUI, including its border.
+const LOG_HEIGHT: u16 = 8;
+/// Length of a countdown started from the TUI.
+const TUI_COUNTDOWN: Duration = Duration::from_secs(5 * 60);
+/// Size of `--screenshot` output when it isn't printed to a terminal.
+const SCREENSHOT_SIZE: (u16, u16) = (100, 30);
+/// Heading of the sidebar group of pinned LEDs, listed above the others.
+const PINNED_GROUP: &str = "Pinned";
+/// Heading of the sidebar group of screen backlights, listed below the LEDs.
+const BACKLIGHTS_GROUP: &str = "Backlights";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    #[default]
+    Sidebar,
+    Mainbar,
+}
+
+impl Pane {
+    /// The panes in the order Tab moves through them.
+    const ALL: [Self; 2] = [Self::Sidebar, Self::Mainbar];
+
+    /// The actions hinted at in the status bar while the pane is focused, with short labels.
+    fn hints(self) -> &'static [(Action, &'static str)] {
+        match self {
+            Self::Sidebar => &[
+                (Action::ToggleLed, "toggle"),
+                (Action::BrightnessUp, "brighter"),
+                (Action::BrightnessDown, "dimmer"),
+                (Action::ChooseTrigger, "trigger"),
+                (Action::EditFilter, "filter"),
+                (Action::ToggleSelecting, "mark"),
+                (Action::ShowHelp, "help"),
+                (Action::Quit, "quit"),
+            ],
+            Self::Mainbar => &[
+                (Action::SelectNext, "scroll"),
+                (Action::ToggleRawView, "raw"),
+                (Action::ToggleChart, "chart"),
+                (Action::FocusNextPane, "LEDs"),
+                (Action::ShowHelp, "help"),
+                (Action::Quit, "quit"),
+            ],
+        }
+    }
+
+    /// The pane after this one, or before it, wrapping around.
+    fn cycle(self, forward: bool) -> Self {
+        let index = Self::ALL.iter().position(|pane| *pane == self).unwrap_or(0);
+        let offset = if forward { 1 } else { Self::ALL.len() - 1 };
+        Self::ALL[(index + offset) % Self::ALL.len()]
+    }
+}
+
+/// What the LEDs in the sidebar are grouped by.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    /// The device part of their names, e.g. `input3` for `input3::capslock`
+    #[default]
+    Device,
+    /// The kernel module or driver they belong to
+    Driver,
+}
+
+/// The popup listing the triggers of an LED to choose from.
+#[derive(Debug)]
+struct TriggerPicker {
+    /// File names of the LEDs the trigger is chosen for
+    leds: Vec<String>,
+    triggers: Triggers,
+    list: ListState,
+}
+
+/// The popup listing the saved profiles to apply one of.
+#[derive(Debug)]
+struct ProfilePicker {
+    profiles: Vec<String>,
+    list: ListState,
+}
+
+/// Actions that are only done once they've been confirmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Confirmed {
+    AllOff,
+    /// Setting the trigger of several LEDs at once
+    SetTrigger {
+        leds: Vec<String>,
+        trigger: String,
+    },
+    ApplyProfile(String),
+    /// Saving a profile over one with the same name
+    SaveProfile(String),
+}
+
+/// The sidebar rows, along with the state they were worked out from.
+#[derive(Debug)]
+struct SidebarCache {
+    leds_generation: u64,
+    query: String,
+    mode: FilterMode,
+    grouping: Grouping,
+    collapsed_groups: HashSet<String>,
+    pinned: Vec<String>,
+    hidden_leds: BTreeSet<String>,
+    show_hidden: bool,
+    rows: Rc<[SidebarRow]>,
+}
+
+/// The main application which holds the state and logic of the application.
+#[derive(Debug, Default)]
+pub struct App {
+    /// Is the application running?
+    running: bool,
+    /// Why LEDs can't be written to, if they can't
+    read_only: Option<String>,
+    /// File names of the LEDs that can't be written to, when others can
+    read_only_leds: HashSet<String>,
+    /// Whether writes that aren't permitted are retried as root through pkexec
+    escalate: bool,
+    /// Whether the state changed since the last frame was drawn
+    needs_redraw: bool,
+    /// The title last set on the terminal
+    terminal_title: String,
+    /// The shortest time between two frames, from `--max-fps`
+    min_frame_interval: Option<Duration>,
+    /// The tick interval while active
+    active_tick_interval: Duration,
+    last_draw: Option<Instant>,
+    /// When a key was last pressed or something last changed
+    last_activity: Option<Instant>,
+    leds: Vec<LED>,
+    /// LEDs that are listed but couldn't be read, shown greyed out
+    failed_leds: Vec<FailedLED>,
+    // selected_led: Option<LED>,
+    log: Log,
+    /// The least serious log entries shown
+    log_level: Level,
+    /// The first log entry in view, or `None` to follow the latest entries
+    log_scroll: Option<usize>,
+    /// How many log entries fit in the log, as last drawn
+    log_page: usize,
+    /// Incremented whenever [`App::leds`] is replaced
+    leds_generation: u64,
+    sidebar_cache: RefCell<Option<SidebarCache>>,
+    focused_pane: Pane,
+    /// Where the panes were last drawn, to find what the mouse points at
+    pane_areas: [Rect; 2],
+    /// How far the main pane is scrolled down, in lines
+    main_scroll: usize,
+    led_list_state: LedListState,
+    led_detail_state: LedDetailState,
+    keymap: Keymap,
+    /// How far the help overlay is scrolled down, while it's open
+    help_scroll: Option<u16>,
+    history: BrightnessHistory,
+    last_tick: Option<Instant>,
+    /// Wall-clock time of the last tick, compared with `last_tick` to detect suspend/resume
+    last_tick_wall: Option<SystemTime>,
+    last_hotplug_scan: Option<Instant>,
+    chart: ChartView,
+    alerts: Vec<AlertRule>,
+    /// Messages of the alerts waiting to be dismissed, oldest first
+    alert_popups: VecDeque<String>,
+    /// Whether an alert rang the bell since the last frame
+    bell_pending: bool,
+    mappings: Vec<(EventMapping, MappingState)>,
+    battery_gauge: Option<BatteryGauge>,
+    countdown: Option<Countdown>,
+    pomodoro: Option<Pomodoro>,
+    pomodoro_hook: Option<String>,
+    /// LEDs (by name or function) that do-not-disturb turns off
+    notification_leds: Vec<String>,
+    /// The state of the notification LEDs from before do-not-disturb was enabled
+    dnd: Option<Profile>,
+    /// LEDs (by name or function) that presentation mode turns off
+    presentation_leds: Vec<String>,
+    presentation_duration: Option<Duration>,
+    presentation: Option<Presentation>,
+    /// Records every frame when running with `--record`
+    recorder: Option<Recorder>,
+    /// A program to open on an LED directory once the current event has been handled
+    pending_external: Option<(ExternalTool, PathBuf)>,
+    /// File name of the LED the selected one is compared with
+    compare_with: Option<String>,
+    /// The raw attributes of the LEDs shown in the raw or compare view, read at most once per
+    /// tick
+    raw_attributes: HashMap<String, io::Result<Attributes>>,
+    /// Whether LEDs are muted, checked every tick
+    muted: bool,
+    /// The status of the running daemon, checked every tick
+    daemon: Option<DaemonStatus>,
+    /// Reads the LEDs of other hosts in the background
+    remote_poller: Option<remote::Poller>,
+    filter: Filter,
+    /// Whether the sidebar groups LEDs by their driver
+    grouping: Grouping,
+    /// Names of the sidebar groups whose LEDs are hidden
+    collapsed_groups: HashSet<String>,
+    /// File names of the LEDs listed first in the sidebar, in the order they were pinned
+    pinned: Vec<String>,
+    /// Whether toggling an LED marks it instead
+    selecting: bool,
+    /// File names of the LEDs that toggling, brightness changes and triggers apply to
+    marked_leds: BTreeSet<String>,
+    /// File names of the LEDs hidden in the TUI, on top of those hidden in the settings
+    hidden_leds: BTreeSet<String>,
+    /// Whether hidden LEDs are listed anyway
+    show_hidden: bool,
+    /// Whether the detail pane shows the raw sysfs attributes of the selected LED
+    raw_view: bool,
+    /// Color temperatures set on multicolor LEDs, by file name
+    color_temperatures: HashMap<String, u32>,
+    /// The color being typed in for the selected LED
+    color_input: Option<TextInput>,
+    /// The brightness being typed in for the selected LED
+    brightness_input: Option<TextInput>,
+    trigger_picker: Option<TriggerPicker>,
+    trigger_settings: Option<SettingsForm>,
+    pattern_editor: Option<PatternEditor>,
+    channel_editor: Option<ChannelEditor>,
+    confirmation: Option<Confirmation<Confirmed>>,
+    /// The name being typed for the profile to save
+    profile_name_input: Option<TextInput>,
+    profile_picker: Option<ProfilePicker>,
+    /// Brightness and trigger changes that can be undone
+    undo_history: UndoHistory,
+    /// Minimum usable brightness of calibrated LEDs
+    calibration: Calibration,
+    presets: Presets,
+    settings: Settings,
+}
+
+impl App {
+    /// Construct a new instance of [`App`].
+    pub fn new(args: Args) -> Self {
+        let mut log = Log::default();
+        let settings = match Settings::load() {
+            Ok((settings, warnings)) => {
+                for (line, warning) in warnings {
+                    log.warn(format!("{}:{}: {}", SETTINGS_FILE, line, warning));
+                }
+                settings
+            }
+            Err(e) => {
+                log.error(format!("Error loading settings: {}", e));
+                Settings::default()
+            }
+        };
+        if let Some(depth) = settings.theme {
+            Theme::set_color_depth(depth);
+        }
+        let scan = match scan_all_leds(&settings.hosts, &mut log) {
+            Ok(scan) => {
+                log.info(format!("Successfully found {} LED(s)", scan.leds.len()));
+                scan
+            }
+            Err(e) => {
+                log.error(format!("Error getting LEDs: {}", e));
+                LedScan::default()
+            }
+        };
+        let mut app = Self::with_leds(args, settings, scan, log);
+        match Session::load() {
+            Ok(session) => app.restore_session(&session),
+            Err(e) => app
+                .log
+                .warn(format!("Not restoring the last session: {}", e)),
+        }
+        app
+    }
+
+    /// Constructs the app around LEDs that have already been read, e.g. from a mock backend.
+    fn with_leds(mut args: Args, settings: Settings, scan: LedScan, mut log: Log) -> Self {
+        let LedScan {
+            leds,
+            failed: failed_leds,
+        } = scan;
+        for failed in &failed_leds {
+            log.error(format!(
+                "Error reading LED {}: {}",
+                failed.file_name, failed.error
+            ));
+        }
+        // Resolve the LEDs named on the command line once, so that the rest of the TUI can
+        // compare sysfs names
+        let mut resolve_led =
+            |identifier: &mut String| match resolve::resolve_name(identifier, &leds) {
+                Ok(file_name) => *identifier = file_name,
+                Err(e) => log.error(format!("Error: {}", e)),
+            };
+        args.alerts
+            .iter_mut()
+            .for_each(|rule| resolve_led(&mut rule.led));
+        args.mappings
+            .iter_mut()
+            .for_each(|mapping| resolve_led(&mut mapping.led));
+        args.battery_led.iter_mut().for_each(&mut resolve_led);
+        let calibration = Calibration::load(&settings).unwrap_or_else(|e| {
+            log.error(format!("Error loading brightness calibration: {}", e));
+            Calibration::default()
+        });
+        // Permission is asked for when writing instead
+        let read_only = if args.escalate {
+            log.info("Writes that aren't permitted will be retried through pkexec");
+            None
+        } else {
+            sysfs::check_writable(&leds)
+        };
+        if let Some(reason) = &read_only {
+            log.warn(format!("Read-only mode: {}", reason));
+            if !args.mappings.is_empty() || args.battery_led.is_some() {
+                log.warn("Event mappings and the battery gauge are disabled");
+                args.mappings.clear();
+                args.battery_led = None;
+            }
+        }
+        let read_only_leds: HashSet<String> = if read_only.is_some() || args.escalate {
+            HashSet::new()
+        } else {
+            leds.iter()
+                .filter(|led| !sysfs::is_writable(led))
+                .map(|led| led.file_name.clone())
+                .collect()
+        };
+        if !read_only_leds.is_empty() {
+            log.warn(format!(
+                "{} LED(s) are read-only: {}",
+                read_only_leds.len(),
+                sysfs::PERMISSION_HINT
+            ));
+        }
+        let remote_poller = remote::Poller::for_leds(&leds);
+        let mut keymap = Keymap::default();
+        for (action, keys) in &settings.keys {
+            keymap.rebind(*action, keys);
+        }
+        Self {
+            read_only,
+            read_only_leds,
+            escalate: args.escalate,
+            running: false,
+            needs_redraw: true,
+            terminal_title: String::new(),
+            min_frame_interval: args.max_fps.map(|fps| Duration::from_secs(1) / fps),
+            active_tick_interval: args
+                .tick_interval
+                .or(settings.refresh_interval)
+                .unwrap_or(TICK_INTERVAL),
+            last_draw: None,
+            last_activity: None,
+            focused_pane: Pane::default(),
+            pane_areas: [Rect::default(); 2],
+            main_scroll: 0,
+            leds,
+            failed_leds,
+            log,
+            log_level: Level::Info,
+            log_scroll: None,
+            log_page: 0,
+            leds_generation: 0,
+            sidebar_cache: RefCell::new(None),
+            led_list_state: LedListState::default(),
+            led_detail_state: LedDetailState::default(),
+            keymap,
+            help_scroll: None,
+            history: BrightnessHistory::default(),
+            last_tick: None,
+            last_tick_wall: None,
+            last_hotplug_scan: None,
+            chart: ChartView::default(),
+            alerts: args.alerts,
+            alert_popups: VecDeque::new(),
+            bell_pending: false,
+            mappings: args
+                .mappings
+                .into_iter()
+                .map(|mapping| (mapping, MappingState::default()))
+                .collect(),
+            battery_gauge: args.battery_led.map(BatteryGauge::new),
+            countdown: None,
+            pomodoro: None,
+            pomodoro_hook: args.pomodoro_hook,
+            notification_leds: quiet::led_set(args.dnd_leds, &quiet::DEFAULT_NOTIFICATION_LEDS),
+            dnd: None,
+            presentation_leds: quiet::led_set(
+                args.presentation_leds,
+                &quiet::DEFAULT_PRESENTATION_LEDS,
+            ),
+            presentation_duration: args.presentation_duration,
+            presentation: None,
+            recorder: None,
+            pending_external: None,
+            compare_with: None,
+            raw_attributes: HashMap::new(),
+            muted: false,
+            daemon: None,
+            remote_poller,
+            filter: Filter::default(),
+            grouping: match settings.group_by {
+                GroupBy::Device => Grouping::Device,
+                GroupBy::Driver => Grouping::Driver,
+            },
+            collapsed_groups: HashSet::new(),
+            pinned: Vec::new(),
+            selecting: false,
+            marked_leds: BTreeSet::new(),
+            hidden_leds: BTreeSet::new(),
+            show_hidden: false,
+            raw_view: false,
+            color_temperatures: HashMap::new(),
+            color_input: None,
+            brightness_input: None,
+            trigger_picker: None,
+            trigger_settings: None,
+            pattern_editor: None,
+            channel_editor: None,
+            confirmation: None,
+            profile_name_input: None,
+            profile_picker: None,
+            undo_history: UndoHistory::default(),
+            calibration,
+            // Presets given on the command line take precedence over the configured ones
+            presets: Presets::new(
+                args.presets
+                    .into_iter()
+                    .chain(settings.presets.iter().cloned())
+                    .collect(),
+            ),
+            settings,
+        }
+    }
+
+    /// Run the application's main loop.
+    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<Log> {
+        self.running = true;
+        if let Err(e) = self.log.persist() {
+            self.log
+                .warn(format!("Not saving the log to {}: {}", log::LOG_FILE, e));
+        }
+        self.last_activity = Some(Instant::now());
+        // The title is cosmetic, so failing to set it isn't worth reporting
+        let _ = terminal::push_title();
+        while self.running {
+            if self
+                .last_tick
+                .is_none_or(|last| last.elapsed() >= self.tick_interval())
+            {
+                self.tick();
+            }
+            if self.needs_redraw && self.frame_due_in().is_zero() {
+                self.needs_redraw = false;
+                self.last_draw = Some(Instant::now());
+                let frame = terminal.draw(|frame| self.render(frame))?;
+                if let Some(recorder) = &mut self.recorder
+                    && let Err(e) = recorder.record(frame.buffer)
+                {
+                    self.log.error(format!("Error recording session: {}", e));
+                    self.recorder = None;
+                }
+                self.update_terminal_title();
+            }
+            if std::mem::take(&mut self.bell_pending) {
+                // Through the backend, so that it doesn't end up in the middle of a frame
+                let backend = terminal.backend_mut();
+                let _ = backend
+                    .write_all(b"\x07")
+                    .and_then(|_| io::Write::flush(backend));
+            }
+            self.handle_crossterm_events()?;
+            if let Some((tool, directory)) = self.pending_external.take() {
+                terminal = self.open_external(terminal, tool, &directory)?;
+                self.needs_redraw = true;
+                // Whatever ran may have changed the title
+                self.terminal_title.clear();
+            }
+        }
+        let _ = terminal::pop_title();
+        if let Err(e) = self.session().save() {
+            self.log.warn(format!(
+                "Error saving the session to {}: {}",
+                SESSION_FILE, e
+            ));
+        }
+        self.log.info("Exiting Glimpse");
+        Ok(self.log)
+    }
+
+    /// Sets the terminal title to the selected LED and active modes, if they changed.
+    fn update_terminal_title(&mut self) {
+        let mut title = "glimpse".to_string();
+        if let Some(led) = self.selected_led() {
+            title.push_str(&format!(
+                ": {} ({})",
+                self.settings.alias(&led.file_name).unwrap_or(&led.name),
+                if led.is_on { "on" } else { "off" }
+            ));
+        }
+        if self.muted {
+            title.push_str(" [muted]");
+        }
+        if self.dnd.is_some() {
+            title.push_str(" [do not disturb]");
+        }
+        if self.presentation.is_some() {
+            title.push_str(" [presentation]");
+        }
+        if title != self.terminal_title {
+            let _ = terminal::set_title(&title);
+            self.terminal_title = title;
+        }
+    }
+
+    /// Opens an LED directory in an external program.
+    ///
+    /// Programs that run in the terminal get it to themselves until they exit, after which the
+    /// TUI is set up again.
+    fn open_external(
+        &mut self,
+        terminal: DefaultTerminal,
+        tool: ExternalTool,
+        directory: &Path,
+    ) -> Result<DefaultTerminal> {
+        let mut command = tool.command(directory);
+        if !tool.runs_in_terminal() {
+            match command.spawn() {
+                Ok(mut child) => {
+                    std::thread::spawn(move || child.wait());
+                    self.log
+                        .info(format!("Opened {} in file manager", directory.display()));
+                }
+                Err(e) => self.log.error(format!("Error opening file manager: {}", e)),
+            }
+            return Ok(terminal);
+        }
+        drop(terminal);
+        let _ = terminal::set_mouse_capture(false);
+        ratatui::restore();
+        let status = command.status();
+        let mut terminal = ratatui::init();
+        terminal::set_mouse_capture(true)?;
+        terminal.clear()?;
+        match status {
+            Ok(status) if !status.success() => self
+                .log
+                .info(format!("External program exited with {}", status)),
+            Ok(_) => {}
+            Err(e) => self
+                .log
+                .error(format!("Error running external program: {}", e)),
+        }
+        Ok(terminal)
+    }
+
+    /// Renders the user interface.
+    ///
+    /// This is where you add new widgets. See the following resources for more information:
+    ///
+    /// - <https://docs.rs/ratatui/latest/ratatui/widgets/index.html>
+    /// - <https://github.com/ratatui/ratatui/tree/main/ratatui-widgets/examples>
+    fn render(&mut self, frame: &mut Frame) {
+        let mut area = frame.area();
+        // Read-only banner
+        if let Some(reason) = &self.read_only {
+            let [banner, rest] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+            frame.render_widget(
+                Line::styled(
+                    format!(" Read-only: {} ", reason),
+                    theme::style(Role::Warning).patch(theme::style(Role::Heading)),
+                )
+                .centered(),
+                banner,
+            );
+            area = rest;
+        }
+        let [area, log_area, status_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(LOG_HEIGHT),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(20), Constraint::Min(20)])
+            .split(area);
+        // Left panel
+        let mut left_panel_title = Line::styled("LEDs", theme::style(Role::Title)).centered();
+        let hidden = self.leds.iter().filter(|led| self.is_hidden(led)).count();
+        if hidden > 0 && !self.show_hidden {
+            left_panel_title.push_span(format!(" ({} hidden)", hidden));
+        }
+        let mut left_panel_block = self.pane_block(Pane::Sidebar).title(left_panel_title);
+        if self.filter.editing || self.filter.is_active() {
+            let mode = match self.filter.mode {
+                FilterMode::Fuzzy => "fuzzy",
+                FilterMode::Regex => "regex",
+            };
+            let mut filter_line = Line::from(format!("/{} [{}]", self.filter.query, mode));
+            if self.filter.error().is_some() {
+                filter_line = filter_line.patch_style(theme::style(Role::Error));
+            }
+            left_panel_block = left_panel_block.title_bottom(filter_line);
+        }
+        let rows = self.sidebar_rows();
+        let mut led_list = LedListWidget::new(&self.leds)
+            .rows(&rows[..])
+            .short_names(self.grouping == Grouping::Device)
+            .aliases(|led| self.settings.alias(&led.file_name))
+            .marked(|led| self.chart.is_plotted(&led.file_name))
+            .read_only(|led| self.read_only_leds.contains(&led.file_name))
+            .hidden(|led| {
+                self.show_hidden
+                    && (self.settings.is_hidden(&led.file_name)
+                        || self.hidden_leds.contains(&led.file_name))
+            })
+            .highlight(|name| self.filter.match_positions(name))
+            .failed(if self.filter.is_active() {
+                &[]
+            } else {
+                &self.failed_leds
+            })
+            .block(left_panel_block);
+        if self.selecting || !self.marked_leds.is_empty() {
+            led_list = led_list.checkboxes(|led| self.marked_leds.contains(&led.file_name));
+        }
+        frame.render_stateful_widget(led_list, layout[0], &mut self.led_list_state);
+        self.pane_areas = [layout[0], layout[1]];
+        // Right panel
+        self.read_shown_attributes();
+        let selected = self.selected_index().map(|index| &self.leds[index]);
+        let compared = self
+            .compare_with
+            .as_ref()
+            .and_then(|file_name| self.leds.iter().find(|led| &led.file_name == file_name))
+            .zip(selected);
+        let main_block = self.pane_block(Pane::Mainbar);
+        if self.chart.visible {
+            self.chart
+                .render(frame, layout[1], main_block, &self.history, &self.leds);
+        } else if let Some((left, right)) = compared {
+            compare::render(
+                frame,
+                layout[1],
+                main_block,
+                left,
+                right,
+                (
+                    &self.raw_attributes[&left.file_name],
+                    &self.raw_attributes[&right.file_name],
+                ),
+                &mut self.main_scroll,
+            );
+        } else if let Some(led) = selected.filter(|_| self.raw_view) {
+            attributes::render(
+                frame,
+                layout[1],
+                main_block,
+                led,
+                &self.raw_attributes[&led.file_name],
+                &mut self.main_scroll,
+            );
+        } else {
+            let mut title = Line::styled("LED detail", theme::style(Role::Title)).centered();
+            if let Some(countdown) = &self.countdown {
+                title.push_span(format!(
+                    " (countdown {})",
+                    countdown::format_remaining(countdown.remaining())
+                ));
+            }
+            if self.dnd.is_some() {
+                title.push_span(" (do not disturb)");
+            }
+            if self.presentation.is_some() {
+                title.push_span(" (presentation)");
+            }
+            if self.muted {
+                title.push_span(" (muted)");
+            }
+            if let Some(daemon) = &self.daemon {
+                match &daemon.profile {
+                    Some(profile) => title.push_span(format!(" (daemon: {})", profile)),
+                    None => title.push_span(" (daemon)"),
+                }
+            }
+            if let Some(pomodoro) = &self.pomodoro {
+                title.push_span(format!(
+                    " ({} {}{})",
+                    pomodoro.phase,
+                    countdown::format_remaining(pomodoro.remaining()),
+                    if pomodoro.is_paused() { ", paused" } else { "" }
+                ));
+            }
+            self.led_detail_state.refresh(
+                selected,
+                selected.and_then(|led| self.settings.alias(&led.file_name)),
+            );
+            self.main_scroll = self
+                .main_scroll
+                .min(self.led_detail_state.fields.len().saturating_sub(1));
+            frame.render_stateful_widget(
+                LedDetailWidget::default()
+                    .scroll(self.main_scroll)
+                    .block(main_block.title(title)),
+                layout[1],
+                &mut self.led_detail_state,
+            );
+        }
+        // Log, scrolled to the latest entries unless scrolled up
+        let entries: Vec<&LogEntry> = self.log.at_least(self.log_level).collect();
+        self.log_page = usize::from(log_area.height.saturating_sub(2));
+        let last_top = entries.len().saturating_sub(self.log_page);
+        let top = self.log_scroll.map_or(last_top, |top| top.min(last_top));
+        let lines: Vec<Line> = entries[top..]
+            .iter()
+            .take(self.log_page)
+            .map(|entry| {
+                let style = match entry.level {
+                    Level::Info => theme::style(Role::Text),
+                    Level::Warning => theme::style(Role::Warning),
+                    Level::Error => theme::style(Role::Error),
+                };
+                Line::from(vec![
+                    Span::styled(format!("{} ", entry.time()), theme::style(Role::Dim)),
+                    Span::styled(entry.message.as_str(), style),
+                ])
+            })
+            .collect();
+        let mut log_title = Line::styled("Log", theme::style(Role::Title));
+        match self.log_level {
+            Level::Info => {}
+            Level::Warning => log_title.push_span(" (warnings and errors)"),
+            Level::Error => log_title.push_span(" (errors)"),
+        }
+        let mut log_block = Block::bordered().title(log_title.centered());
+        if top < last_top {
+            log_block = log_block.title_bottom(
+                Line::styled(
+                    format!(" {} newer, PgDn to follow ", last_top - top),
+                    theme::style(Role::Dim),
+                )
+                .right_aligned(),
+            );
+        }
+        frame.render_widget(Paragraph::new(lines).block(log_block), log_area);
+        if last_top > 0 {
+            frame.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                log_area.inner(Margin::new(0, 1)),
+                &mut ScrollbarState::new(last_top + 1).position(top),
+            );
+        }
+        self.render_status_bar(frame, status_area);
+        // Color prompt
+        if let Some(input) = &self.color_input {
+            input.render(frame, frame.area(), "Color (name or #rrggbb)");
+        }
+        // Brightness prompt
+        if let Some(input) = &self.brightness_input
+            && let Some(led) = self.selected_led()
+        {
+            let title = format!(
+                "Brightness of {} (0-{} or %)",
+                led.file_name, led.max_brightness
+            );
+            input.render(frame, frame.area(), &title);
+        }
+        // Trigger popup
+        if let Some(picker) = &mut self.trigger_picker {
+            let frame_area = frame.area();
+            let height = (picker.triggers.available.len() as u16 + 2).min(frame_area.height);
+            let area = centered_rect(frame_area, 40, height);
+            let leds = match picker.leds.as_slice() {
+                [led] => led.clone(),
+                leds => format!("{} LEDs", leds.len()),
+            };
+            let title =
+                Line::styled(format!("Trigger of {}", leds), theme::style(Role::Title)).centered();
+            let items = picker.triggers.available.iter().map(|trigger| {
+                if picker.triggers.active.as_ref() == Some(trigger) {
+                    Line::styled(format!("{} (active)", trigger), theme::style(Role::Heading))
+                } else {
+                    Line::from(trigger.as_str())
+                }
+            });
+            frame.render_widget(Clear, area);
+            frame.render_stateful_widget(
+                List::new(items)
+                    .style(theme::style(Role::Text))
+                    .highlight_style(theme::style(Role::Selection))
+                    .block(Block::bordered().title(title)),
+                area,
+                &mut picker.list,
+            );
+        }
+        // Profile name prompt
+        if let Some(input) = &self.profile_name_input {
+            input.render(frame, frame.area(), "Save profile as");
+        }
+        // Profile popup
+        if let Some(picker) = &mut self.profile_picker {
+            let frame_area = frame.area();
+            let height = (picker.profiles.len() as u16 + 2).min(frame_area.height);
+            let area = centered_rect(frame_area, 40, height);
+            let title = Line::styled("Apply profile", theme::style(Role::Title)).centered();
+            let items = picker.profiles.iter().map(|name| Line::from(name.as_str()));
+            frame.render_widget(Clear, area);
+            frame.render_stateful_widget(
+                List::new(items)
+                    .style(theme::style(Role::Text))
+                    .highlight_style(theme::style(Role::Selection))
+                    .block(Block::bordered().title(title)),
+                area,
+                &mut picker.list,
+            );
+        }
+        // Trigger settings form
+        if let Some(form) = &self.trigger_settings {
+            form.render(frame, frame.area());
+        }
+        // Pattern editor
+        if let Some(editor) = &self.pattern_editor {
+            editor.render(frame, frame.area());
+        }
+        // Channel editor
+        if let Some(editor) = &self.channel_editor {
+            editor.render(frame, frame.area());
+        }
+        // Confirmation popup
+        if let Some(confirmation) = &self.confirmation {
+            confirmation.render(frame, frame.area());
+        }
+        // Continuations of a pending key sequence
+        if self.keymap.is_pending() {
+            let continuations = self.keymap.continuations();
+            let lines: Vec<Line> = continuations
+                .iter()
+                .map(|(keys, description)| {
+                    Line::from(vec![
+                        Span::styled(format!("{:>6}  ", keys), theme::style(Role::Heading)),
+                        (*description).into(),
+                    ])
+                })
+                .collect();
+            let frame_area = frame.area();
+            let height = (lines.len() as u16 + 2).min(frame_area.height);
+            let width = 32.min(frame_area.width);
+            let area = Rect::new(
+                frame_area.right() - width,
+                frame_area.bottom() - height,
+                width,
+                height,
+            );
+            let title = Line::styled(self.keymap.pending_label(), theme::style(Role::Title));
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(lines).block(Block::bordered().title(title)),
+                area,
+            );
+        }
+        // Help overlay
+        if let Some(scroll) = self.help_scroll {
+            let help = self.keymap.help();
+            let width = help.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+            let lines: Vec<Line> = help
+                .iter()
+                .map(|(keys, description)| {
+                    Line::from(vec![
+                        Span::styled(format!("{:>width$}  ", keys), theme::style(Role::Heading)),
+                        (*description).into(),
+                    ])
+                })
+                .collect();
+            let area = centered_rect(frame.area(), 60, lines.len() as u16 + 2);
+            let title = Line::styled("Keys", theme::style(Role::Title)).centered();
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(lines).scroll((scroll, 0)).block(
+                    Block::bordered()
+                        .title(title)
+                        .title_bottom(Line::styled(" Esc close ", theme::style(Role::Dim))),
+                ),
+                area,
+            );
+        }
+        // Alert popup
+        if let Some(message) = self.alert_popups.front() {
+            let area = centered_rect(frame.area(), 50, 7);
+            let title = match self.alert_popups.len() {
+                1 => "Alert".to_string(),
+                queued => format!("Alert (1 of {})", queued),
+            };
+            let title = Line::styled(
+                title,
+                theme::style(Role::Error).patch(theme::style(Role::Heading)),
+            )
+            .centered();
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(vec![
+                    Line::from(message.as_str()),
+                    Line::from(""),
+                    Line::styled("Press Enter to dismiss", theme::style(Role::Dim)),
+                ])
+                .wrap(Wrap { trim: true })
+                .centered()
+                .block(
+                    Block::bordered()
+                        .border_style(theme::style(Role::Error))
+                        .title(title),
+                ),
+                area,
+            );
+        }
+    }
+
+    /// Updates everything that changes over time.
+    ///
+    /// Marks the TUI for redrawing if anything shown changed, or if it shows something that
+    /// changes all the time.
+    fn tick(&mut self) {
+        let log_entries = self.log.len();
+        let was_pending = self.keymap.is_pending();
+        if self.resumed_from_suspend() {
+            self.log
+                .info("Detected resume from suspend, re-reading all LEDs");
+            self.reload_leds();
+        }
+        self.keymap.expire();
+        self.last_tick = Some(Instant::now());
+        self.last_tick_wall = Some(SystemTime::now());
+        let leds_changed = self.scan_for_hotplug();
+        let brightness_changed = self.sample_brightness();
+        // Other programs may change the trigger without changing the brightness. Reading the
+        // trigger of another host's LED could hold up the TUI, so that's only read on selection
+        let trigger_changed = self.selected_index().is_some_and(|index| {
+            !remote::is_remote(&self.leds[index].file_name)
+                && self.led_detail_state.check_trigger(&self.leds[index])
+        });
+        self.apply_mappings();
+        self.update_battery_gauge();
+        let status_changed = self.check_status();
+        // Read again for the next frame, so the raw views follow the driver
+        self.raw_attributes.clear();
+        self.update_countdown();
+        self.update_pomodoro();
+        if self
+            .presentation
+            .as_ref()
+            .and_then(|presentation| presentation.until)
+            .is_some_and(|until| Instant::now() >= until)
+        {
+            self.toggle_presentation();
+        }
+        let changed = leds_changed
+            || brightness_changed
+            || trigger_changed
+            || status_changed
+            || self.log.len() != log_entries
+            || self.keymap.is_pending() != was_pending;
+        if changed {
+            self.last_activity = Some(Instant::now());
+        }
+        self.needs_redraw |= changed || self.shows_live_data();
+    }
+
+    /// How long to wait between ticks: slower once nothing has happened for a while, unless
+    /// something on screen is animated.
+    fn tick_interval(&self) -> Duration {
+        let idle = self
+            .last_activity
+            .is_some_and(|last| last.elapsed() >= IDLE_AFTER);
+        if idle && !self.shows_live_data() {
+            IDLE_TICK_INTERVAL.max(self.active_tick_interval)
+        } else {
+            self.active_tick_interval
+        }
+    }
+
+    /// How long until the frame rate cap allows drawing the next frame.
+    fn frame_due_in(&self) -> Duration {
+        match (self.min_frame_interval, self.last_draw) {
+            (Some(interval), Some(last)) => interval.saturating_sub(last.elapsed()),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Whether what's on screen changes even when nothing happens, e.g. a running countdown.
+    fn shows_live_data(&self) -> bool {
+        self.countdown.is_some()
+            || self.pomodoro.is_some()
+            || (self.chart.visible && !self.chart.is_paused())
+            || self.compare_with.is_some()
+            || self.raw_view
+    }
+
+    /// Checks whether LEDs were muted or a daemon started or stopped, returning whether either
+    /// changed since the last check.
+    fn check_status(&mut self) -> bool {
+        let muted = mute::is_muted();
+        let daemon = daemon::running();
+        let changed = muted != self.muted || daemon != self.daemon;
+        self.muted = muted;
+        self.daemon = daemon;
+        changed
+    }
+
+    /// Reads the raw attributes of the LEDs the raw or compare view shows, unless they were
+    /// read since the last tick.
+    fn read_shown_attributes(&mut self) {
+        let selected = self.selected_led().map(|led| led.file_name.clone());
+        let shown: Vec<String> = match (&self.compare_with, selected) {
+            (Some(compared), Some(selected)) => vec![compared.clone(), selected],
+            (None, Some(selected)) if self.raw_view => vec![selected],
+            _ => Vec::new(),
+        };
+        for file_name in shown {
+            if self.raw_attributes.contains_key(&file_name) {
+                continue;
+            }
+            let Some(led) = self.leds.iter().find(|led| led.file_name == file_name) else {
+                continue;
+            };
+            let attributes = if remote::is_remote(&file_name) {
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the raw attributes of LEDs of other hosts aren't read",
+                ))
+            } else {
+                attributes::read_attributes(&led.path())
+            };
+            self.raw_attributes.insert(file_name, attributes);
+        }
+    }
+
+    /// Checks whether the system was suspended since the last tick.
+    fn resumed_from_suspend(&self) -> bool {
+        let (Some(last_tick), Some(last_tick_wall)) = (self.last_tick, self.last_tick_wall) else {
+            return false;
+        };
+        SystemTime::now()
+            .duration_since(last_tick_wall)
+            .is_ok_and(|wall_elapsed| {
+                wall_elapsed > last_tick.elapsed() + RESUME_DETECTION_THRESHOLD
+            })
+    }
+
+    /// Discovers all LEDs again, keeping the selected LED selected if it still exists.
+    fn reload_leds(&mut self) {
+        let selected = self.selected_led().map(|led| led.file_name.clone());
+        match scan_all_leds(&self.settings.hosts, &mut self.log) {
+            Ok(scan) => {
+                self.remote_poller = remote::Poller::for_leds(&scan.leds);
+                self.leds = scan.leds;
+                self.failed_leds = scan.failed;
+                self.leds_generation += 1;
+            }
+            Err(e) => {
+                self.log.error(format!("Error getting LEDs: {}", e));
+                return;
+            }
+        }
+        self.select_led_named(selected.as_deref());
+    }
+
+    /// Adds LEDs that appeared since the last scan (e.g. of a keyboard that was plugged in) and
+    /// removes ones that went away, keeping the selected LED selected if it's still there.
+    ///
+    /// Returns whether any LEDs were added or removed.
+    fn scan_for_hotplug(&mut self) -> bool {
+        if self
+            .last_hotplug_scan
+            .is_some_and(|last| last.elapsed() < HOTPLUG_SCAN_INTERVAL)
+        {
+            return false;
+        }
+        self.last_hotplug_scan = Some(Instant::now());
+        let Ok(mut file_names) = led_file_names() else {
+            return false;
+        };
+        let backlights = backlight_file_names().unwrap_or_default();
+        file_names.extend(backlights.iter().cloned());
+        let present: HashSet<&str> = file_names.iter().map(String::as_str).collect();
+        // LEDs of other hosts are only rediscovered by reloading
+        let known: HashSet<&str> = self
+            .leds
+            .iter()
+            .map(|led| led.file_name.as_str())
+            .filter(|file_name| !remote::is_remote(file_name))
+            .collect();
+        let added: Vec<String> = file_names
+            .iter()
+            .filter(|file_name| !known.contains(file_name.as_str()))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = known
+            .iter()
+            .filter(|file_name| !present.contains(*file_name))
+            .map(|file_name| file_name.to_string())
+            .collect();
+        let failed_before = self.failed_leds.len();
+        self.failed_leds.retain(|failed| {
+            remote::is_remote(&failed.file_name) || present.contains(failed.file_name.as_str())
+        });
+        if added.is_empty() && removed.is_empty() && self.failed_leds.len() == failed_before {
+            return false;
+        }
+        let selected = self.selected_led().map(|led| led.file_name.clone());
+        let mut changed = !removed.is_empty() || self.failed_leds.len() != failed_before;
+        self.leds.retain(|led| !removed.contains(&led.file_name));
+        for file_name in &removed {
+            self.log.info(format!("LED removed: {}", file_name));
+            self.read_only_leds.remove(file_name);
+        }
+        for file_name in added {
+            // LEDs still being set up by the driver, or that failed to read, are tried again on
+            // the next scan
+            let led = if backlights.contains(&file_name) {
+                LED::backlight(file_name.clone())
+            } else {
+                LED::new(file_name.clone())
+            };
+            match led {
+                Ok(led) => {
+                    self.log.info(format!("LED added: {}", led.file_name));
+                    if self.read_only.is_none() && !self.escalate && !sysfs::is_writable(&led) {
+                        self.read_only_leds.insert(led.file_name.clone());
+                    }
+                    self.failed_leds
+                        .retain(|failed| failed.file_name != led.file_name);
+                    self.leds.push(led);
+                    changed = true;
+                }
+                Err(NewLEDError::NotFound) => {}
+                Err(error) => {
+                    if let Some(failed) = self
+                        .failed_leds
+                        .iter_mut()
+                        .find(|failed| failed.file_name == file_name)
+                    {
+                        failed.error = error;
+                    } else {
+                        self.log
+                            .error(format!("Error reading LED {}: {}", file_name, error));
+                        self.failed_leds.push(FailedLED { file_name, error });
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if changed {
+            self.leds_generation += 1;
+            self.select_led_named(selected.as_deref());
+        }
+        changed
+    }
+
+    /// Reads the brightness of every LED and records it in the history. LEDs of other hosts
+    /// are updated from what the remote poller read since the last tick instead.
+    ///
+    /// Returns whether the brightness of any LED changed.
+    fn sample_brightness(&mut self) -> bool {
+        let timestamp = SystemTime::now();
+        let mut transitions = Vec::new();
+        let mut changed = false;
+        let mut remote_brightness = HashMap::new();
+        for (host, reading) in self
+            .remote_poller
+            .as_ref()
+            .map(remote::Poller::readings)
+            .unwrap_or_default()
+        {
+            match reading {
+                Ok(brightness) => remote_brightness.extend(brightness),
+                Err(e) => self
+                    .log
+                    .error(format!("Error reading LEDs of {}: {}", host, e)),
+            }
+        }
+        for led in &mut self.leds {
+            let was_on = led.is_on;
+            let previous = led.brightness;
+            let brightness = if remote::is_remote(&led.file_name) {
+                let Some(brightness) = remote_brightness.get(&led.file_name) else {
+                    continue;
+                };
+                led.update_brightness(*brightness);
+                Ok(*brightness)
+            } else {
+                led.refresh()
+            };
+            // A failed read just leaves a gap in the history
+            if let Ok(brightness) = brightness {
+                changed |= brightness != previous;
+                if led.is_on != was_on {
+                    transitions.push((led.file_name.clone(), was_on, led.is_on));
+                }
+                self.history.record(
+                    &led.file_name,
+                    Sample {
+                        timestamp,
+                        brightness,
+                    },
+                );
+            }
+        }
+        for (file_name, was_on, is_on) in transitions {
+            self.check_alerts(&file_name, was_on, is_on);
+        }
+        changed
+    }
+
+    /// Moves the pomodoro on when a phase is over and shows its state on the LED.
+    fn update_pomodoro(&mut self) {
+        let Some(pomodoro) = &mut self.pomodoro else {
+            return;
+        };
+        if pomodoro.update() {
+            self.log
+                .info(format!("Pomodoro: {} started", pomodoro.phase));
+            if let Err(e) = pomodoro::notify(pomodoro.phase, self.pomodoro_hook.as_deref()) {
+                self.log
+                    .error(format!("Error sending pomodoro notification: {}", e));
+            }
+        }
+        let on = pomodoro.led_on();
+        if let Some(led) = self
+            .leds
+            .iter_mut()
+            .find(|led| led.file_name == pomodoro.led)
+            && led.is_on != on
+            && let Err(e) = led.set_on(on)
+        {
+            self.log.error(format!(
+                "Error updating pomodoro on {}: {}",
+                led.file_name, e
+            ));
+            self.pomodoro = None;
+        }
+    }
+
+    /// Blinks the countdown LED according to the time left.
+    fn update_countdown(&mut self) {
+        let Some(countdown) = &self.countdown else {
+            return;
+        };
+        let on = countdown.led_on();
+        let finished = countdown.phase() == countdown::Phase::Finished;
+        if let Some(led) = self
+            .leds
+            .iter_mut()
+            .find(|led| led.file_name == countdown.led)
+            && led.is_on != on
+            && let Err(e) = led.set_on(on)
+        {
+            self.log.error(format!(
+                "Error updating countdown on {}: {}",
+                led.file_name, e
+            ));
+            self.countdown = None;
+            return;
+        }
+        if finished {
+            self.log
+                .info(format!("Countdown on {} finished", countdown.led));
+            self.alert_popups
+                .push_back(format!("Countdown on {} finished", countdown.led));
+            self.countdown = None;
+        }
+    }
+
+    /// Writes the battery percentage to the battery gauge LED when it changes.
+    fn update_battery_gauge(&mut self) {
+        let Some(gauge) = &mut self.battery_gauge else {
+            return;
+        };
+        let percentage = match battery_percentage() {
+            Ok(percentage) => percentage,
+            Err(e) => {
+                self.log
+                    .error(format!("Error reading battery level: {}", e));
+                self.battery_gauge = None;
+                return;
+            }
+        };
+        if gauge.last_percentage == Some(percentage) {
+            return;
+        }
+        gauge.last_percentage = Some(percentage);
+        let Some(led) = self.leds.iter_mut().find(|led| led.file_name == gauge.led) else {
+            self.log
+                .warn(format!("Battery gauge LED {} does not exist", gauge.led));
+            self.battery_gauge = None;
+            return;
+        };
+        let result = led
+            .max_brightness()
+            .map_err(|e| e.to_string())
+            .and_then(|max| {
+                battery::write_threshold_color(led, percentage).map_err(|e| e.to_string())?;
+                led.set_brightness(
+                    self.calibration
+                        .brightness_for(&led.file_name, percentage, max),
+                )
+                .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            self.log.error(format!(
+                "Error showing battery level on {}: {}",
+                led.file_name, e
+            ));
+            self.battery_gauge = None;
+        }
+    }
+
+    /// Turns mapped LEDs on or off when the system condition they indicate changes.
+    fn apply_mappings(&mut self) {
+        for (mapping, state) in &mut self.mappings {
+            let holds = match mapping.condition.evaluate() {
+                Ok(holds) => holds,
+                Err(e) => {
+                    if *state != MappingState::Failing {
+                        self.log.error(format!(
+                            "Error checking condition for {}: {}",
+                            mapping.led, e
+                        ));
+                        *state = MappingState::Failing;
+                    }
+                    continue;
+                }
+            };
+            if *state == MappingState::Evaluated(holds) {
+                continue;
+            }
+            *state = MappingState::Evaluated(holds);
+            let Some(led) = self
+                .leds
+                .iter_mut()
+                .find(|led| led.file_name == mapping.led)
+            else {
+                self.log
+                    .warn(format!("Mapped LED {} does not exist", mapping.led));
+                continue;
+            };
+            match led.set_on(holds) {
+                Ok(()) => self.log.info(format!(
+                    "Turned {} {} (mapped condition changed)",
+                    led.file_name,
+                    if holds { "on" } else { "off" }
+                )),
+                Err(e) => self
+                    .log
+                    .error(format!("Error setting {}: {}", led.file_name, e)),
+            }
+        }
+    }
+
+    /// Fires the alert rules that match a change of an LED's state.
+    fn check_alerts(&mut self, file_name: &str, was_on: bool, is_on: bool) {
+        let state = if is_on { "on" } else { "off" };
+        let message = format!("{} turned {}", file_name, state);
+        for rule in &self.alerts {
+            if !rule.matches(file_name, was_on, is_on) {
+                continue;
+            }
+            self.log.warn(format!("Alert: {}", message));
+            match &rule.action {
+                AlertAction::Bell => self.bell_pending = true,
+                AlertAction::Modal => self.alert_popups.push_back(message.clone()),
+                AlertAction::Hook(command) => {
+                    if let Err(e) = alerts::run_hook(command, file_name, is_on) {
+                        self.log.error(format!("Error running alert hook: {}", e));
+                    }
+                }
+                AlertAction::Webhook(url) => {
+                    if let Err(e) = alerts::send_webhook(url, file_name, was_on, is_on) {
+                        self.log
+                            .error(format!("Error sending alert webhook: {}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the crossterm events and updates the state of [`App`].
+    ///
+    /// Waits at most until the next brightness sample is due, so that the history keeps being
+    /// recorded while there is no input.
+    fn handle_crossterm_events(&mut self) -> Result<()> {
+        let mut timeout = self
+            .last_tick
+            .map(|last| self.tick_interval().saturating_sub(last.elapsed()))
+            .unwrap_or_default();
+        if self.needs_redraw {
+            timeout = timeout.min(self.frame_due_in());
+        }
+        if !event::poll(timeout)? {
+            return Ok(());
+        }
+        self.last_activity = Some(Instant::now());
+        match event::read()? {
+            // it's important to check KeyEventKind::Press to avoid handling key release events
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                self.on_key_event(key);
+                self.needs_redraw = true;
+            }
+            Event::Mouse(mouse) => {
+                self.on_mouse_event(mouse);
+                self.needs_redraw = true;
+            }
+            Event::Resize(_, _) => self.needs_redraw = true,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Selects LEDs clicked in the sidebar, focuses the pane that's clicked and scrolls the pane
+    /// under the mouse.
+    fn on_mouse_event(&mut self, mouse: MouseEvent) {
+        if self.has_popup() {
+            return;
+        }
+        let position = (mouse.column, mouse.row).into();
+        let Some(pane) = Pane::ALL
+            .into_iter()
+            .zip(self.pane_areas)
+            .find_map(|(pane, area)| area.contains(position).then_some(pane))
+        else {
+            return;
+        };
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.focused_pane = pane;
+                let list_top = self.pane_areas[0].y + 1;
+                if pane == Pane::Sidebar && mouse.row >= list_top {
+                    let row = self.led_list_state.offset() + usize::from(mouse.row - list_top);
+                    if row < self.sidebar_rows().len() {
+                        self.led_list_state.select(Some(row));
+                        self.main_scroll = 0;
+                    }
+                }
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let down = mouse.kind == MouseEventKind::ScrollDown;
+                match pane {
+                    Pane::Sidebar => self.move_selection(down),
+                    Pane::Mainbar if down => self.main_scroll += 1,
+                    Pane::Mainbar => self.main_scroll = self.main_scroll.saturating_sub(1),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a popup or prompt is open, which takes all input.
+    fn has_popup(&self) -> bool {
+        !self.alert_popups.is_empty()
+            || self.help_scroll.is_some()
+            || self.filter.editing
+            || self.color_input.is_some()
+            || self.brightness_input.is_some()
+            || self.trigger_picker.is_some()
+            || self.trigger_settings.is_some()
+            || self.pattern_editor.is_some()
+            || self.channel_editor.is_some()
+            || self.confirmation.is_some()
+            || self.profile_name_input.is_some()
+            || self.profile_picker.is_some()
+    }
+
+    /// Handles the key events and updates the state of [`App`].
+    fn on_key_event(&mut self, key: KeyEvent) {
+        if !self.alert_popups.is_empty() {
+            if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
+                self.alert_popups.pop_front();
+            }
+            return;
+        }
+        if let Some(scroll) = &mut self.help_scroll {
+            let last = self.keymap.help().len().saturating_sub(1) as u16;
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('?' | 'q') => self.help_scroll = None,
+                KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => *scroll = (*scroll + 1).min(last),
+                _ => {}
+            }
+            return;
+        }
+        if let Some(confirmation) = &mut self.confirmation {
+            if let Some(confirmed) = confirmation.on_key_event(key) {
+                let action = self
+                    .confirmation
+                    .take()
+                    .map(|confirmation| confirmation.action);
+                if confirmed && let Some(action) = action {
+                    self.perform_confirmed(action);
+                }
+            }
+            return;
+        }
+        if self.filter.editing {
+            self.on_filter_key_event(key);
+            return;
+        }
+        if self.color_input.is_some() {
+            self.on_color_key_event(key);
+            return;
+        }
+        if self.brightness_input.is_some() {
+            self.on_brightness_key_event(key);
+            return;
+        }
+        if self.trigger_picker.is_some() {
+            self.on_trigger_key_event(key);
+            return;
+        }
+        if self.trigger_settings.is_some() {
+            self.on_trigger_settings_key_event(key);
+            return;
+        }
+        if self.pattern_editor.is_some() {
+            self.on_pattern_key_event(key);
+            return;
+        }
+        if self.channel_editor.is_some() {
+            self.on_channel_key_event(key);
+            return;
+        }
+        if self.profile_name_input.is_some() {
+            self.on_profile_name_key_event(key);
+            return;
+        }
+        if self.profile_picker.is_some() {
+            self.on_profile_key_event(key);
+            return;
+        }
+        if key.code == KeyCode::Esc && (self.selecting || !self.marked_leds.is_empty()) {
+            self.selecting = false;
+            self.marked_leds.clear();
+            return;
+        }
+        if key.code == KeyCode::Esc && self.filter.is_active() {
+            self.filter.clear();
+            self.led_list_state.select(None);
+            return;
+        }
+        if let Some(action) = self.keymap.press(key) {
+            self.perform(action);
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        if action == Action::ToggleLed && self.selecting {
+            self.toggle_marked();
+            return;
+        }
+        if action.writes_leds() && self.read_only.is_some() {
+            self.log.warn("Changing LEDs is disabled in read-only mode");
+            return;
+        }
+        let targets = self.targets(action);
+        if action.writes_selected_led()
+            && let Some(led) = targets
+                .iter()
+                .map(|index| &self.leds[*index])
+                .find(|led| self.read_only_leds.contains(&led.file_name))
+        {
+            self.log.warn(format!(
+                "{} is read-only: {}",
+                led.file_name,
+                sysfs::PERMISSION_HINT
+            ));
+            return;
+        }
+        match action {
+            Action::Quit => self.quit(),
+            Action::ShowHelp => self.help_scroll = Some(0),
+            Action::SelectPrevious if self.focused_pane == Pane::Sidebar => {
+                self.move_selection(false);
+            }
+            Action::SelectNext if self.focused_pane == Pane::Sidebar => {
+                self.move_selection(true);
+            }
+            Action::SelectPrevious => self.main_scroll = self.main_scroll.saturating_sub(1),
+            Action::SelectNext => self.main_scroll += 1,
+            Action::SelectFirst => {
+                self.led_list_state.select(None);
+                self.move_selection(true);
+            }
+            Action::SelectLast => {
+                let rows = self.sidebar_rows();
+                let last = rows
+                    .iter()
+                    .rposition(|row| matches!(row, SidebarRow::LED(_)));
+                self.led_list_state.select(last);
+                self.main_scroll = 0;
+            }
+            Action::FocusNextPane => self.focused_pane = self.focused_pane.cycle(true),
+            Action::FocusPreviousPane => self.focused_pane = self.focused_pane.cycle(false),
+            Action::EditFilter => self.filter.editing = true,
+            Action::ToggleLed => self.toggle_leds(&targets),
+            Action::BrightnessUp => self.step_brightness(&targets, true),
+            Action::BrightnessDown => self.step_brightness(&targets, false),
+            Action::EnterBrightness => {
+                if self.selected_index().is_some() {
+                    self.brightness_input = Some(TextInput::new());
+                }
+            }
+            Action::ExportHistory => self.export_history(),
+            Action::ToggleChart => self.chart.visible = !self.chart.visible,
+            Action::ToggleChartPaused => {
+                if self.chart.visible {
+                    self.chart.toggle_paused();
+                }
+            }
+            Action::TogglePlotted => {
+                if let Some(led) = self.selected_led() {
+                    let file_name = led.file_name.clone();
+                    self.chart.toggle_plotted(&file_name);
+                }
+            }
+            Action::ToggleBatteryGauge => self.toggle_battery_gauge(),
+            Action::ToggleCountdown => self.toggle_countdown(),
+            Action::StartOrPausePomodoro => self.start_or_pause_pomodoro(),
+            Action::SkipPomodoroPhase => {
+                if let Some(pomodoro) = &mut self.pomodoro {
+                    pomodoro.skip();
+                    self.log
+                        .info(format!("Pomodoro: skipped to {}", pomodoro.phase));
+                }
+            }
+            Action::StopPomodoro => {
+                if let Some(pomodoro) = self.pomodoro.take() {
+                    self.log
+                        .info(format!("Stopped pomodoro on {}", pomodoro.led));
+                }
+            }
+            Action::ToggleDnd => self.toggle_dnd(),
+            Action::ToggleMute => self.toggle_mute(),
+            Action::TogglePresentation => self.toggle_presentation(),
+            Action::SaveProfile => self.profile_name_input = Some(TextInput::new()),
+            Action::ApplyProfile => self.open_profile_picker(),
+            Action::Undo => self.undo(false),
+            Action::Redo => self.undo(true),
+            Action::AllOff => {
+                self.confirmation = Some(Confirmation::new(
+                    format!("Turn off all {} LEDs?", self.leds.len()),
+                    Confirmed::AllOff,
+                ));
+            }
+            Action::CopyLedInfo => self.copy_led_info(),
+            Action::ToggleCompare => {
+                self.toggle_compare();
+                self.main_scroll = 0;
+            }
+            Action::ToggleGroupByDriver => self.toggle_group_by_driver(),
+            Action::ToggleGroupCollapsed => self.toggle_group_collapsed(),
+            Action::TogglePinned => self.toggle_pinned(),
+            Action::ToggleSelecting => self.selecting = !self.selecting,
+            Action::ToggleHidden => self.toggle_hidden(),
+            Action::ToggleShowHidden => self.toggle_show_hidden(),
+            Action::ScrollLogUp => self.scroll_log(true),
+            Action::ScrollLogDown => self.scroll_log(false),
+            Action::CycleLogLevel => {
+                self.log_level = self.log_level.cycle();
+                self.log_scroll = None;
+            }
+            Action::ToggleRawView => {
+                self.raw_view = !self.raw_view;
+                self.main_scroll = 0;
+            }
+            Action::WarmerColor => self.adjust_color_temperature(false),
+            Action::CoolerColor => self.adjust_color_temperature(true),
+            Action::EnterColor => {
+                if self.selected_index().is_some() {
+                    self.color_input = Some(TextInput::new());
+                }
+            }
+            Action::EditIntensities => self.open_channel_editor(),
+            Action::ChooseTrigger => self.open_trigger_picker(&targets),
+            Action::EditTriggerSettings => self.open_trigger_settings(),
+            Action::Shoot => self.shoot_selected(),
+            Action::ApplyPreset(key) => self.apply_preset(key),
+            Action::OpenIn(tool) => self.open_selected_in(tool),
+        }
+    }
+
+    fn perform_confirmed(&mut self, action: Confirmed) {
+        match action {
+            Confirmed::AllOff => self.all_off(),
+            Confirmed::SetTrigger { leds, trigger } => {
+                let edits = leds
+                    .iter()
+                    .flat_map(|led| self.apply_trigger(led, &trigger))
+                    .collect();
+                self.undo_history.record(edits);
+            }
+            Confirmed::ApplyProfile(name) => self.apply_profile(&name),
+            Confirmed::SaveProfile(name) => self.save_profile(&name),
+        }
+    }
+
+    /// The LEDs an action applies to, by index into [`App::leds`]: the marked ones if there
+    /// are any and the action can apply to several, otherwise the selected one.
+    fn targets(&self, action: Action) -> Vec<usize> {
+        if action.acts_on_marked() && !self.marked_leds.is_empty() {
+            return (0..self.leds.len())
+                .filter(|index| self.marked_leds.contains(&self.leds[*index].file_name))
+                .collect();
+        }
+        self.selected_index().into_iter().collect()
+    }
+
+    /// Marks the selected LED, or unmarks it if it's marked.
+    fn toggle_marked(&mut self) {
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        let file_name = led.file_name.clone();
+        if !self.marked_leds.remove(&file_name) {
+            self.marked_leds.insert(file_name);
+        }
+    }
+
+    /// Turns the LEDs off if they're all on, or otherwise fully on.
+    fn toggle_leds(&mut self, targets: &[usize]) {
+        let on = !targets.iter().all(|index| self.leds[*index].is_on);
+        let edits = targets
+            .iter()
+            .flat_map(|index| self.set_led_on(*index, on))
+            .collect();
+        self.undo_history.record(edits);
+    }
+
+    /// Turns an LED on or off, returning the edits to undo if it worked.
+    fn set_led_on(&mut self, index: usize, on: bool) -> Vec<Edit> {
+        let escalate = self.escalate;
+        let led = &mut self.leds[index];
+        let brightness = if on { led.max_brightness } else { 0 };
+        let old = led.refresh().unwrap_or(led.brightness);
+        let edits = Edit::brightness(led, old, brightness);
+        let request = HelperRequest::Brightness {
+            led: led.file_name.clone(),
+            brightness,
+        };
+        match led
+            .set_on(on)
+            .or_else(|e| retry_as_root(escalate, e, &request))
+        {
+            Ok(()) => {
+                led.is_on = on;
+                self.log.info(format!(
+                    "Turned {} {}",
+                    led.file_name,
+                    if on { "on" } else { "off" }
+                ));
+                edits
+            }
+            Err(e) => {
+                let file_name = led.file_name.clone();
+                self.log_write_error(&file_name, "toggling", &e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Raises or lowers the brightness of the LEDs by [`BRIGHTNESS_STEP_PERCENT`], in steps that
+    /// look even with each LED's gamma, keeping each within its range.
+    fn step_brightness(&mut self, targets: &[usize], up: bool) {
+        let edits = targets
+            .iter()
+            .flat_map(|index| self.step_led_brightness(*index, up))
+            .collect();
+        self.undo_history.record(edits);
+    }
+
+    /// Steps the brightness of an LED, returning the edits to undo if it worked.
+    fn step_led_brightness(&mut self, index: usize, up: bool) -> Vec<Edit> {
+        let led = &self.leds[index];
+        let (name, max_brightness) = (led.file_name.clone(), led.max_brightness);
+        let step = if up {
+            BRIGHTNESS_STEP_PERCENT
+        } else {
+            -BRIGHTNESS_STEP_PERCENT
+        };
+        self.set_led_brightness(index, |calibration, current| {
+            calibration.step(&name, current, max_brightness, step)
+        })
+    }
+
+    /// Sets the brightness of an LED to one worked out from its current brightness and raised
+    /// to its calibrated minimum, returning the edits to undo if it worked.
+    fn set_led_brightness(
+        &mut self,
+        index: usize,
+        brightness: impl FnOnce(&Calibration, u32) -> u32,
+    ) -> Vec<Edit> {
+        let led = &mut self.leds[index];
+        // Re-read, as the brightness may have changed since the last tick
+        let current = match led.refresh() {
+            Ok(brightness) => brightness,
+            Err(e) => {
+                self.log
+                    .error(format!("Error reading {}: {}", led.file_name, e));
+                return Vec::new();
+            }
+        };
+        let brightness = self
+            .calibration
+            .clamp(&led.file_name, brightness(&self.calibration, current));
+        let edits = Edit::brightness(led, current, brightness);
+        let request = HelperRequest::Brightness {
+            led: led.file_name.clone(),
+            brightness,
+        };
+        match led
+            .set_brightness(brightness)
+            .or_else(|e| retry_as_root(self.escalate, e, &request))
+        {
+            Ok(()) => {
+                // Rather than at the next tick, so that the gauge in the detail pane follows
+                // each key press
+                led.brightness = brightness;
+                led.is_on = brightness > 0;
+                self.log.info(format!(
+                    "Set {} to {}",
+                    led.file_name,
+                    format_brightness(brightness, led.max_brightness)
+                ));
+                edits
+            }
+            Err(e) => {
+                let file_name = led.file_name.clone();
+                self.log_write_error(&file_name, "setting", &e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Starts showing the battery level on the selected LED, or stops if it already does.
+    fn toggle_battery_gauge(&mut self) {
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        let file_name = led.file_name.clone();
+        if self
+            .battery_gauge
+            .as_ref()
+            .is_some_and(|gauge| gauge.led == file_name)
+        {
+            self.battery_gauge = None;
+            self.log
+                .info(format!("Stopped showing battery level on {}", file_name));
+        } else {
+            self.log
+                .info(format!("Showing battery level on {}", file_name));
+            self.battery_gauge = Some(BatteryGauge::new(file_name));
+            self.update_battery_gauge();
+        }
+    }
+
+    /// Sets the selected LED to the brightness or color preset bound to `key`. Only brightness
+    /// presets can be undone, like every color change.
+    fn apply_preset(&mut self, key: char) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let led = &self.leds[index];
+        let Some(value) = self.presets.lookup(&led.file_name, key) else {
+            return;
+        };
+        match value {
+            PresetValue::Percent(percent) => {
+                let (name, max_brightness) = (led.file_name.clone(), led.max_brightness);
+                let edits = self.set_led_brightness(index, |calibration, _| {
+                    calibration.brightness_for(&name, percent, max_brightness)
+                });
+                self.undo_history.record(edits);
+            }
+            PresetValue::Color(color) => match multicolor::set_color(led, color) {
+                Ok(()) => self.log.info(format!("Set {} to {}", led.file_name, color)),
+                Err(e) => self
+                    .log
+                    .error(format!("Error setting {}: {}", led.file_name, e)),
+            },
+        }
+    }
+
+    /// Sets the selected LED to the color typed into the color prompt. Not recorded for undoing,
+    /// see [`crate::undo`].
+    fn apply_color_input(&mut self, input: &str) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let led = &self.leds[index];
+        let result = input
+            .trim()
+            .parse::<Rgb>()
+            .map_err(|e| e.to_string())
+            .and_then(|color| {
+                multicolor::set_color(led, color)
+                    .map(|()| color)
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(color) => self.log.info(format!("Set {} to {}", led.file_name, color)),
+            Err(e) => self
+                .log
+                .error(format!("Error setting {}: {}", led.file_name, e)),
+        }
+    }
+
+    fn on_color_key_event(&mut self, key: KeyEvent) {
+        let Some(input) = &mut self.color_input else {
+            return;
+        };
+        match input.on_key_event(key) {
+            Some(true) => {
+                if let Some(input) = self.color_input.take() {
+                    self.apply_color_input(input.value());
+                }
+            }
+            Some(false) => self.color_input = None,
+            None => {}
+        }
+    }
+
+    /// Sets the selected LED to the brightness typed into the brightness prompt, or keeps the
+    /// prompt open with the reason if it's not a brightness the LED can be set to.
+    fn apply_brightness_input(&mut self) {
+        let (Some(index), Some(input)) = (self.selected_index(), &mut self.brightness_input) else {
+            return;
+        };
+        let led = &self.leds[index];
+        let brightness = match Amount::parse_exact(input.value(), led.max_brightness) {
+            Ok(Amount::Raw(brightness)) => brightness,
+            Ok(Amount::Percent(percent)) => {
+                self.calibration
+                    .brightness_for(&led.file_name, percent as u8, led.max_brightness)
+            }
+            Err(e) => {
+                input.error = Some(e.to_string());
+                return;
+            }
+        };
+        self.brightness_input = None;
+        let edits = self.set_led_brightness(index, |_, _| brightness);
+        self.undo_history.record(edits);
+    }
+
+    fn on_brightness_key_event(&mut self, key: KeyEvent) {
+        let Some(input) = &mut self.brightness_input else {
+            return;
+        };
+        match input.on_key_event(key) {
+            Some(true) => self.apply_brightness_input(),
+            Some(false) => self.brightness_input = None,
+            None => {}
+        }
+    }
+
+    /// Opens the trigger popup for the LEDs, listing the triggers of the first one with its
+    /// active trigger selected.
+    fn open_trigger_picker(&mut self, targets: &[usize]) {
+        let Some(led) = targets.first().map(|index| &self.leds[*index]) else {
+            return;
+        };
+        let file_name = led.file_name.clone();
+        match led.triggers() {
+            Ok(Some(triggers)) => {
+                let active = triggers
+                    .active
+                    .as_ref()
+                    .and_then(|active| triggers.available.iter().position(|t| t == active));
+                self.trigger_picker = Some(TriggerPicker {
+                    leds: targets
+                        .iter()
+                        .map(|index| self.leds[*index].file_name.clone())
+                        .collect(),
+                    triggers,
+                    list: ListState::default().with_selected(Some(active.unwrap_or(0))),
+                });
+            }
+            Ok(None) => self.log.info(format!("{} has no triggers", file_name)),
+            Err(e) => self
+                .log
+                .error(format!("Error reading triggers of {}: {}", file_name, e)),
+        }
+    }
+
+    /// Writes the trigger chosen in the trigger popup, returning the edit to undo if it worked.
+    fn apply_trigger(&mut self, file_name: &str, trigger: &str) -> Option<Edit> {
+        let led = self
+            .leds
+            .iter_mut()
+            .find(|led| led.file_name == file_name)?;
+        let old = led
+            .triggers()
+            .ok()
+            .flatten()
+            .and_then(|triggers| triggers.active)
+            .unwrap_or_else(|| "none".to_string());
+        let request = HelperRequest::Trigger {
+            led: file_name.to_string(),
+            trigger: trigger.to_string(),
+        };
+        match led
+            .set_trigger(trigger)
+            .or_else(|e| retry_as_root(self.escalate, e, &request))
+        {
+            Ok(()) => {
+                self.log
+                    .info(format!("Set the trigger of {} to {}", file_name, trigger));
+                let _ = led.refresh();
+                // The trigger may change without the brightness changing
+                self.led_detail_state = LedDetailState::default();
+                Some(Edit::Trigger {
+                    led: file_name.to_string(),
+                    old,
+                    new: trigger.to_string(),
+                })
+            }
+            Err(e) => {
+                self.log_write_error(file_name, "setting the trigger of", &e);
+                None
+            }
+        }
+    }
+
+    /// Writes back the values from before the last change, or redoes the last undone change.
+    fn undo(&mut self, redo: bool) {
+        let step = if redo {
+            self.undo_history.redo()
+        } else {
+            self.undo_history.undo()
+        };
+        let Some(step) = step else {
+            self.log.info(if redo {
+                "Nothing to redo"
+            } else {
+                "Nothing to undo"
+            });
+            return;
+        };
+        // Undone in the opposite order, e.g. the brightness before the trigger that turning an
+        // LED off stopped
+        let edits: Vec<&Edit> = if redo {
+            step.iter().collect()
+        } else {
+            step.iter().rev().collect()
+        };
+        let escalate = self.escalate;
+        for edit in edits {
+            let Some(led) = self.leds.iter_mut().find(|led| led.file_name == edit.led()) else {
+                self.log
+                    .error(format!("Error: {} no longer exists", edit.led()));
+                continue;
+            };
+            match edit
+                .apply(led, !redo)
+                .or_else(|e| retry_as_root(escalate, e, &edit.helper_request(!redo)))
+            {
+                Ok(()) if redo => self.log.info(format!("Redid {}", edit)),
+                Ok(()) => self.log.info(format!("Undid {}", edit)),
+                Err(e) => self.log_write_error(edit.led(), "changing", &e),
+            }
+        }
+        self.led_detail_state = LedDetailState::default();
+    }
+
+    /// Logs a failed write to an LED, e.g. "Error toggling input3::capslock: ...". If writing
+    /// was refused, the LED is marked read-only and the log says what to do about it instead.
+    fn log_write_error(&mut self, file_name: &str, action: &str, error: &std::io::Error) {
+        if error.kind() == ErrorKind::PermissionDenied {
+            self.read_only_leds.insert(file_name.to_string());
+            self.log.warn(format!(
+                "Not allowed to change {}: {}",
+                file_name,
+                sysfs::PERMISSION_HINT
+            ));
+        } else {
+            self.log
+                .error(format!("Error {} {}: {}", action, file_name, error));
+        }
+    }
+
+    fn on_trigger_key_event(&mut self, key: KeyEvent) {
+        let Some(picker) = &mut self.trigger_picker else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.trigger_picker = None,
+            KeyCode::Up | KeyCode::Char('k') => picker.list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => picker.list.select_next(),
+            KeyCode::Home => picker.list.select_first(),
+            KeyCode::End => picker.list.select_last(),
+            KeyCode::Enter => {
+                if let Some(picker) = self.trigger_picker.take() {
+                    let chosen = picker
+                        .list
+                        .selected()
+                        .and_then(|index| picker.triggers.available.get(index));
+                    match (chosen, picker.leds.as_slice()) {
+                        (Some(trigger), [led]) => {
+                            let edit = self.apply_trigger(led, trigger);
+                            self.undo_history.record(edit.into_iter().collect());
+                        }
+                        (Some(trigger), leds) => {
+                            self.confirmation = Some(Confirmation::new(
+                                format!("Set the trigger of {} LEDs to {}?", leds.len(), trigger),
+                                Confirmed::SetTrigger {
+                                    leds: picker.leds.clone(),
+                                    trigger: trigger.clone(),
+                                },
+                            ));
+                        }
+                        (None, _) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Opens the settings form of the selected LED's active trigger, if it has settings, or the
+    /// pattern editor for the `pattern` trigger.
+    fn open_trigger_settings(&mut self) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let file_name = self.leds[index].file_name.clone();
+        if self.refuse_remote(&file_name, "edit the trigger settings of") {
+            return;
+        }
+        let led = &self.leds[index];
+        let active = led.triggers().ok().flatten().and_then(|t| t.active);
+        if active.as_deref() == Some("pattern") {
+            match PatternEditor::open(led) {
+                Ok(editor) => self.pattern_editor = Some(editor),
+                Err(e) => self
+                    .log
+                    .error(format!("Error reading the pattern of {}: {}", file_name, e)),
+            }
+            return;
+        }
+        match SettingsForm::open(led) {
+            Ok(Some(form)) => self.trigger_settings = Some(form),
+            Ok(None) => self.log.info(format!(
+                "The trigger of {} has no settings (try the timer, oneshot or pattern trigger)",
+                file_name
+            )),
+            Err(e) => self.log.error(format!(
+                "Error reading trigger settings of {}: {}",
+                file_name, e
+            )),
+        }
+    }
+
+    /// Fires the selected LED once, if it uses the `oneshot` trigger.
+    fn shoot_selected(&mut self) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let file_name = self.leds[index].file_name.clone();
+        if self.refuse_remote(&file_name, "fire") {
+            return;
+        }
+        let led = &self.leds[index];
+        match trigger_settings::shoot(&led.path()) {
+            Ok(()) => self.log.info(format!("Fired {}", file_name)),
+            Err(e) if e.kind() == ErrorKind::NotFound => self.log.info(format!(
+                "{} isn't using the oneshot trigger (change it with t)",
+                file_name
+            )),
+            Err(e) => self.log.error(format!("Error firing {}: {}", file_name, e)),
+        }
+    }
+
+    fn on_pattern_key_event(&mut self, key: KeyEvent) {
+        let Some(editor) = &mut self.pattern_editor else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.pattern_editor = None,
+            KeyCode::Tab => editor.toggle_hardware(),
+            KeyCode::Backspace => {
+                editor.input.pop();
+            }
+            KeyCode::Char(c) => editor.input.push(c),
+            KeyCode::Enter => {
+                let led_path = PathBuf::from(SYS_CLASS_LEDS).join(&editor.led);
+                match editor.write(&led_path) {
+                    Ok(steps) => {
+                        self.log.info(format!(
+                            "Wrote a {}-step {} to {}",
+                            steps.len(),
+                            editor.attribute(),
+                            editor.led
+                        ));
+                        self.pattern_editor = None;
+                    }
+                    // Keep the editor open so that the pattern can be corrected
+                    Err(e) => self.log.error(format!(
+                        "Error writing the pattern of {}: {}",
+                        editor.led, e
+                    )),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Opens the channel editor for the selected LED, if it's a multicolor LED.
+    fn open_channel_editor(&mut self) {
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        match ChannelEditor::open(led) {
+            Ok(editor) => self.channel_editor = Some(editor),
+            Err(e) => self.log.error(format!(
+                "Error editing the channels of {}: {}",
+                led.file_name, e
+            )),
+        }
+    }
+
+    /// Writes the intensities in the channel editor to its LED.
+    fn write_channels(&mut self, intensities: &[u32]) -> std::io::Result<()> {
+        let Some(editor) = &self.channel_editor else {
+            return Ok(());
+        };
+        let Some(led) = self.leds.iter().find(|led| led.file_name == editor.led) else {
+            return Ok(());
+        };
+        led.set_intensities(intensities)?;
+        // The brightness stays the same, so the details wouldn't be read again otherwise
+        self.led_detail_state = LedDetailState::default();
+        Ok(())
+    }
+
+    fn on_channel_key_event(&mut self, key: KeyEvent) {
+        let Some(editor) = &mut self.channel_editor else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => editor.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => editor.select_next(),
+            KeyCode::Left | KeyCode::Right | KeyCode::Char('h' | 'l' | '-' | '+') => {
+                editor.adjust(matches!(
+                    key.code,
+                    KeyCode::Right | KeyCode::Char('l' | '+')
+                ));
+                let intensities = editor.intensities.clone();
+                if let Err(e) = self.write_channels(&intensities)
+                    && let Some(editor) = self.channel_editor.take()
+                {
+                    self.log_write_error(&editor.led, "setting the channels of", &e);
+                }
+            }
+            KeyCode::Enter => {
+                self.log.info(format!(
+                    "Set the channels of {} to {}",
+                    editor.led,
+                    editor
+                        .intensities
+                        .iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ));
+                self.channel_editor = None;
+            }
+            KeyCode::Esc => {
+                let original = editor.original.clone();
+                if let Err(e) = self.write_channels(&original) {
+                    self.log
+                        .error(format!("Error restoring the channels: {}", e));
+                }
+                self.channel_editor = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_trigger_settings_key_event(&mut self, key: KeyEvent) {
+        let Some(form) = &mut self.trigger_settings else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.trigger_settings = None,
+            KeyCode::Tab | KeyCode::Down => form.focus_next(true),
+            KeyCode::BackTab | KeyCode::Up => form.focus_next(false),
+            KeyCode::Backspace => form.pop(),
+            KeyCode::Char(c) => form.push(c),
+            KeyCode::Enter => {
+                let led_path = PathBuf::from(SYS_CLASS_LEDS).join(&form.led);
+                match form.write(&led_path) {
+                    Ok(()) => {
+                        self.log
+                            .info(format!("Saved {} settings of {}", form.trigger, form.led));
+                        self.trigger_settings = None;
+                    }
+                    // Keep the form open so that the value can be corrected
+                    Err(e) => self.log.error(format!(
+                        "Error saving {} settings of {}: {}",
+                        form.trigger, form.led, e
+                    )),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Makes the selected multicolor LED a cooler or warmer white. Not recorded for undoing, see
+    /// [`crate::undo`].
+    fn adjust_color_temperature(&mut self, cooler: bool) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let led = &self.leds[index];
+        let current = self
+            .color_temperatures
+            .get(&led.file_name)
+            .copied()
+            .unwrap_or(MAX_COLOR_TEMPERATURE);
+        let kelvin = if cooler {
+            current + COLOR_TEMPERATURE_STEP
+        } else {
+            current.saturating_sub(COLOR_TEMPERATURE_STEP)
+        }
+        .clamp(MIN_COLOR_TEMPERATURE, MAX_COLOR_TEMPERATURE);
+        match multicolor::set_color(led, multicolor::color_temperature(kelvin)) {
+            Ok(()) => {
+                self.log
+                    .info(format!("Set {} to {}K", led.file_name, kelvin));
+                self.color_temperatures
+                    .insert(led.file_name.clone(), kelvin);
+            }
+            Err(e) => self
+                .log
+                .error(format!("Error setting {}: {}", led.file_name, e)),
+        }
+    }
+
+    /// Compares the other LEDs with the selected one, or stops comparing.
+    fn toggle_compare(&mut self) {
+        if self.compare_with.take().is_some() {
+            return;
+        }
+        if let Some(led) = self.selected_led() {
+            let file_name = led.file_name.clone();
+            self.log.info(format!(
+                "Comparing with {}, select another LED to see the differences",
+                file_name
+            ));
+            self.compare_with = Some(file_name);
+        }
+    }
+
+    /// Queues opening the selected LED's sysfs directory in an external program.
+    fn open_selected_in(&mut self, tool: ExternalTool) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let file_name = self.leds[index].file_name.clone();
+        if !self.refuse_remote(&file_name, "open the directory of") {
+            self.pending_external = Some((tool, self.leds[index].path()));
+        }
+    }
+
+    /// Logs that something that needs an LED's sysfs directory can't be done to an LED of
+    /// another host, returning whether the LED is one.
+    fn refuse_remote(&mut self, file_name: &str, action: &str) -> bool {
+        let remote = remote::is_remote(file_name);
+        if remote {
+            self.log.warn(format!(
+                "Can't {} {}: only the brightness and trigger of LEDs of other hosts can be \
+                 changed",
+                action, file_name
+            ));
+        }
+        remote
+    }
+
+    /// Copies a summary of the selected LED to the clipboard.
+    fn copy_led_info(&mut self) {
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        let mut details = LedDetailState::default();
+        details.refresh(Some(led), self.settings.alias(&led.file_name));
+        let info: String = details
+            .fields
+            .iter()
+            .map(|(label, value)| format!("{}: {}\n", label, value))
+            .collect();
+        let file_name = led.file_name.clone();
+        match clipboard::copy(&info) {
+            Ok(CopyMethod::Osc52) => self
+                .log
+                .info(format!("Copied info about {} (via OSC 52)", file_name)),
+            Ok(CopyMethod::Osc52AndLocal(tool)) => self.log.info(format!(
+                "Copied info about {} (via OSC 52 and {})",
+                file_name, tool
+            )),
+            Err(e) => self.log.error(format!("Error copying LED info: {}", e)),
+        }
+    }
+
+    /// Turns every LED off, or restores them if they are muted.
+    fn toggle_mute(&mut self) {
+        let unmuting = mute::is_muted();
+        let result = if unmuting {
+            mute::unmute(&mut self.leds)
+        } else {
+            mute::mute(&mut self.leds)
+        };
+        match result {
+            Ok((changed, errors)) => {
+                let action = if unmuting {
+                    "Unmuted, restored"
+                } else {
+                    "Muted, turned off"
+                };
+                self.log.info(format!("{} {} LED(s)", action, changed));
+                for error in errors {
+                    self.log.error(format!("Error: {}", error));
+                }
+            }
+            Err(e) => self.log.error(format!("Error: {}", e)),
+        }
+        self.muted = mute::is_muted();
+    }
+
+    /// Turns every LED off, e.g. when one starts blinking in the middle of the night.
+    fn all_off(&mut self) {
+        let edits: Vec<Edit> = self
+            .leds
+            .iter()
+            .flat_map(|led| Edit::brightness(led, led.brightness, 0))
+            .collect();
+        let (turned_off, errors) = quiet::all_off(&mut self.leds);
+        // Only what actually changed can be undone
+        self.undo_history.record(
+            edits
+                .into_iter()
+                .filter(|edit| {
+                    self.leds
+                        .iter()
+                        .any(|led| led.file_name == edit.led() && !led.is_on)
+                        && !matches!(edit, Edit::Brightness { old: 0, .. })
+                })
+                .collect(),
+        );
+        self.log.info(format!("Turned off {} LED(s)", turned_off));
+        for error in errors {
+            self.log.error(format!("Couldn't turn off {}", error));
+        }
+    }
+
+    /// Saves the state of every LED as a profile.
+    fn save_profile(&mut self, name: &str) {
+        let (profile, errors) = Profile::capture(&self.leds);
+        match profile.save(name) {
+            Ok(()) => self.log.info(format!(
+                "Saved {} LED(s) to profile {}",
+                profile.leds.len(),
+                name
+            )),
+            Err(e) => self
+                .log
+                .error(format!("Error saving profile {}: {}", name, e)),
+        }
+        for error in errors {
+            self.log.error(format!("Couldn't save {}", error));
+        }
+    }
+
+    fn on_profile_name_key_event(&mut self, key: KeyEvent) {
+        let Some(input) = &mut self.profile_name_input else {
+            return;
+        };
+        match input.on_key_event(key) {
+            Some(false) => self.profile_name_input = None,
+            Some(true) => {
+                let Some(input) = self.profile_name_input.take() else {
+                    return;
+                };
+                let name = input.value().trim();
+                if name.is_empty() {
+                    return;
+                }
+                if profiles::exists(name) {
+                    self.confirmation = Some(Confirmation::new(
+                        format!("Replace the saved profile {}?", name),
+                        Confirmed::SaveProfile(name.to_string()),
+                    ));
+                } else {
+                    self.save_profile(name);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Opens the popup listing the saved profiles.
+    fn open_profile_picker(&mut self) {
+        match profiles::list() {
+            Ok(profiles) if profiles.is_empty() => self
+                .log
+                .info("No profiles saved yet, save the current state as one with s"),
+            Ok(profiles) => {
+                self.profile_picker = Some(ProfilePicker {
+                    profiles,
+                    list: ListState::default().with_selected(Some(0)),
+                });
+            }
+            Err(e) => self.log.error(format!("Error listing profiles: {}", e)),
+        }
+    }
+
+    fn on_profile_key_event(&mut self, key: KeyEvent) {
+        let Some(picker) = &mut self.profile_picker else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.profile_picker = None,
+            KeyCode::Up | KeyCode::Char('k') => picker.list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => picker.list.select_next(),
+            KeyCode::Home => picker.list.select_first(),
+            KeyCode::End => picker.list.select_last(),
+            KeyCode::Enter => {
+                let chosen = picker
+                    .list
+                    .selected()
+                    .and_then(|index| picker.profiles.get(index))
+                    .cloned();
+                self.profile_picker = None;
+                if let Some(name) = chosen {
+                    self.confirmation = Some(Confirmation::new(
+                        format!("Set the LEDs to profile {}?", name),
+                        Confirmed::ApplyProfile(name),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the LEDs to the state saved in a profile, which can be undone as one step.
+    fn apply_profile(&mut self, name: &str) {
+        let profile = match Profile::load(name) {
+            Ok(profile) => profile,
+            Err(e) => {
+                self.log
+                    .error(format!("Error loading profile {}: {}", name, e));
+                return;
+            }
+        };
+        let (edits, errors) = profile.apply(&mut self.leds);
+        self.log.info(format!(
+            "Applied profile {}, changed {} LED(s)",
+            name,
+            profiles::changed_leds(&edits)
+        ));
+        for error in errors {
+            self.log.error(format!("Couldn't apply {}", error));
+        }
+        self.undo_history.record(edits);
+        self.led_detail_state = LedDetailState::default();
+    }
+
+    /// Turns the notification LEDs off, or restores them if do-not-disturb is already on.
+    fn toggle_dnd(&mut self) {
+        let errors = match self.dnd.take() {
+            Some(saved) => {
+                let errors = quiet::restore(&mut self.leds, &saved);
+                self.log.info(format!(
+                    "Do-not-disturb off, restored {} LED(s)",
+                    saved.leds.len().saturating_sub(errors.len())
+                ));
+                errors
+            }
+            None => {
+                let (saved, errors) = quiet::turn_off(&mut self.leds, &self.notification_leds);
+                self.log.info(format!(
+                    "Do-not-disturb on, turned off {} LED(s)",
+                    saved.leds.len()
+                ));
+                self.dnd = Some(saved);
+                errors
+            }
+        };
+        for error in errors {
+            self.log.error(format!("Error: {}", error));
+        }
+    }
+
+    /// Turns the distracting LEDs off, or restores them if presentation mode is already on.
+    fn toggle_presentation(&mut self) {
+        let errors = match self.presentation.take() {
+            Some(presentation) => {
+                let errors = quiet::restore(&mut self.leds, &presentation.saved);
+                self.log.info(format!(
+                    "Presentation mode off, restored {} LED(s)",
+                    presentation.saved.leds.len().saturating_sub(errors.len())
+                ));
+                errors
+            }
+            None => {
+                let (saved, errors) = quiet::turn_off(&mut self.leds, &self.presentation_leds);
+                let mut message = format!(
+                    "Presentation mode on, turned off {} LED(s)",
+                    saved.leds.len()
+                );
+                if let Some(duration) = self.presentation_duration {
+                    message.push_str(&format!(" for {}", countdown::format_remaining(duration)));
+                }
+                self.log.info(message);
+                self.presentation = Some(Presentation {
+                    saved,
+                    until: self.presentation_duration.map(|d| Instant::now() + d),
+                });
+                errors
+            }
+        };
+        for error in errors {
+            self.log.error(format!("Error: {}", error));
+        }
+    }
+
+    /// Starts a pomodoro on the selected LED, or pauses/resumes the running one.
+    fn start_or_pause_pomodoro(&mut self) {
+        if let Some(pomodoro) = &mut self.pomodoro {
+            pomodoro.toggle_paused();
+            let state = if pomodoro.is_paused() {
+                "Paused"
+            } else {
+                "Resumed"
+            };
+            self.log.info(format!("{} pomodoro", state));
+            return;
+        }
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        let file_name = led.file_name.clone();
+        self.log.info(format!("Started pomodoro on {}", file_name));
+        self.pomodoro = Some(Pomodoro::new(file_name));
+        self.update_pomodoro();
+    }
+
+    /// Starts a countdown on the selected LED, or cancels the running one.
+    fn toggle_countdown(&mut self) {
+        if let Some(countdown) = self.countdown.take() {
+            self.log
+                .info(format!("Cancelled countdown on {}", countdown.led));
+            return;
+        }
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        let file_name = led.file_name.clone();
+        self.log.info(format!(
+            "Started {} countdown on {}",
+            countdown::format_remaining(TUI_COUNTDOWN),
+            file_name
+        ));
+        self.countdown = Some(Countdown::new(file_name, TUI_COUNTDOWN));
+        self.update_countdown();
+    }
+
+    /// Handles key events while the filter query is being typed.
+    fn on_filter_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Enter => self.filter.editing = false,
+            KeyCode::Tab => self.filter.toggle_mode(),
+            KeyCode::Backspace => self.filter.pop(),
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => return,
+        }
+        // Jump to the LED the query names, by its alias or the way commands resolve identifiers
+        // like `capslock`, or else to the first match
+        let query = self.filter.query.as_str();
+        let resolved = self
+            .leds
+            .iter()
+            .find(|led| self.settings.alias(&led.file_name) == Some(query))
+            .map(|led| led.file_name.clone())
+            .or_else(|| resolve::resolve_name(query, &self.leds).ok())
+            .filter(|_| self.filter.mode == FilterMode::Fuzzy);
+        self.select_led_named(resolved.as_deref());
+    }
+
+    /// Whether the LED is hidden from the sidebar, in the settings or in the TUI.
+    fn is_hidden(&self, led: &LED) -> bool {
+        self.settings.is_hidden(&led.file_name) || self.hidden_leds.contains(&led.file_name)
+    }
+
+    /// Hides the selected LED from the sidebar, or shows it again if it was hidden in the TUI.
+    fn toggle_hidden(&mut self) {
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        let file_name = led.file_name.clone();
+        if self.hidden_leds.remove(&file_name) {
+            self.log.info(format!("Showing {} again", file_name));
+        } else if self.settings.is_hidden(&file_name) {
+            self.log
+                .warn(format!("{} is hidden in {}", file_name, SETTINGS_FILE));
+            return;
+        } else {
+            self.log.info(format!("Hid {}", file_name));
+            self.hidden_leds.insert(file_name.clone());
+        }
+        if self.show_hidden {
+            return;
+        }
+        // The row below takes the hidden LED's place
+        let rows = self.sidebar_rows();
+        let position = self
+            .led_list_state
+            .selected()
+            .map(|row| row.min(rows.len().saturating_sub(1)))
+            .filter(|_| !rows.is_empty());
+        self.led_list_state.select(position);
+    }
+
+    /// Pins the selected LED to the top of the sidebar, or unpins it.
+    fn toggle_pinned(&mut self) {
+        let Some(led) = self.selected_led() else {
+            return;
+        };
+        let file_name = led.file_name.clone();
+        if let Some(position) = self.pinned.iter().position(|pinned| *pinned == file_name) {
+            self.pinned.remove(position);
+        } else {
+            self.pinned.push(file_name.clone());
+        }
+        self.select_led_named(Some(&file_name));
+    }
+
+    /// Lists hidden LEDs along with the others, or stops listing them.
+    fn toggle_show_hidden(&mut self) {
+        let selected = self.selected_led().map(|led| led.file_name.clone());
+        self.show_hidden = !self.show_hidden;
+        self.select_led_named(selected.as_deref());
+    }
+
+    /// Whether the LED is shown in the sidebar.
+    fn is_visible(&self, led: &LED) -> bool {
+        (self.show_hidden || !self.is_hidden(led))
+            && (!self.filter.is_active()
+                || self.filter.matches(&led.file_name)
+                || self
+                    .settings
+                    .alias(&led.file_name)
+                    .is_some_and(|alias| self.filter.matches(alias)))
+    }
+
+    /// The entries of the sidebar list, in order.
+    ///
+    /// Only worked out again when the LEDs, the filter, the grouping or the collapsed groups
+    /// changed.
+    fn sidebar_rows(&self) -> Rc<[SidebarRow]> {
+        let mut cache = self.sidebar_cache.borrow_mut();
+        if let Some(cache) = cache.as_ref().filter(|cache| {
+            cache.leds_generation == self.leds_generation
+                && cache.query == self.filter.query
+                && cache.mode == self.filter.mode
+                && cache.grouping == self.grouping
+                && cache.collapsed_groups == self.collapsed_groups
+                && cache.pinned == self.pinned
+                && cache.hidden_leds == self.hidden_leds
+                && cache.show_hidden == self.show_hidden
+        }) {
+            return cache.rows.clone();
+        }
+        let rows: Rc<[SidebarRow]> = self.compute_sidebar_rows().into();
+        *cache = Some(SidebarCache {
+            leds_generation: self.leds_generation,
+            query: self.filter.query.clone(),
+            mode: self.filter.mode,
+            grouping: self.grouping,
+            collapsed_groups: self.collapsed_groups.clone(),
+            pinned: self.pinned.clone(),
+            hidden_leds: self.hidden_leds.clone(),
+            show_hidden: self.show_hidden,
+            rows: rows.clone(),
+        });
+        rows
+    }
+
+    fn compute_sidebar_rows(&self) -> Vec<SidebarRow> {
+        let group_of = |led: &LED| match self.grouping {
+            Grouping::Device => led.device.clone(),
+            Grouping::Driver => led.driver.clone(),
+        };
+        let (pinned, visible): (Vec<usize>, Vec<usize>) = (0..self.leds.len())
+            .filter(|index| self.is_visible(&self.leds[*index]))
+            .partition(|index| self.pinned.contains(&self.leds[*index].file_name));
+        let (backlights, mut visible): (Vec<usize>, Vec<usize>) = visible
+            .into_iter()
+            .partition(|index| self.leds[*index].is_backlight());
+        // LEDs without a device or known driver go last
+        visible.sort_by_key(|index| {
+            let group = group_of(&self.leds[*index]);
+            (group.is_none(), group)
+        });
+        let ungrouped = match self.grouping {
+            Grouping::Device => "(no device)",
+            Grouping::Driver => "(unknown driver)",
+        };
+        let mut rows = Vec::new();
+        if !pinned.is_empty() {
+            let mut pinned = pinned;
+            pinned.sort_by_key(|index| {
+                self.pinned
+                    .iter()
+                    .position(|file_name| *file_name == self.leds[*index].file_name)
+            });
+            let collapsed =
+                self.collapsed_groups.contains(PINNED_GROUP) && !self.filter.is_active();
+            rows.push(SidebarRow::Group {
+                name: PINNED_GROUP.to_string(),
+                leds: pinned.len(),
+                collapsed,
+                pinned: true,
+            });
+            if !collapsed {
+                rows.extend(pinned.into_iter().map(SidebarRow::LED));
+            }
+        }
+        for chunk in visible.chunk_by(|a, b| group_of(&self.leds[*a]) == group_of(&self.leds[*b])) {
+            let name = group_of(&self.leds[chunk[0]]).unwrap_or_else(|| ungrouped.to_string());
+            // Matches are shown even in collapsed groups while filtering
+            let collapsed = self.collapsed_groups.contains(&name) && !self.filter.is_active();
+            rows.push(SidebarRow::Group {
+                name,
+                leds: chunk.len(),
+                collapsed,
+                pinned: false,
+            });
+            if !collapsed {
+                rows.extend(chunk.iter().copied().map(SidebarRow::LED));
+            }
+        }
+        if !backlights.is_empty() {
+            let collapsed =
+                self.collapsed_groups.contains(BACKLIGHTS_GROUP) && !self.filter.is_active();
+            rows.push(SidebarRow::Group {
+                name: BACKLIGHTS_GROUP.to_string(),
+                leds: backlights.len(),
+                collapsed,
+                pinned: false,
+            });
+            if !collapsed {
+                rows.extend(backlights.into_iter().map(SidebarRow::LED));
+            }
+        }
+        rows
+    }
+
+    /// Moves the selection to the next or previous row. Group headings can be selected, to
+    /// collapse or expand them.
+    fn move_selection(&mut self, forward: bool) {
+        let rows = self.sidebar_rows().len();
+        let next = match (self.led_list_state.selected(), forward) {
+            (None, _) => (rows > 0).then_some(0),
+            (Some(selected), true) => (selected + 1 < rows).then_some(selected + 1),
+            (Some(selected), false) => selected.checked_sub(1),
+        };
+        if next.is_some() {
+            self.led_list_state.select(next);
+            self.main_scroll = 0;
+        }
+    }
+
+    /// Renders the selected LED and keys for the focused pane on a single line.
+    fn render_status_bar(&self, frame: &mut Frame, area: Rect) {
+        let led = match self.selected_led() {
+            _ if self.selecting || !self.marked_leds.is_empty() => Line::from(vec![
+                Span::styled(
+                    if self.selecting {
+                        " Marking "
+                    } else {
+                        " Marked "
+                    },
+                    theme::style(Role::Heading),
+                ),
+                Span::raw(format!("{} LED(s), Esc to clear", self.marked_leds.len())),
+            ]),
+            Some(led) => Line::from(vec![
+                Span::styled(format!(" {} ", led.file_name), theme::style(Role::Heading)),
+                Span::raw(format!(
+                    "{} {}",
+                    format_brightness(led.brightness, led.max_brightness),
+                    if led.is_on { "on" } else { "off" }
+                )),
+            ]),
+            None => Line::styled(" No LED selected", theme::style(Role::Dim)),
+        };
+        // As many hints as fit, most relevant first
+        let room = usize::from(area.width).saturating_sub(led.width() + 3);
+        let mut hints = Line::default();
+        for (action, label) in self.focused_pane.hints() {
+            let Some(keys) = self.keymap.keys_for(*action) else {
+                continue;
+            };
+            let separator = if hints.spans.is_empty() { "" } else { "  " };
+            let keys = Span::styled(
+                format!("{}{}: ", separator, keys),
+                theme::style(Role::Heading),
+            );
+            let label = Span::styled(*label, theme::style(Role::Dim));
+            if hints.width() + keys.width() + label.width() > room {
+                break;
+            }
+            hints.push_span(keys);
+            hints.push_span(label);
+        }
+        let [led_area, hints_area] = Layout::horizontal([
+            Constraint::Length(led.width() as u16 + 2),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+        frame.render_widget(led, led_area);
+        frame.render_widget(hints.right_aligned(), hints_area);
+    }
+
+    /// Scrolls the log by a page, following new entries again once scrolled to the bottom.
+    fn scroll_log(&mut self, up: bool) {
+        let shown = self.log.at_least(self.log_level).count();
+        let last_top = shown.saturating_sub(self.log_page);
+        let top = self.log_scroll.unwrap_or(last_top);
+        let page = self.log_page.max(1);
+        let top = if up {
+            top.saturating_sub(page)
+        } else {
+            top + page
+        };
+        self.log_scroll = (top < last_top).then_some(top);
+    }
+
+    /// A bordered block for a pane, highlighted if the pane has the focus.
+    fn pane_block(&self, pane: Pane) -> Block<'static> {
+        let block = Block::bordered();
+        if self.focused_pane == pane {
+            block.border_style(theme::style(Role::Focus))
+        } else {
+            block
+        }
+    }
+
+    /// Selects the LED with the given file name, or the first LED if it isn't in the sidebar.
+    fn select_led_named(&mut self, file_name: Option<&str>) {
+        let rows = self.sidebar_rows();
+        let position = rows
+            .iter()
+            .position(|row| {
+                matches!(row, SidebarRow::LED(index)
+                    if Some(self.leds[*index].file_name.as_str()) == file_name)
+            })
+            .or_else(|| {
+                rows.iter()
+                    .position(|row| matches!(row, SidebarRow::LED(_)))
+            })
+            .or((!rows.is_empty()).then_some(0));
+        self.led_list_state.select(position);
+    }
+
+    /// The selection and layout to restore the next time the TUI starts.
+    fn session(&self) -> Session {
+        let mut collapsed_groups: Vec<String> = self.collapsed_groups.iter().cloned().collect();
+        collapsed_groups.sort();
+        Session {
+            selected: self.selected_led().map(|led| led.file_name.clone()),
+            group_by: Some(match self.grouping {
+                Grouping::Device => GroupBy::Device,
+                Grouping::Driver => GroupBy::Driver,
+            }),
+            collapsed_groups,
+            pinned: self.pinned.clone(),
+            hidden: self.hidden_leds.iter().cloned().collect(),
+            main_focused: self.focused_pane == Pane::Mainbar,
+            raw_view: self.raw_view,
+            chart: self.chart.visible,
+        }
+    }
+
+    /// Puts the selection and layout back the way they were when the TUI was last quit.
+    fn restore_session(&mut self, session: &Session) {
+        if let Some(group_by) = session.group_by {
+            self.grouping = match group_by {
+                GroupBy::Device => Grouping::Device,
+                GroupBy::Driver => Grouping::Driver,
+            };
+        }
+        self.collapsed_groups = session.collapsed_groups.iter().cloned().collect();
+        self.pinned = session.pinned.clone();
+        self.hidden_leds = session.hidden.iter().cloned().collect();
+        if session.main_focused {
+            self.focused_pane = Pane::Mainbar;
+        }
+        self.raw_view = session.raw_view;
+        self.chart.visible = session.chart;
+        if session.selected.is_some() {
+            self.select_led_named(session.selected.as_deref());
+        }
+    }
+
+    /// Groups the sidebar by driver, or goes back to grouping by device.
+    fn toggle_group_by_driver(&mut self) {
+        let selected = self.selected_led().map(|led| led.file_name.clone());
+        self.grouping = match self.grouping {
+            Grouping::Device => Grouping::Driver,
+            Grouping::Driver => Grouping::Device,
+        };
+        self.select_led_named(selected.as_deref());
+    }
+
+    /// Hides the LEDs of the selected group, or of the group of the selected LED, or shows them
+    /// again. The group's heading is selected afterwards.
+    fn toggle_group_collapsed(&mut self) {
+        let rows = self.sidebar_rows();
+        let Some(selected) = self.led_list_state.selected() else {
+            return;
+        };
+        // The group is the closest heading at or above the selection
+        let Some((position, name)) = rows.iter().take(selected + 1).enumerate().rev().find_map(
+            |(position, row)| match row {
+                SidebarRow::Group { name, .. } => Some((position, name.clone())),
+                SidebarRow::LED(_) => None,
+            },
+        ) else {
+            return;
+        };
+        if !self.collapsed_groups.remove(&name) {
+            self.collapsed_groups.insert(name);
+        }
+        self.led_list_state.select(Some(position));
+        self.main_scroll = 0;
+    }
+
+    /// The LED currently highlighted in the sidebar.
+    fn selected_led(&self) -> Option<&LED> {
+        self.selected_index().map(|index| &self.leds[index])
+    }
+
+    /// Index into [`App::leds`] of the LED currently highlighted in the sidebar.
+    fn selected_index(&self) -> Option<usize> {
+        match self.sidebar_rows().get(self.led_list_state.selected()?)? {
+            SidebarRow::LED(index) => Some(*index),
+            SidebarRow::Group { .. } => None,
+        }
+    }
+
+    /// Writes the recorded brightness history to a CSV file in the working directory.
+    fn export_history(&mut self) {
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = PathBuf::from(format!("glimpse-history-{}.csv", unix_time));
+        match self.history.export_csv(&path) {
+            Ok(rows) => self.log.info(format!(
+                "Exported {} brightness sample(s) to {}",
+                rows,
+                path.display()
+            )),
+            Err(e) => self.log.error(format!("Error exporting history: {}", e)),
+        }
+    }
+
+    /// Set running to false to quit the application.
+    fn quit(&mut self) {
+        self.running = false;
+    }
+}
+
+/// Reads the local LEDs and backlights, then those of the hosts in the settings, logging the
+/// hosts that couldn't be reached.
+fn scan_all_leds(hosts: &[String], log: &mut Log) -> Result<LedScan, NewLEDError> {
+    let mut scan = scan_leds_and_backlights()?;
+    let (remote_scan, errors) = remote::scan_hosts(hosts);
+    for error in errors {
+        log.error(error);
+    }
+    scan.leds.extend(remote_scan.leds);
+    scan.failed.extend(remote_scan.failed);
+    Ok(scan)
+}
+
+/// Retries a failed write as root if `--escalate` was given. The helper only writes to this
+/// machine's LEDs, so writes to LEDs of other hosts aren't retried.
+fn retry_as_root(
+    escalate: bool,
+    error: std::io::Error,
+    request: &HelperRequest,
+) -> std::io::Result<()> {
+    if escalate && !remote::is_remote(request.led()) {
+        helper::retry(error, request)
+    } else {
+        Err(error)
+    }
+}
+
+/// Renders a single frame of the app to a buffer, without a terminal.
+fn render_to_buffer(app: &mut App, width: u16, height: u16) -> Result<Buffer> {
+    let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+    terminal.draw(|frame| app.render(frame))?;
+    Ok(terminal.backend().buffer().clone())
+}
+
+/// The text of a buffer, one line per row, without styling or trailing spaces.
+fn buffer_text(buffer: &Buffer) -> String {
+    let width = buffer.area.width as usize;
+    buffer
+        .content
+        .chunks(width.max(1))
+        .map(|row| {
+            let line: String = row.iter().map(|cell| cell.symbol()).collect();
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+/// Prints what the TUI would show right after starting, as plain text for bug reports. The
+/// terminal's size is used if there is one.
+fn print_screenshot(args: Args) -> Result<()> {
+    let (width, height) = crossterm::terminal::size().unwrap_or(SCREENSHOT_SIZE);
+    let mut app = App::new(args);
+    print!(
+        "{}",
+        buffer_text(&render_to_buffer(&mut app, width, height)?)
+    );
+    Ok(())
+}
+
+/// Runs the TUI until it's quit, then prints its log.
+pub fn launch(args: Args) -> Result<()> {
+    if args.screenshot {
+        return print_screenshot(args);
+    }
+    let recorder = args.record.as_deref().map(Recorder::create).transpose()?;
+    let terminal = ratatui::init();
+    terminal::set_mouse_capture(true)?;
+    let mut app = App::new(args);
+    app.recorder = recorder;
+    let result = app.run(terminal);
+    let _ = terminal::set_mouse_capture(false);
+    ratatui::restore();
+    match result {
+        Ok(logs) => {
+            println!("Printing Glimpse log output...");
+            for entry in logs.entries() {
+                println!("{}", entry);
+            }
+            Ok(())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crossterm::event::KeyModifiers;
+    use ratatui::style::Modifier;
+
+    use super::*;
+    use crate::{
+        backend::{MockBackend, MockLed},
+        led::scan_leds_from,
+    };
+
+    fn screenshot(scan: LedScan) -> String {
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap())
+    }
+
+    #[test]
+    fn lists_leds() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led(
+            "tpacpi::kbd_backlight",
+            MockLed {
+                brightness: 1,
+                ..MockLed::new(2)
+            },
+        );
+        let text = screenshot(scan_leds_from(Arc::new(backend)).unwrap());
+        assert_eq!(text.lines().count(), 30);
+        assert!(text.contains("▾ input3 (1)"));
+        assert!(text.contains("  ○ capslock 0/1"));
+        assert!(text.contains("▾ tpacpi (1)"));
+        assert!(text.contains("  ● kbd_backlight 1/2"));
+        let status = text.lines().last().unwrap();
+        assert!(status.contains("No LED selected"));
+        assert!(status.contains("Space: toggle"));
+        assert!(status.contains("q: quit"));
+    }
+
+    #[test]
+    fn help_lists_bindings() {
+        let mut app = App::with_leds(
+            Args::default(),
+            Settings::default(),
+            LedScan::default(),
+            Log::default(),
+        );
+        app.on_key_event(KeyEvent::from(KeyCode::Char('?')));
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 60).unwrap());
+        assert!(text.contains("Esc, q, Ctrl-c  quit"));
+        assert!(text.contains("channel intensities"));
+        app.on_key_event(KeyEvent::from(KeyCode::Esc));
+        assert!(app.help_scroll.is_none());
+    }
+
+    #[test]
+    fn toggles_marked_leds() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("input3::numlock", MockLed::new(1));
+        backend.add_led("input3::scrolllock", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        app.on_key_event(KeyEvent::from(KeyCode::Char('v')));
+        for led in ["input3::capslock", "input3::scrolllock"] {
+            app.select_led_named(Some(led));
+            app.on_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        }
+        app.on_key_event(KeyEvent::from(KeyCode::Char('v')));
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("[x] ○ capslock"));
+        assert!(text.contains("[ ] ○ numlock"));
+        assert!(text.contains("Marked 2 LED(s)"));
+        app.on_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        let on: Vec<_> = app
+            .leds
+            .iter()
+            .filter(|led| led.is_on)
+            .map(|led| led.file_name.as_str())
+            .collect();
+        assert_eq!(on, ["input3::capslock", "input3::scrolllock"]);
+        app.on_key_event(KeyEvent::from(KeyCode::Esc));
+        assert!(app.marked_leds.is_empty());
+    }
+
+    #[test]
+    fn turns_all_leds_off() {
+        let backend = MockBackend::new();
+        let blinking = MockLed {
+            brightness: 1,
+            triggers: Some(Triggers {
+                available: vec!["none".to_string(), "timer".to_string()],
+                active: Some("timer".to_string()),
+            }),
+            ..MockLed::new(1)
+        };
+        backend.add_led("input3::capslock", blinking);
+        backend.add_led("input3::numlock", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend.clone())).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        // Unplugged since it was read
+        backend.remove_led("input3::numlock");
+        app.on_key_event(KeyEvent::from(KeyCode::Char('!')));
+        assert!(backend.led("input3::capslock").unwrap().brightness > 0);
+        app.on_key_event(KeyEvent::from(KeyCode::Char('y')));
+        let capslock = backend.led("input3::capslock").unwrap();
+        assert_eq!(capslock.brightness, 0);
+        assert_eq!(capslock.triggers.unwrap().active.as_deref(), Some("none"));
+        let messages: Vec<_> = app.log.entries().iter().map(|e| &e.message).collect();
+        assert!(messages.contains(&&"Turned off 1 LED(s)".to_string()));
+        assert!(
+            messages
+                .iter()
+                .any(|message| message.starts_with("Couldn't turn off input3::numlock"))
+        );
+    }
+
+    #[test]
+    fn undoes_and_redoes_changes() {
+        let backend = MockBackend::new();
+        let blinking = MockLed {
+            brightness: 1,
+            triggers: Some(Triggers {
+                available: vec!["none".to_string(), "timer".to_string()],
+                active: Some("timer".to_string()),
+            }),
+            ..MockLed::new(1)
+        };
+        backend.add_led("input3::capslock", blinking.clone());
+        let scan = scan_leds_from(Arc::new(backend.clone())).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        app.select_led_named(None);
+        app.on_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        assert_ne!(backend.led("input3::capslock"), Some(blinking.clone()));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('u')));
+        assert_eq!(backend.led("input3::capslock"), Some(blinking.clone()));
+        app.on_key_event(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        let capslock = backend.led("input3::capslock").unwrap();
+        assert_eq!(capslock.brightness, 0);
+        assert_eq!(capslock.triggers.unwrap().active.as_deref(), Some("none"));
+    }
+
+    #[test]
+    fn undoes_presets() {
+        let backend = MockBackend::new();
+        backend.add_led("tpacpi::kbd_backlight", MockLed::new(100));
+        let scan = scan_leds_from(Arc::new(backend.clone())).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        app.select_led_named(None);
+        app.on_key_event(KeyEvent::from(KeyCode::Char('3')));
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 50);
+        assert_eq!(app.selected_led().unwrap().brightness, 50);
+        app.on_key_event(KeyEvent::from(KeyCode::Char('u')));
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 0);
+    }
+
+    #[test]
+    fn search_selects_resolved_led() {
+        let backend = MockBackend::new();
+        for led in ["platform::micmute", "platform::mute", "input3::capslock"] {
+            backend.add_led(led, MockLed::new(1));
+        }
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let mut settings = Settings::default();
+        settings
+            .aliases
+            .insert("input3::capslock".to_string(), "caps".to_string());
+        let mut app = App::with_leds(Args::default(), settings, scan, Log::default());
+        let search = |app: &mut App, query: &str| {
+            app.filter.clear();
+            app.filter.editing = true;
+            for c in query.chars() {
+                app.on_key_event(KeyEvent::from(KeyCode::Char(c)));
+            }
+            app.selected_led().map(|led| led.file_name.clone())
+        };
+        // Both match, but only one is the mute LED
+        assert_eq!(search(&mut app, "mute").as_deref(), Some("platform::mute"));
+        assert_eq!(
+            search(&mut app, "mic").as_deref(),
+            Some("platform::micmute")
+        );
+        assert_eq!(
+            search(&mut app, "caps").as_deref(),
+            Some("input3::capslock")
+        );
+    }
+
+    #[test]
+    fn queues_alerts() {
+        let args = Args {
+            alerts: ["input3::capslock@change=modal", "input3::numlock=bell"]
+                .iter()
+                .map(|rule| rule.parse().unwrap())
+                .collect(),
+            ..Args::default()
+        };
+        let mut app = App::with_leds(
+            args,
+            Settings::default(),
+            LedScan::default(),
+            Log::default(),
+        );
+        app.check_alerts("input3::capslock", false, true);
+        app.check_alerts("input3::capslock", true, false);
+        app.check_alerts("input3::numlock", false, true);
+        assert!(app.bell_pending);
+        let buffer = render_to_buffer(&mut app, 140, 30).unwrap();
+        assert!(buffer_text(&buffer).contains("Alert (1 of 2)"));
+        app.on_key_event(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.alert_popups, ["input3::capslock turned off"]);
+        app.on_key_event(KeyEvent::from(KeyCode::Esc));
+        assert!(!app.has_popup());
+    }
+
+    #[test]
+    fn ctrl_c_quits_during_a_sequence() {
+        let mut app = App::with_leds(
+            Args::default(),
+            Settings::default(),
+            LedScan::default(),
+            Log::default(),
+        );
+        app.running = true;
+        app.on_key_event(KeyEvent::from(KeyCode::Char('g')));
+        assert!(app.keymap.is_pending());
+        app.on_key_event(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert!(!app.running);
+        assert!(!app.keymap.is_pending());
+    }
+
+    #[test]
+    fn sets_typed_brightness() {
+        let backend = MockBackend::new();
+        backend.add_led("tpacpi::kbd_backlight", MockLed::new(200));
+        let scan = scan_leds_from(Arc::new(backend.clone())).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        app.select_led_named(None);
+        let type_in = |app: &mut App, text: &str| {
+            app.on_key_event(KeyEvent::from(KeyCode::Char('B')));
+            for c in text.chars() {
+                app.on_key_event(KeyEvent::from(KeyCode::Char(c)));
+            }
+            app.on_key_event(KeyEvent::from(KeyCode::Enter));
+        };
+        type_in(&mut app, "250");
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("at most 200"));
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 0);
+        app.on_key_event(KeyEvent::from(KeyCode::Esc));
+        type_in(&mut app, "25%");
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 50);
+        type_in(&mut app, "7");
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 7);
+        app.on_key_event(KeyEvent::from(KeyCode::Char('u')));
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 50);
+    }
+
+    #[test]
+    fn shows_brightness_gauge() {
+        let backend = MockBackend::new();
+        backend.add_led("tpacpi::kbd_backlight", MockLed::new(10));
+        let scan = scan_leds_from(Arc::new(backend.clone())).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        app.select_led_named(None);
+        // The filled part of the gauge is drawn in the same line as the rest, just not dimmed
+        let gauge = |app: &mut App| {
+            let buffer = render_to_buffer(app, 140, 30).unwrap();
+            let line = buffer_text(&buffer)
+                .lines()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            let cells: Vec<_> = buffer.content[140..280]
+                .iter()
+                .filter(|cell| cell.symbol() == "─")
+                .collect();
+            let filled = cells
+                .iter()
+                .filter(|cell| !cell.modifier.contains(Modifier::DIM))
+                .count();
+            (filled, cells.len(), line)
+        };
+        let (filled, _, line) = gauge(&mut app);
+        assert!(line.contains("0/10 (0%)"));
+        assert_eq!(filled, 0);
+        for _ in 0..3 {
+            app.on_key_event(KeyEvent::from(KeyCode::Right));
+        }
+        let (filled, width, line) = gauge(&mut app);
+        assert!(line.contains("3/10 (30%)"));
+        assert_eq!(filled, width * 3 / 10);
+    }
+
+    #[test]
+    fn pins_leds() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("input3::numlock", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        app.select_led_named(Some("input3::numlock"));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('f')));
+        assert_eq!(app.led_list_state.selected(), Some(1));
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("▾ Pinned (1)"));
+        assert!(text.contains("○ input3 numlock 0/1"));
+        assert!(text.contains("▾ input3 (1)"));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('f')));
+        assert!(app.pinned.is_empty());
+        assert_eq!(app.led_list_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn hides_leds() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("input3::numlock", MockLed::new(1));
+        backend.add_led("phy0-led", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let (settings, _) = Settings::parse("hidden = [\"phy0-led\"]").unwrap();
+        let mut app = App::with_leds(Args::default(), settings, scan, Log::default());
+        app.select_led_named(Some("input3::capslock"));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('h')));
+        assert_eq!(
+            app.selected_led().map(|led| led.file_name.as_str()),
+            Some("input3::numlock")
+        );
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("LEDs (2 hidden)"));
+        assert!(!text.contains("○ capslock"));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('H')));
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("capslock 0/1 hidden"));
+        assert!(text.contains("phy0-led 0/1 hidden"));
+        // Only LEDs hidden in the TUI can be shown again from it
+        app.select_led_named(Some("input3::capslock"));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('h')));
+        app.select_led_named(Some("phy0-led"));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('h')));
+        assert!(app.hidden_leds.is_empty());
+        assert!(app.settings.is_hidden("phy0-led"));
+    }
+
+    #[test]
+    fn click_selects_led() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("input3::numlock", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        render_to_buffer(&mut app, 140, 30).unwrap();
+        // Below the border and the group heading
+        app.on_mouse_event(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 2,
+            row: 3,
+            modifiers: KeyModifiers::NONE,
+        });
+        assert_eq!(
+            app.selected_led().map(|led| led.file_name.as_str()),
+            Some("input3::numlock")
+        );
+        assert_eq!(app.focused_pane, Pane::Sidebar);
+    }
+
+    #[test]
+    fn shows_aliases() {
+        let backend = MockBackend::new();
+        backend.add_led("tpacpi::lid_logo_dot", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let (settings, _) =
+            Settings::parse("[aliases]\n\"tpacpi::lid_logo_dot\" = \"Lid logo\"").unwrap();
+        let mut app = App::with_leds(Args::default(), settings, scan, Log::default());
+        app.select_led_named(None);
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("○ Lid logo 0/1"));
+        assert!(text.contains("Alias: Lid logo"));
+        assert!(text.contains("Name: tpacpi lid_logo_dot"));
+    }
+
+    #[test]
+    fn reads_raw_attributes_once_per_tick() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        app.select_led_named(None);
+        app.raw_view = true;
+        // Stands in for what was read earlier, which frames until the next tick reuse
+        let attributes = vec![("brightness".to_string(), "42".to_string())];
+        app.raw_attributes
+            .insert("input3::capslock".to_string(), Ok(attributes));
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("brightness       42"));
+        app.tick();
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(!text.contains("brightness       42"));
+    }
+
+    #[test]
+    fn restores_session() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("input3::numlock", MockLed::new(1));
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        let session = Session {
+            selected: Some("input3::numlock".to_string()),
+            group_by: Some(GroupBy::Driver),
+            main_focused: true,
+            raw_view: true,
+            ..Session::default()
+        };
+        app.restore_session(&session);
+        assert_eq!(app.grouping, Grouping::Driver);
+        assert_eq!(app.session(), session);
+    }
+
+    #[test]
+    fn log_follows_unless_scrolled_up() {
+        let mut log = Log::default();
+        (0..20).for_each(|i| log.info(format!("entry {}", i)));
+        let mut app = App::with_leds(
+            Args::default(),
+            Settings::default(),
+            LedScan::default(),
+            log,
+        );
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("entry 19"));
+        app.perform(Action::ScrollLogUp);
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(!text.contains("entry 19"));
+        assert!(text.contains("PgDn to follow"));
+        app.log.info("entry 20");
+        // A page back down, plus the new entry
+        app.perform(Action::ScrollLogDown);
+        app.perform(Action::ScrollLogDown);
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("entry 20"));
+        assert!(app.log_scroll.is_none());
+    }
+
+    #[test]
+    fn log_filters_by_level() {
+        let mut log = Log::default();
+        log.info("found LEDs");
+        log.error("couldn't read LED");
+        let mut app = App::with_leds(
+            Args::default(),
+            Settings::default(),
+            LedScan::default(),
+            log,
+        );
+        app.perform(Action::CycleLogLevel);
+        app.perform(Action::CycleLogLevel);
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("Log (errors)"));
+        assert!(text.contains("couldn't read LED"));
+        assert!(!text.contains("found LEDs"));
+    }
+
+    #[test]
+    fn rebinds_keys_from_settings() {
+        let (settings, _) = Settings::parse("[keys]\ntoggle = \"t\"").unwrap();
+        let mut app = App::with_leds(
+            Args::default(),
+            settings,
+            LedScan::default(),
+            Log::default(),
+        );
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        let status = text.lines().last().unwrap();
+        assert!(status.contains("t: toggle"));
+        assert!(!status.contains("t: trigger"));
+    }
+
+    #[test]
+    fn groups_leds_by_device() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("input3::numlock", MockLed::new(1));
+        backend.add_led("tpacpi:green:batt", MockLed::new(1));
+        backend.add_led("phy0-led", MockLed::new(1));
+        let text = screenshot(scan_leds_from(Arc::new(backend)).unwrap());
+        let sidebar: Vec<&str> = text
+            .lines()
+            .skip(1)
+            .take(7)
+            .map(|line| line.split('│').nth(1).unwrap_or_default().trim_end())
+            .collect();
+        assert_eq!(
+            sidebar,
+            [
+                "▾ input3 (2)",
+                "  ○ capslock 0/1        0%",
+                "  ○ numlock 0/1         0%",
+                "▾ tpacpi (1)",
+                "  ○ green batt 0/1      0%",
+                "▾ (no device) (1)",
+                "  ○ phy0-led 0/1        0%",
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_backlights_separately() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        let backlights = MockBackend::backlights();
+        backlights.add_led(
+            "intel_backlight",
+            MockLed {
+                brightness: 96000,
+                triggers: None,
+                ..MockLed::new(120000)
+            },
+        );
+        let mut scan = scan_leds_from(Arc::new(backend.clone())).unwrap();
+        scan.leds
+            .extend(scan_leds_from(Arc::new(backlights.clone())).unwrap().leds);
+        let mut app = App::with_leds(Args::default(), Settings::default(), scan, Log::default());
+        let text = buffer_text(&render_to_buffer(&mut app, 140, 30).unwrap());
+        assert!(text.contains("▾ Backlights (1)"));
+        assert!(text.contains("  ● intel_backlight 96000/"));
+
+        // Turning everything off leaves the screen on
+        app.all_off();
+        assert_eq!(backlights.led("intel_backlight").unwrap().brightness, 96000);
+        assert_eq!(backend.led("input3::capslock").unwrap().brightness, 0);
+        app.led_list_state.select(Some(3));
+        app.on_key_event(KeyEvent::from(KeyCode::Char('-')));
+        assert_eq!(backlights.led("intel_backlight").unwrap().brightness, 84000);
+    }
+
+    #[test]
+    fn greys_out_failed_leds() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led(
+            "platform::broken",
+            MockLed {
+                error: Some(ErrorKind::TimedOut),
+                ..MockLed::new(1)
+            },
+        );
+        let text = screenshot(scan_leds_from(Arc::new(backend)).unwrap());
+        assert!(text.contains("  ○ capslock 0/1"));
+        assert!(text.contains("platform broken (I/O error"));
+        assert!(text.contains("Error reading LED platform::broken"));
+    }
+
+    #[test]
+    fn renders_without_leds() {
+        let text = screenshot(LedScan::default());
+        assert_eq!(text.lines().count(), 30);
+        assert!(text.lines().all(|line| line.chars().count() <= 140));
+    }
+
+    #[test]
+    fn doesnt_escalate_remote_writes() {
+        let request = HelperRequest::Brightness {
+            led: "nas/input3::capslock".to_string(),
+            brightness: 1,
+        };
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        // Fails with the original error rather than asking pkexec
+        let error = retry_as_root(true, denied, &request).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
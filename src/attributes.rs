@@ -0,0 +1,76 @@
+use std::{fs, io, path::Path};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Table, TableState},
+};
+
+use crate::{
+    LED,
+    theme::{self, Role},
+};
+
+/// The name and contents of each file in an LED's sysfs directory.
+pub type Attributes = Vec<(String, String)>;
+
+/// Reads every file in an LED's sysfs directory, sorted by name.
+///
+/// Files that can't be read (e.g. write-only attributes such as `shot`) are included with a
+/// description of the error, so it's visible that they exist. Subdirectories are skipped.
+pub fn read_attributes(led_path: &Path) -> io::Result<Attributes> {
+    let mut attributes = Vec::new();
+    for entry in fs::read_dir(led_path)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let value = match fs::read_to_string(&path) {
+            Ok(value) => value.trim_end().to_string(),
+            Err(e) => format!("<unreadable: {}>", e),
+        };
+        attributes.push((entry.file_name().to_string_lossy().into_owned(), value));
+    }
+    attributes.sort();
+    Ok(attributes)
+}
+
+/// Shows the contents of every file in an LED's directory, as read by [`read_attributes`].
+///
+/// The caller reads them again every tick, so the view follows whatever the driver does. The
+/// first `scroll` attributes are scrolled out of view, which is limited to keep one in view.
+pub fn render(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    led: &LED,
+    attributes: &io::Result<Attributes>,
+    scroll: &mut usize,
+) {
+    let title = Line::styled(
+        format!("Raw attributes of {}", led.name),
+        theme::style(Role::Title),
+    )
+    .centered();
+    let block = block.title(title);
+    let attributes = match attributes {
+        Ok(attributes) => attributes,
+        Err(e) => {
+            frame.render_widget(
+                Paragraph::new(format!("Error reading attributes: {}", e)).block(block),
+                area,
+            );
+            return;
+        }
+    };
+    *scroll = (*scroll).min(attributes.len().saturating_sub(1));
+    let rows = attributes
+        .iter()
+        .map(|(name, value)| Row::new([name.clone(), value.replace('\n', " ")]));
+    let table = Table::new(rows, [Constraint::Length(16), Constraint::Fill(1)])
+        .header(Row::new(["Attribute", "Value"]).style(theme::style(Role::Heading)))
+        .block(block);
+    frame.render_stateful_widget(table, area, &mut TableState::new().with_offset(*scroll));
+}
@@ -0,0 +1,565 @@
+//! Where LEDs are read from and written to: sysfs on a real system, or memory in tests.
+//!
+//! Screen backlights are read through the same interface, from [`BacklightBackend`], so that
+//! the TUI can control them like LEDs that have no triggers.
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    led::{self, NewLEDError, SYS_CLASS_BACKLIGHT, SYS_CLASS_LEDS, Triggers},
+    logind,
+};
+
+/// The sysfs class of the devices a backend reads.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceClass {
+    /// LEDs, in [`SYS_CLASS_LEDS`]
+    #[default]
+    Led,
+    /// Screen backlights, in [`SYS_CLASS_BACKLIGHT`]
+    Backlight,
+}
+
+impl DeviceClass {
+    /// The directory the kernel lists the devices in.
+    pub fn dir(self) -> &'static str {
+        match self {
+            Self::Led => SYS_CLASS_LEDS,
+            Self::Backlight => SYS_CLASS_BACKLIGHT,
+        }
+    }
+}
+
+/// Reads and writes the state of LEDs, identified by their file name (e.g. `input3::capslock`).
+pub trait LedBackend: fmt::Debug + Send + Sync {
+    /// The class of the devices, [`DeviceClass::Led`] unless the backend reads backlights.
+    fn class(&self) -> DeviceClass {
+        DeviceClass::Led
+    }
+    /// Lists the file names of all LEDs.
+    fn led_names(&self) -> Result<Vec<String>, NewLEDError>;
+    fn read_brightness(&self, led: &str) -> Result<u32, NewLEDError>;
+    fn write_brightness(&self, led: &str, brightness: u32) -> io::Result<()>;
+    fn read_max_brightness(&self, led: &str) -> Result<u32, NewLEDError>;
+    /// Reads the LED's triggers, or `None` if it can't be driven by triggers.
+    fn read_triggers(&self, led: &str) -> io::Result<Option<Triggers>>;
+    fn write_trigger(&self, led: &str, trigger: &str) -> io::Result<()>;
+    /// Reads the color channels of a multicolor LED, or `None` for other LEDs.
+    fn read_multi_index(&self, led: &str) -> io::Result<Option<Vec<String>>>;
+    /// Reads the intensity of each channel of a multicolor LED, in the order of its channels.
+    fn read_multi_intensity(&self, led: &str) -> io::Result<Vec<u32>>;
+    fn write_multi_intensity(&self, led: &str, intensities: &[u32]) -> io::Result<()>;
+}
+
+/// Lists the names of the devices in a sysfs class directory.
+fn list_dir(dir: &str) -> Result<Vec<String>, NewLEDError> {
+    fs::read_dir(dir)
+        .map_err(NewLEDError::IOError)?
+        .map(|directory| {
+            directory
+                .map_err(NewLEDError::IOError)?
+                .file_name()
+                .into_string()
+                .map_err(|_| NewLEDError::InvalidFileName)
+        })
+        .collect()
+}
+
+/// Writes an attribute of a device in sysfs, tracing the outcome.
+fn write_attribute(path: &Path, led: &str, attribute: &str, value: &str) -> io::Result<()> {
+    let result = fs::write(path.join(attribute), value);
+    match &result {
+        Ok(()) => tracing::debug!(led, attribute, value, "wrote attribute"),
+        Err(e) => {
+            tracing::debug!(led, attribute, value, error = %e, "failed to write attribute")
+        }
+    }
+    result
+}
+
+/// The LEDs in [`SYS_CLASS_LEDS`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SysfsBackend;
+
+impl SysfsBackend {
+    fn path(led: &str) -> PathBuf {
+        PathBuf::from(SYS_CLASS_LEDS).join(led)
+    }
+
+    fn write_attribute(led: &str, attribute: &str, value: &str) -> io::Result<()> {
+        write_attribute(&Self::path(led), led, attribute, value)
+    }
+}
+
+impl LedBackend for SysfsBackend {
+    fn led_names(&self) -> Result<Vec<String>, NewLEDError> {
+        list_dir(SYS_CLASS_LEDS)
+    }
+
+    fn read_brightness(&self, led: &str) -> Result<u32, NewLEDError> {
+        let result = led::read_brightness(&Self::path(led));
+        tracing::trace!(led, ?result, "read brightness");
+        result
+    }
+
+    fn write_brightness(&self, led: &str, brightness: u32) -> io::Result<()> {
+        Self::write_attribute(led, "brightness", &brightness.to_string())
+    }
+
+    fn read_max_brightness(&self, led: &str) -> Result<u32, NewLEDError> {
+        let result = led::read_max_brightness(&Self::path(led));
+        tracing::trace!(led, ?result, "read max_brightness");
+        result
+    }
+
+    fn read_triggers(&self, led: &str) -> io::Result<Option<Triggers>> {
+        let result = led::read_triggers(&Self::path(led));
+        tracing::trace!(led, ?result, "read trigger");
+        result
+    }
+
+    fn write_trigger(&self, led: &str, trigger: &str) -> io::Result<()> {
+        Self::write_attribute(led, "trigger", trigger)
+    }
+
+    fn read_multi_index(&self, led: &str) -> io::Result<Option<Vec<String>>> {
+        match fs::read_to_string(Self::path(led).join("multi_index")) {
+            Ok(index) => Ok(Some(index.split_whitespace().map(str::to_string).collect())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn read_multi_intensity(&self, led: &str) -> io::Result<Vec<u32>> {
+        fs::read_to_string(Self::path(led).join("multi_intensity"))?
+            .split_whitespace()
+            .map(|intensity| {
+                intensity.parse().map_err(|_| {
+                    io::Error::new(ErrorKind::InvalidData, NewLEDError::InvalidBrightness)
+                })
+            })
+            .collect()
+    }
+
+    fn write_multi_intensity(&self, led: &str, intensities: &[u32]) -> io::Result<()> {
+        let intensities: Vec<String> = intensities.iter().map(u32::to_string).collect();
+        Self::write_attribute(led, "multi_intensity", &intensities.join(" "))
+    }
+}
+
+/// The screen backlights in [`SYS_CLASS_BACKLIGHT`], which have a brightness (often with
+/// thousands of levels) but no triggers or color channels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BacklightBackend;
+
+impl BacklightBackend {
+    fn path(backlight: &str) -> PathBuf {
+        PathBuf::from(SYS_CLASS_BACKLIGHT).join(backlight)
+    }
+}
+
+fn unsupported(what: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::Unsupported,
+        format!("backlights have no {}", what),
+    )
+}
+
+impl LedBackend for BacklightBackend {
+    fn class(&self) -> DeviceClass {
+        DeviceClass::Backlight
+    }
+
+    fn led_names(&self) -> Result<Vec<String>, NewLEDError> {
+        // Kernels built without backlight support don't have the directory
+        match list_dir(SYS_CLASS_BACKLIGHT) {
+            Err(NewLEDError::IOError(e)) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    fn read_brightness(&self, backlight: &str) -> Result<u32, NewLEDError> {
+        let result = led::read_brightness(&Self::path(backlight));
+        tracing::trace!(backlight, ?result, "read brightness");
+        result
+    }
+
+    /// Writes to sysfs when running as root, and otherwise asks logind, which lets the user of
+    /// the active session set the brightness. Without logind (or if it refuses), sysfs is tried
+    /// anyway, as a udev rule may allow writing.
+    fn write_brightness(&self, backlight: &str, brightness: u32) -> io::Result<()> {
+        let path = Self::path(backlight);
+        let value = brightness.to_string();
+        if logind::running_as_root() {
+            return write_attribute(&path, backlight, "brightness", &value);
+        }
+        let Err(logind_error) = logind::set_brightness("backlight", backlight, brightness) else {
+            tracing::debug!(backlight, brightness, "set brightness through logind");
+            return Ok(());
+        };
+        tracing::debug!(backlight, error = %logind_error, "logind couldn't set the brightness");
+        write_attribute(&path, backlight, "brightness", &value).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("{} (through logind: {})", e, logind_error),
+            )
+        })
+    }
+
+    fn read_max_brightness(&self, backlight: &str) -> Result<u32, NewLEDError> {
+        led::read_max_brightness(&Self::path(backlight))
+    }
+
+    fn read_triggers(&self, _backlight: &str) -> io::Result<Option<Triggers>> {
+        Ok(None)
+    }
+
+    fn write_trigger(&self, _backlight: &str, _trigger: &str) -> io::Result<()> {
+        Err(unsupported("triggers"))
+    }
+
+    fn read_multi_index(&self, _backlight: &str) -> io::Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    fn read_multi_intensity(&self, _backlight: &str) -> io::Result<Vec<u32>> {
+        Err(unsupported("color channels"))
+    }
+
+    fn write_multi_intensity(&self, _backlight: &str, _intensities: &[u32]) -> io::Result<()> {
+        Err(unsupported("color channels"))
+    }
+}
+
+/// An LED kept in memory by [`MockBackend`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockLed {
+    pub brightness: u32,
+    pub max_brightness: u32,
+    /// `None` for LEDs that can't be driven by triggers
+    pub triggers: Option<Triggers>,
+    /// Makes reading the brightness fail, like with a driver that can't reach its device
+    pub error: Option<ErrorKind>,
+    /// The channels and their intensities, for a multicolor LED
+    pub channels: Option<Vec<(String, u32)>>,
+}
+
+impl MockLed {
+    /// An LED that's off, offering the `none` and `timer` triggers.
+    pub fn new(max_brightness: u32) -> Self {
+        Self {
+            brightness: 0,
+            max_brightness,
+            triggers: Some(Triggers {
+                available: vec!["none".to_string(), "timer".to_string()],
+                active: Some("none".to_string()),
+            }),
+            error: None,
+            channels: None,
+        }
+    }
+}
+
+/// LEDs that only exist in memory. Writes behave like the kernel's: brightness is capped at the
+/// maximum, turning an LED off stops its trigger, and unknown triggers are rejected.
+///
+/// Clones share the same LEDs, so a test can keep one to inspect what the code under test did.
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    leds: Arc<Mutex<BTreeMap<String, MockLed>>>,
+    class: DeviceClass,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A backend whose devices are backlights, which should be added without triggers.
+    pub fn backlights() -> Self {
+        Self {
+            class: DeviceClass::Backlight,
+            ..Self::default()
+        }
+    }
+
+    /// Adds an LED, or replaces the one with the same name.
+    pub fn add_led(&self, name: &str, led: MockLed) {
+        self.leds.lock().unwrap().insert(name.to_string(), led);
+    }
+
+    /// Removes an LED, as if its device was unplugged.
+    pub fn remove_led(&self, name: &str) -> Option<MockLed> {
+        self.leds.lock().unwrap().remove(name)
+    }
+
+    /// The current state of an LED.
+    pub fn led(&self, name: &str) -> Option<MockLed> {
+        self.leds.lock().unwrap().get(name).cloned()
+    }
+
+    fn with_led<T>(
+        &self,
+        name: &str,
+        f: impl FnOnce(&mut MockLed) -> io::Result<T>,
+    ) -> io::Result<T> {
+        match self.leds.lock().unwrap().get_mut(name) {
+            Some(led) => f(led),
+            None => Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("no LED named {}", name),
+            )),
+        }
+    }
+}
+
+impl LedBackend for MockBackend {
+    fn class(&self) -> DeviceClass {
+        self.class
+    }
+
+    fn led_names(&self) -> Result<Vec<String>, NewLEDError> {
+        Ok(self.leds.lock().unwrap().keys().cloned().collect())
+    }
+
+    fn read_brightness(&self, led: &str) -> Result<u32, NewLEDError> {
+        Ok(self.with_led(led, |led| match led.error {
+            Some(kind) => Err(kind.into()),
+            None => Ok(led.brightness),
+        })?)
+    }
+
+    fn write_brightness(&self, led: &str, brightness: u32) -> io::Result<()> {
+        self.with_led(led, |led| {
+            led.brightness = brightness.min(led.max_brightness);
+            if brightness == 0
+                && let Some(triggers) = &mut led.triggers
+            {
+                triggers.active = Some("none".to_string());
+            }
+            Ok(())
+        })
+    }
+
+    fn read_max_brightness(&self, led: &str) -> Result<u32, NewLEDError> {
+        Ok(self.with_led(led, |led| Ok(led.max_brightness))?)
+    }
+
+    fn read_triggers(&self, led: &str) -> io::Result<Option<Triggers>> {
+        self.with_led(led, |led| Ok(led.triggers.clone()))
+    }
+
+    fn write_trigger(&self, led: &str, trigger: &str) -> io::Result<()> {
+        self.with_led(led, |led| {
+            let Some(triggers) = &mut led.triggers else {
+                return Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    "the LED has no triggers",
+                ));
+            };
+            if !triggers
+                .available
+                .iter()
+                .any(|available| available == trigger)
+            {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("unknown trigger {}", trigger),
+                ));
+            }
+            triggers.active = Some(trigger.to_string());
+            Ok(())
+        })
+    }
+
+    fn read_multi_index(&self, led: &str) -> io::Result<Option<Vec<String>>> {
+        self.with_led(led, |led| {
+            Ok(led
+                .channels
+                .as_ref()
+                .map(|channels| channels.iter().map(|(name, _)| name.clone()).collect()))
+        })
+    }
+
+    fn read_multi_intensity(&self, led: &str) -> io::Result<Vec<u32>> {
+        self.with_led(led, |led| match &led.channels {
+            Some(channels) => Ok(channels.iter().map(|(_, intensity)| *intensity).collect()),
+            None => Err(ErrorKind::NotFound.into()),
+        })
+    }
+
+    fn write_multi_intensity(&self, led: &str, intensities: &[u32]) -> io::Result<()> {
+        self.with_led(led, |led| {
+            let max_brightness = led.max_brightness;
+            let Some(channels) = &mut led.channels else {
+                return Err(ErrorKind::NotFound.into());
+            };
+            // Like the kernel, only a value for every channel, none above the maximum, is taken
+            if intensities.len() != channels.len()
+                || intensities
+                    .iter()
+                    .any(|intensity| *intensity > max_brightness)
+            {
+                return Err(ErrorKind::InvalidInput.into());
+            }
+            for ((_, intensity), new) in channels.iter_mut().zip(intensities) {
+                *intensity = *new;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::led::{LED, LedKind, get_all_leds_from, scan_leds_from};
+
+    fn backend() -> MockBackend {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("tpacpi::kbd_backlight", MockLed::new(2));
+        backend
+    }
+
+    #[test]
+    fn reads_leds() {
+        let backend = backend();
+        backend.add_led(
+            "input3::numlock",
+            MockLed {
+                brightness: 1,
+                ..MockLed::new(1)
+            },
+        );
+        let leds = get_all_leds_from(Arc::new(backend)).unwrap();
+        let names: Vec<_> = leds.iter().map(|led| led.file_name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "input3::capslock",
+                "input3::numlock",
+                "tpacpi::kbd_backlight"
+            ]
+        );
+        assert_eq!(leds[1].name, "input3 numlock");
+        assert!(leds[1].is_on);
+        assert!(!leds[2].is_on);
+        assert_eq!(leds[2].max_brightness, 2);
+    }
+
+    #[test]
+    fn keeps_reading_after_failed_led() {
+        let backend = backend();
+        backend.add_led(
+            "platform::broken",
+            MockLed {
+                error: Some(ErrorKind::TimedOut),
+                ..MockLed::new(1)
+            },
+        );
+        let scan = scan_leds_from(Arc::new(backend)).unwrap();
+        assert_eq!(scan.leds.len(), 2);
+        assert_eq!(scan.failed.len(), 1);
+        assert_eq!(scan.failed[0].file_name, "platform::broken");
+    }
+
+    #[test]
+    fn missing_led_is_not_found() {
+        let result = LED::with_backend("nonexistent".to_string(), Arc::new(backend()));
+        assert!(matches!(result, Err(NewLEDError::NotFound)));
+    }
+
+    #[test]
+    fn set_on_uses_max_brightness() {
+        let backend = backend();
+        let mut led = LED::with_backend(
+            "tpacpi::kbd_backlight".to_string(),
+            Arc::new(backend.clone()),
+        )
+        .unwrap();
+        led.set_on(true).unwrap();
+        assert!(led.is_on);
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 2);
+        led.set_on(false).unwrap();
+        assert!(!led.is_on);
+        assert_eq!(backend.led("tpacpi::kbd_backlight").unwrap().brightness, 0);
+    }
+
+    #[test]
+    fn refresh_picks_up_outside_changes() {
+        let backend = backend();
+        let mut led =
+            LED::with_backend("input3::capslock".to_string(), Arc::new(backend.clone())).unwrap();
+        backend.write_brightness("input3::capslock", 1).unwrap();
+        assert_eq!(led.refresh().unwrap(), 1);
+        assert!(led.is_on);
+    }
+
+    #[test]
+    fn brightness_is_capped() {
+        let backend = backend();
+        backend.write_brightness("input3::capslock", 255).unwrap();
+        assert_eq!(backend.read_brightness("input3::capslock").unwrap(), 1);
+    }
+
+    #[test]
+    fn triggers() {
+        let backend = backend();
+        let mut led =
+            LED::with_backend("input3::capslock".to_string(), Arc::new(backend.clone())).unwrap();
+        led.set_trigger("timer").unwrap();
+        let triggers = led.triggers().unwrap().unwrap();
+        assert_eq!(triggers.active.as_deref(), Some("timer"));
+        assert_eq!(
+            led.set_trigger("heartbeat").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        // Like the kernel, turning the LED off stops the trigger
+        led.set_on(false).unwrap();
+        let triggers = led.triggers().unwrap().unwrap();
+        assert_eq!(triggers.active.as_deref(), Some("none"));
+    }
+
+    #[test]
+    fn multicolor() {
+        let backend = backend();
+        backend.add_led(
+            "multicolor:status",
+            MockLed {
+                channels: Some(vec![("red".to_string(), 0), ("green".to_string(), 0)]),
+                ..MockLed::new(255)
+            },
+        );
+        let led =
+            LED::with_backend("multicolor:status".to_string(), Arc::new(backend.clone())).unwrap();
+        assert_eq!(
+            led.kind,
+            LedKind::Multicolor {
+                channels: vec!["red".to_string(), "green".to_string()]
+            }
+        );
+        led.set_intensities(&[255, 128]).unwrap();
+        assert_eq!(led.intensities().unwrap(), [255, 128]);
+        assert!(led.set_intensities(&[255]).is_err());
+        let single =
+            LED::with_backend("input3::capslock".to_string(), Arc::new(backend.clone())).unwrap();
+        assert_eq!(single.kind, LedKind::Single);
+    }
+
+    #[test]
+    fn removed_led_is_gone() {
+        let backend = backend();
+        let mut led =
+            LED::with_backend("input3::capslock".to_string(), Arc::new(backend.clone())).unwrap();
+        backend.remove_led("input3::capslock");
+        assert!(led.refresh().is_err());
+        assert_eq!(backend.led_names().unwrap(), ["tpacpi::kbd_backlight"]);
+    }
+}
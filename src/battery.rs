@@ -0,0 +1,80 @@
+use crate::{
+    LED, led,
+    multicolor::{self, MulticolorError, Rgb},
+};
+
+/// Colour used for multicolor LEDs when the battery is at or below 20%.
+const LOW_COLOR: Rgb = Rgb::new(255, 0, 0);
+/// Colour used for multicolor LEDs when the battery is at or below 50%.
+const MEDIUM_COLOR: Rgb = Rgb::new(255, 160, 0);
+/// Colour used for multicolor LEDs when the battery is above 50%.
+const HIGH_COLOR: Rgb = Rgb::new(0, 255, 0);
+
+/// Makes an LED's brightness follow the battery percentage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatteryGauge {
+    /// File name of the LED showing the battery level
+    pub led: String,
+    /// The percentage last written to the LED
+    pub last_percentage: Option<u8>,
+}
+
+impl BatteryGauge {
+    pub fn new(led: String) -> Self {
+        Self {
+            led,
+            last_percentage: None,
+        }
+    }
+}
+
+/// The raw brightness that represents `percentage` on an LED with the given maximum.
+pub fn brightness_for(percentage: u8, max_brightness: u32) -> u32 {
+    led::percent_to_brightness(u32::from(percentage), max_brightness)
+}
+
+fn threshold_color(percentage: u8) -> Rgb {
+    match percentage {
+        0..=20 => LOW_COLOR,
+        21..=50 => MEDIUM_COLOR,
+        _ => HIGH_COLOR,
+    }
+}
+
+/// Sets a multicolor LED to the colour for the battery level.
+///
+/// Does nothing for LEDs that aren't multicolor.
+pub fn write_threshold_color(led: &LED, percentage: u8) -> Result<(), MulticolorError> {
+    match multicolor::set_color(led, threshold_color(percentage)) {
+        Err(MulticolorError::NotMulticolor) => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{MockBackend, backend::MockLed, led::get_all_leds_from};
+
+    #[test]
+    fn colors_multicolor_leds() {
+        let backend = MockBackend::new();
+        let channels = ["red", "green", "blue"].map(|channel| (channel.to_string(), 0));
+        backend.add_led(
+            "rgb:battery",
+            MockLed {
+                channels: Some(channels.to_vec()),
+                ..MockLed::new(255)
+            },
+        );
+        backend.add_led("input3::capslock", MockLed::new(1));
+        let leds = get_all_leds_from(Arc::new(backend)).unwrap();
+        for led in &leds {
+            write_threshold_color(led, 40).unwrap();
+        }
+        let rgb = leds.iter().find(|led| led.file_name == "rgb:battery");
+        assert_eq!(rgb.unwrap().intensities().unwrap(), [255, 160, 0]);
+    }
+}
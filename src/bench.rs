@@ -0,0 +1,149 @@
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+
+use crate::{
+    get_all_leds, glob,
+    output::{Output, Tone},
+    profiles::LedState,
+    terminal::Progress,
+};
+
+/// Latency percentiles of a series of sysfs accesses.
+struct Latencies {
+    p50: Duration,
+    p90: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+impl Latencies {
+    fn from_samples(mut samples: Vec<Duration>) -> Option<Self> {
+        samples.sort_unstable();
+        let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+        Some(Self {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: *samples.last()?,
+        })
+    }
+
+    fn cells(&self) -> Vec<(String, Tone)> {
+        [self.p50, self.p90, self.p99, self.max]
+            .into_iter()
+            .map(|latency| (format_duration(latency), Tone::Value))
+            .collect()
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}µs", duration.as_secs_f64() * 1_000_000.0)
+}
+
+/// Times reading the `brightness` file of an LED.
+fn bench_reads(led_path: &Path, iterations: usize) -> io::Result<Vec<Duration>> {
+    let brightness_path = led_path.join("brightness");
+    (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            fs::read_to_string(&brightness_path)?;
+            Ok(start.elapsed())
+        })
+        .collect()
+}
+
+/// Times writing the current brightness back to the `brightness` file of an LED.
+fn bench_writes(led_path: &Path, iterations: usize) -> io::Result<Vec<Duration>> {
+    let brightness_path = led_path.join("brightness");
+    let brightness = fs::read_to_string(&brightness_path)?;
+    (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            fs::write(&brightness_path, brightness.trim())?;
+            Ok(start.elapsed())
+        })
+        .collect()
+}
+
+/// Measures the read and write latency of each LED and prints the percentiles, putting each
+/// LED back the way it was afterwards.
+///
+/// With `dry_run`, only prints which LEDs the names and patterns expand to.
+pub fn run(iterations: usize, led_names: Vec<String>, dry_run: bool) -> Result<()> {
+    let mut leds = if led_names.is_empty() {
+        get_all_leds()?
+    } else {
+        glob::expand(&led_names)?
+    };
+    let output = Output::detect();
+    if dry_run {
+        for led in &leds {
+            println!("{}", output.paint(&led.file_name, Tone::Name));
+        }
+        return Ok(());
+    }
+    println!(
+        "{}",
+        output.paint(
+            &format!(
+                "Measuring {} read(s) and write(s) per LED for {} LED(s)",
+                iterations,
+                leds.len()
+            ),
+            Tone::Dim
+        )
+    );
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let progress = Progress::start();
+    let total = leds.len();
+    for (done, led) in leds.iter_mut().enumerate() {
+        progress.update(done, total);
+        let led_path = led.path();
+        // Writing a brightness of 0 stops the LED's trigger, so LEDs whose state can't be
+        // saved to be restored afterwards aren't written to
+        let original = LedState::capture(led);
+        let writes = match &original {
+            Ok(_) => bench_writes(&led_path, iterations),
+            Err(e) => Err(io::Error::new(e.kind(), format!("not restorable: {}", e))),
+        };
+        if let Ok(original) = &original
+            && let Err(e) = original.restore(led)
+        {
+            errors.push(format!("Couldn't restore {}: {}", led.file_name, e));
+        }
+        let results = [
+            ("read", bench_reads(&led_path, iterations)),
+            ("write", writes),
+        ];
+        for (operation, result) in results {
+            let mut row = vec![
+                (led.file_name.clone(), Tone::Name),
+                (operation.to_string(), Tone::Plain),
+            ];
+            match result.map(Latencies::from_samples) {
+                Ok(Some(latencies)) => row.extend(latencies.cells()),
+                Ok(None) => row.push(("no samples".to_string(), Tone::Dim)),
+                Err(e) => row.push((format!("error: {}", e), Tone::Error)),
+            }
+            rows.push(row);
+        }
+    }
+    println!(
+        "{}",
+        output.table(
+            &["LED", "op", "p50", "p90", "p99", "max"],
+            &rows,
+            &[2, 3, 4, 5]
+        )
+    );
+    for error in &errors {
+        eprintln!("{}", output.paint(error, Tone::Error));
+    }
+    Ok(())
+}
@@ -0,0 +1,305 @@
+//! brightnessctl-style invocations, so scripts and keybindings written for brightnessctl or
+//! light work with Glimpse unchanged (e.g. `glimpse -d kbd_backlight s 50%`). The LED can also
+//! be given after the operation, as in `glimpse set kbd_backlight 50%` or `glimpse toggle
+//! capslock`, and can be a glob pattern matching several LEDs, as in
+//! `glimpse set "input*::capslock" 0`.
+
+use std::{fs, str::FromStr, time::Duration};
+
+use color_eyre::{Result, eyre::eyre};
+use thiserror::Error;
+
+use crate::{
+    LED,
+    calibration::Calibration,
+    get_all_leds, glob,
+    led::{brightness_to_percent, percent_to_brightness},
+    output::{Output, Tone},
+    settings::Settings,
+};
+
+/// How long `glimpse blink` keeps the LED on and off by default, as the kernel's `timer` trigger
+/// does.
+pub const DEFAULT_BLINK_DELAY: Duration = Duration::from_millis(500);
+
+/// An amount of brightness, either raw or relative to the maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Amount {
+    Raw(u32),
+    Percent(u32),
+}
+
+/// Why an exact brightness can't be set on an LED.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AmountError {
+    #[error("expected a value like 128 or 50%")]
+    Invalid,
+    #[error("at most 100%")]
+    PercentTooHigh,
+    #[error("at most {0}")]
+    TooHigh(u32),
+}
+
+impl Amount {
+    /// Parses an exact brightness, raw (`128`) or as a percentage (`50%`), rejecting values
+    /// above the LED's maximum instead of clamping them.
+    pub fn parse_exact(s: &str, max_brightness: u32) -> Result<Self, AmountError> {
+        let s = s.trim();
+        let amount = match s.strip_suffix('%') {
+            Some(percent) => Self::Percent(percent.trim().parse().or(Err(AmountError::Invalid))?),
+            None => Self::Raw(s.parse().or(Err(AmountError::Invalid))?),
+        };
+        match amount {
+            Self::Percent(percent) if percent > 100 => Err(AmountError::PercentTooHigh),
+            Self::Raw(value) if value > max_brightness => Err(AmountError::TooHigh(max_brightness)),
+            amount => Ok(amount),
+        }
+    }
+
+    fn to_raw(self, max_brightness: u32) -> u32 {
+        match self {
+            Self::Raw(value) => value,
+            Self::Percent(percent) => percent_to_brightness(percent, max_brightness),
+        }
+    }
+}
+
+/// A brightness value as written for brightnessctl: `50%`, `+10%`, `10%-`, `200`, `+5`, `5-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueSpec {
+    Absolute(Amount),
+    Increase(Amount),
+    Decrease(Amount),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("invalid brightness value \"{0}\"")]
+pub struct ParseValueError(String);
+
+impl FromStr for ValueSpec {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseValueError(s.to_string());
+        let (spec, amount): (fn(Amount) -> Self, &str) = if let Some(rest) = s.strip_prefix('+') {
+            (Self::Increase, rest)
+        } else if let Some(rest) = s.strip_suffix('-') {
+            (Self::Decrease, rest)
+        } else if let Some(rest) = s.strip_suffix('+') {
+            (Self::Increase, rest)
+        } else {
+            (Self::Absolute, s)
+        };
+        let amount = match amount.strip_suffix('%') {
+            Some(percent) => Amount::Percent(percent.parse().map_err(|_| error())?),
+            None => Amount::Raw(amount.parse().map_err(|_| error())?),
+        };
+        Ok(spec(amount))
+    }
+}
+
+impl ValueSpec {
+    /// The new raw brightness, clamped to the LED's range.
+    pub fn apply(self, current: u32, max_brightness: u32) -> u32 {
+        let new = match self {
+            Self::Absolute(amount) => amount.to_raw(max_brightness),
+            Self::Increase(amount) => current.saturating_add(amount.to_raw(max_brightness)),
+            Self::Decrease(amount) => current.saturating_sub(amount.to_raw(max_brightness)),
+        };
+        new.min(max_brightness)
+    }
+}
+
+/// A brightnessctl operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Info,
+    Get,
+    Max,
+    Set(ValueSpec),
+    /// Turn the LED off if it's on, or fully on if it's off
+    Toggle,
+    /// Make a trigger drive the LED, or stop any trigger with `none`
+    Trigger(String),
+    /// Blink the LED with the `timer` trigger, on and off for the given time each
+    Blink(Duration),
+}
+
+impl Operation {
+    /// Whether the operation can be applied to several LEDs at once, when the device is a
+    /// pattern matching more than one.
+    fn applies_to_many(&self) -> bool {
+        matches!(
+            self,
+            Self::Info | Self::Set(_) | Self::Toggle | Self::Trigger(_) | Self::Blink(_)
+        )
+    }
+}
+
+fn read_raw_brightness(led: &LED) -> Result<u32> {
+    let brightness = fs::read_to_string(led.path().join("brightness"))?;
+    Ok(brightness.trim().parse()?)
+}
+
+fn print_info(led: &LED) -> Result<()> {
+    let brightness = read_raw_brightness(led)?;
+    let max_brightness = led.max_brightness()?;
+    let class = if led.is_backlight() {
+        "backlight"
+    } else {
+        "leds"
+    };
+    println!("Device '{}' of class '{}':", led.file_name, class);
+    println!(
+        "\tCurrent brightness: {} ({}%)",
+        brightness,
+        brightness_to_percent(brightness, max_brightness)
+    );
+    println!("\tMax brightness: {}", max_brightness);
+    Ok(())
+}
+
+/// Runs a brightnessctl-style operation on the LEDs matching `device`.
+///
+/// Without a device, the operation applies to the only LED, if there is just one; `info` lists
+/// all LEDs like `brightnessctl -l`. A device pattern matching several LEDs applies `set`,
+/// `toggle`, `trigger` and `blink` to each of them. With `json`, `get` prints a JSON object
+/// describing the LED instead of just its brightness. With `dry_run`, only prints which LEDs
+/// the device expands to.
+pub fn run(operation: Operation, device: Option<String>, json: bool, dry_run: bool) -> Result<()> {
+    let pattern_given = device.is_some();
+    let mut leds = match device {
+        Some(device) => glob::expand(&[device])?,
+        None => get_all_leds()?,
+    };
+    leds.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    if dry_run {
+        let output = Output::detect();
+        for led in &leds {
+            println!("{}", output.paint(&led.file_name, Tone::Name));
+        }
+        return Ok(());
+    }
+    let (settings, _) = Settings::load()?;
+    let calibration = Calibration::load(&settings)?;
+    if leds.len() > 1
+        && !(operation == Operation::Info || pattern_given && operation.applies_to_many())
+    {
+        return Err(eyre!(
+            "{} LEDs match, pick one with -d <device>",
+            leds.len()
+        ));
+    }
+    for led in &mut leds {
+        match &operation {
+            Operation::Info => print_info(led)?,
+            Operation::Get if json => {
+                led.refresh()?;
+                println!("{}", led.to_json()?);
+            }
+            Operation::Get => println!("{}", read_raw_brightness(led)?),
+            Operation::Max => println!("{}", led.max_brightness()?),
+            Operation::Set(value) => {
+                let max_brightness = led.max_brightness()?;
+                let new = match value {
+                    ValueSpec::Absolute(Amount::Percent(percent)) => calibration.brightness_for(
+                        &led.file_name,
+                        (*percent).min(100) as u8,
+                        max_brightness,
+                    ),
+                    ValueSpec::Increase(Amount::Percent(percent)) => calibration.step(
+                        &led.file_name,
+                        read_raw_brightness(led)?,
+                        max_brightness,
+                        (*percent).min(100) as i32,
+                    ),
+                    ValueSpec::Decrease(Amount::Percent(percent)) => calibration.step(
+                        &led.file_name,
+                        read_raw_brightness(led)?,
+                        max_brightness,
+                        -((*percent).min(100) as i32),
+                    ),
+                    _ => calibration.clamp(
+                        &led.file_name,
+                        value.apply(read_raw_brightness(led)?, max_brightness),
+                    ),
+                };
+                led.set_brightness(new)?;
+                print_info(led)?;
+            }
+            Operation::Toggle => {
+                let on = read_raw_brightness(led)? == 0;
+                led.set_on(on)?;
+                print_info(led)?;
+            }
+            Operation::Trigger(trigger) => {
+                led.set_trigger(trigger)?;
+                println!("Device '{}' now uses trigger '{}'", led.file_name, trigger);
+            }
+            Operation::Blink(delay) => {
+                led.set_trigger("timer")?;
+                // The timer trigger only creates its attributes once it's active
+                let led_path = led.path();
+                let millis = delay.as_millis().to_string();
+                fs::write(led_path.join("delay_on"), &millis)?;
+                fs::write(led_path.join("delay_off"), &millis)?;
+                println!("Device '{}' blinks every {} ms", led.file_name, millis);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_value_specs() {
+        use {Amount::*, ValueSpec::*};
+        let cases = [
+            ("50%", Absolute(Percent(50))),
+            ("200", Absolute(Raw(200))),
+            ("+10%", Increase(Percent(10))),
+            ("10%+", Increase(Percent(10))),
+            ("+5", Increase(Raw(5))),
+            ("10%-", Decrease(Percent(10))),
+            ("5-", Decrease(Raw(5))),
+        ];
+        for (value, spec) in cases {
+            assert_eq!(value.parse(), Ok(spec), "{}", value);
+        }
+        for invalid in ["", "%", "-5", "+", "ten", "5.5%"] {
+            assert_eq!(
+                invalid.parse::<ValueSpec>(),
+                Err(ParseValueError(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn applies_value_specs() {
+        let apply = |value: &str, current| value.parse::<ValueSpec>().unwrap().apply(current, 255);
+        assert_eq!(apply("50%", 0), 128);
+        assert_eq!(apply("300", 0), 255);
+        assert_eq!(apply("+10", 250), 255);
+        assert_eq!(apply("10-", 5), 0);
+        assert_eq!(apply("+10%", 100), 126);
+        assert_eq!(apply("200%+", 100), 255);
+    }
+
+    #[test]
+    fn parses_exact_amounts() {
+        assert_eq!(Amount::parse_exact(" 50% ", 255), Ok(Amount::Percent(50)));
+        assert_eq!(Amount::parse_exact("255", 255), Ok(Amount::Raw(255)));
+        assert_eq!(
+            Amount::parse_exact("256", 255),
+            Err(AmountError::TooHigh(255))
+        );
+        assert_eq!(
+            Amount::parse_exact("101%", 255),
+            Err(AmountError::PercentTooHigh)
+        );
+        assert_eq!(Amount::parse_exact("-5", 255), Err(AmountError::Invalid));
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::{
+    battery, dirs, glob,
+    led::format_brightness,
+    output::{Output, Tone},
+    profiles::LedState,
+    settings::Settings,
+};
+
+/// Name of the file in Glimpse's config directory holding the calibrated levels.
+pub const CALIBRATION_FILE: &str = "min-brightness";
+/// The lowest levels are all tried one by one, above this only every 5% of the range.
+const FINE_LEVELS: u32 = 10;
+
+/// How each LED's brightness is adjusted before it is written.
+///
+/// The lowest usable (non-flickering) brightness of calibrated LEDs is stored as `<led> = <level>`
+/// lines in `$XDG_CONFIG_HOME/glimpse/min-brightness`. Gamma values, for LEDs whose perceived
+/// brightness isn't linear in the raw value, come from the `[gamma]` table of `config.toml`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Calibration {
+    min_brightness: BTreeMap<String, u32>,
+    gamma: BTreeMap<String, f64>,
+}
+
+fn calibration_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(CALIBRATION_FILE))
+}
+
+/// Reads `<led> = <value>` lines from a file in the config directory, skipping invalid ones. A
+/// missing file is the same as an empty one.
+fn read_values<T: FromStr>(file_name: &str) -> io::Result<BTreeMap<String, T>> {
+    let Some(path) = dirs::config_dir().map(|dir| dir.join(file_name)) else {
+        return Ok(BTreeMap::new());
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(contents
+        .lines()
+        .filter_map(|line| {
+            let (led, value) = line.split_once('=')?;
+            Some((led.trim().to_string(), value.trim().parse().ok()?))
+        })
+        .collect())
+}
+
+impl Calibration {
+    /// Loads the calibrated levels, with the gamma values from the settings.
+    pub fn load(settings: &Settings) -> io::Result<Self> {
+        Ok(Self {
+            min_brightness: read_values(CALIBRATION_FILE)?,
+            gamma: settings.gamma.clone(),
+        })
+    }
+
+    fn save(&self) -> io::Result<PathBuf> {
+        let path = calibration_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents: String = self
+            .min_brightness
+            .iter()
+            .map(|(led, level)| format!("{} = {}\n", led, level))
+            .collect();
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Raises a non-zero brightness to the LED's calibrated minimum, if it is below it.
+    pub fn clamp(&self, led: &str, brightness: u32) -> u32 {
+        match self.min_brightness.get(led) {
+            Some(min) if brightness > 0 => brightness.max(*min),
+            _ => brightness,
+        }
+    }
+
+    /// The raw brightness to write for a percentage, corrected with the LED's gamma and raised
+    /// to its calibrated minimum.
+    pub fn brightness_for(&self, led: &str, percentage: u8, max_brightness: u32) -> u32 {
+        let brightness = match self.gamma.get(led) {
+            Some(gamma) if percentage > 0 => {
+                let fraction = f64::from(percentage.min(100)) / 100.0;
+                let brightness = (f64::from(max_brightness) * fraction.powf(*gamma)).round();
+                // Anything that isn't off should stay lit, however steep the curve is
+                (brightness as u32).clamp(1.min(max_brightness), max_brightness)
+            }
+            _ => battery::brightness_for(percentage, max_brightness),
+        };
+        self.clamp(led, brightness)
+    }
+
+    /// How bright a raw brightness looks, as a percentage: the inverse of
+    /// [`Calibration::brightness_for`] before the calibrated minimum is applied.
+    fn perceived_percent(&self, led: &str, brightness: u32, max_brightness: u32) -> f64 {
+        if max_brightness == 0 {
+            return 0.0;
+        }
+        let fraction = f64::from(brightness.min(max_brightness)) / f64::from(max_brightness);
+        let fraction = match self.gamma.get(led) {
+            Some(gamma) => fraction.powf(gamma.recip()),
+            None => fraction,
+        };
+        fraction * 100.0
+    }
+
+    /// The raw brightness after raising or lowering an LED's brightness by `percent`, in steps
+    /// that look even with the LED's gamma.
+    ///
+    /// The brightness always moves by at least one level, so that steps too small to change the
+    /// rounded brightness of an LED with few levels still do something.
+    pub fn step(&self, led: &str, current: u32, max_brightness: u32, percent: i32) -> u32 {
+        let perceived = self.perceived_percent(led, current, max_brightness) + f64::from(percent);
+        let target = self.brightness_for(
+            led,
+            perceived.round().clamp(0.0, 100.0) as u8,
+            max_brightness,
+        );
+        match percent.signum() {
+            1 if target <= current => {
+                self.clamp(led, current.saturating_add(1).min(max_brightness))
+            }
+            // Below the calibrated minimum, the next level down is off
+            -1 if target >= current => match current.saturating_sub(1) {
+                lower if self.clamp(led, lower) > lower => 0,
+                lower => lower,
+            },
+            0 => current,
+            _ => target,
+        }
+    }
+}
+
+/// The levels offered during calibration, lowest first.
+fn candidate_levels(max_brightness: u32) -> Vec<u32> {
+    let mut levels: Vec<u32> = (1..=FINE_LEVELS.min(max_brightness))
+        .chain((1..=20).map(|step| max_brightness * step / 20))
+        .filter(|level| *level > 0)
+        .collect();
+    levels.sort_unstable();
+    levels.dedup();
+    levels
+}
+
+/// Steps an LED through its lowest brightness levels until the user confirms one looks right,
+/// and saves that level as the LED's minimum.
+pub fn run(led_name: String) -> Result<()> {
+    let output = Output::detect();
+    let mut leds = glob::expand(std::slice::from_ref(&led_name))?;
+    if leds.len() != 1 {
+        return Err(eyre!(
+            "\"{}\" matches {} LEDs, calibrate one at a time",
+            led_name,
+            leds.len()
+        ));
+    }
+    let mut led = leds.remove(0);
+    let max_brightness = led.max_brightness()?;
+    let original = LedState::capture(&mut led)?;
+    println!(
+        "Calibrating {}. Answer y once the LED is lit steadily without flickering.",
+        output.paint(&led.file_name, Tone::Name)
+    );
+
+    let stdin = io::stdin();
+    let mut chosen = None;
+    for level in candidate_levels(max_brightness) {
+        led.set_brightness(level)?;
+        print!(
+            "Level {}: usable? [y/N/q] ",
+            output.paint(&format_brightness(level, max_brightness), Tone::Value)
+        );
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        stdin.lock().read_line(&mut answer)?;
+        match answer.trim() {
+            "y" | "Y" => {
+                chosen = Some(level);
+                break;
+            }
+            "q" | "Q" => break,
+            _ => {}
+        }
+    }
+    original.restore(&mut led)?;
+
+    let Some(level) = chosen else {
+        println!("No level chosen, calibration unchanged");
+        return Ok(());
+    };
+    // Only the levels are saved, so the gamma values aren't needed
+    let mut calibration = Calibration::load(&Settings::default())?;
+    calibration
+        .min_brightness
+        .insert(led.file_name.clone(), level);
+    let path = calibration.save()?;
+    println!(
+        "Saved minimum brightness {} for {} to {}",
+        output.paint(&level.to_string(), Tone::Value),
+        output.paint(&led.file_name, Tone::Name),
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_gamma(led: &str, gamma: f64) -> Calibration {
+        Calibration {
+            gamma: BTreeMap::from([(led.to_string(), gamma)]),
+            ..Calibration::default()
+        }
+    }
+
+    #[test]
+    fn corrects_with_gamma() {
+        let calibration = with_gamma("kbd_backlight", 2.0);
+        assert_eq!(calibration.brightness_for("kbd_backlight", 50, 100), 25);
+        assert_eq!(calibration.brightness_for("kbd_backlight", 100, 100), 100);
+        assert_eq!(calibration.brightness_for("kbd_backlight", 0, 100), 0);
+        // Steep curves still leave the LED lit
+        assert_eq!(calibration.brightness_for("kbd_backlight", 1, 100), 1);
+        assert_eq!(calibration.brightness_for("capslock", 50, 100), 50);
+        let perceived = calibration.perceived_percent("kbd_backlight", 25, 100);
+        assert!((perceived - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn steps_evenly() {
+        let calibration = with_gamma("kbd_backlight", 2.0);
+        let mut brightness = 0;
+        let mut levels = Vec::new();
+        for _ in 0..10 {
+            brightness = calibration.step("kbd_backlight", brightness, 100, 10);
+            levels.push(brightness);
+        }
+        assert_eq!(levels, [1, 4, 9, 16, 25, 36, 49, 64, 81, 100]);
+        assert_eq!(calibration.step("kbd_backlight", 100, 100, 10), 100);
+        assert_eq!(calibration.step("kbd_backlight", 25, 100, -10), 16);
+        assert_eq!(calibration.step("capslock", 50, 100, 10), 60);
+        assert_eq!(calibration.step("capslock", 5, 100, -10), 0);
+        assert_eq!(calibration.step("capslock", 42, 100, 0), 42);
+    }
+
+    #[test]
+    fn steps_through_few_levels() {
+        let calibration = Calibration::default();
+        assert_eq!(calibration.step("capslock", 0, 1, 10), 1);
+        assert_eq!(calibration.step("capslock", 1, 1, -10), 0);
+        assert_eq!(calibration.step("capslock", 1, 3, 10), 2);
+        assert_eq!(calibration.step("capslock", 0, 0, 10), 0);
+    }
+
+    #[test]
+    fn steps_past_the_minimum() {
+        let calibration = Calibration {
+            min_brightness: BTreeMap::from([("kbd_backlight".to_string(), 30)]),
+            ..Calibration::default()
+        };
+        assert_eq!(calibration.step("kbd_backlight", 0, 100, 10), 30);
+        assert_eq!(calibration.step("kbd_backlight", 30, 100, -10), 0);
+        assert_eq!(calibration.step("kbd_backlight", 50, 100, -10), 40);
+        assert_eq!(calibration.step("kbd_backlight", 31, 100, -1), 30);
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::SystemTime;
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Block, Chart, Dataset, GraphType, LegendPosition, Paragraph},
+};
+
+use crate::{
+    LED,
+    history::BrightnessHistory,
+    theme::{self, Role, Theme},
+};
+
+/// How many seconds of history are visible in the chart.
+const CHART_WINDOW_SECS: f64 = 60.0;
+
+/// State of the realtime chart comparing the brightness of several LEDs.
+#[derive(Debug, Default)]
+pub struct ChartView {
+    /// Whether the chart replaces the detail pane
+    pub visible: bool,
+    /// File names of the LEDs plotted in the chart
+    plotted: Vec<String>,
+    /// When the chart was paused, if it is paused
+    paused_at: Option<SystemTime>,
+}
+
+impl ChartView {
+    pub fn is_plotted(&self, file_name: &str) -> bool {
+        self.plotted.iter().any(|plotted| plotted == file_name)
+    }
+
+    /// Adds the LED to the chart, or removes it if it is already plotted.
+    pub fn toggle_plotted(&mut self, file_name: &str) {
+        if self.is_plotted(file_name) {
+            self.plotted.retain(|plotted| plotted != file_name);
+        } else {
+            self.plotted.push(file_name.to_string());
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Freezes the chart at the current moment, or resumes following live data.
+    pub fn toggle_paused(&mut self) {
+        self.paused_at = match self.paused_at {
+            Some(_) => None,
+            None => Some(SystemTime::now()),
+        };
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        block: Block,
+        history: &BrightnessHistory,
+        leds: &[LED],
+    ) {
+        let end = self.paused_at.unwrap_or_else(SystemTime::now);
+        let points: Vec<Vec<(f64, f64)>> = self
+            .plotted
+            .iter()
+            .map(|file_name| {
+                history
+                    .samples(file_name)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|sample| {
+                        let age = end.duration_since(sample.timestamp).ok()?.as_secs_f64();
+                        (age <= CHART_WINDOW_SECS).then_some((-age, f64::from(sample.brightness)))
+                    })
+                    .collect()
+            })
+            .collect();
+        let datasets = self
+            .plotted
+            .iter()
+            .zip(&points)
+            .enumerate()
+            .map(|(i, (file_name, data))| {
+                let name = leds
+                    .iter()
+                    .find(|led| &led.file_name == file_name)
+                    .map_or(file_name.as_str(), |led| led.name.as_str());
+                Dataset::default()
+                    .name(name.to_string())
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Theme::current().series(i))
+                    .data(data)
+            })
+            .collect();
+
+        let mut title = vec![Span::styled("Brightness chart", theme::style(Role::Title))];
+        if self.is_paused() {
+            title.push(Span::styled(" (paused)", theme::style(Role::Warning)));
+        }
+        let block = block.title(Line::from(title).centered());
+        if self.plotted.is_empty() {
+            frame.render_widget(
+                Paragraph::new("Press m on an LED to add it to the chart").block(block),
+                area,
+            );
+            return;
+        }
+        // Fit the highest maximum of the plotted LEDs
+        let max_brightness = self
+            .plotted
+            .iter()
+            .filter_map(|file_name| leds.iter().find(|led| &led.file_name == file_name))
+            .map(|led| led.max_brightness)
+            .max()
+            .unwrap_or(255)
+            .max(1);
+        let chart = Chart::new(datasets)
+            .block(block)
+            .legend_position(Some(LegendPosition::TopLeft))
+            .hidden_legend_constraints((Constraint::Percentage(60), Constraint::Percentage(60)))
+            .x_axis(
+                Axis::default()
+                    .title("seconds")
+                    .bounds([-CHART_WINDOW_SECS, 0.0])
+                    .labels(["-60", "-30", "0"]),
+            )
+            .y_axis(
+                Axis::default()
+                    .title("brightness")
+                    .bounds([0.0, f64::from(max_brightness)])
+                    .labels([
+                        "0".to_string(),
+                        (max_brightness / 2).to_string(),
+                        max_brightness.to_string(),
+                    ]),
+            );
+        frame.render_widget(chart, area);
+    }
+}
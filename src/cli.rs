@@ -0,0 +1,960 @@
+use std::{path::PathBuf, time::Duration};
+
+use thiserror::Error;
+
+use crate::{
+    alerts::{AlertRule, ParseAlertError},
+    brightnessctl::{DEFAULT_BLINK_DELAY, Operation, ParseValueError},
+    completions::{ParseShellError, Shell},
+    countdown::{ParseDurationError, parse_duration},
+    daemon::DEFAULT_DAEMON_INTERVAL,
+    dbus::Bus,
+    helper::HelperRequest,
+    mapping::{EventMapping, ParseMappingError},
+    multicolor::{ParseColorError, Rgb},
+    presets::{ParsePresetError, Preset},
+    sweep::DEFAULT_SWEEP_STEPS,
+    uleds::DEFAULT_MAX_BRIGHTNESS,
+    watch::DEFAULT_WATCH_INTERVAL,
+};
+
+/// Number of reads and writes per LED done by `glimpse bench` by default.
+const DEFAULT_BENCH_ITERATIONS: usize = 200;
+
+/// Printed by `glimpse --help`.
+pub const USAGE: &str = "\
+Usage: glimpse [options] [command]
+
+Without a command, Glimpse opens the TUI. LEDs can be given by file name, alias, function
+(e.g. capslock), unambiguous prefix or glob pattern.
+
+Commands:
+  list [--json] [--filter-regex <regex>]   Print the names of the LEDs
+  get <led> [--json]                       Print the brightness of an LED
+  set <led> <value>                        Set the brightness: 128, 50%, +10%, 10%-, ...
+  toggle <led>                             Turn an LED off if it's on, or fully on
+  trigger <led> <trigger>                  Make a trigger drive an LED (none to stop)
+  blink <led> [--delay <ms>]               Blink an LED with the timer trigger
+  info [<led>]                             Print the state of LEDs
+  max <led>                                Print the maximum brightness of an LED
+  set-color <led> <color>                  Set a multicolor LED to a name or #rrggbb
+  calibrate <led>                          Find the lowest usable brightness of an LED
+  test <led>... [--steps <n>]              Sweep LEDs through their brightness range
+  bench [<led>...] [-n <n>] [--dry-run]    Measure sysfs read and write latency
+  countdown --led <led> <duration>         Count down a timer on an LED, e.g. 25m
+  off (<led>... | --all)                   Turn LEDs off
+  mute, unmute                             Turn every LED off, and back on as it was
+  watch [<led>...] [--interval <ms>]       Print a JSON line for every change to the LEDs
+  daemon [--profile <name>] [--dbus session|system] [--interval <ms>]
+                                           Keep the LEDs set to a profile, or offer D-Bus
+  export [<led>...]                        Print the state of LEDs as JSON
+  import <file>                            Set the LEDs to the state in an exported file
+  export-tmpfiles [<led>...]               Print tmpfiles.d entries restoring the LEDs
+  config (validate | edit <file>)          Check or edit a config file
+  profile (save <name> | apply <name> | list)
+                                           Save or apply the state of every LED
+  virtual <name> [--max-brightness <n>]    Create a virtual LED until interrupted
+  play <file>                              Replay a recorded TUI session
+  completions (bash | zsh | fish)          Print a shell completion script
+  setup-permissions [--group <group>] [--install]
+                                           Print or install a udev rule for LED access
+
+brightnessctl-style commands (s, g, i, m and -l also work):
+  -d, --device <led>                       The LED, instead of after the command
+  --json                                   Print get as JSON
+  --dry-run                                Only print which LEDs a pattern matches
+
+TUI options:
+  --alert <led>[@on|off|change]=<action>   Ring the bell, show a popup or run a hook
+  --map <condition>=<led>                  Light an LED, e.g. battery<20 or netdev:wlan0:down
+  --battery-led <led>                      Show the battery level on an LED
+  --preset [<led>@]<key>=<percent>|<color> Bind a preset to a number key
+  --pomodoro-hook <command>                Run a command when a pomodoro phase starts
+  --dnd-led <led>                          An LED do-not-disturb turns off
+  --presentation-led <led>                 An LED presentation mode turns off
+  --presentation-duration <duration>       Leave presentation mode by itself after a while
+  --record <file>                          Record the session to play back later
+  --max-fps <n>                            Redraw at most this often per second
+  --tick-rate <ms>                         Re-read the LEDs this often
+  --screenshot                             Print one frame as text instead
+  --escalate                               Retry refused writes as root through pkexec
+
+Options:
+  -v, --verbose                            Log more to the log file (-vv for even more)
+  -h, --help                               Print this help
+";
+
+/// A non-interactive command. Without one, Glimpse launches the TUI.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Measure sysfs read and write latency of LEDs
+    Bench {
+        iterations: usize,
+        /// File names or glob patterns of the LEDs to measure (all LEDs if empty)
+        leds: Vec<String>,
+        /// Only show which LEDs would be measured
+        dry_run: bool,
+    },
+    /// Count down a timer on an LED
+    Countdown { led: String, duration: Duration },
+    /// Replay a recorded TUI session
+    Play { path: PathBuf },
+    /// Print the names of the LEDs
+    List {
+        filter_regex: Option<String>,
+        json: bool,
+    },
+    /// Sweep LEDs through their brightness range, checking the values read back
+    Test { leds: Vec<String>, steps: u32 },
+    /// Find the lowest usable brightness of an LED
+    Calibrate { led: String },
+    /// Turn LEDs off without remembering their state
+    Off {
+        /// File names or glob patterns of the LEDs to turn off (all LEDs, with `--all`, if
+        /// empty)
+        leds: Vec<String>,
+    },
+    /// Turn every LED off, remembering their state
+    Mute,
+    /// Restore the LEDs to how they were before muting
+    Unmute,
+    /// Print tmpfiles.d entries that restore the current LED state at boot
+    ExportTmpfiles { leds: Vec<String> },
+    /// Print a JSON line for every change to the LEDs until interrupted
+    Watch {
+        /// File names or glob patterns of the LEDs to watch (all LEDs if empty)
+        leds: Vec<String>,
+        interval: Duration,
+    },
+    /// Keep the LEDs set to a profile, reapplying it after suspend and hotplug, and/or offer
+    /// the D-Bus service
+    Daemon {
+        profile: Option<String>,
+        bus: Option<Bus>,
+        interval: Duration,
+    },
+    /// Print the state of LEDs as JSON
+    Export { leds: Vec<String> },
+    /// Set the LEDs to the state in a file written by `glimpse export`
+    Import { path: PathBuf },
+    /// Report problems in the config files
+    ConfigValidate,
+    /// Edit a config file, checking it afterwards
+    ConfigEdit { file: String },
+    /// Save the state of every LED as a named profile
+    ProfileSave { name: String },
+    /// Set the LEDs to the state saved in a profile
+    ProfileApply { name: String },
+    /// Print the names of the saved profiles
+    ProfileList,
+    /// Create a virtual LED through uleds until interrupted
+    Virtual { name: String, max_brightness: u32 },
+    /// Set the color of a multicolor LED
+    SetColor { led: String, color: Rgb },
+    /// Print a shell completion script
+    Completions { shell: Shell },
+    /// Print or install a udev rule letting a group change LEDs
+    SetupPermissions {
+        /// The group given access (`leds`, or the user's group, if not given)
+        group: Option<String>,
+        install: bool,
+    },
+    /// Write to an LED on behalf of a TUI without permission to, when run through pkexec
+    Helper { request: HelperRequest },
+    /// Print the usage
+    Help,
+    /// A brightnessctl-style operation, e.g. `s 50%` or `g`
+    Brightness {
+        operation: Operation,
+        /// The device given with `-d`, possibly a glob pattern
+        device: Option<String>,
+        /// Print `get` results as JSON
+        json: bool,
+        /// Only show which LEDs the device expands to
+        dry_run: bool,
+    },
+}
+
+/// Options passed on the command line.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: Option<Command>,
+    pub alerts: Vec<AlertRule>,
+    pub mappings: Vec<EventMapping>,
+    /// LED whose brightness should follow the battery percentage
+    pub battery_led: Option<String>,
+    /// Command run when a pomodoro phase starts, instead of a desktop notification
+    pub pomodoro_hook: Option<String>,
+    /// LEDs (by name or function) that do-not-disturb turns off
+    pub dnd_leds: Vec<String>,
+    /// LEDs (by name or function) that presentation mode turns off
+    pub presentation_leds: Vec<String>,
+    /// How long presentation mode lasts before restoring the LEDs by itself
+    pub presentation_duration: Option<Duration>,
+    /// File to record the TUI session to
+    pub record: Option<PathBuf>,
+    /// Brightness presets bound to number keys
+    pub presets: Vec<Preset>,
+    /// Upper limit on how often the TUI is redrawn per second
+    pub max_fps: Option<u32>,
+    /// How often LEDs are re-read while the TUI is active, from `--tick-rate` in milliseconds
+    pub tick_interval: Option<Duration>,
+    /// Print one frame of the TUI as text instead of running it
+    pub screenshot: bool,
+    /// Retry writes that aren't permitted as root through pkexec
+    pub escalate: bool,
+    /// How many times `-v` was given, for diagnostics in the log file
+    pub verbosity: u8,
+}
+
+#[derive(Debug, Error)]
+pub enum ArgsError {
+    #[error("missing value for {0}")]
+    MissingValue(&'static str),
+    #[error("invalid alert rule \"{0}\": {1}")]
+    InvalidAlert(String, ParseAlertError),
+    #[error("invalid event mapping \"{0}\": {1}")]
+    InvalidMapping(String, ParseMappingError),
+    #[error("invalid preset \"{0}\": {1}")]
+    InvalidPreset(String, ParsePresetError),
+    #[error(transparent)]
+    InvalidBrightness(#[from] ParseValueError),
+    #[error(transparent)]
+    InvalidColor(#[from] ParseColorError),
+    #[error(transparent)]
+    InvalidShell(#[from] ParseShellError),
+    #[error(transparent)]
+    InvalidDuration(#[from] ParseDurationError),
+    #[error("invalid value \"{1}\" for {0}")]
+    InvalidValue(&'static str, String),
+    #[error("unexpected argument \"{0}\"")]
+    Unexpected(String),
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Self::default();
+        let mut device = None;
+        let mut json = false;
+        let mut dry_run = false;
+        let mut blink_delay = None;
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    return Ok(Self {
+                        command: Some(Command::Help),
+                        ..Self::default()
+                    });
+                }
+                "-d" | "--device" => {
+                    let value = args.next().ok_or(ArgsError::MissingValue("--device"))?;
+                    // Don't let it silently replace an LED already given after the operation
+                    if device.replace(value).is_some() {
+                        return Err(ArgsError::Unexpected(arg));
+                    }
+                }
+                "-l" | "--list" if parsed.command.is_none() => {
+                    parsed.command = Some(Command::Brightness {
+                        operation: Operation::Info,
+                        device: None,
+                        json: false,
+                        dry_run: false,
+                    });
+                }
+                "--json" => json = true,
+                "--dry-run" => dry_run = true,
+                "--delay" => {
+                    let value = args.next().ok_or(ArgsError::MissingValue("--delay"))?;
+                    blink_delay = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|ms| *ms > 0)
+                            .map(Duration::from_millis)
+                            .ok_or(ArgsError::InvalidValue("--delay", value))?,
+                    );
+                }
+                "i" | "info" | "g" | "get" | "m" | "max" | "s" | "set" | "toggle" | "trigger"
+                | "blink"
+                    if parsed.command.is_none() =>
+                {
+                    let operation = match arg.as_str() {
+                        "s" | "set" => {
+                            let value = args.next().ok_or(ArgsError::MissingValue("brightness"))?;
+                            // Either `set <value>` like brightnessctl, or `set <led> <value>` when
+                            // the LED isn't given with -d and something follows
+                            match value.parse() {
+                                Ok(value) => Operation::Set(value),
+                                Err(error) => match args.next_if(|next| !next.starts_with('-')) {
+                                    Some(next) if device.is_none() => {
+                                        device = Some(value);
+                                        Operation::Set(next.parse()?)
+                                    }
+                                    _ => return Err(error.into()),
+                                },
+                            }
+                        }
+                        "trigger" => {
+                            let value = args.next().ok_or(ArgsError::MissingValue("trigger"))?;
+                            // Either `trigger <trigger>` with -d, or `trigger <led> <trigger>`
+                            match args.next_if(|next| !next.starts_with('-')) {
+                                Some(trigger) if device.is_none() => {
+                                    device = Some(value);
+                                    Operation::Trigger(trigger)
+                                }
+                                Some(trigger) => return Err(ArgsError::Unexpected(trigger)),
+                                None => Operation::Trigger(value),
+                            }
+                        }
+                        "blink" => Operation::Blink(DEFAULT_BLINK_DELAY),
+                        "i" | "info" => Operation::Info,
+                        "g" | "get" => Operation::Get,
+                        "m" | "max" => Operation::Max,
+                        _ => Operation::Toggle,
+                    };
+                    parsed.command = Some(Command::Brightness {
+                        operation,
+                        device: None,
+                        json: false,
+                        dry_run: false,
+                    });
+                }
+                "--alert" => {
+                    let rule = args.next().ok_or(ArgsError::MissingValue("--alert"))?;
+                    parsed.alerts.push(
+                        rule.parse()
+                            .map_err(|e| ArgsError::InvalidAlert(rule.clone(), e))?,
+                    );
+                }
+                "--map" => {
+                    let mapping = args.next().ok_or(ArgsError::MissingValue("--map"))?;
+                    parsed.mappings.push(
+                        mapping
+                            .parse()
+                            .map_err(|e| ArgsError::InvalidMapping(mapping.clone(), e))?,
+                    );
+                }
+                "--battery-led" => {
+                    parsed.battery_led = Some(
+                        args.next()
+                            .ok_or(ArgsError::MissingValue("--battery-led"))?,
+                    );
+                }
+                "--pomodoro-hook" => {
+                    parsed.pomodoro_hook = Some(
+                        args.next()
+                            .ok_or(ArgsError::MissingValue("--pomodoro-hook"))?,
+                    );
+                }
+                "--dnd-led" => {
+                    parsed
+                        .dnd_leds
+                        .push(args.next().ok_or(ArgsError::MissingValue("--dnd-led"))?);
+                }
+                "--presentation-led" => {
+                    parsed.presentation_leds.push(
+                        args.next()
+                            .ok_or(ArgsError::MissingValue("--presentation-led"))?,
+                    );
+                }
+                "--presentation-duration" => {
+                    let value = args
+                        .next()
+                        .ok_or(ArgsError::MissingValue("--presentation-duration"))?;
+                    parsed.presentation_duration = Some(parse_duration(&value)?);
+                }
+                "--preset" => {
+                    let preset = args.next().ok_or(ArgsError::MissingValue("--preset"))?;
+                    parsed.presets.push(
+                        preset
+                            .parse()
+                            .map_err(|e| ArgsError::InvalidPreset(preset.clone(), e))?,
+                    );
+                }
+                "--max-fps" => {
+                    let value = args.next().ok_or(ArgsError::MissingValue("--max-fps"))?;
+                    parsed.max_fps = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|fps| *fps > 0)
+                            .ok_or(ArgsError::InvalidValue("--max-fps", value))?,
+                    );
+                }
+                "--tick-rate" => {
+                    let value = args.next().ok_or(ArgsError::MissingValue("--tick-rate"))?;
+                    parsed.tick_interval = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|ms| *ms > 0)
+                            .map(Duration::from_millis)
+                            .ok_or(ArgsError::InvalidValue("--tick-rate", value))?,
+                    );
+                }
+                "--screenshot" => parsed.screenshot = true,
+                "--escalate" => parsed.escalate = true,
+                "-v" | "--verbose" => parsed.verbosity = parsed.verbosity.saturating_add(1),
+                "-vv" => parsed.verbosity = parsed.verbosity.saturating_add(2),
+                "--helper" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_helper(&mut args)?);
+                }
+                "--record" => {
+                    parsed.record = Some(
+                        args.next()
+                            .ok_or(ArgsError::MissingValue("--record"))?
+                            .into(),
+                    );
+                }
+                "play" if parsed.command.is_none() => {
+                    let path = args.next().ok_or(ArgsError::MissingValue("recording"))?;
+                    parsed.command = Some(Command::Play { path: path.into() });
+                }
+                "test" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_test(&mut args)?);
+                }
+                "calibrate" if parsed.command.is_none() => {
+                    let led = args.next().ok_or(ArgsError::MissingValue("LED"))?;
+                    parsed.command = Some(Command::Calibrate { led });
+                }
+                "config" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_config(&mut args)?);
+                }
+                "profile" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_profile(&mut args)?);
+                }
+                "virtual" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_virtual(&mut args)?);
+                }
+                "set-color" if parsed.command.is_none() => {
+                    let led = args.next().ok_or(ArgsError::MissingValue("LED"))?;
+                    let color = args
+                        .next()
+                        .ok_or(ArgsError::MissingValue("color"))?
+                        .parse()?;
+                    parsed.command = Some(Command::SetColor { led, color });
+                }
+                "completions" if parsed.command.is_none() => {
+                    let shell = args
+                        .next()
+                        .ok_or(ArgsError::MissingValue("shell"))?
+                        .parse()?;
+                    parsed.command = Some(Command::Completions { shell });
+                }
+                "off" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_off(&mut args)?);
+                }
+                "mute" if parsed.command.is_none() => parsed.command = Some(Command::Mute),
+                "unmute" if parsed.command.is_none() => parsed.command = Some(Command::Unmute),
+                "export-tmpfiles" if parsed.command.is_none() => {
+                    let leds: Vec<String> = args.by_ref().collect();
+                    if let Some(flag) = leds.iter().find(|led| led.starts_with('-')) {
+                        return Err(ArgsError::Unexpected(flag.clone()));
+                    }
+                    parsed.command = Some(Command::ExportTmpfiles { leds });
+                }
+                "watch" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_watch(&mut args)?);
+                }
+                "daemon" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_daemon(&mut args)?);
+                }
+                "export" if parsed.command.is_none() => {
+                    let leds: Vec<String> = args.by_ref().collect();
+                    if let Some(flag) = leds.iter().find(|led| led.starts_with('-')) {
+                        return Err(ArgsError::Unexpected(flag.clone()));
+                    }
+                    parsed.command = Some(Command::Export { leds });
+                }
+                "import" if parsed.command.is_none() => {
+                    let path = args.next().ok_or(ArgsError::MissingValue("state file"))?;
+                    parsed.command = Some(Command::Import { path: path.into() });
+                }
+                "setup-permissions" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_setup_permissions(&mut args)?);
+                }
+                "list" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_list(&mut args)?);
+                }
+                "bench" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_bench(&mut args)?);
+                }
+                "countdown" if parsed.command.is_none() => {
+                    parsed.command = Some(parse_countdown(&mut args)?);
+                }
+                // The LED can be given after the operation instead of with -d
+                _ if device.is_none()
+                    && !arg.starts_with('-')
+                    && matches!(parsed.command, Some(Command::Brightness { .. })) =>
+                {
+                    device = Some(arg);
+                }
+                _ => return Err(ArgsError::Unexpected(arg)),
+            }
+        }
+        if let Some(Command::Brightness {
+            operation,
+            device: command_device,
+            json: command_json,
+            dry_run: command_dry_run,
+        }) = &mut parsed.command
+        {
+            *command_device = device;
+            if json && *operation != Operation::Get {
+                return Err(ArgsError::Unexpected("--json".to_string()));
+            }
+            *command_json = json;
+            if dry_run && matches!(operation, Operation::Info | Operation::Get | Operation::Max) {
+                return Err(ArgsError::Unexpected("--dry-run".to_string()));
+            }
+            *command_dry_run = dry_run;
+            match (operation, blink_delay) {
+                (Operation::Blink(delay), Some(blink_delay)) => *delay = blink_delay,
+                (_, Some(_)) => return Err(ArgsError::Unexpected("--delay".to_string())),
+                _ => {}
+            }
+        } else if device.is_some() {
+            return Err(ArgsError::Unexpected("--device".to_string()));
+        } else if json {
+            return Err(ArgsError::Unexpected("--json".to_string()));
+        } else if dry_run {
+            return Err(ArgsError::Unexpected("--dry-run".to_string()));
+        } else if blink_delay.is_some() {
+            return Err(ArgsError::Unexpected("--delay".to_string()));
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_bench(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut iterations = DEFAULT_BENCH_ITERATIONS;
+    let mut leds = Vec::new();
+    let mut dry_run = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" | "-n" => {
+                let value = args.next().ok_or(ArgsError::MissingValue("--iterations"))?;
+                iterations = value
+                    .parse()
+                    .ok()
+                    .filter(|iterations| *iterations > 0)
+                    .ok_or(ArgsError::InvalidValue("--iterations", value))?;
+            }
+            "--dry-run" => dry_run = true,
+            _ if arg.starts_with('-') => return Err(ArgsError::Unexpected(arg)),
+            _ => leds.push(arg),
+        }
+    }
+    Ok(Command::Bench {
+        iterations,
+        leds,
+        dry_run,
+    })
+}
+
+fn parse_helper(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let operation = args.next().ok_or(ArgsError::MissingValue("--helper"))?;
+    let led = args.next().ok_or(ArgsError::MissingValue("LED"))?;
+    let value = args.next().ok_or(ArgsError::MissingValue("value"))?;
+    let request = match operation.as_str() {
+        "set" => HelperRequest::Brightness {
+            led,
+            brightness: value
+                .parse()
+                .map_err(|_| ArgsError::InvalidValue("brightness", value))?,
+        },
+        "trigger" => HelperRequest::Trigger {
+            led,
+            trigger: value,
+        },
+        _ => return Err(ArgsError::InvalidValue("--helper", operation)),
+    };
+    if let Some(arg) = args.next() {
+        return Err(ArgsError::Unexpected(arg));
+    }
+    Ok(Command::Helper { request })
+}
+
+fn parse_test(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut steps = DEFAULT_SWEEP_STEPS;
+    let mut leds = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--steps" => {
+                let value = args.next().ok_or(ArgsError::MissingValue("--steps"))?;
+                steps = value
+                    .parse()
+                    .ok()
+                    .filter(|steps| *steps > 0)
+                    .ok_or(ArgsError::InvalidValue("--steps", value))?;
+            }
+            _ if arg.starts_with('-') => return Err(ArgsError::Unexpected(arg)),
+            _ => leds.push(arg),
+        }
+    }
+    if leds.is_empty() {
+        return Err(ArgsError::MissingValue("LED"));
+    }
+    Ok(Command::Test { leds, steps })
+}
+
+fn parse_watch(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut interval = DEFAULT_WATCH_INTERVAL;
+    let mut leds = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--interval" => {
+                let value = args.next().ok_or(ArgsError::MissingValue("--interval"))?;
+                interval = Duration::from_millis(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|ms| *ms > 0)
+                        .ok_or(ArgsError::InvalidValue("--interval", value))?,
+                );
+            }
+            _ if arg.starts_with('-') => return Err(ArgsError::Unexpected(arg)),
+            _ => leds.push(arg),
+        }
+    }
+    Ok(Command::Watch { leds, interval })
+}
+
+fn parse_daemon(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut profile = None;
+    let mut bus = None;
+    let mut interval = DEFAULT_DAEMON_INTERVAL;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => {
+                profile = Some(args.next().ok_or(ArgsError::MissingValue("--profile"))?);
+            }
+            "--dbus" => {
+                let value = args.next().ok_or(ArgsError::MissingValue("--dbus"))?;
+                bus = Some(Bus::from_name(&value).ok_or(ArgsError::InvalidValue("--dbus", value))?);
+            }
+            "--interval" => {
+                let value = args.next().ok_or(ArgsError::MissingValue("--interval"))?;
+                interval = Duration::from_millis(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|ms| *ms > 0)
+                        .ok_or(ArgsError::InvalidValue("--interval", value))?,
+                );
+            }
+            _ => return Err(ArgsError::Unexpected(arg)),
+        }
+    }
+    // Without either, the daemon would have nothing to do
+    if profile.is_none() && bus.is_none() {
+        return Err(ArgsError::MissingValue("--profile or --dbus"));
+    }
+    Ok(Command::Daemon {
+        profile,
+        bus,
+        interval,
+    })
+}
+
+fn parse_off(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut all = false;
+    let mut leds = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--all" if leds.is_empty() => all = true,
+            _ if arg.starts_with('-') || all => return Err(ArgsError::Unexpected(arg)),
+            _ => leds.push(arg),
+        }
+    }
+    // Turning off everything has to be asked for explicitly
+    if !all && leds.is_empty() {
+        return Err(ArgsError::MissingValue("LED or --all"));
+    }
+    Ok(Command::Off { leds })
+}
+
+fn parse_list(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut filter_regex = None;
+    let mut json = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--filter-regex" => {
+                filter_regex = Some(
+                    args.next()
+                        .ok_or(ArgsError::MissingValue("--filter-regex"))?,
+                );
+            }
+            _ => return Err(ArgsError::Unexpected(arg)),
+        }
+    }
+    Ok(Command::List { filter_regex, json })
+}
+
+fn parse_setup_permissions(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut group = None;
+    let mut install = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--group" => group = Some(args.next().ok_or(ArgsError::MissingValue("--group"))?),
+            "--install" => install = true,
+            _ => return Err(ArgsError::Unexpected(arg)),
+        }
+    }
+    Ok(Command::SetupPermissions { group, install })
+}
+
+fn parse_countdown(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut led = None;
+    let mut duration = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--led" => led = Some(args.next().ok_or(ArgsError::MissingValue("--led"))?),
+            _ if arg.starts_with('-') || duration.is_some() => {
+                return Err(ArgsError::Unexpected(arg));
+            }
+            _ => {
+                duration = Some(parse_duration(&arg)?);
+            }
+        }
+    }
+    Ok(Command::Countdown {
+        led: led.ok_or(ArgsError::MissingValue("--led"))?,
+        duration: duration.ok_or(ArgsError::MissingValue("duration"))?,
+    })
+}
+
+fn parse_config(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let action = args
+        .next()
+        .ok_or(ArgsError::MissingValue("config action"))?;
+    let command = match action.as_str() {
+        "validate" => Command::ConfigValidate,
+        "edit" => Command::ConfigEdit {
+            file: args.next().ok_or(ArgsError::MissingValue("config file"))?,
+        },
+        _ => return Err(ArgsError::Unexpected(action)),
+    };
+    match args.next() {
+        Some(arg) => Err(ArgsError::Unexpected(arg)),
+        None => Ok(command),
+    }
+}
+
+fn parse_profile(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let action = args
+        .next()
+        .ok_or(ArgsError::MissingValue("profile action"))?;
+    let command = match action.as_str() {
+        "save" => Command::ProfileSave {
+            name: args.next().ok_or(ArgsError::MissingValue("profile name"))?,
+        },
+        "apply" => Command::ProfileApply {
+            name: args.next().ok_or(ArgsError::MissingValue("profile name"))?,
+        },
+        "list" => Command::ProfileList,
+        _ => return Err(ArgsError::Unexpected(action)),
+    };
+    match args.next() {
+        Some(arg) => Err(ArgsError::Unexpected(arg)),
+        None => Ok(command),
+    }
+}
+
+fn parse_virtual(args: &mut impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut name = None;
+    let mut max_brightness = DEFAULT_MAX_BRIGHTNESS;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-brightness" => {
+                let value = args
+                    .next()
+                    .ok_or(ArgsError::MissingValue("--max-brightness"))?;
+                max_brightness = value
+                    .parse()
+                    .ok()
+                    .filter(|max_brightness| *max_brightness > 0)
+                    .ok_or(ArgsError::InvalidValue("--max-brightness", value))?;
+            }
+            _ if arg.starts_with('-') || name.is_some() => return Err(ArgsError::Unexpected(arg)),
+            _ => name = Some(arg),
+        }
+    }
+    Ok(Command::Virtual {
+        name: name.ok_or(ArgsError::MissingValue("LED name"))?,
+        max_brightness,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brightnessctl::{Amount, ValueSpec};
+
+    fn parse(args: &str) -> Result<Args, ArgsError> {
+        Args::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    fn brightness(operation: Operation, device: Option<&str>) -> Command {
+        Command::Brightness {
+            operation,
+            device: device.map(str::to_string),
+            json: false,
+            dry_run: false,
+        }
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(parse("").unwrap().command, None);
+        assert_eq!(
+            parse("list").unwrap().command,
+            Some(Command::List {
+                filter_regex: None,
+                json: false,
+            })
+        );
+        assert_eq!(
+            parse("get capslock").unwrap().command,
+            Some(brightness(Operation::Get, Some("capslock")))
+        );
+        assert_eq!(
+            parse("set capslock 50%").unwrap().command,
+            Some(brightness(
+                Operation::Set(ValueSpec::Absolute(Amount::Percent(50))),
+                Some("capslock")
+            ))
+        );
+        assert_eq!(
+            parse("toggle capslock").unwrap().command,
+            Some(brightness(Operation::Toggle, Some("capslock")))
+        );
+        assert_eq!(
+            parse("trigger capslock timer").unwrap().command,
+            Some(brightness(
+                Operation::Trigger("timer".to_string()),
+                Some("capslock")
+            ))
+        );
+        assert_eq!(
+            parse("get capslock --json").unwrap().command,
+            Some(Command::Brightness {
+                operation: Operation::Get,
+                device: Some("capslock".to_string()),
+                json: true,
+                dry_run: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_brightnessctl_forms() {
+        assert_eq!(
+            parse("-l").unwrap().command,
+            Some(brightness(Operation::Info, None))
+        );
+        assert_eq!(
+            parse("g").unwrap().command,
+            Some(brightness(Operation::Get, None))
+        );
+        assert_eq!(
+            parse("-d kbd s +10%").unwrap().command,
+            Some(brightness(
+                Operation::Set(ValueSpec::Increase(Amount::Percent(10))),
+                Some("kbd")
+            ))
+        );
+        assert_eq!(
+            parse("s 10- --device kbd").unwrap().command,
+            Some(brightness(
+                Operation::Set(ValueSpec::Decrease(Amount::Raw(10))),
+                Some("kbd")
+            ))
+        );
+        assert_eq!(
+            parse("-d kbd trigger none").unwrap().command,
+            Some(brightness(
+                Operation::Trigger("none".to_string()),
+                Some("kbd")
+            ))
+        );
+        assert_eq!(
+            parse("blink kbd --delay 100").unwrap().command,
+            Some(brightness(
+                Operation::Blink(Duration::from_millis(100)),
+                Some("kbd")
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_help() {
+        assert_eq!(parse("--help").unwrap().command, Some(Command::Help));
+        assert_eq!(parse("-v -h").unwrap().command, Some(Command::Help));
+        assert_eq!(
+            parse("toggle capslock -h").unwrap().command,
+            Some(Command::Help)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(matches!(
+            parse("set"),
+            Err(ArgsError::MissingValue("brightness"))
+        ));
+        assert!(matches!(
+            parse("set capslock 5x"),
+            Err(ArgsError::InvalidBrightness(_))
+        ));
+        assert!(matches!(
+            parse("set 5O%"),
+            Err(ArgsError::InvalidBrightness(error)) if error.to_string().contains("5O%")
+        ));
+        assert!(matches!(
+            parse("-d a set b 50"),
+            Err(ArgsError::InvalidBrightness(error)) if error.to_string().contains("\"b\"")
+        ));
+        assert!(matches!(parse("get a -d b"), Err(ArgsError::Unexpected(arg)) if arg == "-d"));
+        assert!(matches!(
+            parse("-d"),
+            Err(ArgsError::MissingValue("--device"))
+        ));
+        assert!(matches!(parse("-d kbd"), Err(ArgsError::Unexpected(arg)) if arg == "--device"));
+        assert!(
+            matches!(parse("set kbd 5 --json"), Err(ArgsError::Unexpected(arg)) if arg == "--json")
+        );
+        assert!(
+            matches!(parse("get kbd --dry-run"), Err(ArgsError::Unexpected(arg)) if arg == "--dry-run")
+        );
+        assert!(
+            matches!(parse("get kbd --delay 5"), Err(ArgsError::Unexpected(arg)) if arg == "--delay")
+        );
+        assert!(
+            matches!(parse("-d a trigger b timer"), Err(ArgsError::Unexpected(arg)) if arg == "timer")
+        );
+        assert!(matches!(parse("get a b"), Err(ArgsError::Unexpected(arg)) if arg == "b"));
+        assert!(matches!(
+            parse("list --frobnicate"),
+            Err(ArgsError::Unexpected(_))
+        ));
+        assert!(matches!(
+            parse("off"),
+            Err(ArgsError::MissingValue("LED or --all"))
+        ));
+        assert!(matches!(
+            parse("daemon"),
+            Err(ArgsError::MissingValue("--profile or --dbus"))
+        ));
+        assert!(matches!(
+            parse("daemon --dbus desktop"),
+            Err(ArgsError::InvalidValue("--dbus", _))
+        ));
+        assert!(matches!(
+            parse("--max-fps 0"),
+            Err(ArgsError::InvalidValue("--max-fps", _))
+        ));
+    }
+}
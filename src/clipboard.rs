@@ -0,0 +1,105 @@
+use std::{
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Local clipboard tools tried (in order) when not connected over SSH.
+const CLIPBOARD_COMMANDS: [&[&str]; 3] = [
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// How the text ended up on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMethod {
+    /// Only the OSC 52 escape sequence was sent, which the terminal may or may not support
+    Osc52,
+    /// OSC 52 was sent and a local clipboard tool was used too
+    Osc52AndLocal(&'static str),
+}
+
+/// Copies text to the clipboard.
+///
+/// The OSC 52 escape sequence is always sent, because it works over SSH as long as the terminal
+/// supports it. On local sessions, the text is also handed to `wl-copy`, `xclip` or `xsel`
+/// (whichever is installed) for terminals that don't.
+pub fn copy(text: &str) -> io::Result<CopyMethod> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+
+    let over_ssh = env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_TTY").is_some();
+    if !over_ssh {
+        for command in CLIPBOARD_COMMANDS {
+            if copy_with(command, text).is_ok() {
+                return Ok(CopyMethod::Osc52AndLocal(command[0]));
+            }
+        }
+    }
+    Ok(CopyMethod::Osc52)
+}
+
+fn copy_with(command: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Closes stdin once written, so the tool knows it has all of the text
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()),
+        None => Ok(()),
+    };
+    // The tools fork to serve the selection and exit once they have the text, so this doesn't
+    // block, and reaps them so that they don't linger as zombies
+    let status = child.wait()?;
+    written?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {}",
+            command[0], status
+        )));
+    }
+    Ok(())
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe, 0x00]), "//4A");
+    }
+}
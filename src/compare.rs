@@ -0,0 +1,84 @@
+use std::{collections::BTreeSet, io};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    style::Style,
+    text::Line,
+    widgets::{Block, Cell, Paragraph, Row, Table, TableState},
+};
+
+use crate::{
+    LED,
+    attributes::Attributes,
+    theme::{self, Role},
+};
+
+/// Shows the attributes of two LEDs next to each other, highlighting the differences.
+///
+/// The attributes are read by the caller with [`crate::attributes::read_attributes`], so that
+/// they aren't read again for every frame. The first `scroll` attributes are scrolled out of
+/// view, which is limited to keep one in view.
+pub fn render(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    left: &LED,
+    right: &LED,
+    attributes: (&io::Result<Attributes>, &io::Result<Attributes>),
+    scroll: &mut usize,
+) {
+    let title = Line::styled("Compare LEDs", theme::style(Role::Title)).centered();
+    let block = block.title(title);
+    let (left_attributes, right_attributes) = match attributes {
+        (Ok(left), Ok(right)) => (left, right),
+        (Err(e), _) | (_, Err(e)) => {
+            frame.render_widget(
+                Paragraph::new(format!("Error reading attributes: {}", e)).block(block),
+                area,
+            );
+            return;
+        }
+    };
+    let names: BTreeSet<&str> = left_attributes
+        .iter()
+        .chain(right_attributes)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let value_of = |attributes: &[(String, String)], name: &str| {
+        attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.replace('\n', " "))
+    };
+    *scroll = (*scroll).min(names.len().saturating_sub(1));
+    let rows = names.into_iter().map(|name| {
+        let left_value = value_of(left_attributes, name);
+        let right_value = value_of(right_attributes, name);
+        let style = if left_value == right_value {
+            Style::new()
+        } else {
+            theme::style(Role::Warning)
+        };
+        Row::new([
+            Cell::from(name.to_string()),
+            Cell::from(left_value.unwrap_or_else(|| "-".to_string())),
+            Cell::from(right_value.unwrap_or_else(|| "-".to_string())),
+        ])
+        .style(style)
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(16),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(["Attribute", left.name.as_str(), right.name.as_str()])
+            .style(theme::style(Role::Heading)),
+    )
+    .block(block);
+    frame.render_stateful_widget(table, area, &mut TableState::new().with_offset(*scroll));
+}
@@ -0,0 +1,196 @@
+//! Shell completion scripts, printed by `glimpse completions <shell>`.
+//!
+//! LED names are completed by listing `/sys/class/leds` when completing, so LEDs that appear
+//! later (e.g. of a keyboard that was plugged in) are completed without regenerating the script.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// The subcommands offered for the first argument.
+const COMMANDS: &[&str] = &[
+    "list",
+    "get",
+    "set",
+    "toggle",
+    "trigger",
+    "blink",
+    "info",
+    "max",
+    "set-color",
+    "calibrate",
+    "test",
+    "bench",
+    "countdown",
+    "off",
+    "watch",
+    "daemon",
+    "mute",
+    "unmute",
+    "export-tmpfiles",
+    "export",
+    "import",
+    "config",
+    "profile",
+    "virtual",
+    "play",
+    "completions",
+    "setup-permissions",
+];
+/// Subcommands and options followed by a single LED.
+const LED_ARGUMENTS: &[&str] = &[
+    "-d",
+    "--device",
+    "--led",
+    "get",
+    "set",
+    "toggle",
+    "trigger",
+    "blink",
+    "info",
+    "max",
+    "set-color",
+    "calibrate",
+];
+/// Subcommands followed by any number of LEDs.
+const LED_LIST_COMMANDS: &[&str] = &["test", "bench", "off", "watch", "export-tmpfiles", "export"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unsupported shell \"{0}\" (expected bash, zsh or fish)")]
+pub struct ParseShellError(String);
+
+impl FromStr for Shell {
+    type Err = ParseShellError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            _ => Err(ParseShellError(s.to_string())),
+        }
+    }
+}
+
+const BASH: &str = r#"_glimpse() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local leds
+    leds="$(ls /sys/class/leds 2>/dev/null)"
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "@COMMANDS@" -- "$cur"))
+        return
+    fi
+    case "$prev" in
+        @LED_ARGUMENTS@)
+            COMPREPLY=($(compgen -W "$leds" -- "$cur"))
+            return
+            ;;
+        completions)
+            COMPREPLY=($(compgen -W "bash zsh fish" -- "$cur"))
+            return
+            ;;
+        profile)
+            COMPREPLY=($(compgen -W "save apply list" -- "$cur"))
+            return
+            ;;
+        import)
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return
+            ;;
+    esac
+    case "${COMP_WORDS[1]}" in
+        @LED_LIST_COMMANDS@)
+            COMPREPLY=($(compgen -W "$leds" -- "$cur"))
+            ;;
+    esac
+}
+
+complete -F _glimpse glimpse
+"#;
+
+const ZSH: &str = r#"#compdef glimpse
+
+_glimpse() {
+    local -a commands leds
+    commands=(@COMMANDS@)
+    leds=(${(f)"$(ls /sys/class/leds 2>/dev/null)"})
+    if (( CURRENT == 2 )); then
+        compadd -a commands
+        return
+    fi
+    case "${words[CURRENT-1]}" in
+        @LED_ARGUMENTS@)
+            compadd -a leds
+            return
+            ;;
+        completions)
+            compadd bash zsh fish
+            return
+            ;;
+        profile)
+            compadd save apply list
+            return
+            ;;
+        import)
+            _files
+            return
+            ;;
+    esac
+    case "${words[2]}" in
+        @LED_LIST_COMMANDS@)
+            compadd -a leds
+            ;;
+    esac
+}
+
+if [[ "$funcstack[1]" == "_glimpse" ]]; then
+    _glimpse "$@"
+else
+    compdef _glimpse glimpse
+fi
+"#;
+
+const FISH: &str = r#"set -l commands @COMMANDS@
+set -l leds "(ls /sys/class/leds 2>/dev/null)"
+complete -c glimpse -f
+complete -c glimpse -n "not __fish_seen_subcommand_from $commands" -a "$commands"
+complete -c glimpse -n "__fish_seen_subcommand_from @LED_COMMANDS@" -a $leds
+complete -c glimpse -s d -l device -x -a $leds
+complete -c glimpse -n "__fish_seen_subcommand_from countdown" -l led -x -a $leds
+complete -c glimpse -n "__fish_seen_subcommand_from completions" -a "bash zsh fish"
+complete -c glimpse -n "__fish_seen_subcommand_from profile" -a "save apply list"
+complete -c glimpse -n "__fish_seen_subcommand_from import" -F
+"#;
+
+/// The completion script for a shell.
+pub fn script(shell: Shell) -> String {
+    let (template, separator) = match shell {
+        Shell::Bash => (BASH, "|"),
+        Shell::Zsh => (ZSH, "|"),
+        Shell::Fish => (FISH, " "),
+    };
+    let led_commands: Vec<&str> = LED_ARGUMENTS
+        .iter()
+        .filter(|argument| !argument.starts_with('-'))
+        .chain(LED_LIST_COMMANDS)
+        .copied()
+        .collect();
+    template
+        .replace("@COMMANDS@", &COMMANDS.join(" "))
+        .replace("@LED_ARGUMENTS@", &LED_ARGUMENTS.join(separator))
+        .replace("@LED_LIST_COMMANDS@", &LED_LIST_COMMANDS.join(separator))
+        .replace("@LED_COMMANDS@", &led_commands.join(" "))
+}
+
+/// Prints the completion script for a shell.
+pub fn run(shell: Shell) {
+    print!("{}", script(shell));
+}
@@ -0,0 +1,221 @@
+//! Checking and editing the files in Glimpse's config directory.
+
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::{
+    calibration::CALIBRATION_FILE,
+    dirs,
+    external::editor_command,
+    get_all_leds,
+    output::{Output, Tone},
+    settings::{SETTINGS_FILE, Settings, SettingsError},
+};
+
+/// The config files, all but [`SETTINGS_FILE`] made of `<led> = <value>` lines.
+const CONFIG_FILES: [&str; 2] = [CALIBRATION_FILE, SETTINGS_FILE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The line is ignored
+    Error,
+    /// The line is used, but probably not as intended
+    Warning,
+}
+
+/// Something wrong with a line of a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub file: &'static str,
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+fn config_path(file: &str) -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .ok_or_else(|| eyre!("no config directory (neither XDG_CONFIG_HOME nor HOME is set)"))?
+        .join(file))
+}
+
+/// Checks that a value is valid for the given config file.
+fn check_value(file: &str, value: &str) -> Option<String> {
+    match file {
+        CALIBRATION_FILE => value
+            .parse::<u32>()
+            .err()
+            .map(|_| format!("\"{}\" is not a brightness level", value)),
+        _ => None,
+    }
+}
+
+/// Finds the problems in one config file's contents.
+///
+/// LEDs that don't exist are only a warning when the list of LEDs is known, as they may be
+/// plugged in later.
+fn check_file(file: &'static str, contents: &str, leds: Option<&HashSet<String>>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, line) in contents.lines().enumerate() {
+        let mut problem = |severity, message| {
+            problems.push(Problem {
+                file,
+                line: i + 1,
+                severity,
+                message,
+            })
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((led, value)) = line.split_once('=') else {
+            problem(Severity::Error, "expected <led> = <value>".to_string());
+            continue;
+        };
+        let (led, value) = (led.trim(), value.trim());
+        if let Some(message) = check_value(file, value) {
+            problem(Severity::Error, message);
+            continue;
+        }
+        if !seen.insert(led.to_string()) {
+            problem(
+                Severity::Warning,
+                format!("{} is set more than once, the last value is used", led),
+            );
+        }
+        if leds.is_some_and(|leds| !leds.contains(led)) {
+            problem(Severity::Warning, format!("there is no LED named {}", led));
+        }
+    }
+    problems
+}
+
+/// Finds the problems in `config.toml`, which stops at the first error.
+fn check_settings(contents: &str) -> Vec<Problem> {
+    let problem = |line, severity, message| Problem {
+        file: SETTINGS_FILE,
+        line,
+        severity,
+        message,
+    };
+    match Settings::parse(contents) {
+        Ok((_, warnings)) => warnings
+            .into_iter()
+            .map(|(line, message)| problem(line, Severity::Warning, message))
+            .collect(),
+        Err(SettingsError::Invalid { line, message }) => {
+            vec![problem(line, Severity::Error, message)]
+        }
+        Err(e) => vec![problem(0, Severity::Error, e.to_string())],
+    }
+}
+
+/// Finds the problems in any config file.
+fn check(file: &'static str, contents: &str, leds: Option<&HashSet<String>>) -> Vec<Problem> {
+    if file == SETTINGS_FILE {
+        check_settings(contents)
+    } else {
+        check_file(file, contents, leds)
+    }
+}
+
+/// Checks every config file, returning the problems found.
+pub fn validate() -> Result<Vec<Problem>> {
+    let leds: Option<HashSet<String>> = get_all_leds()
+        .ok()
+        .map(|leds| leds.into_iter().map(|led| led.file_name).collect());
+    let mut problems = Vec::new();
+    for file in CONFIG_FILES {
+        let contents = match fs::read_to_string(config_path(file)?) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(eyre!("can't read {}: {}", file, e)),
+        };
+        problems.extend(check(file, &contents, leds.as_ref()));
+    }
+    Ok(problems)
+}
+
+/// Prints the problems, returning whether any of them are errors.
+fn report(problems: &[Problem]) -> bool {
+    let output = Output::detect();
+    for problem in problems {
+        let (label, tone) = match problem.severity {
+            Severity::Error => ("error", Tone::Error),
+            Severity::Warning => ("warning", Tone::Value),
+        };
+        println!("{}: {}", output.paint(label, tone), problem);
+    }
+    problems
+        .iter()
+        .any(|problem| problem.severity == Severity::Error)
+}
+
+/// `glimpse config validate`: reports every problem in the config files.
+pub fn run_validate() -> Result<()> {
+    let problems = validate()?;
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    if report(&problems) {
+        return Err(eyre!("the config has errors"));
+    }
+    Ok(())
+}
+
+/// `glimpse config edit`: opens a config file in the editor, and offers to edit it again until
+/// it has no errors.
+pub fn run_edit(file: String) -> Result<()> {
+    let Some(file) = CONFIG_FILES.into_iter().find(|name| *name == file) else {
+        return Err(eyre!(
+            "unknown config file \"{}\", expected one of: {}",
+            file,
+            CONFIG_FILES.join(", ")
+        ));
+    };
+    let path = config_path(file)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    loop {
+        let status = editor_command(&path).status()?;
+        if !status.success() {
+            return Err(eyre!("the editor exited with {}", status));
+        }
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let leds: Option<HashSet<String>> = get_all_leds()
+            .ok()
+            .map(|leds| leds.into_iter().map(|led| led.file_name).collect());
+        let problems = check(file, &contents, leds.as_ref());
+        if !report(&problems) {
+            return Ok(());
+        }
+        print!("Edit {} again? [Y/n] ", file);
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if matches!(answer.trim(), "n" | "N") {
+            return Err(eyre!(
+                "{} has errors, the lines with errors are ignored",
+                file
+            ));
+        }
+    }
+}
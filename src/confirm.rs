@@ -0,0 +1,116 @@
+//! A Yes/No popup asking before doing something that's hard to undo, like turning every LED
+//! off.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Wrap},
+};
+
+use crate::{
+    theme::{self, Role},
+    widgets::centered_rect,
+};
+
+/// A question waiting to be answered, along with what to do if the answer is yes.
+///
+/// No is focused at first, so that pressing Enter out of habit doesn't go ahead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Confirmation<T> {
+    pub question: String,
+    /// What to do once confirmed
+    pub action: T,
+    yes_focused: bool,
+}
+
+impl<T> Confirmation<T> {
+    pub fn new(question: impl Into<String>, action: T) -> Self {
+        Self {
+            question: question.into(),
+            action,
+            yes_focused: false,
+        }
+    }
+
+    /// Handles a key press: `y` or Enter on Yes confirms, `n`, Esc, `q` or Enter on No
+    /// declines, and the arrow keys, Tab, `h` and `l` move the focus between the buttons.
+    ///
+    /// Returns the answer once there is one.
+    pub fn on_key_event(&mut self, key: KeyEvent) -> Option<bool> {
+        match key.code {
+            KeyCode::Char('y' | 'Y') => Some(true),
+            KeyCode::Char('n' | 'N' | 'q') | KeyCode::Esc => Some(false),
+            KeyCode::Enter => Some(self.yes_focused),
+            KeyCode::Left | KeyCode::Right | KeyCode::Tab | KeyCode::BackTab => {
+                self.yes_focused = !self.yes_focused;
+                None
+            }
+            KeyCode::Char('h') => {
+                self.yes_focused = true;
+                None
+            }
+            KeyCode::Char('l') => {
+                self.yes_focused = false;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let title = Line::styled(
+            "Confirm",
+            theme::style(Role::Warning).patch(theme::style(Role::Heading)),
+        )
+        .centered();
+        let button = |label: &'static str, focused: bool| {
+            if focused {
+                Span::styled(label, theme::style(Role::Selection))
+            } else {
+                Span::styled(label, theme::style(Role::Dim))
+            }
+        };
+        let area = centered_rect(area, 50, 7);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(self.question.as_str()),
+                Line::default(),
+                Line::from(vec![
+                    button(" Yes ", self.yes_focused),
+                    Span::raw("   "),
+                    button(" No ", !self.yes_focused),
+                ]),
+            ])
+            .wrap(Wrap { trim: true })
+            .centered()
+            .block(
+                Block::bordered()
+                    .border_style(theme::style(Role::Warning))
+                    .title(title),
+            ),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers() {
+        let press = |confirmation: &mut Confirmation<()>, code| {
+            confirmation.on_key_event(KeyEvent::from(code))
+        };
+        let mut confirmation = Confirmation::new("Turn off all 3 LEDs?", ());
+        assert_eq!(press(&mut confirmation, KeyCode::Char('x')), None);
+        assert_eq!(press(&mut confirmation, KeyCode::Enter), Some(false));
+        assert_eq!(press(&mut confirmation, KeyCode::Left), None);
+        assert_eq!(press(&mut confirmation, KeyCode::Enter), Some(true));
+        assert_eq!(press(&mut confirmation, KeyCode::Esc), Some(false));
+        assert_eq!(press(&mut confirmation, KeyCode::Char('y')), Some(true));
+    }
+}
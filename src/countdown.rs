@@ -0,0 +1,223 @@
+use std::{
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{Result, eyre::eyre};
+use thiserror::Error;
+
+use crate::{
+    glob,
+    output::{Output, Tone},
+};
+
+/// How long the LED is on (and then off) while blinking slowly.
+const SLOW_BLINK_HALF_PERIOD: Duration = Duration::from_millis(1000);
+/// How long the LED is on (and then off) while blinking fast.
+const FAST_BLINK_HALF_PERIOD: Duration = Duration::from_millis(250);
+/// The last part of the countdown in which the LED blinks slowly.
+const SLOW_BLINK_FRACTION: f64 = 1.0 / 3.0;
+/// The last part of the countdown in which the LED blinks fast.
+const FAST_BLINK_FRACTION: f64 = 0.1;
+/// The longest duration accepted, so that working out when it ends can't overflow.
+const MAX_DURATION: Duration = Duration::from_secs(366 * 24 * 3600);
+
+/// What the LED is doing at a point of the countdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Steady,
+    SlowBlink,
+    FastBlink,
+    Finished,
+}
+
+/// A timer shown on an LED: steady, then blinking slowly, then fast, then off.
+#[derive(Debug, Clone)]
+pub struct Countdown {
+    /// File name of the LED showing the countdown
+    pub led: String,
+    duration: Duration,
+    started: Instant,
+}
+
+impl Countdown {
+    pub fn new(led: String, duration: Duration) -> Self {
+        Self {
+            led,
+            duration,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.started.elapsed())
+    }
+
+    pub fn phase(&self) -> Phase {
+        let remaining = self.remaining();
+        let fraction = remaining.as_secs_f64() / self.duration.as_secs_f64();
+        if remaining.is_zero() {
+            Phase::Finished
+        } else if fraction <= FAST_BLINK_FRACTION {
+            Phase::FastBlink
+        } else if fraction <= SLOW_BLINK_FRACTION {
+            Phase::SlowBlink
+        } else {
+            Phase::Steady
+        }
+    }
+
+    /// Whether the LED should currently be lit.
+    pub fn led_on(&self) -> bool {
+        let blink = |half_period: Duration| {
+            (self.started.elapsed().as_millis() / half_period.as_millis()).is_multiple_of(2)
+        };
+        match self.phase() {
+            Phase::Steady => true,
+            Phase::SlowBlink => blink(SLOW_BLINK_HALF_PERIOD),
+            Phase::FastBlink => blink(FAST_BLINK_HALF_PERIOD),
+            Phase::Finished => false,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseDurationError {
+    #[error("invalid duration \"{0}\", expected e.g. 90, 45s, 10m or 1h30m")]
+    Invalid(String),
+    #[error("duration \"{0}\" is longer than a year")]
+    TooLong(String),
+}
+
+/// Parses durations like `90`, `45s`, `10m` or `1h30m`. Plain numbers are seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, ParseDurationError> {
+    let invalid = || ParseDurationError::Invalid(s.to_string());
+    let too_long = || ParseDurationError::TooLong(s.to_string());
+    let seconds = if s.bytes().all(|b| b.is_ascii_digit()) && !s.is_empty() {
+        s.parse::<u64>().map_err(|_| too_long())?
+    } else {
+        let mut total: u64 = 0;
+        let mut number = String::new();
+        for c in s.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let multiplier = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return Err(invalid()),
+            };
+            if number.is_empty() {
+                return Err(invalid());
+            }
+            total = number
+                .parse::<u64>()
+                .ok()
+                .and_then(|number| number.checked_mul(multiplier))
+                .and_then(|seconds| total.checked_add(seconds))
+                .ok_or_else(too_long)?;
+            number.clear();
+        }
+        if !number.is_empty() || total == 0 {
+            return Err(invalid());
+        }
+        total
+    };
+    let duration = Duration::from_secs(seconds);
+    if duration > MAX_DURATION {
+        return Err(too_long());
+    }
+    Ok(duration)
+}
+
+/// Formats a duration as `m:ss` (or `h:mm:ss` when an hour or longer).
+pub fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Runs a countdown on an LED from the command line, blocking until it finishes.
+pub fn run(led_name: String, duration: Duration) -> Result<()> {
+    let mut leds = glob::expand(std::slice::from_ref(&led_name))?;
+    if leds.len() > 1 {
+        return Err(eyre!(
+            "\"{}\" matches {} LEDs, but a countdown needs exactly one",
+            led_name,
+            leds.len()
+        ));
+    }
+    let mut led = leds.remove(0);
+    let countdown = Countdown::new(led.file_name.clone(), duration);
+    let output = Output::detect();
+    let mut stdout = io::stdout();
+    let mut lit = None;
+    loop {
+        let on = countdown.led_on();
+        if lit != Some(on) {
+            led.set_on(on)?;
+            lit = Some(on);
+        }
+        if countdown.phase() == Phase::Finished {
+            break;
+        }
+        write!(
+            stdout,
+            "\r{} remaining on {}  ",
+            output.paint(&format_remaining(countdown.remaining()), Tone::Value),
+            output.paint(&led.file_name, Tone::Name)
+        )?;
+        stdout.flush()?;
+        thread::sleep(Duration::from_millis(50));
+    }
+    writeln!(
+        stdout,
+        "\rCountdown on {} finished\x07       ",
+        led.file_name
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        for invalid in ["", "h", "10x", "1h30", "0m", "-5"] {
+            assert_eq!(
+                parse_duration(invalid),
+                Err(ParseDurationError::Invalid(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        for too_long in [
+            "99999999999999999999",
+            "18446744073709551615h",
+            "5124095576030431h",
+            "9000h",
+        ] {
+            assert_eq!(
+                parse_duration(too_long),
+                Err(ParseDurationError::TooLong(too_long.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! `glimpse daemon`: keeping the LEDs set to a profile without the TUI, and offering the
+//! [`crate::service`] D-Bus service with `--dbus`.
+//!
+//! The profile is applied when the daemon starts, and again whenever the system resumes from
+//! suspend, LEDs in the profile appear (e.g. of a keyboard that was plugged in) or the profile
+//! is saved again. LEDs changed in between are left alone until then. Resumes are signalled by
+//! logind's `PrepareForSleep`, or guessed from the wall clock jumping ahead without logind.
+//!
+//! The daemon keeps its status in `$XDG_STATE_HOME/glimpse/daemon`, as `<key> = <value>` lines,
+//! which the TUI reads to show that a daemon is running.
+
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::{
+    LedBackend, SysfsBackend,
+    dbus::Bus,
+    dirs, get_all_leds,
+    logind::SleepWatcher,
+    output::{Output, Tone},
+    profiles::{self, Profile},
+    service::{SERVICE_NAME, Service},
+};
+
+/// Name of the file in Glimpse's state directory holding the running daemon's status.
+pub const DAEMON_STATUS_FILE: &str = "daemon";
+/// How often the daemon checks for resumes, new LEDs and changes to the profile, unless set
+/// with `--interval`.
+pub const DEFAULT_DAEMON_INTERVAL: Duration = Duration::from_secs(2);
+/// How far the wall clock may run ahead of the monotonic clock between two checks before it's
+/// assumed that the system was suspended, when logind can't say.
+const RESUME_DETECTION_THRESHOLD: Duration = Duration::from_secs(5);
+/// How long to wait for logind's signals after answering D-Bus calls, by which time any sent
+/// in the meantime have arrived.
+const SLEEP_SIGNAL_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// What a running daemon is doing, as shown in the TUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    /// Name of the profile the LEDs are kept set to, if any
+    pub profile: Option<String>,
+    /// The bus the D-Bus service is offered on, if any
+    pub bus: Option<Bus>,
+    /// When the profile was last applied, in seconds since the Unix epoch
+    pub applied_at: Option<u64>,
+    /// How many LEDs couldn't be set when it was last applied
+    pub errors: usize,
+}
+
+fn status_path() -> io::Result<PathBuf> {
+    dirs::state_dir()
+        .map(|dir| dir.join(DAEMON_STATUS_FILE))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no state directory (neither XDG_STATE_HOME nor HOME is set)",
+            )
+        })
+}
+
+impl DaemonStatus {
+    /// Parses a status file, returning `None` if it has no pid.
+    pub fn parse(contents: &str) -> Option<Self> {
+        let (mut pid, mut profile, mut bus, mut applied_at, mut errors) =
+            (None, None, None, None, 0);
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "pid" => pid = value.parse().ok(),
+                "profile" => profile = Some(value.to_string()),
+                "bus" => bus = Bus::from_name(value),
+                "applied_at" => applied_at = value.parse().ok(),
+                "errors" => errors = value.parse().unwrap_or_default(),
+                _ => {}
+            }
+        }
+        Some(Self {
+            pid: pid?,
+            profile,
+            bus,
+            applied_at,
+            errors,
+        })
+    }
+
+    /// The status as the lines [`DaemonStatus::parse`] reads.
+    pub fn serialize(&self) -> String {
+        let mut lines = vec![format!("pid = {}", self.pid)];
+        if let Some(profile) = &self.profile {
+            lines.push(format!("profile = {}", profile));
+        }
+        if let Some(bus) = self.bus {
+            lines.push(format!("bus = {}", bus));
+        }
+        if let Some(applied_at) = self.applied_at {
+            lines.push(format!("applied_at = {}", applied_at));
+        }
+        lines.push(format!("errors = {}", self.errors));
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = status_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.serialize())
+    }
+}
+
+/// The status of the running daemon, if there is one. A status left behind by a daemon that
+/// was killed is ignored.
+pub fn running() -> Option<DaemonStatus> {
+    let contents = fs::read_to_string(status_path().ok()?).ok()?;
+    DaemonStatus::parse(&contents)
+        .filter(|status| Path::new("/proc").join(status.pid.to_string()).exists())
+}
+
+/// The part of a profile for the given LEDs, so that LEDs that aren't plugged in aren't
+/// reported as failing.
+fn profile_for(profile: &Profile, leds: &BTreeSet<String>) -> Profile {
+    Profile {
+        leds: profile
+            .leds
+            .iter()
+            .filter(|(led, _)| leds.contains(*led))
+            .map(|(led, state)| (led.clone(), state.clone()))
+            .collect(),
+    }
+}
+
+/// Waits for the next check, answering D-Bus calls in the meantime if the service is offered.
+///
+/// Returns whether logind said that the system resumed in the meantime. If logind can't be
+/// heard from anymore, `sleep` is dropped so that resumes are guessed from the clock instead.
+fn wait(
+    service: &mut Option<Service>,
+    sleep: &mut Option<SleepWatcher>,
+    interval: Duration,
+    output: &Output,
+) -> Result<bool> {
+    let resumed = match (service, sleep.as_mut()) {
+        (Some(service), sleep) => {
+            service.serve(interval)?;
+            sleep.map_or(Ok(false), |sleep| sleep.wait(SLEEP_SIGNAL_TIMEOUT))
+        }
+        (None, Some(sleep)) => sleep.wait(interval),
+        (None, None) => {
+            thread::sleep(interval);
+            Ok(false)
+        }
+    };
+    Ok(resumed.unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            output.paint(
+                &format!(
+                    "Stopped hearing from logind ({}), guessing resumes instead",
+                    e
+                ),
+                Tone::Error
+            )
+        );
+        *sleep = None;
+        false
+    }))
+}
+
+fn save_status(status: &DaemonStatus, output: &Output) {
+    if let Err(e) = status.save() {
+        eprintln!(
+            "{}",
+            output.paint(&format!("Error saving the status: {}", e), Tone::Error)
+        );
+    }
+}
+
+/// Keeps the LEDs set to a profile and/or offers the D-Bus service on a bus until interrupted.
+pub fn run(profile_name: Option<String>, bus: Option<Bus>, interval: Duration) -> Result<()> {
+    let output = Output::detect();
+    if let Some(status) = running() {
+        return Err(eyre!("a daemon is already running (pid {})", status.pid));
+    }
+    let mut status = DaemonStatus {
+        pid: process::id(),
+        profile: profile_name.clone(),
+        bus,
+        applied_at: None,
+        errors: 0,
+    };
+    let mut service = bus.map(Service::start).transpose()?;
+    if let Some(bus) = bus {
+        println!(
+            "Offering {} on the {} bus",
+            output.paint(SERVICE_NAME, Tone::Name),
+            bus
+        );
+    }
+    let Some(profile_name) = profile_name else {
+        save_status(&status, &output);
+        loop {
+            wait(&mut service, &mut None, interval, &output)?;
+        }
+    };
+    let mut profile = Profile::load(&profile_name)?;
+    let mut profile_modified = profiles::modified(&profile_name);
+    let mut present = BTreeSet::new();
+    let mut last_check = (Instant::now(), SystemTime::now());
+    let mut reason = Some("Started".to_string());
+    let mut sleep = match SleepWatcher::subscribe() {
+        Ok(sleep) => Some(sleep),
+        Err(e) => {
+            eprintln!(
+                "{}",
+                output.paint(
+                    &format!("Can't hear from logind ({}), guessing resumes instead", e),
+                    Tone::Dim
+                )
+            );
+            None
+        }
+    };
+    let mut signalled_resume = false;
+    println!(
+        "Keeping the LEDs set to profile {}",
+        output.paint(&profile_name, Tone::Name)
+    );
+    loop {
+        let names: BTreeSet<String> = SysfsBackend.led_names()?.into_iter().collect();
+        let added: BTreeSet<String> = names.difference(&present).cloned().collect();
+        present = names;
+        let resumed = signalled_resume
+            || sleep.is_none()
+                && SystemTime::now()
+                    .duration_since(last_check.1)
+                    .is_ok_and(|wall| wall > last_check.0.elapsed() + RESUME_DETECTION_THRESHOLD);
+        last_check = (Instant::now(), SystemTime::now());
+        let modified = profiles::modified(&profile_name);
+        if modified != profile_modified {
+            profile_modified = modified;
+            match Profile::load(&profile_name) {
+                Ok(reloaded) => {
+                    profile = reloaded;
+                    reason.get_or_insert_with(|| "Profile changed".to_string());
+                }
+                Err(e) => eprintln!(
+                    "{}",
+                    output.paint(&format!("Error reloading the profile: {}", e), Tone::Error)
+                ),
+            }
+        }
+        if resumed {
+            reason.get_or_insert_with(|| "Resumed from suspend".to_string());
+        }
+        // Only the LEDs that appeared need setting, unless everything is being reapplied
+        let targets = match &reason {
+            Some(_) => present.clone(),
+            None if added.iter().any(|led| profile.leds.contains_key(led)) => {
+                reason = Some(format!("{} LED(s) appeared", added.len()));
+                added
+            }
+            None => BTreeSet::new(),
+        };
+        if let Some(reason) = reason.take() {
+            let mut leds = get_all_leds()?;
+            let (edits, errors) = profile_for(&profile, &targets).apply(&mut leds);
+            println!(
+                "{}: changed {} LED(s)",
+                reason,
+                output.paint(&profiles::changed_leds(&edits).to_string(), Tone::Value)
+            );
+            for error in &errors {
+                eprintln!("{}", output.paint(error, Tone::Error));
+            }
+            status.applied_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since| since.as_secs());
+            status.errors = errors.len();
+            save_status(&status, &output);
+        }
+        signalled_resume = wait(&mut service, &mut sleep, interval, &output)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_status() {
+        let status = DaemonStatus {
+            pid: 4242,
+            profile: Some("night".to_string()),
+            bus: Some(Bus::Session),
+            applied_at: Some(1_760_000_000),
+            errors: 1,
+        };
+        assert_eq!(DaemonStatus::parse(&status.serialize()), Some(status));
+        assert_eq!(DaemonStatus::parse("profile = night\n"), None);
+        assert_eq!(
+            DaemonStatus::parse("pid = 4242\nbus = system\n").and_then(|status| status.bus),
+            Some(Bus::System)
+        );
+    }
+}
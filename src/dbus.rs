@@ -0,0 +1,818 @@
+//! A minimal D-Bus connection, enough for `glimpse daemon --dbus` to offer a service: connecting
+//! to the session or system bus, owning a name, and sending and receiving messages.
+//!
+//! Only the types the service uses can be sent and received: bytes, booleans, `u32`, strings,
+//! object paths, signatures, variants, arrays, structs and dict entries. Unix file descriptors
+//! aren't supported. Messages with other types are skipped, and method calls among them are
+//! answered with [`INVALID_ARGS`].
+
+#[cfg(target_os = "linux")]
+use std::os::{
+    linux::net::SocketAddrExt,
+    unix::{
+        fs::MetadataExt,
+        net::{SocketAddr, UnixStream},
+    },
+};
+use std::{
+    env, fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    time::{Duration, Instant},
+};
+
+/// Where the system bus listens, unless `DBUS_SYSTEM_BUS_ADDRESS` says otherwise.
+const DEFAULT_SYSTEM_BUS_ADDRESS: &str = "unix:path=/var/run/dbus/system_bus_socket";
+/// How long to wait for the bus to answer while connecting.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Messages larger than this are rejected by the D-Bus specification.
+const MAX_MESSAGE_SIZE: usize = 128 * 1024 * 1024;
+
+/// The error returned for method calls that aren't implemented.
+pub const UNKNOWN_METHOD: &str = "org.freedesktop.DBus.Error.UnknownMethod";
+/// The error returned for method calls with the wrong arguments.
+pub const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+/// The error returned for method calls the caller isn't allowed to make.
+const ACCESS_DENIED: &str = "org.freedesktop.DBus.Error.AccessDenied";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bus {
+    Session,
+    System,
+}
+
+impl Bus {
+    /// The bus named `session` or `system`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "session" => Some(Self::Session),
+            "system" => Some(Self::System),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Session => write!(f, "session"),
+            Self::System => write!(f, "system"),
+        }
+    }
+}
+
+/// A value in the body or header of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    U32(u32),
+    String(String),
+    ObjectPath(String),
+    Signature(String),
+    Variant(Box<Value>),
+    /// The signature of the elements, and the elements
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+}
+
+impl Value {
+    pub fn signature(&self) -> String {
+        match self {
+            Self::Byte(_) => "y".to_string(),
+            Self::Bool(_) => "b".to_string(),
+            Self::U32(_) => "u".to_string(),
+            Self::String(_) => "s".to_string(),
+            Self::ObjectPath(_) => "o".to_string(),
+            Self::Signature(_) => "g".to_string(),
+            Self::Variant(_) => "v".to_string(),
+            Self::Array(element, _) => format!("a{}", element),
+            Self::Struct(fields) => {
+                let fields: String = fields.iter().map(Self::signature).collect();
+                format!("({})", fields)
+            }
+            Self::DictEntry(key, value) => format!("{{{}{}}}", key.signature(), value.signature()),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) | Self::ObjectPath(string) | Self::Signature(string) => {
+                Some(string)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            Self::U32(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+/// How values of a type are aligned in a message.
+fn alignment(code: u8) -> usize {
+    match code {
+        b'y' | b'g' | b'v' => 1,
+        b'(' | b'{' | b'x' | b't' | b'd' => 8,
+        _ => 4,
+    }
+}
+
+/// The length of the first complete type in a signature, e.g. 5 for `a(su)u`.
+fn single_type_length(signature: &[u8]) -> io::Result<usize> {
+    match signature.first() {
+        Some(b'a') => Ok(1 + single_type_length(&signature[1..])?),
+        Some(&open @ (b'(' | b'{')) => {
+            let close = if open == b'(' { b')' } else { b'}' };
+            let mut length = 1;
+            loop {
+                match signature.get(length) {
+                    Some(c) if *c == close => return Ok(length + 1),
+                    Some(_) => length += single_type_length(&signature[length..])?,
+                    None => return Err(invalid("unterminated signature")),
+                }
+            }
+        }
+        Some(b'y' | b'b' | b'n' | b'q' | b'i' | b'u' | b'x' | b't' | b'd' | b's' | b'o')
+        | Some(b'g' | b'v' | b'h') => Ok(1),
+        _ => Err(invalid("invalid signature")),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("D-Bus: {}", message))
+}
+
+/// Writes values in the D-Bus wire format, little-endian.
+#[derive(Debug, Default)]
+struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        while !self.buffer.len().is_multiple_of(alignment) {
+            self.buffer.push(0);
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Byte(byte) => self.buffer.push(*byte),
+            Value::Bool(bool) => self.u32(u32::from(*bool)),
+            Value::U32(number) => self.u32(*number),
+            Value::String(string) | Value::ObjectPath(string) => {
+                self.u32(string.len() as u32);
+                self.buffer.extend_from_slice(string.as_bytes());
+                self.buffer.push(0);
+            }
+            Value::Signature(signature) => {
+                self.buffer.push(signature.len() as u8);
+                self.buffer.extend_from_slice(signature.as_bytes());
+                self.buffer.push(0);
+            }
+            Value::Variant(value) => {
+                self.value(&Value::Signature(value.signature()));
+                self.value(value);
+            }
+            Value::Array(element, items) => {
+                self.u32(0);
+                let length_at = self.buffer.len() - 4;
+                // The padding before the first element doesn't count towards the length
+                self.align(alignment(element.as_bytes()[0]));
+                let start = self.buffer.len();
+                for item in items {
+                    self.value(item);
+                }
+                let length = (self.buffer.len() - start) as u32;
+                self.buffer[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+            }
+            Value::Struct(fields) => {
+                self.align(8);
+                for field in fields {
+                    self.value(field);
+                }
+            }
+            Value::DictEntry(key, value) => {
+                self.align(8);
+                self.value(key);
+                self.value(value);
+            }
+        }
+    }
+}
+
+/// Reads values in the D-Bus wire format. Offsets are relative to the start of the message,
+/// which alignment is based on.
+struct Reader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn align(&mut self, alignment: usize) {
+        self.position = self.position.next_multiple_of(alignment);
+    }
+
+    fn bytes(&mut self, count: usize) -> io::Result<&[u8]> {
+        let bytes = self
+            .buffer
+            .get(self.position..self.position + count)
+            .ok_or_else(|| invalid("message too short"))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.align(4);
+        let bytes: [u8; 4] = self.bytes(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn string(&mut self, length: usize) -> io::Result<String> {
+        let bytes = self.bytes(length + 1)?;
+        String::from_utf8(bytes[..length].to_vec()).map_err(|_| invalid("invalid UTF-8"))
+    }
+
+    /// Reads one value of the first complete type in the signature.
+    fn value(&mut self, signature: &[u8]) -> io::Result<Value> {
+        let code = *signature
+            .first()
+            .ok_or_else(|| invalid("empty signature"))?;
+        self.align(alignment(code));
+        Ok(match code {
+            b'y' => Value::Byte(self.bytes(1)?[0]),
+            b'b' => Value::Bool(self.u32()? != 0),
+            b'u' => Value::U32(self.u32()?),
+            b's' | b'o' => {
+                let length = self.u32()? as usize;
+                let string = self.string(length)?;
+                if code == b's' {
+                    Value::String(string)
+                } else {
+                    Value::ObjectPath(string)
+                }
+            }
+            b'g' => {
+                let length = usize::from(self.bytes(1)?[0]);
+                Value::Signature(self.string(length)?)
+            }
+            b'v' => {
+                let Value::Signature(signature) = self.value(b"g")? else {
+                    unreachable!()
+                };
+                if single_type_length(signature.as_bytes())? != signature.len() {
+                    return Err(invalid("variants have to hold a single type"));
+                }
+                Value::Variant(Box::new(self.value(signature.as_bytes())?))
+            }
+            b'a' => {
+                let length = self.u32()? as usize;
+                let element = &signature[1..1 + single_type_length(&signature[1..])?];
+                self.align(alignment(element[0]));
+                let end = self.position + length;
+                let mut items = Vec::new();
+                while self.position < end {
+                    items.push(self.value(element)?);
+                }
+                let element = String::from_utf8_lossy(element).into_owned();
+                Value::Array(element, items)
+            }
+            b'(' => {
+                let mut fields = Vec::new();
+                let mut rest = &signature[1..];
+                while rest.first() != Some(&b')') {
+                    let length = single_type_length(rest)?;
+                    fields.push(self.value(&rest[..length])?);
+                    rest = &rest[length..];
+                }
+                Value::Struct(fields)
+            }
+            b'{' => {
+                let key_length = single_type_length(&signature[1..])?;
+                let key = self.value(&signature[1..])?;
+                let value = self.value(&signature[1 + key_length..])?;
+                Value::DictEntry(Box::new(key), Box::new(value))
+            }
+            _ => return Err(invalid("unsupported type")),
+        })
+    }
+
+    /// Reads values until the signature is used up.
+    fn values(&mut self, mut signature: &[u8]) -> io::Result<Vec<Value>> {
+        let mut values = Vec::new();
+        while !signature.is_empty() {
+            let length = single_type_length(signature)?;
+            values.push(self.value(&signature[..length])?);
+            signature = &signature[length..];
+        }
+        Ok(values)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+/// Set on method calls that don't want a reply.
+const NO_REPLY_EXPECTED: u8 = 0x1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub kind: MessageType,
+    pub flags: u8,
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    fn new(kind: MessageType) -> Self {
+        Self {
+            kind,
+            flags: 0,
+            serial: 0,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            destination: None,
+            sender: None,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn method_call(destination: &str, path: &str, interface: &str, member: &str) -> Self {
+        Self {
+            destination: Some(destination.to_string()),
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            ..Self::new(MessageType::MethodCall)
+        }
+    }
+
+    pub fn signal(path: &str, interface: &str, member: &str, body: Vec<Value>) -> Self {
+        Self {
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            body,
+            ..Self::new(MessageType::Signal)
+        }
+    }
+
+    /// A successful reply to this method call.
+    pub fn reply(&self, body: Vec<Value>) -> Self {
+        Self {
+            reply_serial: Some(self.serial),
+            destination: self.sender.clone(),
+            body,
+            ..Self::new(MessageType::MethodReturn)
+        }
+    }
+
+    /// An error reply to this method call.
+    pub fn error_reply(&self, name: &str, message: &str) -> Self {
+        Self {
+            error_name: Some(name.to_string()),
+            body: vec![Value::String(message.to_string())],
+            ..self.reply(Vec::new())
+        }
+        .with_kind(MessageType::Error)
+    }
+
+    fn with_kind(mut self, kind: MessageType) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn expects_reply(&self) -> bool {
+        self.kind == MessageType::MethodCall && self.flags & NO_REPLY_EXPECTED == 0
+    }
+
+    /// The signature of the body.
+    pub fn signature(&self) -> String {
+        self.body.iter().map(Value::signature).collect()
+    }
+
+    /// The message in the wire format.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Writer::default();
+        for value in &self.body {
+            body.value(value);
+        }
+        let field = |code: u8, value: Value| {
+            Value::Struct(vec![Value::Byte(code), Value::Variant(Box::new(value))])
+        };
+        let mut fields = Vec::new();
+        let strings = [
+            (1, self.path.clone().map(Value::ObjectPath)),
+            (2, self.interface.clone().map(Value::String)),
+            (3, self.member.clone().map(Value::String)),
+            (4, self.error_name.clone().map(Value::String)),
+            (5, self.reply_serial.map(Value::U32)),
+            (6, self.destination.clone().map(Value::String)),
+        ];
+        for (code, value) in strings {
+            if let Some(value) = value {
+                fields.push(field(code, value));
+            }
+        }
+        if !self.body.is_empty() {
+            fields.push(field(8, Value::Signature(self.signature())));
+        }
+        let mut message = Writer::default();
+        message.value(&Value::Byte(b'l'));
+        message.value(&Value::Byte(self.kind as u8));
+        message.value(&Value::Byte(self.flags));
+        message.value(&Value::Byte(1));
+        message.u32(body.buffer.len() as u32);
+        message.u32(self.serial);
+        message.value(&Value::Array("(yv)".to_string(), fields));
+        message.align(8);
+        message.buffer.extend_from_slice(&body.buffer);
+        message.buffer
+    }
+
+    /// The length of the message at the start of `buffer`, from its fixed header, or `None` if
+    /// the buffer doesn't hold the fixed header yet.
+    ///
+    /// Errors mean that the stream can't be split into messages anymore.
+    fn length(buffer: &[u8]) -> io::Result<Option<usize>> {
+        if buffer.len() < 16 {
+            return Ok(None);
+        }
+        let mut reader = Reader {
+            buffer,
+            position: 4,
+            big_endian: big_endian(buffer[0])?,
+        };
+        let body_length = reader.u32()? as usize;
+        reader.position = 12;
+        let fields_length = reader.u32()? as usize;
+        let length = (16 + fields_length).next_multiple_of(8) + body_length;
+        if length > MAX_MESSAGE_SIZE {
+            return Err(invalid("message too large"));
+        }
+        Ok(Some(length))
+    }
+
+    /// Decodes the header of a whole message, returning the message without its body, the
+    /// signature of the body and where the body starts.
+    fn decode_header(buffer: &[u8]) -> io::Result<(Self, String, usize)> {
+        let mut reader = Reader {
+            buffer,
+            position: 8,
+            big_endian: big_endian(buffer[0])?,
+        };
+        let serial = reader.u32()?;
+        let kind = match buffer[1] {
+            1 => MessageType::MethodCall,
+            2 => MessageType::MethodReturn,
+            3 => MessageType::Error,
+            4 => MessageType::Signal,
+            _ => return Err(invalid("unknown message type")),
+        };
+        let mut message = Self {
+            flags: buffer[2],
+            serial,
+            ..Self::new(kind)
+        };
+        let Value::Array(_, fields) = reader.value(b"a(yv)")? else {
+            unreachable!()
+        };
+        let mut signature = String::new();
+        for field in fields {
+            let Value::Struct(field) = field else {
+                unreachable!()
+            };
+            let (Value::Byte(code), Value::Variant(value)) = (&field[0], &field[1]) else {
+                unreachable!()
+            };
+            let string = value.as_str().map(str::to_string);
+            match code {
+                1 => message.path = string,
+                2 => message.interface = string,
+                3 => message.member = string,
+                4 => message.error_name = string,
+                5 => message.reply_serial = value.as_u32(),
+                6 => message.destination = string,
+                7 => message.sender = string,
+                8 => signature = string.unwrap_or_default(),
+                _ => {}
+            }
+        }
+        reader.align(8);
+        Ok((message, signature, reader.position))
+    }
+
+    /// Decodes a whole message, as measured by [`Message::length`].
+    fn decode(buffer: &[u8]) -> io::Result<Self> {
+        let (mut message, signature, body_start) = Self::decode_header(buffer)?;
+        let mut reader = Reader {
+            buffer,
+            position: body_start,
+            big_endian: big_endian(buffer[0])?,
+        };
+        message.body = reader.values(signature.as_bytes())?;
+        Ok(message)
+    }
+}
+
+fn big_endian(byte_order: u8) -> io::Result<bool> {
+    match byte_order {
+        b'l' => Ok(false),
+        b'B' => Ok(true),
+        _ => Err(invalid("unknown byte order")),
+    }
+}
+
+/// Decodes `%xx` escapes in a D-Bus address.
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut unescaped = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match value
+            .get(i + 1..i + 3)
+            .map(|hex| u8::from_str_radix(hex, 16))
+        {
+            Some(Ok(byte)) if bytes[i] == b'%' => {
+                unescaped.push(byte);
+                i += 3;
+            }
+            _ => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Connects to the first `unix:` address in a D-Bus address list.
+#[cfg(target_os = "linux")]
+fn connect_to(addresses: &str) -> io::Result<UnixStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no usable D-Bus address");
+    for address in addresses.split(';') {
+        let Some(options) = address.strip_prefix("unix:") else {
+            continue;
+        };
+        for option in options.split(',') {
+            let result = match option.split_once('=') {
+                Some(("path", path)) => UnixStream::connect(unescape(path)),
+                Some(("abstract", name)) => SocketAddr::from_abstract_name(unescape(name))
+                    .and_then(|address| UnixStream::connect_addr(&address)),
+                _ => continue,
+            };
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = e,
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// A connection to a message bus.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct Connection {
+    stream: UnixStream,
+    /// Bytes received that don't make up a whole message yet
+    received: Vec<u8>,
+    last_serial: u32,
+}
+
+#[cfg(target_os = "linux")]
+impl Connection {
+    /// A connection over a stream that is already authenticated.
+    pub fn from_stream(stream: UnixStream) -> Self {
+        Self {
+            stream,
+            received: Vec::new(),
+            last_serial: 0,
+        }
+    }
+
+    /// Connects and authenticates to a bus, as the user running Glimpse.
+    pub fn connect(bus: Bus) -> io::Result<Self> {
+        let addresses = match bus {
+            Bus::Session => env::var("DBUS_SESSION_BUS_ADDRESS")
+                .or_else(|_| {
+                    env::var("XDG_RUNTIME_DIR").map(|dir| format!("unix:path={}/bus", dir))
+                })
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no session bus address found (neither DBUS_SESSION_BUS_ADDRESS nor \
+                         XDG_RUNTIME_DIR is set)",
+                    )
+                })?,
+            Bus::System => env::var("DBUS_SYSTEM_BUS_ADDRESS")
+                .unwrap_or_else(|_| DEFAULT_SYSTEM_BUS_ADDRESS.to_string()),
+        };
+        let mut stream = connect_to(&addresses)?;
+        stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+        let uid = std::fs::metadata("/proc/self")?.uid().to_string();
+        let uid: String = uid.bytes().map(|byte| format!("{:02x}", byte)).collect();
+        stream.write_all(format!("\0AUTH EXTERNAL {}\r\n", uid).as_bytes())?;
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        if !line.starts_with("OK ") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("the {} bus refused authentication", bus),
+            ));
+        }
+        stream.write_all(b"BEGIN\r\n")?;
+        let mut connection = Self::from_stream(stream);
+        connection.call(Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+        ))?;
+        Ok(connection)
+    }
+
+    /// Sends a message, returning its serial.
+    pub fn send(&mut self, mut message: Message) -> io::Result<u32> {
+        self.last_serial += 1;
+        message.serial = self.last_serial;
+        self.stream.write_all(&message.encode())?;
+        Ok(message.serial)
+    }
+
+    /// Waits up to `timeout` for a message, returning `None` if none arrived.
+    pub fn receive(&mut self, timeout: Duration) -> io::Result<Option<Message>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(length) = Message::length(&self.received)?
+                && self.received.len() >= length
+            {
+                let buffer: Vec<u8> = self.received.drain(..length).collect();
+                match Message::decode(&buffer) {
+                    Ok(message) => return Ok(Some(message)),
+                    Err(e) => {
+                        self.reject(&buffer, &e)?;
+                        continue;
+                    }
+                }
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+            let mut chunk = [0; 4096];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the bus closed the connection",
+                    ));
+                }
+                Ok(read) => self.received.extend_from_slice(&chunk[..read]),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Skips a message that couldn't be decoded, e.g. as it has arguments of a type that isn't
+    /// supported, replying with [`INVALID_ARGS`] if it's a method call expecting a reply.
+    fn reject(&mut self, buffer: &[u8], error: &io::Error) -> io::Result<()> {
+        tracing::debug!(%error, "skipped a message that couldn't be decoded");
+        match Message::decode_header(buffer) {
+            Ok((call, _, _)) if call.expects_reply() => {
+                self.send(call.error_reply(INVALID_ARGS, &error.to_string()))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Calls a method and waits for its reply, dropping any other messages in the meantime.
+    /// Only meant for connections that aren't used for anything but calls, or before they are.
+    ///
+    /// Error replies are returned as errors of kind [`io::ErrorKind::PermissionDenied`] if access
+    /// was denied, or [`io::ErrorKind::Other`].
+    pub fn call(&mut self, message: Message) -> io::Result<Message> {
+        let serial = self.send(message)?;
+        loop {
+            let reply = self
+                .receive(CONNECT_TIMEOUT)?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "the bus didn't reply"))?;
+            if reply.reply_serial != Some(serial) {
+                continue;
+            }
+            if reply.kind == MessageType::Error {
+                let name = reply.error_name.as_deref().unwrap_or("unknown error");
+                let text = reply.body.first().and_then(Value::as_str).unwrap_or("");
+                let kind = match name {
+                    ACCESS_DENIED => io::ErrorKind::PermissionDenied,
+                    _ => io::ErrorKind::Other,
+                };
+                return Err(io::Error::new(kind, format!("{}: {}", name, text)));
+            }
+            return Ok(reply);
+        }
+    }
+
+    /// Takes ownership of a well-known name, failing if another connection owns it.
+    pub fn request_name(&mut self, name: &str) -> io::Result<()> {
+        /// `DBUS_NAME_FLAG_DO_NOT_QUEUE`
+        const DO_NOT_QUEUE: u32 = 0x4;
+        /// `DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER` and `..._ALREADY_OWNER`
+        const OWNER: [u32; 2] = [1, 4];
+        let mut request = Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "RequestName",
+        );
+        request.body = vec![Value::String(name.to_string()), Value::U32(DO_NOT_QUEUE)];
+        let reply = self.call(request)?;
+        match reply.body.first().and_then(Value::as_u32) {
+            Some(code) if OWNER.contains(&code) => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is already owned by another process", name),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_messages() {
+        let mut call = Message::method_call(
+            "dev.mmk21.Glimpse",
+            "/dev/mmk21/Glimpse",
+            "dev.mmk21.Glimpse",
+            "SetBrightness",
+        );
+        call.serial = 7;
+        call.body = vec![
+            Value::String("input3::capslock".to_string()),
+            Value::U32(1),
+            Value::Array(
+                "{sv}".to_string(),
+                vec![Value::DictEntry(
+                    Box::new(Value::String("Leds".to_string())),
+                    Box::new(Value::Variant(Box::new(Value::Array(
+                        "(suus)".to_string(),
+                        vec![Value::Struct(vec![
+                            Value::String("phy0-led".to_string()),
+                            Value::U32(0),
+                            Value::U32(1),
+                            Value::String("phy0rx".to_string()),
+                        ])],
+                    )))),
+                )],
+            ),
+        ];
+        assert_eq!(call.signature(), "sua{sv}");
+        let encoded = call.encode();
+        assert_eq!(Message::length(&encoded).unwrap(), Some(encoded.len()));
+        assert_eq!(Message::length(&encoded[..15]).unwrap(), None);
+        assert_eq!(Message::decode(&encoded).unwrap(), call);
+        assert_eq!(single_type_length(b"a(su)u").unwrap(), 5);
+    }
+}
@@ -0,0 +1,29 @@
+use std::{fs, path::Path};
+
+/// How many parent devices are searched for a driver, e.g. from an input device up to the USB
+/// interface that owns it.
+const MAX_PARENT_DEPTH: usize = 4;
+
+/// Finds the kernel driver behind an LED, preferring the module name over the driver name.
+///
+/// Starts at the LED's `device` link and walks up the device tree, because LEDs often belong to
+/// a device (such as an input device) that has no driver of its own.
+pub fn resolve_driver(led_path: &Path) -> Option<String> {
+    let mut device = fs::canonicalize(led_path.join("device")).ok()?;
+    for _ in 0..MAX_PARENT_DEPTH {
+        let driver = device.join("driver");
+        if let Some(name) = link_name(&driver.join("module")).or_else(|| link_name(&driver)) {
+            return Some(name);
+        }
+        if !device.pop() {
+            break;
+        }
+    }
+    None
+}
+
+/// The last component of a symlink's target.
+fn link_name(link: &Path) -> Option<String> {
+    let target = fs::read_link(link).ok()?;
+    Some(target.file_name()?.to_string_lossy().into_owned())
+}
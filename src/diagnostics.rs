@@ -0,0 +1,56 @@
+//! Internal diagnostics through `tracing`, written to the log file when asked for with `-v` or
+//! `GLIMPSE_LOG`.
+//!
+//! These are for debugging Glimpse itself (e.g. every sysfs read and write), unlike the
+//! messages in the TUI's log pane, which are for users.
+
+use std::{
+    env,
+    io::{self, Write},
+};
+
+use color_eyre::{Result, eyre::eyre};
+use tracing_subscriber::{filter::Targets, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::log;
+
+/// Overrides `-v` with directives like `glimpse=trace` or `glimpse::backend=debug,info`.
+pub const FILTER_VARIABLE: &str = "GLIMPSE_LOG";
+
+/// The filter for `-v` (debug) and `-vv` (trace), if diagnostics are wanted at all.
+fn filter(verbosity: u8) -> Result<Option<Targets>> {
+    if let Some(directives) = env::var(FILTER_VARIABLE)
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+    {
+        return directives
+            .parse()
+            .map(Some)
+            .map_err(|e| eyre!("invalid {}: {}", FILTER_VARIABLE, e));
+    }
+    Ok(match verbosity {
+        0 => None,
+        1 => Some("glimpse=debug".parse()?),
+        _ => Some("glimpse=trace".parse()?),
+    })
+}
+
+/// Starts writing diagnostics to the log file, if `-v` or `GLIMPSE_LOG` asks for them.
+pub fn init(verbosity: u8) -> Result<()> {
+    let Some(filter) = filter(verbosity)? else {
+        return Ok(());
+    };
+    let path = log::log_file_path()?;
+    // Opened for each event, so that it follows the log file when the TUI rotates it
+    let writer = move || -> Box<dyn Write> {
+        match log::open_for_appending(&path) {
+            Ok(file) => Box::new(file),
+            Err(_) => Box::new(io::sink()),
+        }
+    };
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_ansi(false).with_writer(writer))
+        .with(filter)
+        .try_init()?;
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use std::{env, path::PathBuf};
+
+/// Resolves an XDG base directory, falling back to the given path inside `$HOME`.
+fn xdg_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    env::var_os(variable)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+        .map(|dir| dir.join("glimpse"))
+}
+
+/// Glimpse's directory under `$XDG_CONFIG_HOME` (usually `~/.config/glimpse`).
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Glimpse's directory under `$XDG_STATE_HOME` (usually `~/.local/state/glimpse`).
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
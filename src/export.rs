@@ -0,0 +1,183 @@
+//! Dumping the state of the LEDs as JSON with `glimpse export`, and setting them back to it
+//! with `glimpse import`, e.g. to back up a setup or copy it to another machine.
+//!
+//! The file is an object with the format `version` and the LEDs in the same form as
+//! `glimpse list --json`:
+//!
+//! ```json
+//! {"version":1,"leds":[
+//! {"name":"input3::capslock","path":"/sys/class/leds/input3::capslock","brightness":1,"max_brightness":1,"trigger":"none"}
+//! ]}
+//! ```
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use color_eyre::Result;
+use thiserror::Error;
+
+use crate::{
+    LED, get_all_leds, glob,
+    json::{self, ParseJsonError, Value},
+    output::{Output, Tone},
+    profiles::{self, LedState, Profile},
+    terminal::Progress,
+};
+
+/// Version of the format written by [`export`], increased when older versions of Glimpse
+/// couldn't import it correctly anymore.
+const FORMAT_VERSION: u64 = 1;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ImportError {
+    #[error(transparent)]
+    InvalidJson(#[from] ParseJsonError),
+    #[error("unsupported state file version {0}, expected {FORMAT_VERSION}")]
+    UnsupportedVersion(u64),
+    #[error("invalid state file: {0}")]
+    Invalid(String),
+}
+
+/// The state of the LEDs as the JSON document [`parse`] reads.
+pub fn export(leds: &[LED]) -> io::Result<String> {
+    let objects = leds
+        .iter()
+        .map(LED::to_json)
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(format!(
+        "{{\"version\":{},\"leds\":[\n{}\n]}}\n",
+        FORMAT_VERSION,
+        objects.join(",\n")
+    ))
+}
+
+/// Reads an exported state as a profile for the LEDs.
+///
+/// Brightness levels are scaled to LEDs whose maximum differs from the exported one, as the
+/// same LED can have a different range on another machine or driver version.
+pub fn parse(contents: &str, leds: &[LED]) -> Result<Profile, ImportError> {
+    let document = json::parse(contents)?;
+    match document.get("version").and_then(Value::as_u64) {
+        Some(FORMAT_VERSION) => {}
+        Some(version) => return Err(ImportError::UnsupportedVersion(version)),
+        None => return Err(ImportError::Invalid("missing version".to_string())),
+    }
+    let entries = document
+        .get("leds")
+        .and_then(Value::as_array)
+        .ok_or_else(|| ImportError::Invalid("missing leds array".to_string()))?;
+    let mut profile = Profile::default();
+    for (i, entry) in entries.iter().enumerate() {
+        let invalid = |message: &str| ImportError::Invalid(format!("LED {}: {}", i + 1, message));
+        let name = entry
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("missing name"))?;
+        let brightness = entry
+            .get("brightness")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid("missing brightness"))?;
+        let trigger = match entry.get("trigger") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(trigger)) => Some(trigger.clone()).filter(|t| t != "none"),
+            Some(_) => return Err(invalid("the trigger has to be a string or null")),
+        };
+        let exported_max = entry.get("max_brightness").and_then(Value::as_u64);
+        let local_max = leds
+            .iter()
+            .find(|led| led.file_name == name)
+            .map(|led| u64::from(led.max_brightness));
+        let brightness = match (exported_max, local_max) {
+            (Some(exported), Some(local)) if exported > 0 && exported != local => {
+                (brightness.min(exported) * local + exported / 2) / exported
+            }
+            _ => brightness,
+        };
+        profile.leds.insert(
+            name.to_string(),
+            LedState {
+                brightness: u32::try_from(brightness).unwrap_or(u32::MAX),
+                trigger,
+                color: None,
+            },
+        );
+    }
+    Ok(profile)
+}
+
+/// Prints the state of the LEDs given (by name or glob pattern), or of all of them.
+pub fn run_export(led_names: Vec<String>) -> Result<()> {
+    let leds = if led_names.is_empty() {
+        let mut leds = get_all_leds()?;
+        leds.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        leds
+    } else {
+        glob::expand(&led_names)?
+    };
+    print!("{}", export(&leds)?);
+    Ok(())
+}
+
+/// Sets the LEDs to the state in an exported file, or standard input for `-`, failing if any
+/// LED couldn't be set.
+pub fn run_import(path: &Path) -> Result<()> {
+    let contents = if path == Path::new("-") {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        contents
+    } else {
+        fs::read_to_string(path)?
+    };
+    let mut leds = get_all_leds()?;
+    let profile = parse(&contents, &leds)?;
+    let progress = Progress::start();
+    let (edits, errors) =
+        profile.apply_with_progress(&mut leds, |done, total| progress.update(done, total));
+    drop(progress);
+    let output = Output::detect();
+    profiles::report_applied(
+        &format!(
+            "Imported {}",
+            output.paint(&path.display().to_string(), Tone::Name)
+        ),
+        &edits,
+        &errors,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{MockBackend, backend::MockLed, led::get_all_leds_from};
+
+    #[test]
+    fn round_trips() {
+        let backend = MockBackend::new();
+        backend.add_led(
+            "input3::capslock",
+            MockLed {
+                brightness: 1,
+                ..MockLed::new(1)
+            },
+        );
+        backend.add_led("tpacpi::kbd_backlight", MockLed::new(2));
+        let leds = get_all_leds_from(Arc::new(backend)).unwrap();
+        let profile = parse(&export(&leds).unwrap(), &leds).unwrap();
+        assert_eq!(profile, Profile::capture(&leds).0);
+
+        // From a machine where the keyboard backlight has 100 levels
+        let contents = "{\"version\":1,\"leds\":[\
+            {\"name\":\"tpacpi::kbd_backlight\",\"brightness\":50,\"max_brightness\":100}]}";
+        let profile = parse(contents, &leds).unwrap();
+        assert_eq!(profile.leds["tpacpi::kbd_backlight"].brightness, 1);
+        assert_eq!(
+            parse("{\"version\":2,\"leds\":[]}", &leds),
+            Err(ImportError::UnsupportedVersion(2))
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use std::{env, path::Path, process::Command};
+
+/// A program that can be pointed at an LED's sysfs directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalTool {
+    /// `$VISUAL` or `$EDITOR` (falling back to `vi`)
+    Editor,
+    /// `$SHELL` (falling back to `sh`), started in the directory
+    Shell,
+    /// The desktop's file manager, via `xdg-open`
+    FileManager,
+}
+
+impl ExternalTool {
+    /// Whether the tool runs in the terminal, so the TUI has to step aside while it runs.
+    pub fn runs_in_terminal(self) -> bool {
+        !matches!(self, Self::FileManager)
+    }
+
+    pub fn command(self, directory: &Path) -> Command {
+        match self {
+            Self::Editor => {
+                let mut command = editor_command(directory);
+                command.current_dir(directory);
+                command
+            }
+            Self::Shell => {
+                let mut command =
+                    Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_string()));
+                command.current_dir(directory);
+                command
+            }
+            Self::FileManager => {
+                let mut command = Command::new("xdg-open");
+                command.arg(directory);
+                command
+            }
+        }
+    }
+}
+
+/// Opens a file or directory in `$VISUAL` or `$EDITOR` (falling back to `vi`).
+pub fn editor_command(path: &Path) -> Command {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // The editor variable may contain arguments, e.g. "code --wait"
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path);
+    command
+}
@@ -0,0 +1,147 @@
+use regex::Regex;
+
+/// How the filter query is matched against LED names.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// The query's characters have to appear in the name in order, case-insensitively
+    #[default]
+    Fuzzy,
+    /// The query is a regular expression
+    Regex,
+}
+
+/// The filter narrowing down the LED list.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub query: String,
+    pub mode: FilterMode,
+    /// Whether the query is being typed in
+    pub editing: bool,
+    /// The compiled query in regex mode, or the error if it isn't a valid regex
+    regex: Option<Result<Regex, regex::Error>>,
+}
+
+impl Filter {
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty()
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.compile();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.compile();
+    }
+
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.editing = false;
+        self.compile();
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            FilterMode::Fuzzy => FilterMode::Regex,
+            FilterMode::Regex => FilterMode::Fuzzy,
+        };
+        self.compile();
+    }
+
+    fn compile(&mut self) {
+        self.regex = (self.mode == FilterMode::Regex).then(|| Regex::new(&self.query));
+    }
+
+    /// The error message if the query is an invalid regex.
+    pub fn error(&self) -> Option<String> {
+        match &self.regex {
+            Some(Err(e)) => Some(e.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The positions of the characters in `name` that match the query, for highlighting them.
+    pub fn match_positions(&self, name: &str) -> Vec<usize> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        match self.mode {
+            FilterMode::Fuzzy => fuzzy_positions(&self.query, name).unwrap_or_default(),
+            FilterMode::Regex => match &self.regex {
+                Some(Ok(regex)) => {
+                    let matched: Vec<_> =
+                        regex.find_iter(name).map(|found| found.range()).collect();
+                    name.char_indices()
+                        .enumerate()
+                        .filter(|(_, (byte, _))| matched.iter().any(|range| range.contains(byte)))
+                        .map(|(position, _)| position)
+                        .collect()
+                }
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self.mode {
+            FilterMode::Fuzzy => fuzzy_matches(&self.query, name),
+            // An invalid regex matches nothing, so that the list reflects the typo
+            FilterMode::Regex => self
+                .regex
+                .as_ref()
+                .is_some_and(|regex| regex.as_ref().is_ok_and(|regex| regex.is_match(name))),
+        }
+    }
+}
+
+/// Checks whether all characters of the query appear in the name, in order.
+pub fn fuzzy_matches(query: &str, name: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| name.any(|c| c == q))
+}
+
+/// The positions of the first characters in `name` that match the query's characters in order,
+/// or `None` if they don't all appear.
+pub fn fuzzy_positions(query: &str, name: &str) -> Option<Vec<usize>> {
+    let mut name = name.chars().enumerate();
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(|q| {
+            name.find(|(_, c)| c.to_lowercase().any(|c| c == q))
+                .map(|(position, _)| position)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_fuzzily() {
+        assert!(fuzzy_matches("cpslk", "input3::capslock"));
+        assert!(fuzzy_matches("CAPS", "input3::capslock"));
+        assert!(!fuzzy_matches("kcolspac", "input3::capslock"));
+        assert_eq!(fuzzy_positions("c3", "abc3"), Some(vec![2, 3]));
+        assert_eq!(fuzzy_positions("x", "abc"), None);
+    }
+
+    #[test]
+    fn matches_regexes() {
+        let mut filter = Filter::default();
+        filter.toggle_mode();
+        "lock$".chars().for_each(|c| filter.push(c));
+        assert!(filter.matches("input3::capslock"));
+        assert!(!filter.matches("input3::capslock_led"));
+        assert_eq!(filter.match_positions("numlock"), [3, 4, 5, 6]);
+        filter.push('(');
+        assert!(filter.error().is_some());
+        assert!(!filter.matches("input3::capslock"));
+    }
+}
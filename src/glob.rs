@@ -0,0 +1,138 @@
+use thiserror::Error;
+
+use crate::{
+    LED, NewLEDError, get_all_leds,
+    resolve::{ResolveError, resolve},
+};
+
+#[derive(Debug, Error)]
+pub enum ExpandError {
+    #[error("no LEDs match \"{0}\"")]
+    NoMatches(String),
+    #[error(transparent)]
+    Lookup(#[from] NewLEDError),
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+}
+
+/// Checks whether a name matches a shell-style pattern with `*`, `?` and `[...]` classes.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_from(&pattern, &name)
+}
+
+fn matches_from(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| matches_from(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && matches_from(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(end) = pattern.iter().skip(1).position(|c| *c == ']') else {
+                return name.first() == Some(&'[') && matches_from(&pattern[1..], &name[1..]);
+            };
+            let class = &pattern[1..end + 1];
+            let Some(c) = name.first() else {
+                return false;
+            };
+            let (negated, class) = match class.first() {
+                Some('!' | '^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut in_class = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    in_class |= (class[i]..=class[i + 2]).contains(c);
+                    i += 3;
+                } else {
+                    in_class |= class[i] == *c;
+                    i += 1;
+                }
+            }
+            in_class != negated && matches_from(&pattern[end + 2..], &name[1..])
+        }
+        Some(literal) => name.first() == Some(literal) && matches_from(&pattern[1..], &name[1..]),
+    }
+}
+
+pub fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// Resolves LED identifiers and glob patterns to the matching LEDs, in the order given.
+///
+/// Identifiers are looked up with [`resolve`], so they can be shortened to a function or prefix;
+/// patterns must match at least one LED. LEDs matched more than once are only included the first
+/// time.
+pub fn expand(patterns: &[String]) -> Result<Vec<LED>, ExpandError> {
+    let mut available = get_all_leds()?;
+    available.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    let mut order: Vec<usize> = Vec::new();
+    for pattern in patterns {
+        let matching = if is_pattern(pattern) {
+            let matching: Vec<usize> = (0..available.len())
+                .filter(|&i| matches(pattern, &available[i].file_name))
+                .collect();
+            if matching.is_empty() {
+                return Err(ExpandError::NoMatches(pattern.clone()));
+            }
+            matching
+        } else {
+            vec![resolve(pattern, &available)?]
+        };
+        for i in matching {
+            if !order.contains(&i) {
+                order.push(i);
+            }
+        }
+    }
+    let mut available: Vec<Option<LED>> = available.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .filter_map(|i| available[i].take())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("input*::capslock", "input3::capslock"));
+        assert!(matches("input*::capslock", "input::capslock"));
+        assert!(!matches("input*::capslock", "input3::numlock"));
+        assert!(matches("*", ""));
+        assert!(matches("phy?-led", "phy0-led"));
+        assert!(!matches("phy?-led", "phy-led"));
+        assert!(!matches("phy?-led", "phy10-led"));
+    }
+
+    #[test]
+    fn matches_classes() {
+        assert!(matches("input[0-9]::*", "input3::scrolllock"));
+        assert!(!matches("input[0-9]::*", "inputx::scrolllock"));
+        assert!(matches("input[!0-2]::*", "input3::numlock"));
+        assert!(!matches("input[^0-3]::*", "input3::numlock"));
+        assert!(matches("[ab]c", "bc"));
+        // An unclosed class matches a literal `[`
+        assert!(matches("[x", "[x"));
+        assert!(!matches("[x", "x"));
+    }
+
+    #[test]
+    fn matches_whole_name() {
+        assert!(!matches("capslock", "input3::capslock"));
+        assert!(!matches("input3", "input3::capslock"));
+        assert!(matches("input3::capslock", "input3::capslock"));
+    }
+
+    #[test]
+    fn detects_patterns() {
+        assert!(is_pattern("input*"));
+        assert!(is_pattern("phy?"));
+        assert!(is_pattern("input[0-9]"));
+        assert!(!is_pattern("input3::capslock"));
+    }
+}
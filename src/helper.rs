@@ -0,0 +1,107 @@
+//! Writing to LEDs as root through pkexec, so that desktop users can change LEDs without running
+//! the whole TUI as root.
+//!
+//! With `--escalate`, writes that aren't permitted are retried by running
+//! `pkexec glimpse --helper ...`, which asks the desktop's polkit agent for a password. The
+//! helper only ever writes the `brightness` or `trigger` of an LED in [`SYS_CLASS_LEDS`].
+
+use std::{
+    env, io,
+    process::{Command, Stdio},
+};
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::{LED, NewLEDError, led::SYS_CLASS_LEDS};
+
+/// A write done by the helper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HelperRequest {
+    /// `--helper set <led> <brightness>`
+    Brightness { led: String, brightness: u32 },
+    /// `--helper trigger <led> <trigger>`
+    Trigger { led: String, trigger: String },
+}
+
+impl HelperRequest {
+    pub fn led(&self) -> &str {
+        match self {
+            Self::Brightness { led, .. } | Self::Trigger { led, .. } => led,
+        }
+    }
+
+    /// The arguments after `--helper`.
+    fn args(&self) -> [String; 3] {
+        match self {
+            Self::Brightness { led, brightness } => {
+                ["set".to_string(), led.clone(), brightness.to_string()]
+            }
+            Self::Trigger { led, trigger } => ["trigger".to_string(), led.clone(), trigger.clone()],
+        }
+    }
+}
+
+/// Carries out a request as the helper, after checking that it names an existing LED and, for
+/// triggers, one the LED offers.
+pub fn run(request: HelperRequest) -> Result<()> {
+    // Running as root, so don't let the name escape the LED directory
+    let name = request.led();
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        return Err(eyre!("invalid LED name \"{}\"", name));
+    }
+    let mut led = LED::new(name.to_string()).map_err(|e| match e {
+        NewLEDError::NotFound => eyre!("no LED named {} in {}", name, SYS_CLASS_LEDS),
+        e => e.into(),
+    })?;
+    match &request {
+        HelperRequest::Brightness { brightness, .. } => {
+            led.set_brightness((*brightness).min(led.max_brightness))?;
+        }
+        HelperRequest::Trigger { trigger, .. } => {
+            let offered = led
+                .triggers()?
+                .is_some_and(|triggers| triggers.available.contains(trigger));
+            if !offered {
+                return Err(eyre!("{} doesn't offer the {} trigger", name, trigger));
+            }
+            led.set_trigger(trigger)?;
+        }
+    }
+    Ok(())
+}
+
+/// Retries a write through pkexec if it failed for lack of permission, returning the original
+/// error otherwise.
+pub fn retry(error: io::Error, request: &HelperRequest) -> io::Result<()> {
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        escalate(request)
+    } else {
+        Err(error)
+    }
+}
+
+/// Carries out a request as root by running the helper through pkexec, waiting for the user to
+/// authenticate.
+///
+/// pkexec is kept away from the terminal, which the TUI is drawing on, so only a graphical
+/// polkit agent can ask for the password.
+pub fn escalate(request: &HelperRequest) -> io::Result<()> {
+    tracing::debug!(?request, "retrying through pkexec");
+    let status = Command::new("pkexec")
+        .arg(env::current_exe()?)
+        .arg("--helper")
+        .args(request.args())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        // pkexec exits with 126 when authentication is dismissed or fails
+        Err(io::Error::other(match status.code() {
+            Some(126) => "authentication through pkexec failed".to_string(),
+            _ => format!("the pkexec helper failed ({})", status),
+        }))
+    }
+}
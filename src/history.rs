@@ -0,0 +1,146 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How many samples are kept for each LED before the oldest ones are dropped.
+pub const HISTORY_CAPACITY: usize = 1200;
+
+/// A single brightness reading of an LED.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub timestamp: SystemTime,
+    pub brightness: u32,
+}
+
+/// Records the brightness of every LED over time.
+#[derive(Debug)]
+pub struct BrightnessHistory {
+    capacity: usize,
+    samples: HashMap<String, VecDeque<Sample>>,
+}
+
+impl Default for BrightnessHistory {
+    fn default() -> Self {
+        Self::new(HISTORY_CAPACITY)
+    }
+}
+
+impl BrightnessHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Store a new reading for the LED with the given file name.
+    pub fn record(&mut self, file_name: &str, sample: Sample) {
+        let samples = self.samples.entry(file_name.to_string()).or_default();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// The recorded samples for one LED, oldest first.
+    pub fn samples(&self, file_name: &str) -> Option<&VecDeque<Sample>> {
+        self.samples.get(file_name)
+    }
+
+    /// Writes every recorded sample to a CSV file, ordered by time.
+    ///
+    /// Returns the number of rows written (excluding the header).
+    pub fn export_csv(&self, path: &Path) -> io::Result<usize> {
+        let mut rows: Vec<(&str, &Sample)> = self
+            .samples
+            .iter()
+            .flat_map(|(file_name, samples)| samples.iter().map(|s| (file_name.as_str(), s)))
+            .collect();
+        rows.sort_by(|a, b| a.1.timestamp.cmp(&b.1.timestamp).then(a.0.cmp(b.0)));
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "timestamp,led,brightness")?;
+        for (file_name, sample) in &rows {
+            let unix_time = sample
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            writeln!(
+                writer,
+                "{:.3},{},{}",
+                unix_time,
+                csv_field(file_name),
+                sample.brightness
+            )?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+}
+
+/// Quotes a CSV field if it contains characters that would break the row.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_samples() {
+        let mut history = BrightnessHistory::new(2);
+        for brightness in 0..3 {
+            let sample = Sample {
+                timestamp: UNIX_EPOCH,
+                brightness,
+            };
+            history.record("input3::capslock", sample);
+        }
+        let samples = history.samples("input3::capslock").unwrap();
+        let brightness: Vec<u32> = samples.iter().map(|sample| sample.brightness).collect();
+        assert_eq!(brightness, [1, 2]);
+        assert!(history.samples("phy0-led").is_none());
+    }
+
+    #[test]
+    fn exports_csv_by_time() {
+        let mut history = BrightnessHistory::default();
+        let at = |millis| UNIX_EPOCH + Duration::from_millis(millis);
+        history.record(
+            "input3::capslock",
+            Sample {
+                timestamp: at(2500),
+                brightness: 1,
+            },
+        );
+        history.record(
+            "odd,\"name\"",
+            Sample {
+                timestamp: at(1000),
+                brightness: 0,
+            },
+        );
+        let path = env::temp_dir().join(format!("glimpse-history-test-{}.csv", process::id()));
+        assert_eq!(history.export_csv(&path).unwrap(), 2);
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            contents,
+            "timestamp,led,brightness\n\
+             1.000,\"odd,\"\"name\"\"\",0\n\
+             2.500,input3::capslock,1\n"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Reading JSON, for files written by `glimpse export` (which writes it with
+//! [`crate::output::json_string`] and `format!`).
+//!
+//! Numbers are only read as whole, non-negative numbers, as nothing Glimpse exports needs more.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("invalid JSON at byte {position}: {message}")]
+pub struct ParseJsonError {
+    position: usize,
+    message: String,
+}
+
+impl Value {
+    /// The value of a key, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Object(object) => object.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a whole JSON document.
+pub fn parse(input: &str) -> Result<Value, ParseJsonError> {
+    let mut parser = Parser { input, position: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < input.len() {
+        return Err(parser.error("unexpected data after the end"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    /// Byte offset of the next character to read
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ParseJsonError {
+        ParseJsonError {
+            position: self.position,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseJsonError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(format!("expected {}", expected))),
+        }
+    }
+
+    /// Consumes a keyword like `null`, returning the value it stands for.
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, ParseJsonError> {
+        if self.input[self.position..].starts_with(keyword) {
+            self.position += keyword.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseJsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Value::String),
+            Some('0'..='9') => self.number(),
+            Some('n') => self.keyword("null", Value::Null),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('-') => Err(self.error("negative numbers aren't supported")),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, ParseJsonError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.peek().is_some_and(|c| matches!(c, '.' | 'e' | 'E')) {
+            return Err(self.error("only whole numbers are supported"));
+        }
+        self.input[start..self.position]
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| self.error("number too large"))
+    }
+
+    fn string(&mut self) -> Result<String, ParseJsonError> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex = self
+                                .input
+                                .get(self.position..self.position + 4)
+                                .ok_or_else(|| self.error("incomplete \\u escape"))?;
+                            self.position += 4;
+                            u32::from_str_radix(hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid \\u escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    string.push(escaped);
+                }
+                Some(c) if c.is_control() => return Err(self.error("unescaped control character")),
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseJsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected , or ]")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, ParseJsonError> {
+        self.expect('{')?;
+        let mut object = BTreeMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Value::Object(object));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            object.insert(key, self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(object)),
+                _ => return Err(self.error("expected , or }")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exported_state() {
+        let value = parse(
+            "{\"version\": 1, \"leds\": [\n\
+             {\"name\": \"input3::capslock\", \"brightness\": 1, \"trigger\": null},\n\
+             {\"name\": \"tab\\t\\u00e9\", \"on\": true, \"list\": []}\n\
+             ]}",
+        )
+        .unwrap();
+        assert_eq!(value.get("version").and_then(Value::as_u64), Some(1));
+        let leds = value.get("leds").and_then(Value::as_array).unwrap();
+        assert_eq!(leds[0].get("trigger"), Some(&Value::Null));
+        assert_eq!(leds[1].get("name").and_then(Value::as_str), Some("tab\té"));
+        assert_eq!(leds[1].get("on"), Some(&Value::Bool(true)));
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("{\"a\": 1} x").is_err());
+        assert!(parse("-1").is_err());
+    }
+}
@@ -0,0 +1,680 @@
+//! Key bindings, including multi-key sequences like `g g`.
+
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use thiserror::Error;
+
+use crate::external::ExternalTool;
+
+/// How long the first keys of a sequence wait for the rest before they're dropped.
+pub const PENDING_KEY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Everything that can be bound to keys in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ShowHelp,
+    SelectPrevious,
+    SelectNext,
+    SelectFirst,
+    SelectLast,
+    FocusNextPane,
+    FocusPreviousPane,
+    EditFilter,
+    ToggleLed,
+    BrightnessUp,
+    BrightnessDown,
+    EnterBrightness,
+    ExportHistory,
+    ToggleChart,
+    ToggleChartPaused,
+    TogglePlotted,
+    ToggleBatteryGauge,
+    ToggleCountdown,
+    StartOrPausePomodoro,
+    SkipPomodoroPhase,
+    StopPomodoro,
+    ToggleDnd,
+    ToggleMute,
+    TogglePresentation,
+    AllOff,
+    SaveProfile,
+    ApplyProfile,
+    Undo,
+    Redo,
+    CopyLedInfo,
+    ToggleCompare,
+    ToggleGroupByDriver,
+    ToggleGroupCollapsed,
+    ToggleHidden,
+    ToggleShowHidden,
+    TogglePinned,
+    ToggleSelecting,
+    ToggleRawView,
+    ScrollLogUp,
+    ScrollLogDown,
+    CycleLogLevel,
+    WarmerColor,
+    CoolerColor,
+    EnterColor,
+    EditIntensities,
+    ChooseTrigger,
+    EditTriggerSettings,
+    Shoot,
+    ApplyPreset(char),
+    OpenIn(ExternalTool),
+}
+
+/// The names of actions in the `[keys]` table of `config.toml`.
+const ACTION_NAMES: [(&str, Action); 52] = [
+    ("quit", Action::Quit),
+    ("help", Action::ShowHelp),
+    ("select_previous", Action::SelectPrevious),
+    ("select_next", Action::SelectNext),
+    ("select_first", Action::SelectFirst),
+    ("select_last", Action::SelectLast),
+    ("focus_next_pane", Action::FocusNextPane),
+    ("focus_previous_pane", Action::FocusPreviousPane),
+    ("filter", Action::EditFilter),
+    ("toggle", Action::ToggleLed),
+    ("brightness_up", Action::BrightnessUp),
+    ("brightness_down", Action::BrightnessDown),
+    ("set_brightness", Action::EnterBrightness),
+    ("export_history", Action::ExportHistory),
+    ("chart", Action::ToggleChart),
+    ("pause_chart", Action::ToggleChartPaused),
+    ("plot", Action::TogglePlotted),
+    ("battery_gauge", Action::ToggleBatteryGauge),
+    ("countdown", Action::ToggleCountdown),
+    ("pomodoro", Action::StartOrPausePomodoro),
+    ("skip_pomodoro_phase", Action::SkipPomodoroPhase),
+    ("stop_pomodoro", Action::StopPomodoro),
+    ("do_not_disturb", Action::ToggleDnd),
+    ("mute", Action::ToggleMute),
+    ("presentation", Action::TogglePresentation),
+    ("all_off", Action::AllOff),
+    ("save_profile", Action::SaveProfile),
+    ("apply_profile", Action::ApplyProfile),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("copy_info", Action::CopyLedInfo),
+    ("compare", Action::ToggleCompare),
+    ("group_by", Action::ToggleGroupByDriver),
+    ("collapse_group", Action::ToggleGroupCollapsed),
+    ("hide", Action::ToggleHidden),
+    ("show_hidden", Action::ToggleShowHidden),
+    ("pin", Action::TogglePinned),
+    ("mark", Action::ToggleSelecting),
+    ("raw_attributes", Action::ToggleRawView),
+    ("scroll_log_up", Action::ScrollLogUp),
+    ("scroll_log_down", Action::ScrollLogDown),
+    ("log_level", Action::CycleLogLevel),
+    ("warmer_color", Action::WarmerColor),
+    ("cooler_color", Action::CoolerColor),
+    ("set_color", Action::EnterColor),
+    ("channel_intensities", Action::EditIntensities),
+    ("trigger", Action::ChooseTrigger),
+    ("trigger_settings", Action::EditTriggerSettings),
+    ("fire_oneshot", Action::Shoot),
+    ("open_editor", Action::OpenIn(ExternalTool::Editor)),
+    ("open_shell", Action::OpenIn(ExternalTool::Shell)),
+    (
+        "open_file_manager",
+        Action::OpenIn(ExternalTool::FileManager),
+    ),
+];
+
+impl Action {
+    /// Finds an action by its name in `config.toml`, e.g. `brightness_up` or `preset_1`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(digit) = name.strip_prefix("preset_") {
+            let mut chars = digit.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(digit @ '0'..='9'), None) => Some(Self::ApplyPreset(digit)),
+                _ => None,
+            };
+        }
+        ACTION_NAMES
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|(_, action)| *action)
+    }
+
+    /// Whether the action writes to LEDs, so it's unavailable in read-only mode.
+    pub fn writes_leds(self) -> bool {
+        matches!(
+            self,
+            Self::ToggleLed
+                | Self::BrightnessUp
+                | Self::BrightnessDown
+                | Self::EnterBrightness
+                | Self::ToggleBatteryGauge
+                | Self::ToggleCountdown
+                | Self::StartOrPausePomodoro
+                | Self::SkipPomodoroPhase
+                | Self::ToggleDnd
+                | Self::ToggleMute
+                | Self::TogglePresentation
+                | Self::AllOff
+                | Self::ApplyProfile
+                | Self::Undo
+                | Self::Redo
+                | Self::WarmerColor
+                | Self::CoolerColor
+                | Self::EnterColor
+                | Self::EditIntensities
+                | Self::ChooseTrigger
+                | Self::EditTriggerSettings
+                | Self::Shoot
+                | Self::ApplyPreset(_)
+        )
+    }
+
+    /// Whether the action applies to every marked LED, when some are marked, rather than only
+    /// the selected one.
+    pub fn acts_on_marked(self) -> bool {
+        matches!(
+            self,
+            Self::ToggleLed | Self::BrightnessUp | Self::BrightnessDown | Self::ChooseTrigger
+        )
+    }
+
+    /// Whether the action writes to the selected LED (or the marked ones), so it's unavailable
+    /// if that LED is read-only.
+    pub fn writes_selected_led(self) -> bool {
+        matches!(
+            self,
+            Self::ToggleLed
+                | Self::BrightnessUp
+                | Self::BrightnessDown
+                | Self::EnterBrightness
+                | Self::WarmerColor
+                | Self::CoolerColor
+                | Self::EnterColor
+                | Self::EditIntensities
+                | Self::ChooseTrigger
+                | Self::EditTriggerSettings
+                | Self::Shoot
+                | Self::ApplyPreset(_)
+        )
+    }
+}
+
+/// A single key press, with Shift folded into the character for letter keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    modifiers: KeyModifiers,
+    code: KeyCode,
+}
+
+impl KeyPress {
+    const fn new(code: KeyCode) -> Self {
+        Self {
+            modifiers: KeyModifiers::NONE,
+            code,
+        }
+    }
+
+    const fn char(c: char) -> Self {
+        Self::new(KeyCode::Char(c))
+    }
+
+    const fn ctrl(c: char) -> Self {
+        Self {
+            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Char(c),
+        }
+    }
+
+    fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            code => code.to_string(),
+        };
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            format!("Ctrl-{}", key)
+        } else {
+            key
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unknown key \"{0}\", expected a character or a name like ctrl+c, space or pageup")]
+pub struct ParseKeyError(String);
+
+/// Keys are written as a character (`q`, `G`, `+`) or a name (`space`, `enter`, `esc`, `tab`,
+/// `backspace`, `up`, `pageup`, `home`, `f1`, ...), optionally after `ctrl+`, or `shift+tab`.
+impl FromStr for KeyPress {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseKeyError(s.to_string());
+        let (ctrl, key) = match s.get(..5) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("ctrl+") && s.len() > 5 => (true, &s[5..]),
+            _ => (false, s),
+        };
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "shift+tab" | "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => KeyCode::F(
+                    name.strip_prefix('f')
+                        .and_then(|number| number.parse().ok())
+                        .filter(|number| (1..=12).contains(number))
+                        .ok_or_else(error)?,
+                ),
+            },
+        };
+        Ok(match code {
+            KeyCode::Char(c) if ctrl => Self::ctrl(c.to_ascii_lowercase()),
+            _ if ctrl => return Err(error()),
+            code => Self::new(code),
+        })
+    }
+}
+
+/// Parses a sequence of keys separated by spaces, e.g. `g g`.
+pub fn parse_keys(keys: &str) -> Result<Vec<KeyPress>, ParseKeyError> {
+    let keys: Vec<KeyPress> = keys
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    if keys.is_empty() {
+        return Err(ParseKeyError(String::new()));
+    }
+    Ok(keys)
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        let modifiers = match key.code {
+            KeyCode::Char(_) | KeyCode::BackTab => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        let code = match key.code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            code => code,
+        };
+        Self { modifiers, code }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    keys: Vec<KeyPress>,
+    action: Action,
+    description: &'static str,
+}
+
+/// Maps key sequences to actions, remembering the start of a sequence between presses.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+    pending: Vec<KeyPress>,
+    pending_since: Option<Instant>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        let mut bindings = vec![
+            (vec![KeyPress::new(KeyCode::Esc)], Quit, "quit"),
+            (vec![KeyPress::char('q')], Quit, "quit"),
+            (vec![KeyPress::ctrl('c')], Quit, "quit"),
+            (vec![KeyPress::char('?')], ShowHelp, "help"),
+            (
+                vec![KeyPress::new(KeyCode::Up)],
+                SelectPrevious,
+                "previous LED",
+            ),
+            (vec![KeyPress::new(KeyCode::Down)], SelectNext, "next LED"),
+            (
+                vec![KeyPress::char('g'), KeyPress::char('g')],
+                SelectFirst,
+                "first LED",
+            ),
+            (
+                vec![KeyPress::char('g'), KeyPress::char('e')],
+                SelectLast,
+                "last LED",
+            ),
+            (vec![KeyPress::char('G')], SelectLast, "last LED"),
+            (
+                vec![KeyPress::char('g'), KeyPress::char('d')],
+                ToggleGroupByDriver,
+                "group by driver/device",
+            ),
+            (
+                vec![KeyPress::char('z')],
+                ToggleGroupCollapsed,
+                "collapse group",
+            ),
+            (vec![KeyPress::char('f')], TogglePinned, "pin LED"),
+            (
+                vec![KeyPress::char('v')],
+                ToggleSelecting,
+                "mark several LEDs",
+            ),
+            (vec![KeyPress::char('h')], ToggleHidden, "hide LED"),
+            (
+                vec![KeyPress::char('H')],
+                ToggleShowHidden,
+                "show hidden LEDs",
+            ),
+            (
+                vec![KeyPress::new(KeyCode::Tab)],
+                FocusNextPane,
+                "next pane",
+            ),
+            (
+                vec![KeyPress::new(KeyCode::BackTab)],
+                FocusPreviousPane,
+                "previous pane",
+            ),
+            (vec![KeyPress::char('/')], EditFilter, "filter"),
+            (vec![KeyPress::char(' ')], ToggleLed, "toggle LED"),
+            (vec![KeyPress::new(KeyCode::Enter)], ToggleLed, "toggle LED"),
+            (vec![KeyPress::char('+')], BrightnessUp, "brighter"),
+            (vec![KeyPress::char('=')], BrightnessUp, "brighter"),
+            (
+                vec![KeyPress::new(KeyCode::Right)],
+                BrightnessUp,
+                "brighter",
+            ),
+            (vec![KeyPress::char('-')], BrightnessDown, "dimmer"),
+            (vec![KeyPress::new(KeyCode::Left)], BrightnessDown, "dimmer"),
+            (vec![KeyPress::char('B')], EnterBrightness, "set brightness"),
+            (vec![KeyPress::char('e')], ExportHistory, "export history"),
+            (vec![KeyPress::char('c')], ToggleChart, "chart"),
+            (vec![KeyPress::char('p')], ToggleChartPaused, "pause chart"),
+            (vec![KeyPress::char('m')], TogglePlotted, "plot LED"),
+            (
+                vec![KeyPress::char('b')],
+                ToggleBatteryGauge,
+                "battery gauge",
+            ),
+            (vec![KeyPress::char('d')], ToggleCountdown, "countdown"),
+            (vec![KeyPress::char('w')], StartOrPausePomodoro, "pomodoro"),
+            (
+                vec![KeyPress::char('n')],
+                SkipPomodoroPhase,
+                "skip pomodoro phase",
+            ),
+            (vec![KeyPress::char('W')], StopPomodoro, "stop pomodoro"),
+            (vec![KeyPress::char('D')], ToggleDnd, "do not disturb"),
+            (vec![KeyPress::char('M')], ToggleMute, "mute"),
+            (
+                vec![KeyPress::char('P')],
+                TogglePresentation,
+                "presentation mode",
+            ),
+            (vec![KeyPress::char('!')], AllOff, "turn all LEDs off"),
+            (vec![KeyPress::char('s')], SaveProfile, "save profile"),
+            (vec![KeyPress::char('a')], ApplyProfile, "apply profile"),
+            (vec![KeyPress::char('u')], Undo, "undo"),
+            (vec![KeyPress::ctrl('r')], Redo, "redo"),
+            (vec![KeyPress::char('y')], CopyLedInfo, "copy LED info"),
+            (vec![KeyPress::char('x')], ToggleCompare, "compare LEDs"),
+            (vec![KeyPress::char('r')], ToggleRawView, "raw attributes"),
+            (
+                vec![KeyPress::new(KeyCode::PageUp)],
+                ScrollLogUp,
+                "scroll log up",
+            ),
+            (
+                vec![KeyPress::new(KeyCode::PageDown)],
+                ScrollLogDown,
+                "scroll log down",
+            ),
+            (vec![KeyPress::char('L')], CycleLogLevel, "log level"),
+            (vec![KeyPress::char('[')], WarmerColor, "warmer white"),
+            (vec![KeyPress::char(']')], CoolerColor, "cooler white"),
+            (vec![KeyPress::char('C')], EnterColor, "set color"),
+            (
+                vec![KeyPress::char('i')],
+                EditIntensities,
+                "channel intensities",
+            ),
+            (vec![KeyPress::char('t')], ChooseTrigger, "change trigger"),
+            (
+                vec![KeyPress::char('T')],
+                EditTriggerSettings,
+                "trigger settings",
+            ),
+            (vec![KeyPress::char('o')], Shoot, "fire oneshot"),
+            (
+                vec![KeyPress::char('E')],
+                OpenIn(ExternalTool::Editor),
+                "open in editor",
+            ),
+            (
+                vec![KeyPress::char('S')],
+                OpenIn(ExternalTool::Shell),
+                "open shell",
+            ),
+            (
+                vec![KeyPress::char('O')],
+                OpenIn(ExternalTool::FileManager),
+                "open in file manager",
+            ),
+        ];
+        bindings.extend(
+            ('0'..='9').map(|digit| (vec![KeyPress::char(digit)], ApplyPreset(digit), "preset")),
+        );
+        Self {
+            bindings: bindings
+                .into_iter()
+                .map(|(keys, action, description)| Binding {
+                    keys,
+                    action,
+                    description,
+                })
+                .collect(),
+            pending: Vec::new(),
+            pending_since: None,
+        }
+    }
+}
+
+impl Keymap {
+    /// Binds an action to other keys instead of its default ones, taking the keys from any
+    /// action they were bound to.
+    pub fn rebind(&mut self, action: Action, keys: &[Vec<KeyPress>]) {
+        let description = self
+            .bindings
+            .iter()
+            .find(|binding| binding.action == action)
+            .map_or("", |binding| binding.description);
+        self.bindings
+            .retain(|binding| binding.action != action && !keys.contains(&binding.keys));
+        self.bindings.extend(keys.iter().map(|keys| Binding {
+            keys: keys.clone(),
+            action,
+            description,
+        }));
+    }
+
+    /// Handles a key press, returning the action once a whole sequence has been typed.
+    ///
+    /// Keys that don't continue any sequence are dropped along with the pending ones, and Esc
+    /// only cancels a pending sequence.
+    pub fn press(&mut self, key: KeyEvent) -> Option<Action> {
+        let key = KeyPress::from(key);
+        // Ctrl-C has to quit whatever was typed before it, so it isn't matched as part of a
+        // sequence
+        if key == KeyPress::ctrl('c') {
+            self.cancel();
+            return Some(Action::Quit);
+        }
+        if self.is_pending() && key.code == KeyCode::Esc {
+            self.cancel();
+            return None;
+        }
+        self.pending.push(key);
+        let mut is_prefix = false;
+        for binding in &self.bindings {
+            if binding.keys == self.pending {
+                let action = binding.action;
+                self.cancel();
+                return Some(action);
+            }
+            is_prefix |= binding.keys.starts_with(&self.pending);
+        }
+        if is_prefix {
+            self.pending_since = Some(Instant::now());
+        } else {
+            self.cancel();
+        }
+        None
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+        self.pending_since = None;
+    }
+
+    /// Drops the pending keys if the rest of the sequence hasn't come in time.
+    pub fn expire(&mut self) {
+        if self
+            .pending_since
+            .is_some_and(|since| since.elapsed() >= PENDING_KEY_TIMEOUT)
+        {
+            self.cancel();
+        }
+    }
+
+    /// The typed keys of the pending sequence, e.g. `g`.
+    pub fn pending_label(&self) -> String {
+        self.pending
+            .iter()
+            .map(KeyPress::label)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The shortest keys bound to an action, e.g. `q` rather than `Esc` for quitting.
+    pub fn keys_for(&self, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.action == action)
+            .map(|binding| {
+                let keys: Vec<String> = binding.keys.iter().map(KeyPress::label).collect();
+                keys.join(" ")
+            })
+            .min_by_key(String::len)
+    }
+
+    /// Every binding, with the keys of actions bound more than once joined, in the order they
+    /// were bound.
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        let mut help: Vec<(String, &'static str)> = Vec::new();
+        for binding in &self.bindings {
+            let keys: Vec<String> = binding.keys.iter().map(KeyPress::label).collect();
+            let keys = keys.join(" ");
+            match help
+                .iter_mut()
+                .find(|(_, description)| *description == binding.description)
+            {
+                Some((bound, _)) => {
+                    bound.push_str(", ");
+                    bound.push_str(&keys);
+                }
+                None => help.push((keys, binding.description)),
+            }
+        }
+        help
+    }
+
+    /// The keys that can follow the pending ones, with what they'd do.
+    pub fn continuations(&self) -> Vec<(String, &'static str)> {
+        self.bindings
+            .iter()
+            .filter(|binding| {
+                binding.keys.len() > self.pending.len() && binding.keys.starts_with(&self.pending)
+            })
+            .map(|binding| {
+                let rest: Vec<String> = binding.keys[self.pending.len()..]
+                    .iter()
+                    .map(KeyPress::label)
+                    .collect();
+                (rest.join(" "), binding.description)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!("q".parse(), Ok(KeyPress::char('q')));
+        assert_eq!("Space".parse(), Ok(KeyPress::char(' ')));
+        assert_eq!("CTRL+R".parse(), Ok(KeyPress::ctrl('r')));
+        assert_eq!("shift+tab".parse(), Ok(KeyPress::new(KeyCode::BackTab)));
+        assert_eq!("f12".parse(), Ok(KeyPress::new(KeyCode::F(12))));
+        assert_eq!(
+            parse_keys("g  g"),
+            Ok(vec![KeyPress::char('g'), KeyPress::char('g')])
+        );
+        for invalid in ["f13", "ctrl+up", "ctrl+", "enterr", ""] {
+            assert_eq!(
+                invalid.parse::<KeyPress>(),
+                Err(ParseKeyError(invalid.to_string()))
+            );
+        }
+        assert_eq!(parse_keys(" "), Err(ParseKeyError(String::new())));
+    }
+
+    #[test]
+    fn matches_sequences() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.press(KeyEvent::from(KeyCode::Char('g'))), None);
+        assert_eq!(keymap.pending_label(), "g");
+        assert_eq!(
+            keymap.press(KeyEvent::from(KeyCode::Char('g'))),
+            Some(Action::SelectFirst)
+        );
+        // Esc cancels the sequence rather than quitting
+        keymap.press(KeyEvent::from(KeyCode::Char('g')));
+        assert_eq!(keymap.press(KeyEvent::from(KeyCode::Esc)), None);
+        assert!(!keymap.is_pending());
+        keymap.press(KeyEvent::from(KeyCode::Char('g')));
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.press(ctrl_c), Some(Action::Quit));
+        assert!(!keymap.is_pending());
+    }
+}
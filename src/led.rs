@@ -0,0 +1,489 @@
+//! Finding the LEDs in `/sys/class/leds` and reading and changing their state, as well as the
+//! screen backlights in `/sys/class/backlight`, which are read as LEDs without triggers.
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+use thiserror::Error;
+
+use crate::{
+    backend::{BacklightBackend, DeviceClass, LedBackend, SysfsBackend},
+    device, output,
+};
+
+/// Where the kernel lists the LEDs.
+pub const SYS_CLASS_LEDS: &str = "/sys/class/leds";
+/// Where the kernel lists the screen backlights.
+pub const SYS_CLASS_BACKLIGHT: &str = "/sys/class/backlight";
+/// Upper limit on the threads reading LEDs in parallel.
+const MAX_ENUMERATION_THREADS: usize = 8;
+
+/// An LED, as last read from its backend (sysfs, unless created with [`LED::with_backend`]).
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct LED {
+    /// Name of the LED's directory, e.g. `input3::capslock`
+    pub file_name: String,
+    /// The file name made readable
+    pub name: String,
+    /// The device part of a `device:color:function` name, e.g. `input3`
+    pub device: Option<String>,
+    /// The color part of a `device:color:function` name, e.g. `green`
+    pub color: Option<String>,
+    /// The function part of a `device:color:function` name, e.g. `capslock`
+    pub function: Option<String>,
+    pub is_on: bool,
+    /// Kernel module or driver the LED belongs to, if it could be found
+    pub driver: Option<String>,
+    pub brightness: u32,
+    /// The highest brightness, read when the LED is found
+    pub max_brightness: u32,
+    pub kind: LedKind,
+    backend: Arc<dyn LedBackend>,
+}
+
+/// What an LED can be set to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LedKind {
+    /// Just a brightness
+    Single,
+    /// An intensity for each color channel (e.g. `red green blue`), all scaled by the
+    /// brightness
+    Multicolor { channels: Vec<String> },
+    /// A screen backlight: just a brightness, usually with far more levels than an LED
+    Backlight,
+}
+
+/// Why an LED couldn't be read.
+#[derive(Debug, Error)]
+pub enum NewLEDError {
+    #[error("LED does not exist")]
+    NotFound,
+    #[error("Invalid brightness value")]
+    InvalidBrightness,
+    /// File name is invalid UTF-8
+    #[error("Invalid encoding in file name")]
+    InvalidFileName,
+    #[error("I/O error: {0}")]
+    IOError(std::io::Error),
+}
+
+impl From<std::io::Error> for NewLEDError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IOError(err)
+    }
+}
+
+impl From<NewLEDError> for std::io::Error {
+    fn from(err: NewLEDError) -> Self {
+        match err {
+            NewLEDError::IOError(err) => err,
+            NewLEDError::NotFound => Self::new(ErrorKind::NotFound, err),
+            NewLEDError::InvalidBrightness | NewLEDError::InvalidFileName => {
+                Self::new(ErrorKind::InvalidData, err)
+            }
+        }
+    }
+}
+
+/// The triggers an LED can be driven by, as listed in its `trigger` file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Triggers {
+    /// Every trigger the kernel offers for the LED, including `none`
+    pub available: Vec<String>,
+    /// The trigger marked with square brackets
+    pub active: Option<String>,
+}
+
+impl Triggers {
+    /// Parses the contents of a `trigger` file, e.g. `none [timer] heartbeat`.
+    pub fn parse(contents: &str) -> Self {
+        let mut triggers = Self::default();
+        for trigger in contents.split_whitespace() {
+            match trigger
+                .strip_prefix('[')
+                .and_then(|trigger| trigger.strip_suffix(']'))
+            {
+                Some(active) => {
+                    triggers.active = Some(active.to_string());
+                    triggers.available.push(active.to_string());
+                }
+                None => triggers.available.push(trigger.to_string()),
+            }
+        }
+        triggers
+    }
+}
+
+impl LED {
+    /// Reads the LED with the given directory name in [`SYS_CLASS_LEDS`].
+    pub fn new(file_name: String) -> Result<Self, NewLEDError> {
+        Self::with_backend(file_name, Arc::new(SysfsBackend))
+    }
+
+    /// Reads the LED with the given file name from a backend, which is used for all of the
+    /// LED's reads and writes from then on.
+    pub fn with_backend(
+        file_name: String,
+        backend: Arc<dyn LedBackend>,
+    ) -> Result<Self, NewLEDError> {
+        let brightness = backend.read_brightness(&file_name).map_err(|e| match e {
+            NewLEDError::IOError(e) if e.kind() == ErrorKind::NotFound => NewLEDError::NotFound,
+            e => e,
+        })?;
+        let max_brightness = backend.read_max_brightness(&file_name)?;
+        let kind = match backend.read_multi_index(&file_name)? {
+            _ if backend.class() == DeviceClass::Backlight => LedKind::Backlight,
+            Some(channels) => LedKind::Multicolor { channels },
+            None => LedKind::Single,
+        };
+        let led_path = PathBuf::from(backend.class().dir()).join(&file_name);
+        let (device, color, function) = parse_name(&file_name);
+        Ok(Self {
+            name: file_name.clone().replace("::", " "),
+            device,
+            color,
+            function,
+            file_name,
+            is_on: brightness > 0,
+            driver: device::resolve_driver(&led_path),
+            brightness,
+            max_brightness,
+            kind,
+            backend,
+        })
+    }
+
+    /// The color and function of the LED, for when it's shown under its device. Falls back to
+    /// the file name if the name has neither.
+    pub fn short_name(&self) -> String {
+        let parts: Vec<&str> = [&self.color, &self.function]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if parts.is_empty() {
+            self.file_name.clone()
+        } else {
+            parts.join(" ")
+        }
+    }
+
+    /// Reads the backlight with the given directory name in [`SYS_CLASS_BACKLIGHT`].
+    pub fn backlight(file_name: String) -> Result<Self, NewLEDError> {
+        Self::with_backend(file_name, Arc::new(BacklightBackend))
+    }
+
+    /// Whether this is a screen backlight rather than an LED.
+    pub fn is_backlight(&self) -> bool {
+        self.kind == LedKind::Backlight
+    }
+
+    /// The LED's directory in sysfs.
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(self.backend.class().dir()).join(&self.file_name)
+    }
+
+    /// Turns the LED fully on (using its `max_brightness`) or off.
+    pub fn set_on(&mut self, on: bool) -> io::Result<()> {
+        let value = if on {
+            self.backend.read_max_brightness(&self.file_name)?
+        } else {
+            0
+        };
+        self.backend.write_brightness(&self.file_name, value)?;
+        self.is_on = on;
+        Ok(())
+    }
+
+    /// Reads the LED's triggers, or `None` if it has no trigger file.
+    pub fn triggers(&self) -> io::Result<Option<Triggers>> {
+        self.backend.read_triggers(&self.file_name)
+    }
+
+    /// Makes a trigger drive the LED, or stops any trigger from doing so with `none`.
+    pub fn set_trigger(&mut self, trigger: &str) -> io::Result<()> {
+        self.backend.write_trigger(&self.file_name, trigger)
+    }
+
+    /// Describes the LED as a JSON object, for scripts and status bars. The trigger is `null` for
+    /// LEDs without a trigger file.
+    pub fn to_json(&self) -> io::Result<String> {
+        let trigger = self
+            .triggers()?
+            .and_then(|triggers| triggers.active)
+            .map_or_else(
+                || "null".to_string(),
+                |trigger| output::json_string(&trigger),
+            );
+        Ok(format!(
+            "{{\"name\":{},\"path\":{},\"brightness\":{},\"max_brightness\":{},\"trigger\":{}}}",
+            output::json_string(&self.file_name),
+            output::json_string(&self.path().to_string_lossy()),
+            self.brightness,
+            self.max_brightness,
+            trigger
+        ))
+    }
+
+    /// Reads the highest brightness value the LED supports.
+    pub fn max_brightness(&self) -> Result<u32, NewLEDError> {
+        self.backend.read_max_brightness(&self.file_name)
+    }
+
+    /// Writes a raw brightness value to the LED.
+    pub fn set_brightness(&mut self, brightness: u32) -> io::Result<()> {
+        self.backend.write_brightness(&self.file_name, brightness)?;
+        self.is_on = brightness > 0;
+        Ok(())
+    }
+
+    /// Reads the intensity of each channel of a multicolor LED, in the order of the channels in
+    /// [`LedKind::Multicolor`].
+    pub fn intensities(&self) -> io::Result<Vec<u32>> {
+        self.backend.read_multi_intensity(&self.file_name)
+    }
+
+    /// Sets the intensity of each channel of a multicolor LED, which has to be given for every
+    /// channel.
+    pub fn set_intensities(&self, intensities: &[u32]) -> io::Result<()> {
+        self.backend
+            .write_multi_intensity(&self.file_name, intensities)
+    }
+
+    /// Re-reads the brightness, updating [`LED::is_on`].
+    pub fn refresh(&mut self) -> Result<u32, NewLEDError> {
+        let brightness = self.backend.read_brightness(&self.file_name)?;
+        self.update_brightness(brightness);
+        Ok(brightness)
+    }
+
+    /// Takes a brightness read some other way, e.g. along with other LEDs, as the current one.
+    pub fn update_brightness(&mut self, brightness: u32) {
+        self.brightness = brightness;
+        // Writes since the last read may have changed this even if the brightness reads the same
+        self.is_on = brightness > 0;
+    }
+}
+
+/// Splits an LED name following the kernel's `device:color:function` convention, with empty
+/// parts left out. Device names can contain colons themselves, so the name is split from the
+/// end. Names with one colon are taken as `device:function`, and names without any as just a
+/// function.
+pub fn parse_name(file_name: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let part = |part: &str| (!part.is_empty()).then(|| part.to_string());
+    let mut parts = file_name.rsplitn(3, ':');
+    let last = parts.next().unwrap_or_default();
+    match (parts.next(), parts.next()) {
+        (Some(color), Some(device)) => (part(device), part(color), part(last)),
+        (Some(device), None) => (part(device), None, part(last)),
+        _ => (None, None, part(last)),
+    }
+}
+
+pub(crate) fn read_max_brightness(led_path: &Path) -> Result<u32, NewLEDError> {
+    let max_brightness_data = fs::read_to_string(led_path.join("max_brightness"))?;
+    max_brightness_data
+        .trim()
+        .parse()
+        .map_err(|_| NewLEDError::InvalidBrightness)
+}
+
+pub(crate) fn read_triggers(led_path: &Path) -> std::io::Result<Option<Triggers>> {
+    match fs::read_to_string(led_path.join("trigger")) {
+        Ok(contents) => Ok(Some(Triggers::parse(&contents))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn read_brightness(led_path: &Path) -> Result<u32, NewLEDError> {
+    parse_brightness(&fs::read_to_string(led_path.join("brightness"))?)
+}
+
+fn parse_brightness(brightness_data: &str) -> Result<u32, NewLEDError> {
+    brightness_data
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| NewLEDError::InvalidBrightness)
+}
+
+/// The raw brightness for a percentage of the maximum, rounded to the nearest level.
+/// Percentages above 100 are taken as 100.
+pub fn percent_to_brightness(percent: u32, max_brightness: u32) -> u32 {
+    let brightness = (u64::from(max_brightness) * u64::from(percent.min(100)) + 50) / 100;
+    u32::try_from(brightness).unwrap_or(max_brightness)
+}
+
+/// A brightness as a percentage of the maximum, rounded to the nearest percent. Only off and
+/// full brightness are shown as 0% and 100%, so that a dim LED isn't taken for an off one.
+pub fn brightness_to_percent(brightness: u32, max_brightness: u32) -> u32 {
+    if max_brightness == 0 {
+        return 0;
+    }
+    let brightness = brightness.min(max_brightness);
+    let max = u64::from(max_brightness);
+    let percent = (u64::from(brightness) * 100 + max / 2) / max;
+    match brightness {
+        0 => 0,
+        _ if brightness == max_brightness => 100,
+        _ => percent.clamp(1, 99) as u32,
+    }
+}
+
+/// A brightness with its maximum and percentage, e.g. `96000/120000 (80%)`.
+pub fn format_brightness(brightness: u32, max_brightness: u32) -> String {
+    format!(
+        "{}/{} ({}%)",
+        brightness,
+        max_brightness,
+        brightness_to_percent(brightness, max_brightness)
+    )
+}
+
+/// Lists the file names of all LEDs, without reading the LEDs.
+pub fn led_file_names() -> Result<Vec<String>, NewLEDError> {
+    SysfsBackend.led_names()
+}
+
+/// Lists the file names of all backlights, without reading them.
+pub fn backlight_file_names() -> Result<Vec<String>, NewLEDError> {
+    BacklightBackend.led_names()
+}
+
+/// An LED that's listed but couldn't be read, e.g. because its driver reports an invalid
+/// brightness.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct FailedLED {
+    pub file_name: String,
+    pub error: NewLEDError,
+}
+
+/// The result of reading every LED: the ones that could be read, and the ones that couldn't.
+#[derive(Debug, Default)]
+pub struct LedScan {
+    pub leds: Vec<LED>,
+    pub failed: Vec<FailedLED>,
+}
+
+/// Reads every LED in [`SYS_CLASS_LEDS`], skipping the ones that can't be read.
+pub fn get_all_leds() -> Result<Vec<LED>, NewLEDError> {
+    get_all_leds_from(Arc::new(SysfsBackend))
+}
+
+/// Reads every LED from a backend, skipping the ones that can't be read.
+pub fn get_all_leds_from(backend: Arc<dyn LedBackend>) -> Result<Vec<LED>, NewLEDError> {
+    Ok(scan_leds_from(backend)?.leds)
+}
+
+/// Reads every LED in [`SYS_CLASS_LEDS`], keeping track of the ones that can't be read.
+pub fn scan_leds() -> Result<LedScan, NewLEDError> {
+    scan_leds_from(Arc::new(SysfsBackend))
+}
+
+/// Reads every LED and every backlight in [`SYS_CLASS_BACKLIGHT`], for the TUI.
+///
+/// [`scan_leds`] leaves backlights out, so that commands acting on all LEDs leave the screen
+/// alone.
+pub fn scan_leds_and_backlights() -> Result<LedScan, NewLEDError> {
+    let mut scan = scan_leds()?;
+    let backlights = scan_leds_from(Arc::new(BacklightBackend))?;
+    scan.leds.extend(backlights.leds);
+    scan.failed.extend(backlights.failed);
+    Ok(scan)
+}
+
+/// Reads every LED from a backend, spread over a few threads because some (e.g. ones backed by
+/// ACPI or an EC) take tens of milliseconds per read.
+///
+/// Only failing to list the LEDs is an error. LEDs that disappear while being read are left
+/// out, and ones that can't be read for other reasons are listed in [`LedScan::failed`].
+pub fn scan_leds_from(backend: Arc<dyn LedBackend>) -> Result<LedScan, NewLEDError> {
+    let _span = tracing::debug_span!("scan_leds").entered();
+    let file_names = backend.led_names()?;
+    tracing::debug!(leds = file_names.len(), "listed LEDs");
+    let threads = thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .clamp(1, MAX_ENUMERATION_THREADS);
+    let chunk_size = file_names.len().div_ceil(threads).max(1);
+    let results: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = file_names
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let backend = &backend;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|file_name| {
+                            (
+                                file_name,
+                                LED::with_backend(file_name.clone(), backend.clone()),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("LED enumeration thread panicked"))
+            .collect()
+    });
+    let mut scan = LedScan::default();
+    for (file_name, result) in results {
+        match result {
+            Ok(led) => scan.leds.push(led),
+            Err(NewLEDError::NotFound) => {}
+            Err(error) => scan.failed.push(FailedLED {
+                file_name: file_name.clone(),
+                error,
+            }),
+        }
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_percentages() {
+        assert_eq!(percent_to_brightness(50, 255), 128);
+        assert_eq!(percent_to_brightness(150, 255), 255);
+        assert_eq!(percent_to_brightness(1, 1), 0);
+        assert_eq!(percent_to_brightness(100, u32::MAX), u32::MAX);
+        assert_eq!(brightness_to_percent(128, 255), 50);
+        // Nearly off or fully on still reads as neither
+        assert_eq!(brightness_to_percent(1, 120000), 1);
+        assert_eq!(brightness_to_percent(119900, 120000), 99);
+        assert_eq!(brightness_to_percent(300, 255), 100);
+        assert_eq!(brightness_to_percent(0, 0), 0);
+        assert_eq!(format_brightness(96000, 120000), "96000/120000 (80%)");
+    }
+
+    #[test]
+    fn parses_triggers() {
+        let triggers = Triggers::parse("none kbd-capslock [timer] heartbeat\n");
+        assert_eq!(triggers.active.as_deref(), Some("timer"));
+        assert_eq!(
+            triggers.available,
+            ["none", "kbd-capslock", "timer", "heartbeat"]
+        );
+        assert_eq!(
+            Triggers::parse("[none] timer").active.as_deref(),
+            Some("none")
+        );
+        // Drivers without an active trigger don't mark any
+        let triggers = Triggers::parse("none timer");
+        assert_eq!(triggers.active, None);
+        assert_eq!(triggers.available, ["none", "timer"]);
+        assert_eq!(Triggers::parse(""), Triggers::default());
+    }
+}
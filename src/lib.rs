@@ -0,0 +1,179 @@
+//! Discovering and controlling the LEDs in `/sys/class/leds`, along with the Glimpse TUI and
+//! command line interface built on top.
+//!
+//! The [`led`] module can be used on its own:
+//!
+//! ```no_run
+//! let mut leds = glimpse::get_all_leds()?;
+//! for led in &mut leds {
+//!     println!("{}: {}/{}", led.file_name, led.brightness, led.max_brightness);
+//!     led.set_on(false)?;
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! LEDs are read from sysfs unless another [`LedBackend`] is given, such as the in-memory
+//! [`MockBackend`] for tests (see [`LED::with_backend`] and [`led::get_all_leds_from`]).
+//!
+//! The TUI's LED list and detail panels are in [`widgets`], for embedding in other ratatui
+//! programs, styled by [`theme`]. The rest of the TUI and the command line interface are only
+//! reachable through [`run`].
+
+use color_eyre::Result;
+
+use crate::cli::{Args, Command};
+
+pub use crate::{
+    backend::{BacklightBackend, LedBackend, MockBackend, SysfsBackend},
+    led::{FailedLED, LED, LedKind, LedScan, NewLEDError, Triggers, get_all_leds, scan_leds},
+};
+
+mod alerts;
+mod app;
+mod attributes;
+pub mod backend;
+mod battery;
+mod bench;
+mod brightnessctl;
+mod calibration;
+mod chart;
+mod cli;
+mod clipboard;
+mod compare;
+mod completions;
+mod config;
+mod confirm;
+mod countdown;
+mod daemon;
+// Off Linux only `Bus` is used, to parse `--dbus` and the daemon's status
+#[cfg_attr(not(target_os = "linux"), allow(dead_code, unused_imports))]
+mod dbus;
+mod device;
+mod diagnostics;
+mod dirs;
+mod export;
+mod external;
+mod filter;
+mod glob;
+mod helper;
+mod history;
+mod json;
+mod keymap;
+pub mod led;
+mod log;
+#[cfg_attr(not(target_os = "linux"), path = "unsupported/logind.rs")]
+mod logind;
+mod mapping;
+mod multicolor;
+mod mute;
+mod output;
+mod pattern;
+mod pomodoro;
+mod presets;
+mod profiles;
+mod quiet;
+mod recording;
+mod remote;
+mod resolve;
+#[cfg_attr(not(target_os = "linux"), path = "unsupported/service.rs")]
+mod service;
+mod session;
+mod settings;
+mod sweep;
+pub mod sysfs;
+mod terminal;
+mod text_input;
+pub mod theme;
+mod tmpfiles;
+mod trigger_settings;
+mod udev;
+mod uleds;
+mod undo;
+mod watch;
+pub mod widgets;
+
+/// Runs Glimpse as invoked on the command line: a subcommand if one was given, otherwise the
+/// TUI.
+pub fn run() -> Result<()> {
+    let mut args = Args::parse(std::env::args().skip(1))?;
+    diagnostics::init(args.verbosity)?;
+    if let Some(command) = args.command.take() {
+        return match command {
+            Command::Bench {
+                iterations,
+                leds,
+                dry_run,
+            } => bench::run(iterations, leds, dry_run),
+            Command::Countdown { led, duration } => countdown::run(led, duration),
+            Command::Play { path } => recording::play(&path),
+            Command::List { filter_regex, json } => list_leds(filter_regex.as_deref(), json),
+            Command::Test { leds, steps } => sweep::run(leds, steps),
+            Command::Calibrate { led } => calibration::run(led),
+            Command::Off { leds } => quiet::run_off(leds),
+            Command::Mute => mute::run(false),
+            Command::Unmute => mute::run(true),
+            Command::ExportTmpfiles { leds } => tmpfiles::run(leds),
+            Command::Watch { leds, interval } => watch::run(leds, interval),
+            Command::Daemon {
+                profile,
+                bus,
+                interval,
+            } => daemon::run(profile, bus, interval),
+            Command::Export { leds } => export::run_export(leds),
+            Command::Import { path } => export::run_import(&path),
+            Command::ConfigValidate => config::run_validate(),
+            Command::ConfigEdit { file } => config::run_edit(file),
+            Command::ProfileSave { name } => profiles::run_save(&name),
+            Command::ProfileApply { name } => profiles::run_apply(&name),
+            Command::ProfileList => profiles::run_list(),
+            Command::Virtual {
+                name,
+                max_brightness,
+            } => uleds::run(name, max_brightness),
+            Command::SetColor { led, color } => multicolor::run(led, color),
+            Command::Helper { request } => helper::run(request),
+            Command::SetupPermissions { group, install } => udev::run(group, install),
+            Command::Help => {
+                print!("{}", cli::USAGE);
+                Ok(())
+            }
+            Command::Completions { shell } => {
+                completions::run(shell);
+                Ok(())
+            }
+            Command::Brightness {
+                operation,
+                device,
+                json,
+                dry_run,
+            } => brightnessctl::run(operation, device, json, dry_run),
+        };
+    }
+    app::launch(args)
+}
+
+/// Prints the file names of all LEDs, optionally only those matching a regex, or a JSON array
+/// describing them.
+fn list_leds(filter_regex: Option<&str>, json: bool) -> Result<()> {
+    let filter = filter_regex.map(regex::Regex::new).transpose()?;
+    let output = output::Output::detect();
+    let mut leds = get_all_leds()?;
+    leds.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    leds.retain(|led| {
+        filter
+            .as_ref()
+            .is_none_or(|filter| filter.is_match(&led.file_name))
+    });
+    if json {
+        let objects = leds
+            .iter()
+            .map(LED::to_json)
+            .collect::<std::io::Result<Vec<_>>>()?;
+        println!("[{}]", objects.join(","));
+        return Ok(());
+    }
+    for led in leds {
+        println!("{}", output.paint(&led.file_name, output::Tone::Name));
+    }
+    Ok(())
+}
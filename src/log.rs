@@ -0,0 +1,260 @@
+//! The TUI's log: messages with the time they were logged and how serious they are.
+//!
+//! While the TUI runs, the log is also appended to [`LOG_FILE`] in the state directory, so that
+//! it can be read after Glimpse has closed.
+
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::dirs;
+
+/// The log file, in Glimpse's state directory.
+pub const LOG_FILE: &str = "glimpse.log";
+/// Once the log file grows past this, it's moved to `glimpse.log.1` (replacing the previous
+/// one) and a new one is started.
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+
+/// How serious a log entry is, from least to most.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    /// The next level to filter the log by, wrapping around to showing everything.
+    pub fn cycle(self) -> Self {
+        match self {
+            Self::Info => Self::Warning,
+            Self::Warning => Self::Error,
+            Self::Error => Self::Info,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub timestamp: SystemTime,
+    pub level: Level,
+    pub message: String,
+}
+
+impl LogEntry {
+    fn unix_time(&self) -> u64 {
+        self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    /// The time the entry was logged as `HH:MM:SS`, in UTC.
+    pub fn time(&self) -> String {
+        let seconds = self.unix_time() % (24 * 60 * 60);
+        format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+
+    /// The date the entry was logged as `YYYY-MM-DD`, in UTC.
+    pub fn date(&self) -> String {
+        // Howard Hinnant's days_from_civil, backwards
+        let days = self.unix_time() / (24 * 60 * 60) + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Entries are written as `HH:MM:SS message`, with the level before the message unless it's
+/// just information.
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Level::Info => write!(f, "{} {}", self.time(), self.message),
+            level => write!(f, "{} {}: {}", self.time(), level, self.message),
+        }
+    }
+}
+
+/// The entries logged so far, oldest first.
+#[derive(Debug, Default)]
+pub struct Log {
+    entries: Vec<LogEntry>,
+    file: Option<LogFile>,
+}
+
+impl Log {
+    pub fn push(&mut self, level: Level, message: impl Into<String>) {
+        let entry = LogEntry {
+            timestamp: SystemTime::now(),
+            level,
+            message: message.into(),
+        };
+        // The log file is only a convenience, so stop writing to it rather than report errors
+        // about it in the log
+        if let Some(file) = &mut self.file
+            && file.write(&entry).is_err()
+        {
+            self.file = None;
+        }
+        self.entries.push(entry);
+    }
+
+    /// Appends the entries so far to the log file, and the rest as they're logged.
+    ///
+    /// Returns the path of the log file.
+    pub fn persist(&mut self) -> io::Result<PathBuf> {
+        let mut file = LogFile::open()?;
+        for entry in &self.entries {
+            file.write(entry)?;
+        }
+        let path = file.path.clone();
+        self.file = Some(file);
+        Ok(path)
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Level::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Level::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Level::Error, message);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// The entries at least as serious as `level`.
+    pub fn at_least(&self, level: Level) -> impl Iterator<Item = &LogEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.level >= level)
+    }
+}
+
+/// Where [`LOG_FILE`] is, creating the state directory if needed.
+pub fn log_file_path() -> io::Result<PathBuf> {
+    let dir = dirs::state_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "no state directory (neither XDG_STATE_HOME nor HOME is set)",
+        )
+    })?;
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(LOG_FILE))
+}
+
+pub fn open_for_appending(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// [`LOG_FILE`], opened for appending.
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open() -> io::Result<Self> {
+        let path = log_file_path()?;
+        let file = open_for_appending(&path)?;
+        let size = file.metadata()?.len();
+        let mut log_file = Self { path, file, size };
+        if log_file.size > MAX_LOG_FILE_SIZE {
+            log_file.rotate()?;
+        }
+        Ok(log_file)
+    }
+
+    /// Moves the log file aside and starts a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        let mut old = self.path.clone().into_os_string();
+        old.push(".1");
+        fs::rename(&self.path, old)?;
+        self.file = open_for_appending(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, entry: &LogEntry) -> io::Result<()> {
+        if self.size > MAX_LOG_FILE_SIZE {
+            self.rotate()?;
+        }
+        let line = format!(
+            "{} {} {:<7} {}\n",
+            entry.date(),
+            entry.time(),
+            entry.level,
+            entry.message
+        );
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn logged_at(unix_time: u64) -> LogEntry {
+        LogEntry {
+            timestamp: UNIX_EPOCH + Duration::from_secs(unix_time),
+            level: Level::Info,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn dates_and_times() {
+        assert_eq!(logged_at(0).date(), "1970-01-01");
+        let leap_day = logged_at(1_709_210_096);
+        assert_eq!(leap_day.date(), "2024-02-29");
+        assert_eq!(leap_day.time(), "12:34:56");
+        assert_eq!(logged_at(1_735_689_599).date(), "2024-12-31");
+    }
+}
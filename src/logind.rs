@@ -0,0 +1,147 @@
+//! Setting the brightness of backlights through systemd-logind, which lets the user of the
+//! active session do so without being able to write to sysfs, and hearing from logind when the
+//! system suspends and resumes.
+
+use std::{
+    fs, io,
+    os::unix::fs::MetadataExt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::dbus::{Bus, Connection, Message, MessageType, Value};
+
+const LOGIN1: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+/// The session Glimpse runs in, as resolved by logind from the caller.
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+
+/// The connection to the system bus, kept between writes as stepping the brightness writes
+/// several times a second.
+static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// Whether Glimpse runs as root, and so can write to sysfs without asking logind.
+pub fn running_as_root() -> bool {
+    fs::metadata("/proc/self").is_ok_and(|metadata| metadata.uid() == 0)
+}
+
+/// Asks logind to set the brightness of a device in the `backlight` or `leds` subsystem.
+pub fn set_brightness(subsystem: &str, name: &str, brightness: u32) -> io::Result<()> {
+    let mut connection = CONNECTION.lock().unwrap_or_else(|e| e.into_inner());
+    if connection.is_none() {
+        *connection = Some(Connection::connect(Bus::System)?);
+    }
+    let mut call = Message::method_call(LOGIN1, SESSION_PATH, SESSION_INTERFACE, "SetBrightness");
+    call.body = vec![
+        Value::String(subsystem.to_string()),
+        Value::String(name.to_string()),
+        Value::U32(brightness),
+    ];
+    let result = connection.as_mut().map_or_else(
+        || Err(io::ErrorKind::NotConnected.into()),
+        |connection| connection.call(call),
+    );
+    match result {
+        Ok(_) => Ok(()),
+        // Error replies leave the connection usable, but other errors may not have
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::Other | io::ErrorKind::PermissionDenied
+            ) =>
+        {
+            Err(e)
+        }
+        Err(e) => {
+            *connection = None;
+            Err(e)
+        }
+    }
+}
+
+/// Whether a message is logind's `PrepareForSleep` signal, and if so, whether the system is
+/// about to sleep (`true`) or has just woken up (`false`).
+fn prepare_for_sleep(message: &Message) -> Option<bool> {
+    if message.kind != MessageType::Signal
+        || message.interface.as_deref() != Some(MANAGER_INTERFACE)
+        || message.member.as_deref() != Some("PrepareForSleep")
+    {
+        return None;
+    }
+    match message.body.first() {
+        Some(Value::Bool(sleeping)) => Some(*sleeping),
+        _ => None,
+    }
+}
+
+/// A subscription to logind's `PrepareForSleep` signal, which tells when the system resumes
+/// from suspend or hibernation.
+#[derive(Debug)]
+pub struct SleepWatcher {
+    connection: Connection,
+}
+
+impl SleepWatcher {
+    /// Connects to the system bus and asks for logind's sleep signals.
+    pub fn subscribe() -> io::Result<Self> {
+        let mut connection = Connection::connect(Bus::System)?;
+        let mut add_match = Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "AddMatch",
+        );
+        add_match.body = vec![Value::String(format!(
+            "type='signal',sender='{}',path='{}',interface='{}',member='PrepareForSleep'",
+            LOGIN1, MANAGER_PATH, MANAGER_INTERFACE
+        ))];
+        connection.call(add_match)?;
+        Ok(Self { connection })
+    }
+
+    /// Waits up to `timeout` for the system to resume, returning early if it does.
+    ///
+    /// Returns whether it resumed, including resumes signalled since the last wait.
+    pub fn wait(&mut self, timeout: Duration) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(message) = self.connection.receive(remaining)? else {
+                return Ok(false);
+            };
+            if prepare_for_sleep(&message) == Some(false) {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_sleep_signals() {
+        let signal = |sleeping| {
+            Message::signal(
+                MANAGER_PATH,
+                MANAGER_INTERFACE,
+                "PrepareForSleep",
+                vec![Value::Bool(sleeping)],
+            )
+        };
+        assert_eq!(prepare_for_sleep(&signal(true)), Some(true));
+        assert_eq!(prepare_for_sleep(&signal(false)), Some(false));
+        let other = Message::signal(
+            MANAGER_PATH,
+            MANAGER_INTERFACE,
+            "PrepareForShutdown",
+            vec![Value::Bool(false)],
+        );
+        assert_eq!(prepare_for_sleep(&other), None);
+        let call = Message::method_call(LOGIN1, MANAGER_PATH, MANAGER_INTERFACE, "PrepareForSleep");
+        assert_eq!(prepare_for_sleep(&call), None);
+    }
+}
@@ -0,0 +1,173 @@
+use std::{fs, io, path::Path, str::FromStr};
+
+use thiserror::Error;
+
+const SYS_CLASS_POWER_SUPPLY: &str = "/sys/class/power_supply";
+const SYS_CLASS_NET: &str = "/sys/class/net";
+
+/// A system condition that can drive an LED.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Battery charge is below the given percentage
+    BatteryBelow(u8),
+    /// Battery charge is above the given percentage
+    BatteryAbove(u8),
+    /// The network interface is up
+    InterfaceUp(String),
+    /// The network interface is down
+    InterfaceDown(String),
+}
+
+/// Lights an LED while a system condition holds, and turns it off otherwise.
+///
+/// Mappings are written as `<condition>=<led>`, where the condition is one of `battery<N`,
+/// `battery>N`, `netdev:<interface>:up` or `netdev:<interface>:down`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventMapping {
+    pub condition: Condition,
+    /// File name of the LED used as an indicator
+    pub led: String,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseMappingError {
+    #[error("expected <condition>=<led>")]
+    MissingLed,
+    #[error("unknown condition \"{0}\"")]
+    UnknownCondition(String),
+    #[error("invalid battery percentage \"{0}\"")]
+    InvalidPercentage(String),
+}
+
+impl FromStr for EventMapping {
+    type Err = ParseMappingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (condition, led) = s.split_once('=').ok_or(ParseMappingError::MissingLed)?;
+        if led.is_empty() {
+            return Err(ParseMappingError::MissingLed);
+        }
+        Ok(Self {
+            condition: condition.parse()?,
+            led: led.to_string(),
+        })
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseMappingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_percentage = |value: &str| {
+            value
+                .parse::<u8>()
+                .ok()
+                .filter(|percentage| *percentage <= 100)
+                .ok_or_else(|| ParseMappingError::InvalidPercentage(value.to_string()))
+        };
+        if let Some(value) = s.strip_prefix("battery<") {
+            return Ok(Self::BatteryBelow(parse_percentage(value)?));
+        }
+        if let Some(value) = s.strip_prefix("battery>") {
+            return Ok(Self::BatteryAbove(parse_percentage(value)?));
+        }
+        if let Some(rest) = s.strip_prefix("netdev:") {
+            match rest.rsplit_once(':') {
+                Some((interface, "up")) if !interface.is_empty() => {
+                    return Ok(Self::InterfaceUp(interface.to_string()));
+                }
+                Some((interface, "down")) if !interface.is_empty() => {
+                    return Ok(Self::InterfaceDown(interface.to_string()));
+                }
+                _ => {}
+            }
+        }
+        Err(ParseMappingError::UnknownCondition(s.to_string()))
+    }
+}
+
+impl Condition {
+    /// Checks whether the condition currently holds.
+    pub fn evaluate(&self) -> io::Result<bool> {
+        match self {
+            Self::BatteryBelow(threshold) => Ok(battery_percentage()? < *threshold),
+            Self::BatteryAbove(threshold) => Ok(battery_percentage()? > *threshold),
+            Self::InterfaceUp(interface) => interface_is_up(interface),
+            Self::InterfaceDown(interface) => interface_is_up(interface).map(|up| !up),
+        }
+    }
+}
+
+/// Charge of the first battery found under `/sys/class/power_supply`, in percent.
+pub fn battery_percentage() -> io::Result<u8> {
+    for entry in fs::read_dir(SYS_CLASS_POWER_SUPPLY)? {
+        let path = entry?.path();
+        let is_battery = fs::read_to_string(path.join("type"))
+            .is_ok_and(|supply_type| supply_type.trim() == "Battery");
+        if !is_battery {
+            continue;
+        }
+        let capacity = fs::read_to_string(path.join("capacity"))?;
+        return capacity
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid battery capacity"));
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "no battery found"))
+}
+
+fn interface_is_up(interface: &str) -> io::Result<bool> {
+    let operstate = fs::read_to_string(Path::new(SYS_CLASS_NET).join(interface).join("operstate"))?;
+    Ok(operstate.trim() == "up")
+}
+
+/// The last known outcome of a mapping, used to only act when it changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MappingState {
+    #[default]
+    Unknown,
+    Failing,
+    Evaluated(bool),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mappings() {
+        assert_eq!(
+            "battery<20=platform::charging".parse(),
+            Ok(EventMapping {
+                condition: Condition::BatteryBelow(20),
+                led: "platform::charging".to_string(),
+            })
+        );
+        let mapping: EventMapping = "netdev:wlan0:down=phy0::wlan".parse().unwrap();
+        assert_eq!(
+            mapping.condition,
+            Condition::InterfaceDown("wlan0".to_string())
+        );
+        assert_eq!(
+            "battery>80".parse::<Condition>(),
+            Ok(Condition::BatteryAbove(80))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_mappings() {
+        let parse = |mapping: &str| mapping.parse::<EventMapping>().unwrap_err();
+        assert_eq!(parse("battery<20"), ParseMappingError::MissingLed);
+        assert_eq!(parse("battery<20="), ParseMappingError::MissingLed);
+        assert_eq!(
+            parse("battery<101=led"),
+            ParseMappingError::InvalidPercentage("101".to_string())
+        );
+        for condition in ["netdev::up", "netdev:wlan0:sideways", "cpu>50"] {
+            assert_eq!(
+                parse(&format!("{}=led", condition)),
+                ParseMappingError::UnknownCondition(condition.to_string())
+            );
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Multicolor LEDs, whose color is set through `multi_intensity`, one value per channel listed
+//! in `multi_index`.
+
+use std::{fmt, io, str::FromStr};
+
+use color_eyre::{Result, eyre::eyre};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+};
+use thiserror::Error;
+
+use crate::{
+    LED, LedKind, NewLEDError, glob,
+    theme::{self, Role},
+    widgets::centered_rect,
+};
+
+/// Color temperatures offered for multicolor LEDs, from warm to cool white.
+pub const MIN_COLOR_TEMPERATURE: u32 = 2700;
+pub const MAX_COLOR_TEMPERATURE: u32 = 6500;
+/// How much the color temperature changes with each key press.
+pub const COLOR_TEMPERATURE_STEP: u32 = 100;
+
+/// Color names accepted in addition to hex codes, with their CSS values.
+const NAMED_COLORS: [(&str, Rgb); 20] = [
+    ("black", Rgb::new(0x00, 0x00, 0x00)),
+    ("white", Rgb::new(0xff, 0xff, 0xff)),
+    ("red", Rgb::new(0xff, 0x00, 0x00)),
+    ("green", Rgb::new(0x00, 0x80, 0x00)),
+    ("lime", Rgb::new(0x00, 0xff, 0x00)),
+    ("blue", Rgb::new(0x00, 0x00, 0xff)),
+    ("yellow", Rgb::new(0xff, 0xff, 0x00)),
+    ("orange", Rgb::new(0xff, 0xa5, 0x00)),
+    ("amber", Rgb::new(0xff, 0xbf, 0x00)),
+    ("gold", Rgb::new(0xff, 0xd7, 0x00)),
+    ("pink", Rgb::new(0xff, 0xc0, 0xcb)),
+    ("magenta", Rgb::new(0xff, 0x00, 0xff)),
+    ("purple", Rgb::new(0x80, 0x00, 0x80)),
+    ("violet", Rgb::new(0xee, 0x82, 0xee)),
+    ("cyan", Rgb::new(0x00, 0xff, 0xff)),
+    ("teal", Rgb::new(0x00, 0x80, 0x80)),
+    ("turquoise", Rgb::new(0x40, 0xe0, 0xd0)),
+    ("indigo", Rgb::new(0x4b, 0x00, 0x82)),
+    ("navy", Rgb::new(0x00, 0x00, 0x80)),
+    ("warmwhite", Rgb::new(0xff, 0xb4, 0x6b)),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Rgb {
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unknown color \"{0}\", expected a name like \"orange\" or a hex code like #ff8800")]
+pub struct ParseColorError(String);
+
+/// Colors are written as CSS-style names (`teal`) or hex codes (`#ff8800`, `#f80`).
+impl FromStr for Rgb {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseColorError(s.to_string());
+        let Some(hex) = s.strip_prefix('#') else {
+            let name = s.to_lowercase().replace([' ', '-', '_'], "");
+            return NAMED_COLORS
+                .iter()
+                .find(|(color_name, _)| *color_name == name)
+                .map(|(_, color)| *color)
+                .ok_or_else(error);
+        };
+        if !hex.is_ascii() {
+            return Err(error());
+        }
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| error());
+        match hex.len() {
+            3 => {
+                let short = |i: usize| channel(&hex[i..i + 1].repeat(2));
+                Ok(Self::new(short(0)?, short(1)?, short(2)?))
+            }
+            6 => Ok(Self::new(
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MulticolorError {
+    #[error("not a multicolor LED")]
+    NotMulticolor,
+    #[error(transparent)]
+    Lookup(#[from] NewLEDError),
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+}
+
+/// The intensity of each channel of a multicolor LED for a color.
+///
+/// Channels other than red, green and blue (e.g. `white`) get the part shared by all three.
+fn intensities_for(channels: &[String], color: Rgb, max_brightness: u32) -> Vec<u32> {
+    let scale = |value: u8| u32::from(value) * max_brightness / 255;
+    channels
+        .iter()
+        .map(|channel| match channel.as_str() {
+            "red" => scale(color.red),
+            "green" => scale(color.green),
+            "blue" => scale(color.blue),
+            _ => scale(color.red.min(color.green).min(color.blue)),
+        })
+        .collect()
+}
+
+/// Sets the color of a multicolor LED, leaving its overall brightness alone.
+pub fn set_color(led: &LED, color: Rgb) -> Result<(), MulticolorError> {
+    let LedKind::Multicolor { channels } = &led.kind else {
+        return Err(MulticolorError::NotMulticolor);
+    };
+    led.set_intensities(&intensities_for(channels, color, led.max_brightness()?))?;
+    Ok(())
+}
+
+/// Whether a multicolor LED is already set to a color, as far as its channels can tell.
+pub fn has_color(led: &LED, color: Rgb) -> Result<bool, MulticolorError> {
+    let LedKind::Multicolor { channels } = &led.kind else {
+        return Err(MulticolorError::NotMulticolor);
+    };
+    Ok(led.intensities()? == intensities_for(channels, color, led.max_brightness()?))
+}
+
+/// The color a multicolor LED is set to.
+///
+/// Channels other than red, green and blue only count for LEDs without any of those.
+pub fn color_of(led: &LED) -> Result<Rgb, MulticolorError> {
+    let LedKind::Multicolor { channels } = &led.kind else {
+        return Err(MulticolorError::NotMulticolor);
+    };
+    let max_brightness = led.max_brightness()?.max(1);
+    let scale = |intensity: u32| {
+        let value = (u64::from(intensity.min(max_brightness)) * 255
+            + u64::from(max_brightness) / 2)
+            / u64::from(max_brightness);
+        value as u8
+    };
+    let (mut red, mut green, mut blue, mut other) = (None, None, None, 0);
+    for (channel, intensity) in channels.iter().zip(led.intensities()?) {
+        match channel.as_str() {
+            "red" => red = Some(scale(intensity)),
+            "green" => green = Some(scale(intensity)),
+            "blue" => blue = Some(scale(intensity)),
+            _ => other = other.max(scale(intensity)),
+        }
+    }
+    let fallback = if [red, green, blue].iter().all(Option::is_none) {
+        other
+    } else {
+        0
+    };
+    Ok(Rgb::new(
+        red.unwrap_or(fallback),
+        green.unwrap_or(fallback),
+        blue.unwrap_or(fallback),
+    ))
+}
+
+/// Sets the color of every multicolor LED matching `led_name`.
+pub fn run(led_name: String, color: Rgb) -> Result<()> {
+    for led in glob::expand(&[led_name])? {
+        set_color(&led, color).map_err(|e| eyre!("{}: {}", led.file_name, e))?;
+        println!("Set {} to {}", led.file_name, color);
+    }
+    Ok(())
+}
+
+/// The channel editor popup, for setting the intensity of each channel of a multicolor LED.
+#[derive(Debug, Clone)]
+pub struct ChannelEditor {
+    /// File name of the LED
+    pub led: String,
+    channels: Vec<String>,
+    pub intensities: Vec<u32>,
+    /// The intensities when the editor was opened, restored on cancel
+    pub original: Vec<u32>,
+    max_brightness: u32,
+    selected: usize,
+}
+
+impl ChannelEditor {
+    /// Opens the editor with the LED's current intensities.
+    pub fn open(led: &LED) -> Result<Self, MulticolorError> {
+        let LedKind::Multicolor { channels } = &led.kind else {
+            return Err(MulticolorError::NotMulticolor);
+        };
+        let intensities = led.intensities()?;
+        Ok(Self {
+            led: led.file_name.clone(),
+            channels: channels.clone(),
+            original: intensities.clone(),
+            intensities,
+            max_brightness: led.max_brightness,
+            selected: 0,
+        })
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.channels.len().saturating_sub(1));
+    }
+
+    /// Raises or lowers the intensity of the selected channel by 5% of the maximum.
+    pub fn adjust(&mut self, up: bool) {
+        let step = (self.max_brightness / 20).max(1);
+        if let Some(intensity) = self.intensities.get_mut(self.selected) {
+            *intensity = if up {
+                (*intensity + step).min(self.max_brightness)
+            } else {
+                intensity.saturating_sub(step)
+            };
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let title = Line::styled(
+            format!("Channels of {}", self.led),
+            theme::style(Role::Title),
+        )
+        .centered();
+        let width = self.channels.iter().map(String::len).max().unwrap_or(0);
+        let mut lines: Vec<Line> = self
+            .channels
+            .iter()
+            .zip(&self.intensities)
+            .enumerate()
+            .map(|(i, (channel, intensity))| {
+                let line = Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$}  ", channel),
+                        theme::style(Role::Heading).patch(theme::led_color(channel)),
+                    ),
+                    Span::raw(format!("{}/{}", intensity, self.max_brightness)),
+                ]);
+                if i == self.selected {
+                    line.style(theme::style(Role::Selection))
+                } else {
+                    line
+                }
+            })
+            .collect();
+        lines.extend([
+            Line::default(),
+            Line::styled(
+                "↑/↓ channel, ←/→ intensity, Enter keep, Esc cancel",
+                theme::style(Role::Dim),
+            ),
+        ]);
+        let area = centered_rect(area, 50, lines.len() as u16 + 2);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+/// Approximates the color of a black body at the given temperature, in Kelvin.
+///
+/// Uses Tanner Helland's fit of the CIE 1964 data, which is close enough for LEDs between 1000K
+/// and 40000K.
+pub fn color_temperature(kelvin: u32) -> Rgb {
+    let temperature = f64::from(kelvin.clamp(1000, 40000)) / 100.0;
+    let red = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.698_727_446 * (temperature - 60.0).powf(-0.133_204_759_2)
+    };
+    let green = if temperature <= 66.0 {
+        99.470_802_586_1 * temperature.ln() - 161.119_568_166_1
+    } else {
+        288.122_169_528_3 * (temperature - 60.0).powf(-0.075_514_849_2)
+    };
+    let blue = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.517_731_223_1 * (temperature - 10.0).ln() - 305.044_792_730_7
+    };
+    let channel = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+    Rgb {
+        red: channel(red),
+        green: channel(green),
+        blue: channel(blue),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{MockBackend, backend::MockLed, led::get_all_leds_from};
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!("teal".parse(), Ok(Rgb::new(0x00, 0x80, 0x80)));
+        assert_eq!("Warm White".parse(), Ok(Rgb::new(0xff, 0xb4, 0x6b)));
+        assert_eq!("warm-white".parse(), Ok(Rgb::new(0xff, 0xb4, 0x6b)));
+        assert_eq!("#ff8800".parse(), Ok(Rgb::new(0xff, 0x88, 0x00)));
+        assert_eq!("#F80".parse(), Ok(Rgb::new(0xff, 0x88, 0x00)));
+        for invalid in ["mauve", "#ff88", "#gg8800", "#ff880", "#ééé", "ff8800", ""] {
+            assert_eq!(
+                invalid.parse::<Rgb>(),
+                Err(ParseColorError(invalid.to_string()))
+            );
+        }
+        let color = Rgb::new(0x40, 0xe0, 0xd0);
+        assert_eq!(color.to_string(), "#40e0d0");
+        assert_eq!(color.to_string().parse(), Ok(color));
+    }
+
+    #[test]
+    fn reads_back_colors() {
+        let backend = MockBackend::new();
+        let channels = ["red", "green", "blue", "white"].map(|channel| (channel.to_string(), 0));
+        backend.add_led(
+            "rgb:status",
+            MockLed {
+                channels: Some(channels.to_vec()),
+                ..MockLed::new(100)
+            },
+        );
+        let leds = get_all_leds_from(Arc::new(backend)).unwrap();
+        let orange = Rgb::new(0xff, 0xa5, 0x00);
+        assert!(!has_color(&leds[0], orange).unwrap());
+        set_color(&leds[0], orange).unwrap();
+        assert_eq!(leds[0].intensities().unwrap(), [100, 64, 0, 0]);
+        assert!(has_color(&leds[0], orange).unwrap());
+        // Only as precise as the LED's channels
+        assert_eq!(color_of(&leds[0]).unwrap(), Rgb::new(0xff, 0xa3, 0x00));
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    LED, dirs, get_all_leds,
+    output::{Output, Tone},
+    profiles::{LedState, Profile},
+};
+
+/// Name of the file in Glimpse's state directory holding the state from before muting.
+const MUTE_STATE_FILE: &str = "mute-state";
+
+#[derive(Debug, Error)]
+pub enum MuteError {
+    #[error("LEDs are already muted")]
+    AlreadyMuted,
+    #[error("LEDs are not muted")]
+    NotMuted,
+    #[error("no state directory (neither XDG_STATE_HOME nor HOME is set)")]
+    NoStateDir,
+    #[error("invalid saved state: {0}")]
+    InvalidState(String),
+    #[error("I/O error: {0}")]
+    IOError(#[from] io::Error),
+}
+
+/// The LEDs that were changed and the errors for those that couldn't be.
+pub type MuteOutcome = (usize, Vec<String>);
+
+fn state_path() -> Result<PathBuf, MuteError> {
+    Ok(dirs::state_dir()
+        .ok_or(MuteError::NoStateDir)?
+        .join(MUTE_STATE_FILE))
+}
+
+/// Whether LEDs are currently muted (by this or another Glimpse process).
+pub fn is_muted() -> bool {
+    state_path().is_ok_and(|path| path.exists())
+}
+
+/// Saves the state of every LED and turns them all off, leaving backlights alone.
+///
+/// The state is saved to a file first, in the same format as profiles, so that
+/// `glimpse unmute` (or the TUI) can restore it later, even from another process.
+pub fn mute(leds: &mut [LED]) -> Result<MuteOutcome, MuteError> {
+    mute_to(&state_path()?, leds)
+}
+
+fn mute_to(path: &Path, leds: &mut [LED]) -> Result<MuteOutcome, MuteError> {
+    if path.exists() {
+        return Err(MuteError::AlreadyMuted);
+    }
+    let mut errors = Vec::new();
+    let mut saved = Profile::default();
+    for led in leds.iter_mut().filter(|led| !led.is_backlight()) {
+        match LedState::capture(led) {
+            Ok(state) => {
+                saved.leds.insert(led.file_name.clone(), state);
+            }
+            Err(e) => errors.push(format!("{}: {}", led.file_name, e)),
+        }
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, saved.serialize())?;
+
+    let mut muted = 0;
+    for led in leds
+        .iter_mut()
+        .filter(|led| saved.leds.contains_key(&led.file_name))
+    {
+        match led.set_on(false) {
+            Ok(()) => muted += 1,
+            Err(e) => errors.push(format!("{}: {}", led.file_name, e)),
+        }
+    }
+    Ok((muted, errors))
+}
+
+/// Restores the state saved by [`mute`] and forgets it.
+pub fn unmute(leds: &mut [LED]) -> Result<MuteOutcome, MuteError> {
+    unmute_from(&state_path()?, leds)
+}
+
+fn unmute_from(path: &Path, leds: &mut [LED]) -> Result<MuteOutcome, MuteError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(MuteError::NotMuted),
+        Err(e) => return Err(e.into()),
+    };
+    let saved = Profile::parse(MUTE_STATE_FILE, &contents)
+        .map_err(|e| MuteError::InvalidState(e.to_string()))?;
+    let (_, errors) = saved.apply(leds);
+    fs::remove_file(path)?;
+    Ok((saved.leds.len().saturating_sub(errors.len()), errors))
+}
+
+/// Mutes or unmutes all LEDs from the command line.
+pub fn run(unmuting: bool) -> color_eyre::Result<()> {
+    let output = Output::detect();
+    let mut leds = get_all_leds()?;
+    let (changed, errors) = if unmuting {
+        unmute(&mut leds)?
+    } else {
+        mute(&mut leds)?
+    };
+    let action = if unmuting { "Restored" } else { "Turned off" };
+    println!(
+        "{} {} LED(s)",
+        action,
+        output.paint(&changed.to_string(), Tone::Value)
+    );
+    for error in &errors {
+        eprintln!("{}", output.paint(error, Tone::Error));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process, sync::Arc};
+
+    use super::*;
+    use crate::{MockBackend, Triggers, backend::MockLed, led::get_all_leds_from};
+
+    #[test]
+    fn unmute_restores_triggers() {
+        let backend = MockBackend::new();
+        // Driven by its trigger, but dark while the disk is idle
+        backend.add_led(
+            "disk-activity",
+            MockLed {
+                brightness: 0,
+                triggers: Some(Triggers {
+                    available: vec!["none".to_string(), "disk-activity".to_string()],
+                    active: Some("disk-activity".to_string()),
+                }),
+                ..MockLed::new(1)
+            },
+        );
+        backend.add_led(
+            "input3::capslock",
+            MockLed {
+                brightness: 1,
+                ..MockLed::new(1)
+            },
+        );
+        let mut leds = get_all_leds_from(Arc::new(backend.clone())).unwrap();
+        let path = env::temp_dir().join(format!("glimpse-mute-test-{}", process::id()));
+        assert_eq!(mute_to(&path, &mut leds).unwrap(), (2, Vec::new()));
+        assert!(matches!(
+            mute_to(&path, &mut leds),
+            Err(MuteError::AlreadyMuted)
+        ));
+        let disk = backend.led("disk-activity").unwrap();
+        assert_eq!(disk.triggers.unwrap().active.as_deref(), Some("none"));
+        assert_eq!(backend.led("input3::capslock").unwrap().brightness, 0);
+        assert_eq!(unmute_from(&path, &mut leds).unwrap(), (2, Vec::new()));
+        let disk = backend.led("disk-activity").unwrap();
+        assert_eq!(
+            disk.triggers.unwrap().active.as_deref(),
+            Some("disk-activity")
+        );
+        assert_eq!(backend.led("input3::capslock").unwrap().brightness, 1);
+        assert!(!path.exists());
+    }
+}
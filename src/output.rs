@@ -0,0 +1,149 @@
+use std::io::{self, IsTerminal};
+
+use crossterm::style::Stylize;
+use ratatui::text::Line;
+
+use crate::theme::ColorDepth;
+
+/// The role of a piece of text in command output, which decides how it's styled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Plain,
+    Heading,
+    /// An LED name
+    Name,
+    /// A measured or read value
+    Value,
+    Dim,
+    Error,
+}
+
+/// Formats the human-readable output of the non-interactive commands.
+///
+/// Colours are only used when stdout is a terminal, `NO_COLOR` isn't set and `TERM` isn't
+/// `dumb`, so piping the output into other programs gives plain text.
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    color: bool,
+}
+
+impl Output {
+    pub fn detect() -> Self {
+        let color = io::stdout().is_terminal() && ColorDepth::detect() != ColorDepth::Mono;
+        Self { color }
+    }
+
+    pub fn paint(&self, text: &str, tone: Tone) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        match tone {
+            Tone::Plain => text.to_string(),
+            Tone::Heading => text.bold().to_string(),
+            Tone::Name => text.cyan().to_string(),
+            Tone::Value => text.green().to_string(),
+            Tone::Dim => text.dim().to_string(),
+            Tone::Error => text.red().to_string(),
+        }
+    }
+
+    /// Lays out rows in aligned columns below a heading row.
+    ///
+    /// Columns are left-aligned, except those listed in `right_aligned`.
+    pub fn table(
+        &self,
+        headings: &[&str],
+        rows: &[Vec<(String, Tone)>],
+        right_aligned: &[usize],
+    ) -> String {
+        // Rows can have more cells than there are headings
+        let columns = rows.iter().map(Vec::len).fold(headings.len(), usize::max);
+        let mut widths = vec![0; columns];
+        for (column, heading) in headings.iter().enumerate() {
+            widths[column] = text_width(heading);
+        }
+        for row in rows {
+            for (column, (text, _)) in row.iter().enumerate() {
+                widths[column] = widths[column].max(text_width(text));
+            }
+        }
+        let format_row = |cells: Vec<(&str, Tone)>| {
+            let last = cells.len().saturating_sub(1);
+            let formatted: Vec<String> = cells
+                .into_iter()
+                .enumerate()
+                .map(|(column, (text, tone))| {
+                    let padding = " ".repeat(widths[column].saturating_sub(text_width(text)));
+                    let text = self.paint(text, tone);
+                    if right_aligned.contains(&column) {
+                        format!("{}{}", padding, text)
+                    } else if column == last {
+                        text
+                    } else {
+                        format!("{}{}", text, padding)
+                    }
+                })
+                .collect();
+            formatted.join("  ")
+        };
+        let mut lines = vec![format_row(
+            headings
+                .iter()
+                .map(|heading| (*heading, Tone::Heading))
+                .collect(),
+        )];
+        for row in rows {
+            lines.push(format_row(
+                row.iter()
+                    .map(|(text, tone)| (text.as_str(), *tone))
+                    .collect(),
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Width of the text in terminal columns.
+fn text_width(text: &str) -> usize {
+    Line::from(text).width()
+}
+
+/// Encodes a string as a JSON string literal.
+pub fn json_string(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len() + 2);
+    encoded.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            c if c.is_control() => encoded.push_str(&format!("\\u{:04x}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_columns_from_every_row() {
+        let output = Output { color: false };
+        let rows = vec![
+            vec![
+                ("input3::capslock".to_string(), Tone::Name),
+                ("1".to_string(), Tone::Value),
+                ("extra".to_string(), Tone::Dim),
+            ],
+            vec![("phy0-led".to_string(), Tone::Name)],
+        ];
+        assert_eq!(
+            output.table(&["LED", "brightness"], &rows, &[1]),
+            "LED               brightness\n\
+             input3::capslock           1  extra\n\
+             phy0-led"
+        );
+    }
+}
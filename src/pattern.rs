@@ -0,0 +1,237 @@
+//! Editing the brightness sequence of LEDs using the `pattern` trigger.
+
+use std::{fs, io, path::Path};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Wrap},
+};
+use thiserror::Error;
+
+use crate::{
+    LED,
+    theme::{self, Role},
+    widgets::centered_rect,
+};
+
+#[derive(Debug, Error)]
+pub enum PatternError {
+    #[error("the pattern is empty")]
+    Empty,
+    #[error("{0:?} is not a number")]
+    InvalidNumber(String),
+    #[error("brightness {brightness} is higher than the maximum of {max_brightness}")]
+    TooBright {
+        brightness: u32,
+        max_brightness: u32,
+    },
+    #[error("brightness {0} has no duration")]
+    MissingDuration(u32),
+    #[error("I/O error: {0}")]
+    IOError(#[from] io::Error),
+}
+
+/// One step of a pattern: the LED goes from `brightness` towards the next step's brightness
+/// over `duration` milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternStep {
+    pub brightness: u32,
+    pub duration: u32,
+}
+
+/// Parses `<brightness> <duration>` pairs separated by whitespace (the kernel's format) or
+/// commas.
+pub fn parse(input: &str, max_brightness: u32) -> Result<Vec<PatternStep>, PatternError> {
+    let numbers = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|number| !number.is_empty())
+        .map(|number| {
+            number
+                .parse::<u32>()
+                .map_err(|_| PatternError::InvalidNumber(number.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if numbers.is_empty() {
+        return Err(PatternError::Empty);
+    }
+    if let [.., brightness] = numbers[..]
+        && numbers.len() % 2 == 1
+    {
+        return Err(PatternError::MissingDuration(brightness));
+    }
+    numbers
+        .chunks_exact(2)
+        .map(|pair| {
+            let (brightness, duration) = (pair[0], pair[1]);
+            if brightness > max_brightness {
+                return Err(PatternError::TooBright {
+                    brightness,
+                    max_brightness,
+                });
+            }
+            Ok(PatternStep {
+                brightness,
+                duration,
+            })
+        })
+        .collect()
+}
+
+/// Formats steps the way the kernel expects them to be written.
+pub fn format(steps: &[PatternStep]) -> String {
+    steps
+        .iter()
+        .map(|step| format!("{} {}", step.brightness, step.duration))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The pattern editor popup, for an LED whose trigger is `pattern`.
+#[derive(Debug, Clone)]
+pub struct PatternEditor {
+    /// File name of the LED
+    pub led: String,
+    max_brightness: u32,
+    pub input: String,
+    /// Whether the pattern is written to `hw_pattern`, for the LED's hardware to run
+    pub hardware: bool,
+    /// The `hw_pattern` attribute only exists if the driver can run patterns itself
+    has_hw_pattern: bool,
+}
+
+impl PatternEditor {
+    /// Opens the editor with the LED's current pattern, the one in `hw_pattern` if the hardware
+    /// is running one.
+    pub fn open(led: &LED) -> io::Result<Self> {
+        let led_path = led.path();
+        let hw_pattern = match fs::read_to_string(led_path.join("hw_pattern")) {
+            Ok(pattern) => Some(pattern.trim().to_string()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let (input, hardware) = match &hw_pattern {
+            Some(pattern) if !pattern.is_empty() => (pattern.clone(), true),
+            _ => {
+                let pattern = fs::read_to_string(led_path.join("pattern"))?;
+                (pattern.trim().to_string(), false)
+            }
+        };
+        Ok(Self {
+            led: led.file_name.clone(),
+            max_brightness: led.max_brightness,
+            input,
+            hardware,
+            has_hw_pattern: hw_pattern.is_some(),
+        })
+    }
+
+    /// Switches between writing `pattern` and `hw_pattern`, if the LED has the latter.
+    pub fn toggle_hardware(&mut self) {
+        self.hardware = !self.hardware && self.has_hw_pattern;
+    }
+
+    pub fn attribute(&self) -> &'static str {
+        if self.hardware {
+            "hw_pattern"
+        } else {
+            "pattern"
+        }
+    }
+
+    /// Validates the typed in pattern and writes it to the LED at `led_path`.
+    pub fn write(&self, led_path: &Path) -> Result<Vec<PatternStep>, PatternError> {
+        let steps = parse(&self.input, self.max_brightness)?;
+        fs::write(led_path.join(self.attribute()), format(&steps))?;
+        Ok(steps)
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let title = Line::styled(
+            format!("Pattern of {}", self.led),
+            theme::style(Role::Title),
+        )
+        .centered();
+        let status = match parse(&self.input, self.max_brightness) {
+            Ok(steps) => Line::from(format!(
+                "{} step(s), {} ms per cycle",
+                steps.len(),
+                steps
+                    .iter()
+                    .map(|step| u64::from(step.duration))
+                    .sum::<u64>()
+            )),
+            Err(e) => Line::styled(e.to_string(), theme::style(Role::Error)),
+        };
+        let mut hint = String::from("Pairs of <brightness> <ms>, Enter save, Esc cancel");
+        if self.has_hw_pattern {
+            hint.push_str(", Tab hardware/software");
+        }
+        let lines = vec![
+            Line::from(vec![
+                Span::styled(
+                    format!("{}: ", self.attribute()),
+                    theme::style(Role::Heading),
+                ),
+                Span::raw(self.input.as_str()),
+                Span::styled("_", theme::style(Role::Dim)),
+            ]),
+            status,
+            Line::default(),
+            Line::styled(hint, theme::style(Role::Dim)),
+        ];
+        let area = centered_rect(area, 60, lines.len() as u16 + 4);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_steps() {
+        let steps = parse("0 500, 255 500\n", 255).unwrap();
+        assert_eq!(
+            steps,
+            [
+                PatternStep {
+                    brightness: 0,
+                    duration: 500,
+                },
+                PatternStep {
+                    brightness: 255,
+                    duration: 500,
+                },
+            ]
+        );
+        assert_eq!(format(&steps), "0 500 255 500");
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(matches!(parse(" , ", 255), Err(PatternError::Empty)));
+        assert!(matches!(
+            parse("0 500 255", 255),
+            Err(PatternError::MissingDuration(255))
+        ));
+        assert!(matches!(
+            parse("0 500 x 500", 255),
+            Err(PatternError::InvalidNumber(number)) if number == "x"
+        ));
+        assert!(matches!(
+            parse("256 500", 255),
+            Err(PatternError::TooBright {
+                brightness: 256,
+                max_brightness: 255,
+            })
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fmt,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+const FOCUS_DURATION: Duration = Duration::from_secs(25 * 60);
+const SHORT_BREAK_DURATION: Duration = Duration::from_secs(5 * 60);
+const LONG_BREAK_DURATION: Duration = Duration::from_secs(15 * 60);
+/// Every this many focus sessions, the break is a long one.
+const FOCUS_SESSIONS_PER_LONG_BREAK: u32 = 4;
+/// How long the LED is on (and then off) while blinking during a break.
+const BREAK_BLINK_HALF_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PomodoroPhase {
+    Focus,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    fn duration(self) -> Duration {
+        match self {
+            Self::Focus => FOCUS_DURATION,
+            Self::ShortBreak => SHORT_BREAK_DURATION,
+            Self::LongBreak => LONG_BREAK_DURATION,
+        }
+    }
+}
+
+impl fmt::Display for PomodoroPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Focus => "focus",
+            Self::ShortBreak => "short break",
+            Self::LongBreak => "long break",
+        })
+    }
+}
+
+/// Drives an LED through focus sessions (solid) and breaks (blinking).
+#[derive(Debug, Clone)]
+pub struct Pomodoro {
+    /// File name of the LED showing the pomodoro state
+    pub led: String,
+    pub phase: PomodoroPhase,
+    completed_focus_sessions: u32,
+    /// Time spent in the current phase before the last pause
+    elapsed_before_pause: Duration,
+    /// When the timer was last started or resumed, or `None` while paused
+    running_since: Option<Instant>,
+}
+
+impl Pomodoro {
+    /// Starts a focus session on the LED.
+    pub fn new(led: String) -> Self {
+        Self {
+            led,
+            phase: PomodoroPhase::Focus,
+            completed_focus_sessions: 0,
+            elapsed_before_pause: Duration::ZERO,
+            running_since: Some(Instant::now()),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.elapsed_before_pause + self.running_since.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.phase.duration().saturating_sub(self.elapsed())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.running_since.is_none()
+    }
+
+    pub fn toggle_paused(&mut self) {
+        match self.running_since.take() {
+            Some(since) => self.elapsed_before_pause += since.elapsed(),
+            None => self.running_since = Some(Instant::now()),
+        }
+    }
+
+    /// Moves on to the next phase, even if the current one isn't over.
+    pub fn skip(&mut self) {
+        self.phase = match self.phase {
+            PomodoroPhase::Focus => {
+                self.completed_focus_sessions += 1;
+                if self
+                    .completed_focus_sessions
+                    .is_multiple_of(FOCUS_SESSIONS_PER_LONG_BREAK)
+                {
+                    PomodoroPhase::LongBreak
+                } else {
+                    PomodoroPhase::ShortBreak
+                }
+            }
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => PomodoroPhase::Focus,
+        };
+        self.elapsed_before_pause = Duration::ZERO;
+        if !self.is_paused() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    /// Advances to the next phase if the current one is over.
+    ///
+    /// Returns whether the phase changed.
+    pub fn update(&mut self) -> bool {
+        if self.remaining().is_zero() {
+            self.skip();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the LED should currently be lit.
+    pub fn led_on(&self) -> bool {
+        match self.phase {
+            PomodoroPhase::Focus => true,
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => {
+                (self.elapsed().as_millis() / BREAK_BLINK_HALF_PERIOD.as_millis()).is_multiple_of(2)
+            }
+        }
+    }
+}
+
+/// Tells the user that a new phase has started.
+///
+/// Runs the configured hook command if there is one (with the phase in `GLIMPSE_POMODORO_PHASE`),
+/// and otherwise sends a desktop notification with `notify-send`.
+pub fn notify(phase: PomodoroPhase, hook: Option<&str>) -> std::io::Result<()> {
+    let mut command = match hook {
+        Some(hook) => {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(hook);
+            command
+        }
+        None => {
+            let mut command = Command::new("notify-send");
+            command
+                .arg("Glimpse")
+                .arg(format!("Pomodoro: {} started", phase));
+            command
+        }
+    };
+    let mut child = command
+        .env("GLIMPSE_POMODORO_PHASE", phase.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
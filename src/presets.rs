@@ -0,0 +1,192 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::multicolor::Rgb;
+
+/// Presets used when none are given on the command line.
+const DEFAULT_PRESETS: [(char, u8); 5] = [('1', 0), ('2', 25), ('3', 50), ('4', 75), ('5', 100)];
+
+/// What a preset does to the LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetValue {
+    /// Set the brightness, in percent of the maximum
+    Percent(u8),
+    /// Set the color of a multicolor LED
+    Color(Rgb),
+}
+
+/// A brightness or color applied to the selected LED with a number key.
+///
+/// Presets are written as `[<led>@]<key>=<percent>[%]` or `[<led>@]<key>=<color>`, e.g. `2=25%`
+/// for all LEDs, `tpacpi::kbd_backlight@2=50` for one LED or `rgb:status@3=teal`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    /// The LED the preset applies to, or `None` for all LEDs
+    pub led: Option<String>,
+    pub key: char,
+    pub value: PresetValue,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParsePresetError {
+    #[error("expected [<led>@]<key>=<percent>|<color>")]
+    MissingPercent,
+    #[error("preset keys must be a single digit")]
+    InvalidKey,
+    #[error("invalid percentage or color \"{0}\"")]
+    InvalidValue(String),
+}
+
+impl FromStr for Preset {
+    type Err = ParsePresetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, value) = s.split_once('=').ok_or(ParsePresetError::MissingPercent)?;
+        let (led, key) = match target.rsplit_once('@') {
+            Some((led, key)) if !led.is_empty() => (Some(led.to_string()), key),
+            _ => (None, target),
+        };
+        Ok(Self {
+            led,
+            key: parse_key(key)?,
+            value: value.parse()?,
+        })
+    }
+}
+
+/// Parses the number key a preset is bound to.
+pub fn parse_key(key: &str) -> Result<char, ParsePresetError> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) if key.is_ascii_digit() => Ok(key),
+        _ => Err(ParsePresetError::InvalidKey),
+    }
+}
+
+impl FromStr for PresetValue {
+    type Err = ParsePresetError;
+
+    /// Parses a percentage, with or without `%`, or a color.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let percent = value.strip_suffix('%').unwrap_or(value);
+        if percent.starts_with(|c: char| c.is_ascii_digit()) {
+            Ok(Self::Percent(
+                percent
+                    .parse()
+                    .ok()
+                    .filter(|percent| *percent <= 100)
+                    .ok_or_else(|| ParsePresetError::InvalidValue(value.to_string()))?,
+            ))
+        } else {
+            Ok(Self::Color(value.parse().map_err(|_| {
+                ParsePresetError::InvalidValue(value.to_string())
+            })?))
+        }
+    }
+}
+
+/// All brightness presets, looked up per LED before falling back to the global ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Presets(Vec<Preset>);
+
+impl Default for Presets {
+    fn default() -> Self {
+        Self(
+            DEFAULT_PRESETS
+                .iter()
+                .map(|(key, percent)| Preset {
+                    led: None,
+                    key: *key,
+                    value: PresetValue::Percent(*percent),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Presets {
+    /// Uses the given presets, or the defaults if there are none.
+    pub fn new(presets: Vec<Preset>) -> Self {
+        if presets.is_empty() {
+            Self::default()
+        } else {
+            Self(presets)
+        }
+    }
+
+    /// The value bound to `key` for the LED, if any.
+    pub fn lookup(&self, led: &str, key: char) -> Option<PresetValue> {
+        let find = |for_led: Option<&str>| {
+            self.0
+                .iter()
+                .find(|preset| preset.key == key && preset.led.as_deref() == for_led)
+                .map(|preset| preset.value)
+        };
+        find(Some(led)).or_else(|| find(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_presets() {
+        assert_eq!(
+            "2=25%".parse(),
+            Ok(Preset {
+                led: None,
+                key: '2',
+                value: PresetValue::Percent(25),
+            })
+        );
+        assert_eq!(
+            "tpacpi::kbd_backlight@2=50".parse(),
+            Ok(Preset {
+                led: Some("tpacpi::kbd_backlight".to_string()),
+                key: '2',
+                value: PresetValue::Percent(50),
+            })
+        );
+        let color: Preset = "rgb:status@3=teal".parse().unwrap();
+        assert_eq!(color.value, PresetValue::Color(Rgb::new(0x00, 0x80, 0x80)));
+        // The LED name can contain @ itself
+        let preset: Preset = "a@b@4=#ff8800".parse().unwrap();
+        assert_eq!(preset.led.as_deref(), Some("a@b"));
+    }
+
+    #[test]
+    fn rejects_invalid_presets() {
+        assert_eq!("2".parse::<Preset>(), Err(ParsePresetError::MissingPercent));
+        for key in ["a=50", "12=50", "=50"] {
+            assert_eq!(key.parse::<Preset>(), Err(ParsePresetError::InvalidKey));
+        }
+        for value in ["101", "50%%", "mauve", ""] {
+            assert_eq!(
+                value.parse::<PresetValue>(),
+                Err(ParsePresetError::InvalidValue(value.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn looks_up_per_led_first() {
+        let presets = Presets::new(vec![
+            "2=25".parse().unwrap(),
+            "tpacpi::kbd_backlight@2=50".parse().unwrap(),
+        ]);
+        let lookup = |led| presets.lookup(led, '2');
+        assert_eq!(
+            lookup("tpacpi::kbd_backlight"),
+            Some(PresetValue::Percent(50))
+        );
+        assert_eq!(lookup("input3::capslock"), Some(PresetValue::Percent(25)));
+        assert_eq!(presets.lookup("input3::capslock", '3'), None);
+        let defaults = Presets::new(Vec::new());
+        assert_eq!(
+            defaults.lookup("input3::capslock", '5'),
+            Some(PresetValue::Percent(100))
+        );
+    }
+}
@@ -0,0 +1,550 @@
+//! Profiles: the brightness and trigger of every LED saved under a name, like "work" or
+//! "night", to be applied again later from the TUI or with `glimpse profile apply <name>`.
+//!
+//! Each profile is a file in `$XDG_CONFIG_HOME/glimpse/profiles` made of
+//! `<led> = <brightness> [<trigger>] [color=<color>]` lines. The trigger is left out for LEDs
+//! without one, and the color, a name like `teal` or a hex code like `#ff8800`, for LEDs that
+//! aren't multicolor.
+
+use std::{collections::BTreeMap, fs, io, path::PathBuf, time::SystemTime};
+
+use color_eyre::{Result, eyre::eyre};
+use thiserror::Error;
+
+use crate::{
+    LED, LedKind, dirs, get_all_leds,
+    multicolor::{self, ParseColorError, Rgb},
+    output::{Output, Tone},
+    terminal::Progress,
+    undo::Edit,
+};
+
+/// Name of the directory in Glimpse's config directory holding the profiles.
+pub const PROFILES_DIR: &str = "profiles";
+
+/// What a profile sets an LED to, also used to put LEDs back the way they were after turning
+/// them off for a while (muting, do-not-disturb, presentation mode, sweeps and benchmarks).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedState {
+    pub brightness: u32,
+    /// The active trigger, or `None` for LEDs without a trigger file or with the `none` trigger
+    pub trigger: Option<String>,
+    /// The color of a multicolor LED
+    pub color: Option<Rgb>,
+}
+
+/// Prefix of the color in a profile line, which tells it apart from the trigger.
+const COLOR_PREFIX: &str = "color=";
+
+/// The color of an LED, or `None` if it isn't multicolor.
+fn capture_color(led: &LED) -> io::Result<Option<Rgb>> {
+    if !matches!(led.kind, LedKind::Multicolor { .. }) {
+        return Ok(None);
+    }
+    multicolor::color_of(led)
+        .map(Some)
+        .map_err(io::Error::other)
+}
+
+impl LedState {
+    /// Reads the current state of an LED.
+    pub fn capture(led: &mut LED) -> io::Result<Self> {
+        let trigger = led
+            .triggers()?
+            .and_then(|triggers| triggers.active)
+            .filter(|trigger| trigger != "none");
+        Ok(Self {
+            brightness: led.refresh()?,
+            trigger,
+            color: capture_color(led)?,
+        })
+    }
+
+    /// Puts an LED back into this state, only writing what differs, and returns the changes
+    /// made.
+    pub fn restore(&self, led: &mut LED) -> io::Result<Vec<Edit>> {
+        let mut edits = Vec::new();
+        let result = reconcile(led, self, &mut edits);
+        // Keep the cached state in sync even if restoring only partly worked
+        let _ = led.refresh();
+        result.map(|()| edits)
+    }
+}
+
+/// The saved state of each LED, by file name.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub leds: BTreeMap<String, LedState>,
+}
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("no config directory (neither XDG_CONFIG_HOME nor HOME is set)")]
+    NoConfigDir,
+    #[error("invalid profile name \"{0}\"")]
+    InvalidName(String),
+    #[error("there is no profile named {0}")]
+    NotFound(String),
+    #[error("{name}:{line}: {message}")]
+    Invalid {
+        name: String,
+        line: usize,
+        message: String,
+    },
+    #[error("I/O error: {0}")]
+    IOError(#[from] io::Error),
+}
+
+fn profiles_dir() -> Result<PathBuf, ProfileError> {
+    Ok(dirs::config_dir()
+        .ok_or(ProfileError::NoConfigDir)?
+        .join(PROFILES_DIR))
+}
+
+/// The file of a profile, as long as its name can't point outside the profiles directory.
+fn profile_path(name: &str) -> Result<PathBuf, ProfileError> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        return Err(ProfileError::InvalidName(name.to_string()));
+    }
+    Ok(profiles_dir()?.join(name))
+}
+
+/// The names of the saved profiles, in alphabetical order.
+pub fn list() -> Result<Vec<String>, ProfileError> {
+    let entries = match fs::read_dir(profiles_dir()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_file()
+            && let Some(name) = entry.file_name().to_str()
+            && !name.starts_with('.')
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Whether a profile with the name has been saved.
+pub fn exists(name: &str) -> bool {
+    profile_path(name).is_ok_and(|path| path.is_file())
+}
+
+/// When a profile was last saved, to notice it being saved again.
+pub fn modified(name: &str) -> Option<SystemTime> {
+    fs::metadata(profile_path(name).ok()?)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl Profile {
+    /// Reads the current state of the LEDs, returning errors for those whose trigger or color
+    /// couldn't be read, which are left out. Backlights are left out too, as the screen's brightness
+    /// is better left to the desktop.
+    pub fn capture(leds: &[LED]) -> (Self, Vec<String>) {
+        let mut profile = Self::default();
+        let mut errors = Vec::new();
+        for led in leds.iter().filter(|led| !led.is_backlight()) {
+            match led
+                .triggers()
+                .and_then(|triggers| Ok((triggers, capture_color(led)?)))
+            {
+                Ok((triggers, color)) => {
+                    let trigger = triggers
+                        .and_then(|triggers| triggers.active)
+                        .filter(|trigger| trigger != "none");
+                    profile.leds.insert(
+                        led.file_name.clone(),
+                        LedState {
+                            brightness: led.brightness,
+                            trigger,
+                            color,
+                        },
+                    );
+                }
+                Err(e) => errors.push(format!("{}: {}", led.file_name, e)),
+            }
+        }
+        (profile, errors)
+    }
+
+    /// Parses the contents of the profile called `name`, which is only used in errors.
+    pub fn parse(name: &str, contents: &str) -> Result<Self, ProfileError> {
+        let mut profile = Self::default();
+        for (i, line) in contents.lines().enumerate() {
+            let invalid = |message: String| ProfileError::Invalid {
+                name: name.to_string(),
+                line: i + 1,
+                message,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((led, value)) = line.split_once('=') else {
+                return Err(invalid(
+                    "expected <led> = <brightness> [<trigger>] [color=<color>]".to_string(),
+                ));
+            };
+            let mut fields = value.split_whitespace();
+            let brightness = fields
+                .next()
+                .and_then(|brightness| brightness.parse().ok())
+                .ok_or_else(|| {
+                    invalid(format!("\"{}\" is not a brightness level", value.trim()))
+                })?;
+            let (mut trigger, mut color) = (None, None);
+            for field in fields {
+                match field.strip_prefix(COLOR_PREFIX) {
+                    Some(value) if color.is_none() => {
+                        color = Some(
+                            value
+                                .parse()
+                                .map_err(|e: ParseColorError| invalid(e.to_string()))?,
+                        );
+                    }
+                    None if trigger.is_none() && color.is_none() => {
+                        trigger = Some(field.to_string());
+                    }
+                    _ => return Err(invalid(format!("unexpected \"{}\"", field))),
+                }
+            }
+            profile.leds.insert(
+                led.trim().to_string(),
+                LedState {
+                    brightness,
+                    trigger,
+                    color,
+                },
+            );
+        }
+        Ok(profile)
+    }
+
+    /// The profile as the lines [`Profile::parse`] reads.
+    pub fn serialize(&self) -> String {
+        self.leds
+            .iter()
+            .map(|(led, state)| {
+                let mut line = format!("{} = {}", led, state.brightness);
+                if let Some(trigger) = &state.trigger {
+                    line.push_str(&format!(" {}", trigger));
+                }
+                if let Some(color) = state.color {
+                    line.push_str(&format!(" {}{}", COLOR_PREFIX, color));
+                }
+                line + "\n"
+            })
+            .collect()
+    }
+
+    pub fn load(name: &str) -> Result<Self, ProfileError> {
+        match fs::read_to_string(profile_path(name)?) {
+            Ok(contents) => Self::parse(name, &contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Err(ProfileError::NotFound(name.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Saves the profile under the name, replacing any profile with the same name.
+    pub fn save(&self, name: &str) -> Result<(), ProfileError> {
+        let path = profile_path(name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    /// Sets each LED in the profile to its saved state, only writing what differs.
+    ///
+    /// LEDs that aren't in the profile are left alone. Returns the changes made, which can be
+    /// undone, and errors for the LEDs that couldn't be set or no longer exist.
+    pub fn apply(&self, leds: &mut [LED]) -> (Vec<Edit>, Vec<String>) {
+        self.apply_with_progress(leds, |_, _| {})
+    }
+
+    /// [`Profile::apply`], calling `progress` with how many of the profile's LEDs have been set
+    /// so far and how many there are in total before each one.
+    pub fn apply_with_progress(
+        &self,
+        leds: &mut [LED],
+        mut progress: impl FnMut(usize, usize),
+    ) -> (Vec<Edit>, Vec<String>) {
+        let mut edits = Vec::new();
+        let mut errors = Vec::new();
+        for (done, (file_name, state)) in self.leds.iter().enumerate() {
+            progress(done, self.leds.len());
+            let Some(led) = leds.iter_mut().find(|led| led.file_name == *file_name) else {
+                errors.push(format!("{}: LED no longer exists", file_name));
+                continue;
+            };
+            match state.restore(led) {
+                Ok(restored) => edits.extend(restored),
+                Err(e) => errors.push(format!("{}: {}", file_name, e)),
+            }
+        }
+        (edits, errors)
+    }
+}
+
+/// Writes the parts of an LED's state that differ from the saved one, adding them to `edits`.
+///
+/// The trigger is set first, because setting it can change the brightness. The brightness of
+/// an LED driven by a trigger is left to the trigger, but not its color. Colors aren't recorded
+/// in `edits`, as they can't be undone.
+fn reconcile(led: &mut LED, state: &LedState, edits: &mut Vec<Edit>) -> io::Result<()> {
+    match (led.triggers()?, &state.trigger) {
+        (Some(triggers), _) => {
+            let old = triggers.active.unwrap_or_else(|| "none".to_string());
+            let new = state.trigger.as_deref().unwrap_or("none");
+            if old != new {
+                led.set_trigger(new)?;
+                edits.push(Edit::Trigger {
+                    led: led.file_name.clone(),
+                    old,
+                    new: new.to_string(),
+                });
+            }
+        }
+        (None, Some(_)) => return Err(io::Error::other("the LED no longer has triggers")),
+        (None, None) => {}
+    }
+    if let Some(color) = state.color
+        && !multicolor::has_color(led, color).map_err(io::Error::other)?
+    {
+        multicolor::set_color(led, color).map_err(io::Error::other)?;
+    }
+    if state.trigger.is_some() {
+        return Ok(());
+    }
+    let old = led.refresh().map_err(io::Error::other)?;
+    let new = state.brightness.min(led.max_brightness);
+    if old != new {
+        led.set_brightness(new)?;
+        edits.push(Edit::Brightness {
+            led: led.file_name.clone(),
+            old,
+            new,
+        });
+    }
+    Ok(())
+}
+
+/// Saves the current state of every LED as a profile, from the command line.
+pub fn run_save(name: &str) -> Result<()> {
+    let output = Output::detect();
+    let leds = get_all_leds()?;
+    let (profile, errors) = Profile::capture(&leds);
+    profile.save(name)?;
+    println!(
+        "Saved {} LED(s) to profile {}",
+        output.paint(&profile.leds.len().to_string(), Tone::Value),
+        output.paint(name, Tone::Name)
+    );
+    for error in &errors {
+        eprintln!("{}", output.paint(error, Tone::Error));
+    }
+    Ok(())
+}
+
+/// Applies a profile from the command line, failing if any LED couldn't be set.
+pub fn run_apply(name: &str) -> Result<()> {
+    let output = Output::detect();
+    let profile = Profile::load(name)?;
+    let mut leds = get_all_leds()?;
+    let progress = Progress::start();
+    let (edits, errors) =
+        profile.apply_with_progress(&mut leds, |done, total| progress.update(done, total));
+    drop(progress);
+    report_applied(
+        &format!("Applied profile {}", output.paint(name, Tone::Name)),
+        &edits,
+        &errors,
+    )
+}
+
+/// Prints how many LEDs applying a profile changed and why others couldn't be, failing if
+/// any couldn't be.
+pub fn report_applied(summary: &str, edits: &[Edit], errors: &[String]) -> Result<()> {
+    let output = Output::detect();
+    println!(
+        "{}, changed {} LED(s)",
+        summary,
+        output.paint(&changed_leds(edits).to_string(), Tone::Value)
+    );
+    for error in errors {
+        eprintln!("{}", output.paint(error, Tone::Error));
+    }
+    if !errors.is_empty() {
+        return Err(eyre!("{} LED(s) could not be set", errors.len()));
+    }
+    Ok(())
+}
+
+/// Prints the names of the saved profiles.
+pub fn run_list() -> Result<()> {
+    let output = Output::detect();
+    for name in list()? {
+        println!("{}", output.paint(&name, Tone::Name));
+    }
+    Ok(())
+}
+
+/// How many different LEDs the edits changed.
+pub fn changed_leds(edits: &[Edit]) -> usize {
+    let mut leds: Vec<&str> = edits.iter().map(Edit::led).collect();
+    leds.sort();
+    leds.dedup();
+    leds.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{MockBackend, Triggers, backend::MockLed, led::get_all_leds_from};
+
+    #[test]
+    fn round_trips() {
+        let contents = "input3::capslock = 1\nphy0-led = 0 phy0rx\n\
+            rgb:status = 255 heartbeat color=#ff8800\n";
+        let profile = Profile::parse("night", contents).unwrap();
+        assert_eq!(
+            profile.leds["phy0-led"],
+            LedState {
+                brightness: 0,
+                trigger: Some("phy0rx".to_string()),
+                color: None,
+            }
+        );
+        assert_eq!(profile.serialize(), contents);
+        let error = Profile::parse("night", "\ninput3::capslock = on\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "night:2: \"on\" is not a brightness level"
+        );
+    }
+
+    #[test]
+    fn parses_colors() {
+        let profile = Profile::parse("night", "rgb:status = 255 color=Teal\n").unwrap();
+        assert_eq!(
+            profile.leds["rgb:status"],
+            LedState {
+                brightness: 255,
+                trigger: None,
+                color: Some(Rgb::new(0x00, 0x80, 0x80)),
+            }
+        );
+        assert_eq!(profile.serialize(), "rgb:status = 255 color=#008080\n");
+        let error = Profile::parse("night", "rgb:status = 255 color=mauve\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("night:1: unknown color \"mauve\"")
+        );
+        // The trigger goes before the color
+        let error = Profile::parse("night", "rgb:status = 255 color=teal timer\n").unwrap_err();
+        assert_eq!(error.to_string(), "night:1: unexpected \"timer\"");
+    }
+
+    #[test]
+    fn applies_colors() {
+        let backend = MockBackend::new();
+        backend.add_led(
+            "rgb:status",
+            MockLed {
+                brightness: 255,
+                channels: Some(vec![("red".to_string(), 0), ("green".to_string(), 0)]),
+                ..MockLed::new(255)
+            },
+        );
+        let mut leds = get_all_leds_from(Arc::new(backend.clone())).unwrap();
+        let profile = Profile::parse("night", "rgb:status = 255 color=#ff8800\n").unwrap();
+        assert_eq!(profile.apply(&mut leds), (Vec::new(), Vec::new()));
+        assert_eq!(leds[0].intensities().unwrap(), [255, 0x88]);
+        assert_eq!(Profile::capture(&leds).0, profile);
+    }
+
+    #[test]
+    fn applies_what_differs() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led(
+            "phy0-led",
+            MockLed {
+                brightness: 1,
+                triggers: Some(Triggers {
+                    available: vec!["none".to_string(), "phy0rx".to_string()],
+                    active: Some("none".to_string()),
+                }),
+                ..MockLed::new(1)
+            },
+        );
+        let mut leds = get_all_leds_from(Arc::new(backend.clone())).unwrap();
+        // Only the trigger of phy0-led differs
+        let profile = Profile::parse(
+            "work",
+            "input3::capslock = 0\nphy0-led = 1 phy0rx\nmissing = 1\n",
+        )
+        .unwrap();
+        let (edits, errors) = profile.apply(&mut leds);
+        assert_eq!(
+            edits,
+            [Edit::Trigger {
+                led: "phy0-led".to_string(),
+                old: "none".to_string(),
+                new: "phy0rx".to_string(),
+            }]
+        );
+        assert_eq!(errors, ["missing: LED no longer exists"]);
+        let phy0 = backend.led("phy0-led").unwrap();
+        assert_eq!(phy0.triggers.unwrap().active.as_deref(), Some("phy0rx"));
+        let mut expected = profile.leds;
+        expected.remove("missing");
+        assert_eq!(Profile::capture(&leds).0.leds, expected);
+    }
+
+    #[test]
+    fn restore_keeps_triggers() {
+        let backend = MockBackend::new();
+        backend.add_led(
+            "ata1::disk-activity",
+            MockLed {
+                brightness: 1,
+                triggers: Some(Triggers {
+                    available: vec!["none".to_string(), "disk-activity".to_string()],
+                    active: Some("none".to_string()),
+                }),
+                ..MockLed::new(1)
+            },
+        );
+        let mut leds = get_all_leds_from(Arc::new(backend.clone())).unwrap();
+        // Captured while the disk was idle, so the LED was dark
+        let state = LedState {
+            brightness: 0,
+            trigger: Some("disk-activity".to_string()),
+            color: None,
+        };
+        let edits = state.restore(&mut leds[0]).unwrap();
+        assert_eq!(edits.len(), 1);
+        let led = backend.led("ata1::disk-activity").unwrap();
+        assert_eq!(
+            led.triggers.unwrap().active.as_deref(),
+            Some("disk-activity")
+        );
+        // The brightness is left to the trigger
+        assert_eq!(
+            LedState::capture(&mut leds[0]).unwrap().trigger,
+            state.trigger
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Turning off a set of LEDs: temporarily, for do-not-disturb and presentation mode, or all of
+//! them at once with `glimpse off --all`.
+
+use std::time::Instant;
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::{
+    LED, get_all_leds, glob,
+    output::{Output, Tone},
+    profiles::{LedState, Profile},
+    resolve,
+    terminal::Progress,
+};
+
+/// LED functions (the last part of `device:color:function`) that are treated as notification
+/// LEDs when no others are given with `--dnd-led`.
+pub const DEFAULT_NOTIFICATION_LEDS: [&str; 4] = ["mail", "message", "messaging", "charging"];
+
+/// LED functions that presentation mode turns off when no others are given with
+/// `--presentation-led`: the ones that blink with disk and network activity.
+pub const DEFAULT_PRESENTATION_LEDS: [&str; 8] = [
+    "disk",
+    "disk-activity",
+    "disk-read",
+    "disk-write",
+    "activity",
+    "lan",
+    "wlan",
+    "wan",
+];
+
+/// Uses the LEDs given on the command line, or the defaults if there are none.
+pub fn led_set(given: Vec<String>, defaults: &[&str]) -> Vec<String> {
+    if given.is_empty() {
+        defaults.iter().map(|led| led.to_string()).collect()
+    } else {
+        given
+    }
+}
+
+/// Checks whether an LED is part of a set, by full name or by function.
+pub fn in_set(led: &LED, set: &[String]) -> bool {
+    let function = resolve::function(&led.file_name);
+    set.iter()
+        .any(|pattern| *pattern == led.file_name || pattern == function)
+}
+
+/// Turns off every LED in the set, returning their previous state and any errors.
+pub fn turn_off(leds: &mut [LED], set: &[String]) -> (Profile, Vec<String>) {
+    let mut saved = Profile::default();
+    let mut errors = Vec::new();
+    for led in leds.iter_mut().filter(|led| in_set(led, set)) {
+        let state = match LedState::capture(led) {
+            Ok(state) => state,
+            Err(e) => {
+                errors.push(format!("{}: {}", led.file_name, e));
+                continue;
+            }
+        };
+        match led.set_on(false) {
+            Ok(()) => {
+                saved.leds.insert(led.file_name.clone(), state);
+            }
+            Err(e) => errors.push(format!("{}: {}", led.file_name, e)),
+        }
+    }
+    (saved, errors)
+}
+
+/// Turns off every LED, stopping their triggers too, without saving their state. Backlights
+/// are left on, as turning them off would blank the screen.
+///
+/// Returns how many were turned off, and errors for the LEDs that couldn't be.
+pub fn all_off(leds: &mut [LED]) -> (usize, Vec<String>) {
+    all_off_with_progress(leds, |_, _| {})
+}
+
+/// [`all_off`], calling `progress` with how many LEDs have been turned off so far and how many
+/// there are in total before each one.
+pub fn all_off_with_progress(
+    leds: &mut [LED],
+    mut progress: impl FnMut(usize, usize),
+) -> (usize, Vec<String>) {
+    let mut turned_off = 0;
+    let mut errors = Vec::new();
+    let total = leds.iter().filter(|led| !led.is_backlight()).count();
+    for (done, led) in leds
+        .iter_mut()
+        .filter(|led| !led.is_backlight())
+        .enumerate()
+    {
+        progress(done, total);
+        match led.set_on(false) {
+            Ok(()) => turned_off += 1,
+            Err(e) => errors.push(format!("{}: {}", led.file_name, e)),
+        }
+    }
+    (turned_off, errors)
+}
+
+/// Turns off LEDs from the command line: the ones given (by name or glob pattern), or all of
+/// them if there are none.
+pub fn run_off(led_names: Vec<String>) -> Result<()> {
+    let output = Output::detect();
+    let mut leds = if led_names.is_empty() {
+        get_all_leds()?
+    } else {
+        glob::expand(&led_names)?
+    };
+    let progress = Progress::start();
+    let (turned_off, errors) =
+        all_off_with_progress(&mut leds, |done, total| progress.update(done, total));
+    drop(progress);
+    println!(
+        "Turned off {} LED(s)",
+        output.paint(&turned_off.to_string(), Tone::Value)
+    );
+    for error in &errors {
+        eprintln!("{}", output.paint(error, Tone::Error));
+    }
+    if !errors.is_empty() {
+        return Err(eyre!("{} LED(s) could not be turned off", errors.len()));
+    }
+    Ok(())
+}
+
+/// Puts LEDs back the way they were before [`turn_off`].
+pub fn restore(leds: &mut [LED], saved: &Profile) -> Vec<String> {
+    saved.apply(leds).1
+}
+
+/// LEDs turned off by presentation mode, and when to turn them back on.
+#[derive(Debug)]
+pub struct Presentation {
+    pub saved: Profile,
+    /// When presentation mode ends by itself, if it has a duration
+    pub until: Option<Instant>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{MockBackend, Triggers, backend::MockLed, led::get_all_leds_from};
+
+    #[test]
+    fn restores_presentation_leds() {
+        let backend = MockBackend::new();
+        let triggered = |trigger: &str| MockLed {
+            brightness: 0,
+            triggers: Some(Triggers {
+                available: vec!["none".to_string(), trigger.to_string()],
+                active: Some(trigger.to_string()),
+            }),
+            ..MockLed::new(1)
+        };
+        backend.add_led("ata1::disk-activity", triggered("disk-activity"));
+        backend.add_led("phy0::wlan", triggered("phy0tx"));
+        backend.add_led("input3::capslock", MockLed::new(1));
+        let mut leds = get_all_leds_from(Arc::new(backend.clone())).unwrap();
+        let set = led_set(Vec::new(), &DEFAULT_PRESENTATION_LEDS);
+        let (saved, errors) = turn_off(&mut leds, &set);
+        assert!(errors.is_empty());
+        assert_eq!(
+            saved.leds.keys().collect::<Vec<_>>(),
+            ["ata1::disk-activity", "phy0::wlan"]
+        );
+        let active = |led: &str| backend.led(led).unwrap().triggers.unwrap().active;
+        assert_eq!(active("phy0::wlan").as_deref(), Some("none"));
+        assert!(restore(&mut leds, &saved).is_empty());
+        assert_eq!(
+            active("ata1::disk-activity").as_deref(),
+            Some("disk-activity")
+        );
+        assert_eq!(active("phy0::wlan").as_deref(), Some("phy0tx"));
+    }
+
+    #[test]
+    fn reports_progress_turning_off() {
+        let backend = MockBackend::new();
+        for led in ["input3::capslock", "input3::numlock"] {
+            backend.add_led(
+                led,
+                MockLed {
+                    brightness: 1,
+                    ..MockLed::new(1)
+                },
+            );
+        }
+        let mut leds = get_all_leds_from(Arc::new(backend.clone())).unwrap();
+        let mut reported = Vec::new();
+        let outcome = all_off_with_progress(&mut leds, |done, total| reported.push((done, total)));
+        assert_eq!(outcome, (2, Vec::new()));
+        assert_eq!(reported, [(0, 2), (1, 2)]);
+        assert_eq!(backend.led("input3::numlock").unwrap().brightness, 0);
+    }
+}
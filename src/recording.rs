@@ -0,0 +1,146 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{Result, eyre::eyre};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    queue,
+    style::{Attribute, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+};
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+};
+
+/// First line of every recording, identifying the format version.
+const HEADER: &str = "glimpse-recording 1";
+
+/// Writes every rendered frame to a file, along with when it was drawn.
+///
+/// Each frame is stored as a `frame <milliseconds> <width> <height>` line followed by one line of
+/// ANSI-styled text per terminal row, so a recording can be replayed in any terminal.
+#[derive(Debug)]
+pub struct Recorder {
+    writer: BufWriter<File>,
+    started: Instant,
+    last_frame: Option<Buffer>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", HEADER)?;
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            last_frame: None,
+        })
+    }
+
+    /// Appends a frame, unless it looks exactly like the previous one.
+    pub fn record(&mut self, buffer: &Buffer) -> io::Result<()> {
+        if self.last_frame.as_ref() == Some(buffer) {
+            return Ok(());
+        }
+        let area = buffer.area;
+        writeln!(
+            self.writer,
+            "frame {} {} {}",
+            self.started.elapsed().as_millis(),
+            area.width,
+            area.height
+        )?;
+        for y in area.top()..area.bottom() {
+            let mut line = Vec::new();
+            let mut last_style = None;
+            for x in area.left()..area.right() {
+                let cell = &buffer[(x, y)];
+                let style = (cell.fg, cell.bg, cell.modifier);
+                if last_style != Some(style) {
+                    write_style(&mut line, cell.fg, cell.bg, cell.modifier)?;
+                    last_style = Some(style);
+                }
+                line.write_all(cell.symbol().as_bytes())?;
+            }
+            queue!(line, SetAttribute(Attribute::Reset), ResetColor)?;
+            self.writer.write_all(&line)?;
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.flush()?;
+        self.last_frame = Some(buffer.clone());
+        Ok(())
+    }
+}
+
+fn write_style(out: &mut Vec<u8>, fg: Color, bg: Color, modifier: Modifier) -> io::Result<()> {
+    queue!(
+        out,
+        SetAttribute(Attribute::Reset),
+        SetForegroundColor(fg.into()),
+        SetBackgroundColor(bg.into())
+    )?;
+    let attributes = [
+        (Modifier::BOLD, Attribute::Bold),
+        (Modifier::DIM, Attribute::Dim),
+        (Modifier::ITALIC, Attribute::Italic),
+        (Modifier::UNDERLINED, Attribute::Underlined),
+        (Modifier::REVERSED, Attribute::Reverse),
+        (Modifier::CROSSED_OUT, Attribute::CrossedOut),
+    ];
+    for (flag, attribute) in attributes {
+        if modifier.contains(flag) {
+            queue!(out, SetAttribute(attribute))?;
+        }
+    }
+    Ok(())
+}
+
+/// Replays a recording in the terminal with its original timing.
+pub fn play(path: &Path) -> Result<()> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    if lines.next().transpose()?.as_deref() != Some(HEADER) {
+        return Err(eyre!("{} is not a Glimpse recording", path.display()));
+    }
+    let mut stdout = io::stdout();
+    queue!(stdout, Hide)?;
+    let started = Instant::now();
+    let result = (|| -> Result<()> {
+        while let Some(line) = lines.next().transpose()? {
+            let Some((timestamp, height)) = parse_frame_header(&line) else {
+                return Err(eyre!("invalid frame header \"{}\"", line));
+            };
+            if let Some(delay) = timestamp.checked_sub(started.elapsed()) {
+                thread::sleep(delay);
+            }
+            queue!(stdout, MoveTo(0, 0), Clear(ClearType::All))?;
+            for row in 0..height {
+                let text = lines
+                    .next()
+                    .transpose()?
+                    .ok_or_else(|| eyre!("recording ends in the middle of a frame"))?;
+                queue!(stdout, MoveTo(0, row))?;
+                stdout.write_all(text.as_bytes())?;
+            }
+            stdout.flush()?;
+        }
+        Ok(())
+    })();
+    queue!(stdout, Show, SetAttribute(Attribute::Reset), ResetColor)?;
+    writeln!(stdout)?;
+    result
+}
+
+/// Parses a `frame <milliseconds> <width> <height>` line into the timestamp and height.
+fn parse_frame_header(line: &str) -> Option<(Duration, u16)> {
+    let mut parts = line.strip_prefix("frame ")?.split(' ');
+    let timestamp = Duration::from_millis(parts.next()?.parse().ok()?);
+    let _width: u16 = parts.next()?.parse().ok()?;
+    let height = parts.next()?.parse().ok()?;
+    Some((timestamp, height))
+}
@@ -0,0 +1,415 @@
+//! LEDs of other machines, read and written over SSH, so that the TUI can show the LEDs of a
+//! few servers next to the local ones.
+//!
+//! A host's LEDs are named `<host>/<file name>`, which can't clash with a local LED as sysfs
+//! names have no slashes, and which groups them under the host in the sidebar. Connections to a
+//! host are shared through an OpenSSH control master, so only the first read pays for the
+//! handshake.
+//!
+//! The TUI reads the brightness of a host's LEDs in the background with a [`Poller`], and a
+//! host that can't be reached is left alone for a while rather than waited for on every read.
+//! Only hosts reached with `ssh` are supported, not ones running a Glimpse daemon.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    io::{self, ErrorKind},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::LedBackend,
+    led::{self, LED, LedScan, NewLEDError, SYS_CLASS_LEDS, Triggers},
+};
+
+/// Separates the host from the LED's file name on that host.
+const HOST_SEPARATOR: char = '/';
+/// How long to wait for a host to answer before giving up on it, in seconds.
+const CONNECT_TIMEOUT: u32 = 5;
+/// How long an idle connection to a host is kept open, in seconds.
+const CONTROL_PERSIST: u32 = 60;
+/// `ssh` exits with this when it fails itself, e.g. as the host can't be reached, rather than
+/// passing on the exit status of the command.
+const SSH_FAILED: i32 = 255;
+/// How long a host that couldn't be reached is left alone before it's tried again.
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+/// How often a [`Poller`] reads the LEDs of each host.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When each host that couldn't be reached may be tried again.
+static UNREACHABLE: Mutex<BTreeMap<String, Instant>> = Mutex::new(BTreeMap::new());
+
+/// Whether an LED is on another machine.
+pub fn is_remote(file_name: &str) -> bool {
+    file_name.contains(HOST_SEPARATOR)
+}
+
+/// The host of an LED on another machine, and its file name there.
+pub fn split_host(file_name: &str) -> Option<(&str, &str)> {
+    file_name.split_once(HOST_SEPARATOR)
+}
+
+/// How long until a host that couldn't be reached may be tried again, if it's left alone.
+fn retry_in(host: &str) -> Option<Duration> {
+    let unreachable = UNREACHABLE.lock().unwrap_or_else(|e| e.into_inner());
+    unreachable
+        .get(host)?
+        .checked_duration_since(Instant::now())
+}
+
+/// Records whether a host could be reached, leaving it alone for [`RETRY_AFTER`] if not.
+fn set_reachable(host: &str, reachable: bool) {
+    let mut unreachable = UNREACHABLE.lock().unwrap_or_else(|e| e.into_inner());
+    if reachable {
+        unreachable.remove(host);
+    } else {
+        unreachable.insert(host.to_string(), Instant::now() + RETRY_AFTER);
+    }
+}
+
+/// Quotes a string for a POSIX shell.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Works out what went wrong from the error output of a failed command on the host.
+fn error_from(stderr: &str) -> io::Error {
+    let message = stderr.trim();
+    let kind = if message.contains("No such file or directory") {
+        ErrorKind::NotFound
+    } else if message.contains("Permission denied") {
+        ErrorKind::PermissionDenied
+    } else {
+        ErrorKind::Other
+    };
+    io::Error::new(kind, message.to_string())
+}
+
+/// The LEDs in [`SYS_CLASS_LEDS`] on another machine, reached with `ssh` (so hosts can be
+/// anything `ssh` accepts, including aliases from `~/.ssh/config`).
+///
+/// Hosts have to let the user in without a password prompt, e.g. with a key loaded into an
+/// agent, and the user there needs permission to write to the LEDs for changes to work.
+#[derive(Debug, Clone)]
+pub struct SshBackend {
+    host: String,
+}
+
+impl SshBackend {
+    pub fn new(host: impl Into<String>) -> Self {
+        Self { host: host.into() }
+    }
+
+    /// The file name of an LED on the host, from its name here.
+    fn remote_name<'a>(&self, file_name: &'a str) -> io::Result<&'a str> {
+        file_name
+            .strip_prefix(self.host.as_str())
+            .and_then(|name| name.strip_prefix(HOST_SEPARATOR))
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("{} is not an LED of {}", file_name, self.host),
+                )
+            })
+    }
+
+    fn attribute_path(&self, file_name: &str, attribute: &str) -> io::Result<String> {
+        Ok(format!(
+            "{}/{}/{}",
+            SYS_CLASS_LEDS,
+            self.remote_name(file_name)?,
+            attribute
+        ))
+    }
+
+    /// Runs a shell command on the host, returning what it printed.
+    ///
+    /// Fails straight away while the host is left alone after it couldn't be reached.
+    fn run(&self, script: &str) -> io::Result<String> {
+        if let Some(wait) = retry_in(&self.host) {
+            return Err(io::Error::new(
+                ErrorKind::NotConnected,
+                format!(
+                    "{} couldn't be reached, trying again in {}s",
+                    self.host,
+                    wait.as_secs() + 1
+                ),
+            ));
+        }
+        let control_path = env::var_os("XDG_RUNTIME_DIR")
+            .map_or_else(env::temp_dir, PathBuf::from)
+            .join("glimpse-ssh-%C");
+        let output = Command::new("ssh")
+            .arg("-o")
+            .arg("BatchMode=yes")
+            .arg("-o")
+            .arg(format!("ConnectTimeout={}", CONNECT_TIMEOUT))
+            .arg("-o")
+            .arg("ControlMaster=auto")
+            .arg("-o")
+            .arg(format!("ControlPath={}", control_path.display()))
+            .arg("-o")
+            .arg(format!("ControlPersist={}", CONTROL_PERSIST))
+            .arg(&self.host)
+            .arg("--")
+            .arg(script)
+            .stdin(Stdio::null())
+            .output()?;
+        set_reachable(&self.host, output.status.code() != Some(SSH_FAILED));
+        let result = if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(error_from(&String::from_utf8_lossy(&output.stderr)))
+        };
+        tracing::trace!(host = self.host, script, ?result, "ran command over ssh");
+        result
+    }
+
+    fn read_attribute(&self, file_name: &str, attribute: &str) -> io::Result<String> {
+        self.run(&format!(
+            "cat {}",
+            quote(&self.attribute_path(file_name, attribute)?)
+        ))
+    }
+
+    fn write_attribute(&self, file_name: &str, attribute: &str, value: &str) -> io::Result<()> {
+        self.run(&format!(
+            "printf %s {} > {}",
+            quote(value),
+            quote(&self.attribute_path(file_name, attribute)?)
+        ))
+        .map(|_| ())
+    }
+
+    /// Reads the brightness of every LED of the host with one command, by name here. LEDs
+    /// whose brightness can't be read are left out.
+    pub fn read_brightnesses(&self) -> io::Result<BTreeMap<String, u32>> {
+        let output = self.run(&format!(
+            "cd {} && grep -H . */brightness || true",
+            quote(SYS_CLASS_LEDS)
+        ))?;
+        Ok(parse_brightnesses(&self.host, &output))
+    }
+
+    fn read_number(&self, file_name: &str, attribute: &str) -> Result<u32, NewLEDError> {
+        self.read_attribute(file_name, attribute)?
+            .trim()
+            .parse()
+            .map_err(|_| NewLEDError::InvalidBrightness)
+    }
+}
+
+impl LedBackend for SshBackend {
+    fn led_names(&self) -> Result<Vec<String>, NewLEDError> {
+        Ok(self
+            .run(&format!("ls -1 {}", quote(SYS_CLASS_LEDS)))?
+            .lines()
+            .map(|name| format!("{}{}{}", self.host, HOST_SEPARATOR, name))
+            .collect())
+    }
+
+    fn read_brightness(&self, led: &str) -> Result<u32, NewLEDError> {
+        self.read_number(led, "brightness")
+    }
+
+    fn write_brightness(&self, led: &str, brightness: u32) -> io::Result<()> {
+        self.write_attribute(led, "brightness", &brightness.to_string())
+    }
+
+    fn read_max_brightness(&self, led: &str) -> Result<u32, NewLEDError> {
+        self.read_number(led, "max_brightness")
+    }
+
+    fn read_triggers(&self, led: &str) -> io::Result<Option<Triggers>> {
+        match self.read_attribute(led, "trigger") {
+            Ok(contents) => Ok(Some(Triggers::parse(&contents))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_trigger(&self, led: &str, trigger: &str) -> io::Result<()> {
+        self.write_attribute(led, "trigger", trigger)
+    }
+
+    fn read_multi_index(&self, led: &str) -> io::Result<Option<Vec<String>>> {
+        match self.read_attribute(led, "multi_index") {
+            Ok(index) => Ok(Some(index.split_whitespace().map(str::to_string).collect())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn read_multi_intensity(&self, led: &str) -> io::Result<Vec<u32>> {
+        self.read_attribute(led, "multi_intensity")?
+            .split_whitespace()
+            .map(|intensity| {
+                intensity.parse().map_err(|_| {
+                    io::Error::new(ErrorKind::InvalidData, NewLEDError::InvalidBrightness)
+                })
+            })
+            .collect()
+    }
+
+    fn write_multi_intensity(&self, led: &str, intensities: &[u32]) -> io::Result<()> {
+        let intensities: Vec<String> = intensities.iter().map(u32::to_string).collect();
+        self.write_attribute(led, "multi_intensity", &intensities.join(" "))
+    }
+}
+
+/// Parses the `<led>/brightness:<brightness>` lines printed by `grep -H`, naming the LEDs as
+/// LEDs of the host.
+fn parse_brightnesses(host: &str, output: &str) -> BTreeMap<String, u32> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (led, brightness) = line.split_once("/brightness:")?;
+            let name = format!("{}{}{}", host, HOST_SEPARATOR, led);
+            Some((name, brightness.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// What a [`Poller`] read from a host: the brightness of each of its LEDs, or why they
+/// couldn't be read.
+pub type Reading = (String, io::Result<BTreeMap<String, u32>>);
+
+/// Reads the brightness of the LEDs of some hosts in the background, with one `ssh` call per
+/// host every [`POLL_INTERVAL`], so that slow or unreachable hosts don't hold up the TUI.
+#[derive(Debug)]
+pub struct Poller {
+    readings: mpsc::Receiver<Reading>,
+}
+
+impl Poller {
+    /// Starts reading the hosts, until the poller is dropped.
+    pub fn start(hosts: BTreeSet<String>) -> Self {
+        let (sender, readings) = mpsc::channel();
+        for host in hosts {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let backend = SshBackend::new(host.as_str());
+                let mut failing = false;
+                loop {
+                    let started = Instant::now();
+                    let reading = backend.read_brightnesses();
+                    // Errors are only passed on when the host stops answering, not again on
+                    // every read until it answers again
+                    let repeated = failing && reading.is_err();
+                    failing = reading.is_err();
+                    if !repeated && sender.send((host.clone(), reading)).is_err() {
+                        return;
+                    }
+                    thread::sleep(POLL_INTERVAL.saturating_sub(started.elapsed()));
+                }
+            });
+        }
+        Self { readings }
+    }
+
+    /// Starts reading the hosts of the remote LEDs among `leds`, if there are any.
+    pub fn for_leds(leds: &[LED]) -> Option<Self> {
+        let hosts: BTreeSet<String> = leds
+            .iter()
+            .filter_map(|led| split_host(&led.file_name))
+            .map(|(host, _)| host.to_string())
+            .collect();
+        (!hosts.is_empty()).then(|| Self::start(hosts))
+    }
+
+    /// The readings that arrived since the last call, oldest first.
+    pub fn readings(&self) -> Vec<Reading> {
+        self.readings.try_iter().collect()
+    }
+}
+
+/// Reads the LEDs of each host, returning them along with an error for every host that
+/// couldn't be reached.
+pub fn scan_hosts(hosts: &[String]) -> (LedScan, Vec<String>) {
+    let mut scan = LedScan::default();
+    let mut errors = Vec::new();
+    for host in hosts {
+        match led::scan_leds_from(Arc::new(SshBackend::new(host.as_str()))) {
+            Ok(host_scan) => {
+                scan.leds.extend(host_scan.leds);
+                scan.failed.extend(host_scan.failed);
+            }
+            Err(e) => errors.push(format!("Error getting LEDs of {}: {}", host, e)),
+        }
+    }
+    (scan, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_for_the_shell() {
+        assert_eq!(quote("input3::capslock"), "'input3::capslock'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("$(reboot)"), "'$(reboot)'");
+    }
+
+    #[test]
+    fn maps_names_to_the_host() {
+        let backend = SshBackend::new("pi@kitchen");
+        assert_eq!(
+            backend
+                .attribute_path("pi@kitchen/input3::capslock", "brightness")
+                .unwrap(),
+            "/sys/class/leds/input3::capslock/brightness"
+        );
+        let other = backend
+            .attribute_path("nas/input3::capslock", "brightness")
+            .unwrap_err();
+        assert_eq!(other.kind(), ErrorKind::NotFound);
+        assert!(is_remote("pi@kitchen/input3::capslock"));
+        assert!(!is_remote("input3::capslock"));
+        assert_eq!(
+            split_host("pi@kitchen/input3::capslock"),
+            Some(("pi@kitchen", "input3::capslock"))
+        );
+    }
+
+    #[test]
+    fn parses_batched_brightness() {
+        let output = "input3::capslock/brightness:1\nphy0-led/brightness:255\nodd/brightness:x\n";
+        assert_eq!(
+            parse_brightnesses("nas", output),
+            BTreeMap::from([
+                ("nas/input3::capslock".to_string(), 1),
+                ("nas/phy0-led".to_string(), 255),
+            ])
+        );
+    }
+
+    #[test]
+    fn leaves_unreachable_hosts_alone() {
+        let backend = SshBackend::new("unreachable.invalid");
+        set_reachable("unreachable.invalid", false);
+        assert!(retry_in("unreachable.invalid").is_some_and(|wait| wait <= RETRY_AFTER));
+        // Fails without running ssh
+        let error = backend.read_brightnesses().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotConnected);
+        set_reachable("unreachable.invalid", true);
+        assert_eq!(retry_in("unreachable.invalid"), None);
+    }
+
+    #[test]
+    fn classifies_remote_errors() {
+        let missing =
+            error_from("cat: /sys/class/leds/x/trigger: No such file or directory\n").kind();
+        assert_eq!(missing, ErrorKind::NotFound);
+        let refused = error_from("sh: /sys/class/leds/x/brightness: Permission denied\n").kind();
+        assert_eq!(refused, ErrorKind::PermissionDenied);
+        assert_eq!(
+            error_from("ssh: connect to host nas port 22: No route to host").kind(),
+            ErrorKind::Other
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Finding LEDs from the identifiers users type, which are often shorter than sysfs names.
+
+use thiserror::Error;
+
+use crate::LED;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResolveError {
+    #[error("no LED matches \"{0}\"")]
+    NotFound(String),
+    #[error("\"{0}\" is ambiguous, it could be any of: {candidates}", candidates = .1.join(", "))]
+    Ambiguous(String, Vec<String>),
+}
+
+/// The function of an LED, the last part of `device:color:function`.
+pub fn function(file_name: &str) -> &str {
+    file_name.rsplit(':').next().unwrap_or(file_name)
+}
+
+/// The LED's name without its color, `device:function`, which stays the same when a driver
+/// starts reporting colors.
+fn alias(file_name: &str) -> Option<String> {
+    let parts: Vec<&str> = file_name.split(':').collect();
+    match parts.as_slice() {
+        [device, _, function] => Some(format!("{}:{}", device, function)),
+        _ => None,
+    }
+}
+
+/// Finds the LED an identifier refers to, returning its index in `leds`.
+///
+/// Tries, in order: the exact sysfs name, the name without its color (`device:function`), the
+/// function alone (e.g. `capslock`), and finally a prefix of the name. The first kind that
+/// matches anything decides; matching more than one LED is an error listing the candidates.
+pub fn resolve(identifier: &str, leds: &[LED]) -> Result<usize, ResolveError> {
+    let rules: [&dyn Fn(&str) -> bool; 4] = [
+        &|name| name == identifier,
+        &|name| alias(name).is_some_and(|alias| alias == identifier),
+        &|name| function(name) == identifier,
+        &|name| name.starts_with(identifier),
+    ];
+    for rule in rules {
+        let matching: Vec<usize> = (0..leds.len())
+            .filter(|&i| rule(&leds[i].file_name))
+            .collect();
+        match matching.as_slice() {
+            [] => continue,
+            [i] => return Ok(*i),
+            _ => {
+                let mut candidates: Vec<String> = matching
+                    .iter()
+                    .map(|&i| leds[i].file_name.clone())
+                    .collect();
+                candidates.sort();
+                return Err(ResolveError::Ambiguous(identifier.to_string(), candidates));
+            }
+        }
+    }
+    Err(ResolveError::NotFound(identifier.to_string()))
+}
+
+/// Resolves an identifier to the sysfs name of the LED it refers to.
+pub fn resolve_name(identifier: &str, leds: &[LED]) -> Result<String, ResolveError> {
+    resolve(identifier, leds).map(|i| leds[i].file_name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{MockBackend, backend::MockLed, led::get_all_leds_from};
+
+    #[test]
+    fn resolves_identifiers() {
+        let backend = MockBackend::new();
+        for led in [
+            "input3::capslock",
+            "input4::capslock",
+            "tpacpi:green:kbd_backlight",
+            "phy0-led",
+        ] {
+            backend.add_led(led, MockLed::new(1));
+        }
+        let mut leds = get_all_leds_from(Arc::new(backend)).unwrap();
+        leds.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        let name = |identifier| resolve_name(identifier, &leds);
+        assert_eq!(name("phy0-led").as_deref(), Ok("phy0-led"));
+        assert_eq!(
+            name("tpacpi:kbd_backlight").as_deref(),
+            Ok("tpacpi:green:kbd_backlight")
+        );
+        assert_eq!(
+            name("kbd_backlight").as_deref(),
+            Ok("tpacpi:green:kbd_backlight")
+        );
+        assert_eq!(name("phy").as_deref(), Ok("phy0-led"));
+        assert_eq!(
+            name("capslock"),
+            Err(ResolveError::Ambiguous(
+                "capslock".to_string(),
+                vec![
+                    "input3::capslock".to_string(),
+                    "input4::capslock".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            name("numlock"),
+            Err(ResolveError::NotFound("numlock".to_string()))
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! The `dev.mmk21.Glimpse` D-Bus service run by `glimpse daemon --dbus`, for desktop applets and
+//! scripts that would rather not write to sysfs themselves.
+//!
+//! The object `/dev/mmk21/Glimpse` has these methods on the `dev.mmk21.Glimpse` interface:
+//!
+//! - `ListLeds() -> a(suus)`: the name, brightness, maximum brightness and active trigger of
+//!   every LED, with an empty trigger for LEDs that can't be driven by triggers
+//! - `SetBrightness(s led, u brightness)`
+//! - `SetTrigger(s led, s trigger)`
+//!
+//! LEDs can be given by any name `glimpse` accepts elsewhere, e.g. `capslock`. The LEDs are also
+//! the read-only `Leds` property, and `PropertiesChanged` is emitted when any of them change,
+//! whether through the service or not.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+
+use crate::{
+    LED, LedBackend, SysfsBackend,
+    dbus::{self, Bus, Connection, Message, MessageType, Value},
+    led::get_all_leds_from,
+    resolve,
+    watch::{self, Observed},
+};
+
+/// The well-known name the service owns.
+pub const SERVICE_NAME: &str = "dev.mmk21.Glimpse";
+/// The interface with the methods for controlling LEDs.
+pub const INTERFACE: &str = "dev.mmk21.Glimpse";
+/// The path of the one object the service offers.
+pub const OBJECT_PATH: &str = "/dev/mmk21/Glimpse";
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const INTROSPECTABLE_INTERFACE: &str = "org.freedesktop.DBus.Introspectable";
+const PEER_INTERFACE: &str = "org.freedesktop.DBus.Peer";
+
+/// Returned when an LED name doesn't match exactly one LED.
+const UNKNOWN_LED: &str = "dev.mmk21.Glimpse.Error.UnknownLed";
+/// Returned when writing to an LED fails, e.g. for lack of permission.
+const FAILED: &str = "dev.mmk21.Glimpse.Error.Failed";
+
+const DOCTYPE: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+"#;
+const INTROSPECTION: &str = r#"<node>
+  <interface name="dev.mmk21.Glimpse">
+    <method name="ListLeds">
+      <arg name="leds" type="a(suus)" direction="out"/>
+    </method>
+    <method name="SetBrightness">
+      <arg name="led" type="s" direction="in"/>
+      <arg name="brightness" type="u" direction="in"/>
+    </method>
+    <method name="SetTrigger">
+      <arg name="led" type="s" direction="in"/>
+      <arg name="trigger" type="s" direction="in"/>
+    </method>
+    <property name="Leds" type="a(suus)" access="read"/>
+  </interface>
+  <interface name="org.freedesktop.DBus.Properties">
+    <method name="Get">
+      <arg name="interface" type="s" direction="in"/>
+      <arg name="property" type="s" direction="in"/>
+      <arg name="value" type="v" direction="out"/>
+    </method>
+    <method name="GetAll">
+      <arg name="interface" type="s" direction="in"/>
+      <arg name="properties" type="a{sv}" direction="out"/>
+    </method>
+    <signal name="PropertiesChanged">
+      <arg name="interface" type="s"/>
+      <arg name="changed_properties" type="a{sv}"/>
+      <arg name="invalidated_properties" type="as"/>
+    </signal>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg name="xml" type="s" direction="out"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.DBus.Peer">
+    <method name="Ping"/>
+  </interface>
+</node>
+"#;
+
+/// The `Leds` property: an `a(suus)` of every LED, sorted by name.
+fn leds_value(backend: Arc<dyn LedBackend>) -> io::Result<Value> {
+    let mut leds = get_all_leds_from(backend)?;
+    leds.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    let mut items = Vec::new();
+    for led in leds {
+        let trigger = led
+            .triggers()?
+            .map(|triggers| triggers.active.unwrap_or_else(|| "none".to_string()));
+        items.push(Value::Struct(vec![
+            Value::String(led.file_name),
+            Value::U32(led.brightness),
+            Value::U32(led.max_brightness),
+            Value::String(trigger.unwrap_or_default()),
+        ]));
+    }
+    Ok(Value::Array("(suus)".to_string(), items))
+}
+
+/// The `a{sv}` holding the `Leds` property, as returned by `GetAll` and sent in
+/// `PropertiesChanged`.
+fn properties(backend: Arc<dyn LedBackend>) -> io::Result<Value> {
+    Ok(Value::Array(
+        "{sv}".to_string(),
+        vec![Value::DictEntry(
+            Box::new(Value::String("Leds".to_string())),
+            Box::new(Value::Variant(Box::new(leds_value(backend)?))),
+        )],
+    ))
+}
+
+/// An error to reply to a method call with.
+#[derive(Debug)]
+struct MethodError {
+    name: &'static str,
+    message: String,
+}
+
+impl MethodError {
+    fn new(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<io::Error> for MethodError {
+    fn from(error: io::Error) -> Self {
+        Self::new(FAILED, error.to_string())
+    }
+}
+
+/// Finds the LED a method call refers to.
+fn find_led(identifier: &str, backend: Arc<dyn LedBackend>) -> Result<LED, MethodError> {
+    let mut leds = get_all_leds_from(backend).map_err(io::Error::from)?;
+    let index = resolve::resolve(identifier, &leds)
+        .map_err(|e| MethodError::new(UNKNOWN_LED, e.to_string()))?;
+    Ok(leds.swap_remove(index))
+}
+
+fn set_brightness(body: &[Value], backend: Arc<dyn LedBackend>) -> Result<(), MethodError> {
+    let [Value::String(led), Value::U32(brightness)] = body else {
+        return Err(MethodError::new(dbus::INVALID_ARGS, "expected (su)"));
+    };
+    let mut led = find_led(led, backend)?;
+    if *brightness > led.max_brightness {
+        return Err(MethodError::new(
+            dbus::INVALID_ARGS,
+            format!(
+                "{} is above the maximum brightness of {} ({})",
+                brightness, led.file_name, led.max_brightness
+            ),
+        ));
+    }
+    led.set_brightness(*brightness)
+        .map_err(|e| MethodError::new(FAILED, format!("{}: {}", led.file_name, e)))
+}
+
+fn set_trigger(body: &[Value], backend: Arc<dyn LedBackend>) -> Result<(), MethodError> {
+    let [Value::String(led), Value::String(trigger)] = body else {
+        return Err(MethodError::new(dbus::INVALID_ARGS, "expected (ss)"));
+    };
+    let mut led = find_led(led, backend)?;
+    let Some(triggers) = led.triggers()? else {
+        return Err(MethodError::new(
+            dbus::INVALID_ARGS,
+            format!("{} can't be driven by triggers", led.file_name),
+        ));
+    };
+    if !triggers.available.contains(trigger) {
+        return Err(MethodError::new(
+            dbus::INVALID_ARGS,
+            format!("{} has no trigger \"{}\"", led.file_name, trigger),
+        ));
+    }
+    led.set_trigger(trigger)
+        .map_err(|e| MethodError::new(FAILED, format!("{}: {}", led.file_name, e)))
+}
+
+/// Introspection data for the object or one of its parents, so that tools like `busctl tree`
+/// can find it.
+fn introspect(path: &str) -> String {
+    let node = if path == OBJECT_PATH {
+        INTROSPECTION.to_string()
+    } else {
+        let prefix = path.trim_end_matches('/');
+        let child = OBJECT_PATH[prefix.len() + 1..]
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        format!("<node>\n  <node name=\"{}\"/>\n</node>\n", child)
+    };
+    format!("{}{}", DOCTYPE, node)
+}
+
+/// Handles a method call, returning the body of the reply.
+fn call_method(call: &Message, backend: Arc<dyn LedBackend>) -> Result<Vec<Value>, MethodError> {
+    let path = call.path.as_deref().unwrap_or_default();
+    let member = call.member.as_deref().unwrap_or_default();
+    let is_parent = path == "/" || OBJECT_PATH.starts_with(&format!("{}/", path));
+    match (call.interface.as_deref(), member) {
+        (None | Some(INTROSPECTABLE_INTERFACE), "Introspect")
+            if path == OBJECT_PATH || is_parent =>
+        {
+            Ok(vec![Value::String(introspect(path))])
+        }
+        (None | Some(PEER_INTERFACE), "Ping") => Ok(Vec::new()),
+        _ if path != OBJECT_PATH => Err(MethodError::new(
+            "org.freedesktop.DBus.Error.UnknownObject",
+            format!("no object at {}", path),
+        )),
+        (None | Some(INTERFACE), "ListLeds") => Ok(vec![leds_value(backend)?]),
+        (None | Some(INTERFACE), "SetBrightness") => {
+            set_brightness(&call.body, backend).map(|()| Vec::new())
+        }
+        (None | Some(INTERFACE), "SetTrigger") => {
+            set_trigger(&call.body, backend).map(|()| Vec::new())
+        }
+        (None | Some(PROPERTIES_INTERFACE), "Get") => match call.body.as_slice() {
+            [Value::String(interface), Value::String(property)]
+                if interface == INTERFACE && property == "Leds" =>
+            {
+                Ok(vec![Value::Variant(Box::new(leds_value(backend)?))])
+            }
+            _ => Err(MethodError::new(
+                "org.freedesktop.DBus.Error.UnknownProperty",
+                "the only property is dev.mmk21.Glimpse.Leds",
+            )),
+        },
+        (None | Some(PROPERTIES_INTERFACE), "GetAll") => match call.body.as_slice() {
+            [Value::String(interface)] if interface == INTERFACE => Ok(vec![properties(backend)?]),
+            [Value::String(_)] => Ok(vec![Value::Array("{sv}".to_string(), Vec::new())]),
+            _ => Err(MethodError::new(dbus::INVALID_ARGS, "expected (s)")),
+        },
+        (None | Some(PROPERTIES_INTERFACE), "Set") => Err(MethodError::new(
+            "org.freedesktop.DBus.Error.PropertyReadOnly",
+            "Leds is read-only, use SetBrightness or SetTrigger",
+        )),
+        _ => Err(MethodError::new(
+            dbus::UNKNOWN_METHOD,
+            format!("unknown method {}", member),
+        )),
+    }
+}
+
+/// Handles a method call, returning the reply.
+fn handle(call: &Message, backend: Arc<dyn LedBackend>) -> Message {
+    match call_method(call, backend) {
+        Ok(body) => call.reply(body),
+        Err(error) => call.error_reply(error.name, &error.message),
+    }
+}
+
+/// The service, connected to a bus and owning [`SERVICE_NAME`] there.
+pub struct Service {
+    connection: Connection,
+    /// The LEDs as last announced, to tell when `PropertiesChanged` is due
+    observed: BTreeMap<String, Observed>,
+}
+
+impl Service {
+    pub fn start(bus: Bus) -> Result<Self> {
+        let mut connection = Connection::connect(bus)?;
+        connection.request_name(SERVICE_NAME)?;
+        Ok(Self {
+            connection,
+            observed: watch::observe(&SysfsBackend, &[], &BTreeMap::new())?,
+        })
+    }
+
+    /// Answers method calls for `timeout`, emitting `PropertiesChanged` whenever the LEDs have
+    /// changed since it was last emitted.
+    ///
+    /// Only errors on the connection itself are returned: calls that can't be decoded or
+    /// handled are answered with an error.
+    pub fn serve(&mut self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(message) = self.connection.receive(remaining)? else {
+                break;
+            };
+            if message.kind != MessageType::MethodCall {
+                continue;
+            }
+            let reply = handle(&message, Arc::new(SysfsBackend));
+            if message.expects_reply() {
+                self.connection.send(reply)?;
+            }
+            if message
+                .member
+                .as_deref()
+                .is_some_and(|member| member.starts_with("Set"))
+            {
+                self.announce_changes()?;
+            }
+        }
+        self.announce_changes()
+    }
+
+    /// Emits `PropertiesChanged` if the LEDs have changed. Failing to read the LEDs only puts
+    /// that off until the next time, so that only a broken connection stops the service.
+    fn announce_changes(&mut self) -> Result<()> {
+        let changed = watch::observe(&SysfsBackend, &[], &self.observed).and_then(|observed| {
+            if watch::changes(&self.observed, &observed).is_empty() {
+                return Ok(None);
+            }
+            Ok(Some((observed, properties(Arc::new(SysfsBackend))?)))
+        });
+        let (observed, properties) = match changed {
+            Ok(Some(changed)) => changed,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                tracing::debug!(error = %e, "couldn't read the LEDs to announce changes");
+                return Ok(());
+            }
+        };
+        self.observed = observed;
+        self.connection.send(Message::signal(
+            OBJECT_PATH,
+            PROPERTIES_INTERFACE,
+            "PropertiesChanged",
+            vec![
+                Value::String(INTERFACE.to_string()),
+                properties,
+                Value::Array("s".to_string(), Vec::new()),
+            ],
+        ))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, os::unix::net::UnixStream};
+
+    use super::*;
+    use crate::{MockBackend, backend::MockLed};
+
+    fn call(member: &str, body: Vec<Value>) -> Message {
+        let mut call = Message::method_call(SERVICE_NAME, OBJECT_PATH, INTERFACE, member);
+        call.body = body;
+        call.sender = Some(":1.42".to_string());
+        call
+    }
+
+    #[test]
+    fn controls_leds() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        let backend = Arc::new(backend);
+        let set = call(
+            "SetBrightness",
+            vec![Value::String("capslock".to_string()), Value::U32(1)],
+        );
+        let reply = handle(&set, backend.clone());
+        assert_eq!(reply.kind, MessageType::MethodReturn);
+        assert_eq!(reply.destination.as_deref(), Some(":1.42"));
+        assert_eq!(backend.led("input3::capslock").unwrap().brightness, 1);
+
+        let reply = handle(&call("ListLeds", Vec::new()), backend.clone());
+        assert_eq!(
+            reply.body,
+            [Value::Array(
+                "(suus)".to_string(),
+                vec![Value::Struct(vec![
+                    Value::String("input3::capslock".to_string()),
+                    Value::U32(1),
+                    Value::U32(1),
+                    Value::String("none".to_string()),
+                ])]
+            )]
+        );
+
+        let set = call(
+            "SetTrigger",
+            vec![
+                Value::String("numlock".to_string()),
+                Value::String("timer".to_string()),
+            ],
+        );
+        let reply = handle(&set, backend.clone());
+        assert_eq!(reply.error_name.as_deref(), Some(UNKNOWN_LED));
+        let set = call(
+            "SetBrightness",
+            vec![Value::String("capslock".to_string()), Value::U32(2)],
+        );
+        assert_eq!(
+            handle(&set, backend).error_name.as_deref(),
+            Some(dbus::INVALID_ARGS)
+        );
+    }
+
+    #[test]
+    fn answers_after_undecodable_calls() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut service = Service {
+            connection: Connection::from_stream(server),
+            observed: BTreeMap::new(),
+        };
+        // gdbus sends plain numbers as int32s, which the service can't read
+        let mut set = Message::method_call(SERVICE_NAME, OBJECT_PATH, PROPERTIES_INTERFACE, "Set");
+        set.serial = 1;
+        set.sender = Some(":1.42".to_string());
+        set.body = vec![
+            Value::String(INTERFACE.to_string()),
+            Value::String("Leds".to_string()),
+            Value::Variant(Box::new(Value::U32(50))),
+        ];
+        let mut encoded = set.encode();
+        let variant = encoded
+            .windows(3)
+            .rposition(|bytes| bytes == b"\x01u\0")
+            .unwrap();
+        encoded[variant + 1] = b'i';
+        let mut ping = Message::method_call(SERVICE_NAME, OBJECT_PATH, PEER_INTERFACE, "Ping");
+        ping.serial = 2;
+        ping.sender = Some(":1.42".to_string());
+        encoded.extend(ping.encode());
+        (&client).write_all(&encoded).unwrap();
+
+        service.serve(Duration::from_millis(50)).unwrap();
+        let mut client = Connection::from_stream(client);
+        let mut replies = Vec::new();
+        while let Some(reply) = client.receive(Duration::from_millis(50)).unwrap() {
+            if let Some(serial) = reply.reply_serial {
+                replies.push((serial, reply.kind, reply.error_name));
+            }
+        }
+        assert_eq!(
+            replies,
+            [
+                (1, MessageType::Error, Some(dbus::INVALID_ARGS.to_string())),
+                (2, MessageType::MethodReturn, None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! The TUI's session: the selected LED, how the panes were laid out and which LEDs were pinned
+//! or hidden when it was last quit, restored the next time it starts.
+//!
+//! It's saved as `<key> = <value>` lines in `$XDG_STATE_HOME/glimpse/session`. Lines that can't
+//! be read are skipped, since losing part of the session is better than not starting.
+
+use std::{fs, io, path::PathBuf};
+
+use crate::{dirs, settings::GroupBy};
+
+/// Name of the file in Glimpse's state directory holding the last session.
+pub const SESSION_FILE: &str = "session";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// File name of the selected LED
+    pub selected: Option<String>,
+    /// The grouping chosen in the TUI, overriding `group_by` from the settings
+    pub group_by: Option<GroupBy>,
+    /// Names of the collapsed sidebar groups
+    pub collapsed_groups: Vec<String>,
+    /// File names of the LEDs pinned to the top of the sidebar, in the order they were pinned
+    pub pinned: Vec<String>,
+    /// File names of the LEDs hidden from the sidebar in the TUI
+    pub hidden: Vec<String>,
+    /// Whether the main pane had focus rather than the sidebar
+    pub main_focused: bool,
+    /// Whether the detail pane showed the raw sysfs attributes
+    pub raw_view: bool,
+    /// Whether the chart replaced the detail pane
+    pub chart: bool,
+}
+
+fn session_path() -> io::Result<PathBuf> {
+    dirs::state_dir()
+        .map(|dir| dir.join(SESSION_FILE))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no state directory (neither XDG_STATE_HOME nor HOME is set)",
+            )
+        })
+}
+
+impl Session {
+    pub fn parse(contents: &str) -> Self {
+        let mut session = Self::default();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "selected" if !value.is_empty() => session.selected = Some(value.to_string()),
+                "group_by" => {
+                    session.group_by = match value {
+                        "device" => Some(GroupBy::Device),
+                        "driver" => Some(GroupBy::Driver),
+                        _ => None,
+                    }
+                }
+                "collapsed" if !value.is_empty() => {
+                    session.collapsed_groups.push(value.to_string());
+                }
+                "pinned" if !value.is_empty() => session.pinned.push(value.to_string()),
+                "hidden" if !value.is_empty() => session.hidden.push(value.to_string()),
+                "focus" => session.main_focused = value == "main",
+                "raw_view" => session.raw_view = value == "true",
+                "chart" => session.chart = value == "true",
+                _ => {}
+            }
+        }
+        session
+    }
+
+    /// The session as the lines [`Session::parse`] reads.
+    pub fn serialize(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(selected) = &self.selected {
+            lines.push(format!("selected = {}", selected));
+        }
+        if let Some(group_by) = self.group_by {
+            let group_by = match group_by {
+                GroupBy::Device => "device",
+                GroupBy::Driver => "driver",
+            };
+            lines.push(format!("group_by = {}", group_by));
+        }
+        for group in &self.collapsed_groups {
+            lines.push(format!("collapsed = {}", group));
+        }
+        for led in &self.pinned {
+            lines.push(format!("pinned = {}", led));
+        }
+        for led in &self.hidden {
+            lines.push(format!("hidden = {}", led));
+        }
+        let focus = if self.main_focused { "main" } else { "sidebar" };
+        lines.push(format!("focus = {}", focus));
+        lines.push(format!("raw_view = {}", self.raw_view));
+        lines.push(format!("chart = {}", self.chart));
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// Reads the last session, or an empty one if there isn't one.
+    pub fn load() -> io::Result<Self> {
+        match fs::read_to_string(session_path()?) {
+            Ok(contents) => Ok(Self::parse(&contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = session_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.serialize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let session = Session {
+            selected: Some("input3::capslock".to_string()),
+            group_by: Some(GroupBy::Driver),
+            collapsed_groups: vec!["phy0".to_string(), "platform".to_string()],
+            pinned: vec![
+                "tpacpi::lid_logo_dot".to_string(),
+                "input3::capslock".to_string(),
+            ],
+            hidden: vec!["cpu0".to_string()],
+            main_focused: true,
+            raw_view: false,
+            chart: true,
+        };
+        assert_eq!(Session::parse(&session.serialize()), session);
+        assert_eq!(
+            Session::parse("group_by = sideways\nfocus\nchart = true\n"),
+            Session {
+                chart: true,
+                ..Session::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,521 @@
+//! `config.toml`: the TUI's settings, read from Glimpse's config directory at startup.
+//!
+//! Only the part of TOML that the settings need is understood: `key = value` lines with
+//! strings, numbers and arrays of strings, the `[keys]`, `[aliases]`, `[presets]` and `[gamma]`
+//! tables, and `#` comments. Every setting is optional.
+//!
+//! ```toml
+//! group_by = "driver"
+//! refresh_interval = 500
+//! theme = "256"
+//! hidden = ["phy0-led", "input*::scrolllock"]
+//! hosts = ["pi@kitchen", "nas"]
+//!
+//! [keys]
+//! toggle = "t"
+//! quit = ["q", "ctrl+c"]
+//! select_first = "g g"
+//!
+//! [aliases]
+//! "tpacpi::lid_logo_dot" = "Lid logo"
+//!
+//! # Number keys setting the selected LED, replacing the default 0%, 25%, ... 100%
+//! [presets]
+//! 1 = "0%"
+//! 2 = "25%"
+//! 3 = "teal"
+//!
+//! # Overrides for one LED
+//! [presets."tpacpi::kbd_backlight"]
+//! 2 = "50%"
+//!
+//! # For LEDs whose perceived brightness isn't linear in the raw value
+//! [gamma]
+//! "tpacpi::kbd_backlight" = 2.2
+//! ```
+
+use std::{collections::BTreeMap, fs, io, time::Duration};
+
+use thiserror::Error;
+
+use crate::{
+    dirs, glob,
+    keymap::{Action, KeyPress, parse_keys},
+    presets::{self, Preset, PresetValue},
+    theme::ColorDepth,
+};
+
+pub const SETTINGS_FILE: &str = "config.toml";
+
+/// What the sidebar groups LEDs under, and so the order they're listed in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    #[default]
+    Device,
+    Driver,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Settings {
+    pub group_by: GroupBy,
+    /// How often LEDs are re-read, unless set with `--tick-rate`
+    pub refresh_interval: Option<Duration>,
+    /// The colour depth to use instead of the detected one
+    pub theme: Option<ColorDepth>,
+    /// Names or glob patterns of LEDs left out of the sidebar
+    pub hidden: Vec<String>,
+    /// Actions bound to other keys than their defaults, from the `[keys]` table
+    pub keys: Vec<(Action, Vec<Vec<KeyPress>>)>,
+    /// Friendly names of LEDs by file name, from the `[aliases]` table
+    pub aliases: BTreeMap<String, String>,
+    /// Other machines whose LEDs are shown too, reached over SSH
+    pub hosts: Vec<String>,
+    /// Brightness presets bound to number keys, from the `[presets]` table and the per-LED
+    /// `[presets."<led>"]` tables
+    pub presets: Vec<Preset>,
+    /// Gamma values of LEDs by file name, from the `[gamma]` table
+    pub gamma: BTreeMap<String, f64>,
+}
+
+/// The table the lines being parsed belong to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Table {
+    Settings,
+    Keys,
+    Aliases,
+    /// Presets for every LED, or for the one named
+    Presets(Option<String>),
+    Gamma,
+}
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("no config directory (neither XDG_CONFIG_HOME nor HOME is set)")]
+    NoConfigDir,
+    #[error("{SETTINGS_FILE}:{line}: {message}")]
+    Invalid { line: usize, message: String },
+    #[error("I/O error: {0}")]
+    IOError(#[from] io::Error),
+}
+
+/// A value on the right of `=`.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(u64),
+    Float(f64),
+    Array(Vec<String>),
+}
+
+impl Value {
+    fn parse(value: &str) -> Result<Self, String> {
+        if let Some(items) = value.strip_prefix('[') {
+            let items = items
+                .strip_suffix(']')
+                .ok_or("arrays have to be closed on the same line")?;
+            return items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(parse_string)
+                .collect::<Result<_, _>>()
+                .map(Self::Array);
+        }
+        if value.starts_with('"') {
+            return parse_string(value).map(Self::String);
+        }
+        let number = value.replace('_', "");
+        if let Ok(integer) = number.parse() {
+            return Ok(Self::Integer(integer));
+        }
+        number
+            .parse()
+            .ok()
+            .filter(|float: &f64| float.is_finite())
+            .map(Self::Float)
+            .ok_or_else(|| format!("{} is not a string, number or array of strings", value))
+    }
+}
+
+/// Parses a basic TOML string, which can't contain escapes here.
+fn parse_string(value: &str) -> Result<String, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .filter(|value| !value.contains(['"', '\\']))
+        .map(str::to_string)
+        .ok_or_else(|| format!("{} is not a string", value))
+}
+
+/// Drops a `#` comment from a line, unless it's inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+impl Settings {
+    /// Parses the contents of `config.toml`. Unknown settings are returned as warnings rather
+    /// than errors, so that settings from newer versions don't stop older ones from starting.
+    pub fn parse(contents: &str) -> Result<(Self, Vec<(usize, String)>), SettingsError> {
+        let mut settings = Self::default();
+        let mut warnings = Vec::new();
+        let mut table = Table::Settings;
+        for (i, line) in contents.lines().enumerate() {
+            let line_number = i + 1;
+            let invalid = |message: String| SettingsError::Invalid {
+                line: line_number,
+                message,
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                table = match name.strip_suffix(']').map(str::trim) {
+                    Some("keys") => Table::Keys,
+                    Some("aliases") => Table::Aliases,
+                    Some("presets") => Table::Presets(None),
+                    Some("gamma") => Table::Gamma,
+                    Some(name) if name.starts_with("presets.") => {
+                        let led = name["presets.".len()..].trim();
+                        let led = if led.starts_with('"') {
+                            parse_string(led).map_err(invalid)?
+                        } else {
+                            led.to_string()
+                        };
+                        Table::Presets(Some(led))
+                    }
+                    _ => {
+                        return Err(invalid(format!(
+                            "unknown table {}, expected [keys], [aliases], [presets] or [gamma]",
+                            line
+                        )));
+                    }
+                };
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid("expected <setting> = <value>".to_string()));
+            };
+            let key = key.trim();
+            let value = Value::parse(value.trim()).map_err(invalid)?;
+            if matches!(table, Table::Aliases | Table::Gamma) {
+                // LED names have colons, so are usually quoted
+                let led = if key.starts_with('"') {
+                    parse_string(key).map_err(invalid)?
+                } else {
+                    key.to_string()
+                };
+                match (&table, value) {
+                    (Table::Aliases, Value::String(alias)) => {
+                        settings.aliases.insert(led, alias);
+                    }
+                    (Table::Aliases, _) => {
+                        return Err(invalid(format!("the alias of {} has to be a string", led)));
+                    }
+                    (_, Value::Integer(gamma)) if gamma > 0 => {
+                        settings.gamma.insert(led, gamma as f64);
+                    }
+                    (_, Value::Float(gamma)) if gamma > 0.0 => {
+                        settings.gamma.insert(led, gamma);
+                    }
+                    _ => {
+                        return Err(invalid(format!(
+                            "the gamma of {} has to be a positive number",
+                            led
+                        )));
+                    }
+                }
+                continue;
+            }
+            if let Table::Presets(led) = &table {
+                let key = if key.starts_with('"') {
+                    parse_string(key).map_err(invalid)?
+                } else {
+                    key.to_string()
+                };
+                let key = presets::parse_key(&key).map_err(|e| invalid(e.to_string()))?;
+                let value = match value {
+                    Value::String(value) => value.parse::<PresetValue>(),
+                    Value::Integer(percent) => percent.to_string().parse(),
+                    Value::Float(_) | Value::Array(_) => {
+                        return Err(invalid(format!(
+                            "preset {} has to be a percentage or a color",
+                            key
+                        )));
+                    }
+                }
+                .map_err(|e| invalid(e.to_string()))?;
+                settings.presets.push(Preset {
+                    led: led.clone(),
+                    key,
+                    value,
+                });
+                continue;
+            }
+            if table == Table::Keys {
+                let Some(action) = Action::from_name(key) else {
+                    warnings.push((line_number, format!("unknown action \"{}\"", key)));
+                    continue;
+                };
+                let keys = match value {
+                    Value::String(keys) => vec![keys],
+                    Value::Array(keys) => keys,
+                    Value::Integer(_) | Value::Float(_) => {
+                        return Err(invalid(format!(
+                            "the keys for {} have to be a string or an array of strings",
+                            key
+                        )));
+                    }
+                };
+                let keys = keys
+                    .iter()
+                    .map(|keys| parse_keys(keys))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| invalid(e.to_string()))?;
+                settings.keys.push((action, keys));
+                continue;
+            }
+            match (key, value) {
+                ("group_by", Value::String(group_by)) => {
+                    settings.group_by = match group_by.as_str() {
+                        "device" => GroupBy::Device,
+                        "driver" => GroupBy::Driver,
+                        _ => {
+                            return Err(invalid(format!(
+                                "unknown grouping \"{}\", expected device or driver",
+                                group_by
+                            )));
+                        }
+                    }
+                }
+                ("refresh_interval", Value::Integer(ms)) if ms > 0 => {
+                    settings.refresh_interval = Some(Duration::from_millis(ms));
+                }
+                ("theme", Value::String(theme)) => {
+                    settings.theme = match theme.as_str() {
+                        "auto" => None,
+                        "truecolor" => Some(ColorDepth::TrueColor),
+                        "256" => Some(ColorDepth::Ansi256),
+                        "16" => Some(ColorDepth::Ansi16),
+                        "mono" => Some(ColorDepth::Mono),
+                        _ => {
+                            return Err(invalid(format!(
+                                "unknown theme \"{}\", expected auto, truecolor, 256, 16 or mono",
+                                theme
+                            )));
+                        }
+                    }
+                }
+                ("hidden", Value::Array(hidden)) => settings.hidden = hidden,
+                ("hosts", Value::Array(hosts)) => settings.hosts = hosts,
+                ("group_by" | "theme", _) => {
+                    return Err(invalid(format!("{} has to be a string", key)));
+                }
+                ("refresh_interval", _) => {
+                    return Err(invalid(
+                        "refresh_interval has to be a positive number of milliseconds".to_string(),
+                    ));
+                }
+                ("hidden" | "hosts", _) => {
+                    return Err(invalid(format!("{} has to be an array of strings", key)));
+                }
+                _ => warnings.push((line_number, format!("unknown setting \"{}\"", key))),
+            }
+        }
+        Ok((settings, warnings))
+    }
+
+    /// Reads `config.toml`, with the defaults if there isn't one.
+    pub fn load() -> Result<(Self, Vec<(usize, String)>), SettingsError> {
+        let path = dirs::config_dir()
+            .ok_or(SettingsError::NoConfigDir)?
+            .join(SETTINGS_FILE);
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((Self::default(), Vec::new())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The friendly name given to an LED, if it has one.
+    pub fn alias(&self, file_name: &str) -> Option<&str> {
+        self.aliases.get(file_name).map(String::as_str)
+    }
+
+    /// Whether an LED is left out of the sidebar.
+    pub fn is_hidden(&self, file_name: &str) -> bool {
+        self.hidden
+            .iter()
+            .any(|pattern| glob::matches(pattern, file_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::Presets;
+
+    #[test]
+    fn parses_settings() {
+        let (settings, warnings) = Settings::parse(
+            "# Glimpse settings\n\
+             group_by = \"driver\"\n\
+             refresh_interval = 1_000 # ms\n\
+             theme = \"mono\"\n\
+             hidden = [\"phy0-led\", \"input*::scrolllock\"]\n\
+             hosts = [\"pi@kitchen\"]\n\
+             colour = \"#ff8800\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                group_by: GroupBy::Driver,
+                refresh_interval: Some(Duration::from_secs(1)),
+                theme: Some(ColorDepth::Mono),
+                hidden: vec!["phy0-led".to_string(), "input*::scrolllock".to_string()],
+                keys: Vec::new(),
+                aliases: BTreeMap::new(),
+                hosts: vec!["pi@kitchen".to_string()],
+                presets: Vec::new(),
+                gamma: BTreeMap::new(),
+            }
+        );
+        assert_eq!(warnings, [(7, "unknown setting \"colour\"".to_string())]);
+        assert!(settings.is_hidden("input3::scrolllock"));
+        assert!(!settings.is_hidden("input3::capslock"));
+    }
+
+    #[test]
+    fn parses_keys() {
+        let (settings, warnings) = Settings::parse(
+            "[keys]\n\
+             toggle = \"t\"\n\
+             quit = [\"Q\", \"ctrl+x\"]\n\
+             select_first = \"g g\"\n\
+             fly = \"f\"\n",
+        )
+        .unwrap();
+        let keys = |keys: &[&str]| -> Vec<Vec<KeyPress>> {
+            keys.iter().map(|keys| parse_keys(keys).unwrap()).collect()
+        };
+        assert_eq!(
+            settings.keys,
+            [
+                (Action::ToggleLed, keys(&["t"])),
+                (Action::Quit, keys(&["Q", "ctrl+x"])),
+                (Action::SelectFirst, keys(&["g g"])),
+            ]
+        );
+        assert_eq!(warnings, [(5, "unknown action \"fly\"".to_string())]);
+        assert!(Settings::parse("[keys]\nquit = \"ctrl+enter\"").is_err());
+        assert!(Settings::parse("[keys]\nquit = \"hyper\"").is_err());
+    }
+
+    #[test]
+    fn parses_aliases() {
+        let (settings, _) = Settings::parse(
+            "[aliases]\n\
+             \"tpacpi::lid_logo_dot\" = \"Lid logo\"\n\
+             phy0-led = \"Wi-Fi\"\n",
+        )
+        .unwrap();
+        assert_eq!(settings.alias("tpacpi::lid_logo_dot"), Some("Lid logo"));
+        assert_eq!(settings.alias("phy0-led"), Some("Wi-Fi"));
+        assert_eq!(settings.alias("input3::capslock"), None);
+        assert!(Settings::parse("[aliases]\nphy0-led = 1").is_err());
+    }
+
+    #[test]
+    fn parses_gamma() {
+        let (settings, _) = Settings::parse(
+            "[gamma]\n\
+             \"tpacpi::kbd_backlight\" = 2.2\n\
+             phy0-led = 2\n",
+        )
+        .unwrap();
+        assert_eq!(
+            settings.gamma,
+            BTreeMap::from([
+                ("phy0-led".to_string(), 2.0),
+                ("tpacpi::kbd_backlight".to_string(), 2.2),
+            ])
+        );
+        let error = Settings::parse("[gamma]\nphy0-led = 0").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "config.toml:2: the gamma of phy0-led has to be a positive number"
+        );
+        assert!(Settings::parse("[gamma]\nphy0-led = -1.5").is_err());
+        assert!(Settings::parse("[gamma]\nphy0-led = \"2.2\"").is_err());
+        assert!(Settings::parse("refresh_interval = 0.5").is_err());
+    }
+
+    #[test]
+    fn parses_presets() {
+        let (settings, warnings) = Settings::parse(
+            "[presets]\n\
+             1 = \"0%\"\n\
+             2 = 25\n\
+             \"3\" = \"teal\"\n\
+             \n\
+             [presets.\"tpacpi::kbd_backlight\"]\n\
+             2 = \"50%\"\n",
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+        let presets = Presets::new(settings.presets);
+        assert_eq!(
+            presets.lookup("input3::capslock", '1'),
+            Some(PresetValue::Percent(0))
+        );
+        assert_eq!(
+            presets.lookup("input3::capslock", '2'),
+            Some(PresetValue::Percent(25))
+        );
+        assert_eq!(
+            presets.lookup("tpacpi::kbd_backlight", '2'),
+            Some(PresetValue::Percent(50))
+        );
+        assert_eq!(
+            presets.lookup("input3::capslock", '3'),
+            Some(PresetValue::Color("teal".parse().unwrap()))
+        );
+        // Configured presets replace the defaults
+        assert_eq!(presets.lookup("input3::capslock", '5'), None);
+    }
+
+    #[test]
+    fn rejects_invalid_presets() {
+        let error = Settings::parse("[presets]\n10 = \"50%\"").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "config.toml:2: preset keys must be a single digit"
+        );
+        let error = Settings::parse("[presets.phy0-led]\n1 = \"150%\"").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "config.toml:2: invalid percentage or color \"150%\""
+        );
+        assert!(Settings::parse("[presets]\n1 = \"not a color\"").is_err());
+        assert!(Settings::parse("[presets]\n1 = [\"50%\"]").is_err());
+        assert!(Settings::parse("[presets.\"unclosed]\n1 = 0").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let error = Settings::parse("\ntheme = 256\n").unwrap_err();
+        assert_eq!(error.to_string(), "config.toml:2: theme has to be a string");
+        assert!(Settings::parse("refresh_interval = 0").is_err());
+        assert!(Settings::parse("hidden = [\"a\"").is_err());
+        assert!(Settings::parse("[ui]").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::{thread, time::Duration};
+
+use color_eyre::Result;
+
+use crate::{
+    glob,
+    output::{Output, Tone},
+    profiles::LedState,
+    terminal::Progress,
+};
+
+/// Default number of steps from off to full brightness.
+pub const DEFAULT_SWEEP_STEPS: u32 = 10;
+/// How long each brightness level is held, so the sweep can be followed by eye.
+const STEP_DELAY: Duration = Duration::from_millis(150);
+
+/// The brightness values to write: from 0 up to `max_brightness` and back down again.
+fn sweep_values(max_brightness: u32, steps: u32) -> Vec<u32> {
+    let steps = steps.min(max_brightness).max(1);
+    let up: Vec<u32> = (0..=steps).map(|i| max_brightness * i / steps).collect();
+    up.iter().chain(up.iter().rev().skip(1)).copied().collect()
+}
+
+/// Sweeps the brightness of each LED up and down, checking that every value reads back as
+/// written, then restores the LED's original state.
+pub fn run(led_names: Vec<String>, steps: u32) -> Result<()> {
+    let output = Output::detect();
+    let leds = glob::expand(&led_names)?;
+    let mut failed = 0;
+    let progress = Progress::start();
+    let total = leds.len();
+    for (done, mut led) in leds.into_iter().enumerate() {
+        progress.update(done, total);
+        let max_brightness = led.max_brightness()?;
+        let original = LedState::capture(&mut led)?;
+        println!(
+            "Testing {} (max_brightness {})",
+            output.paint(&led.file_name, Tone::Name),
+            max_brightness
+        );
+        let mut rows = Vec::new();
+        let mut mismatches = 0;
+        for value in sweep_values(max_brightness, steps) {
+            let result = led
+                .set_brightness(value)
+                .map_err(|e| e.to_string())
+                .and_then(|()| {
+                    thread::sleep(STEP_DELAY);
+                    led.refresh().map_err(|e| e.to_string())
+                });
+            let (read_back, status) = match result {
+                Ok(read_back) if read_back == value => (read_back.to_string(), ("ok", Tone::Value)),
+                Ok(read_back) => (read_back.to_string(), ("mismatch", Tone::Error)),
+                Err(e) => (e, ("error", Tone::Error)),
+            };
+            if status.0 != "ok" {
+                mismatches += 1;
+            }
+            rows.push(vec![
+                (value.to_string(), Tone::Plain),
+                (read_back, Tone::Plain),
+                (status.0.to_string(), status.1),
+            ]);
+        }
+        println!(
+            "{}",
+            output.table(&["written", "read back", "result"], &rows, &[0, 1])
+        );
+        original.restore(&mut led)?;
+        if mismatches > 0 {
+            failed += 1;
+            println!(
+                "{}\n",
+                output.paint(
+                    &format!("{} of {} steps did not match", mismatches, rows.len()),
+                    Tone::Error
+                )
+            );
+        } else {
+            println!("{}\n", output.paint("All steps matched", Tone::Value));
+        }
+    }
+    if failed > 0 {
+        return Err(color_eyre::eyre::eyre!(
+            "{} LED(s) did not report the brightness that was written",
+            failed
+        ));
+    }
+    Ok(())
+}
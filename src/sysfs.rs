@@ -0,0 +1,89 @@
+//! Checks on the sysfs filesystem the LEDs live in.
+
+use std::{fs, io::ErrorKind};
+
+use crate::{LED, led::SYS_CLASS_LEDS, logind};
+
+/// Whether the filesystem mounted at `/sys` is read-only, according to `/proc/mounts`.
+fn sys_mounted_read_only() -> bool {
+    let Ok(mounts) = fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let options = fields.nth(1)?;
+            Some((mount_point, options))
+        })
+        // The last mount on /sys is the one in effect
+        .rfind(|(mount_point, _)| *mount_point == "/sys")
+        .is_some_and(|(_, options)| options.split(',').any(|option| option == "ro"))
+}
+
+/// What to do when writing to an LED isn't permitted.
+pub const PERMISSION_HINT: &str =
+    "run Glimpse as root or add a udev rule with `glimpse setup-permissions`";
+
+/// Whether a backlight can be set through logind instead of sysfs, which is only known once
+/// it's tried.
+fn through_logind(led: &LED) -> bool {
+    led.is_backlight() && !logind::running_as_root()
+}
+
+/// Whether the LED's brightness can be written to, tried the same way as in
+/// [`check_writable`]. Errors other than being refused say nothing, so count as writable.
+pub fn is_writable(led: &LED) -> bool {
+    if through_logind(led) {
+        return true;
+    }
+    match fs::OpenOptions::new()
+        .write(true)
+        .open(led.path().join("brightness"))
+    {
+        Err(e) => !matches!(
+            e.kind(),
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem
+        ),
+        Ok(_) => true,
+    }
+}
+
+/// Finds out whether LEDs can be changed, returning why not if they can't.
+///
+/// Opening a `brightness` file for writing is enough to be refused by a read-only mount,
+/// missing permissions or an SELinux denial, without changing anything.
+pub fn check_writable(leds: &[LED]) -> Option<String> {
+    if sys_mounted_read_only() {
+        return Some("/sys is mounted read-only".to_string());
+    }
+    let mut denied = None;
+    for led in leds {
+        if through_logind(led) {
+            return None;
+        }
+        match fs::OpenOptions::new()
+            .write(true)
+            .open(led.path().join("brightness"))
+        {
+            // One writable LED is enough to make writing worthwhile
+            Ok(_) => return None,
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                denied = Some(format!(
+                    "writing to LEDs in {} is not permitted ({})",
+                    SYS_CLASS_LEDS, PERMISSION_HINT
+                ))
+            }
+            Err(e) if e.kind() == ErrorKind::ReadOnlyFilesystem => {
+                denied = Some(format!(
+                    "the filesystem holding the LEDs in {} is read-only",
+                    SYS_CLASS_LEDS
+                ))
+            }
+            Err(_) => {}
+        }
+    }
+    // Other errors (e.g. LEDs disappearing) don't say anything about writability
+    denied
+}
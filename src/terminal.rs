@@ -0,0 +1,85 @@
+//! Terminal title and taskbar progress, through escape sequences.
+
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+};
+
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::SetTitle,
+};
+
+/// Whether stdout is a terminal that understands escape sequences.
+fn is_capable() -> bool {
+    io::stdout().is_terminal() && env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// Saves the current title on the terminal's title stack (xterm `CSI 22 ; 0 t`), so that
+/// [`pop_title`] can put it back.
+pub fn push_title() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[22;0t")?;
+    stdout.flush()
+}
+
+/// Restores the title saved by [`push_title`].
+pub fn pop_title() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[23;0t")?;
+    stdout.flush()
+}
+
+pub fn set_title(title: &str) -> io::Result<()> {
+    execute!(io::stdout(), SetTitle(title))
+}
+
+/// Has the terminal report mouse clicks and wheel scrolling, or stop reporting them.
+pub fn set_mouse_capture(enabled: bool) -> io::Result<()> {
+    if enabled {
+        execute!(io::stdout(), EnableMouseCapture)
+    } else {
+        execute!(io::stdout(), DisableMouseCapture)
+    }
+}
+
+/// Shows the progress of a batch operation in the terminal's tab or taskbar, using the
+/// `OSC 9 ; 4` sequence from ConEmu (also understood by Windows Terminal, WezTerm and others).
+///
+/// Terminals without support ignore it. The progress is cleared when this is dropped.
+#[derive(Debug)]
+pub struct Progress {
+    enabled: bool,
+}
+
+impl Progress {
+    pub fn start() -> Self {
+        Self {
+            enabled: is_capable(),
+        }
+    }
+
+    pub fn update(&self, done: usize, total: usize) {
+        if self.enabled && total > 0 {
+            let percent = done.min(total) * 100 / total;
+            Self::emit(&format!("\x1b]9;4;1;{}\x07", percent));
+        }
+    }
+
+    fn emit(sequence: &str) {
+        let mut stdout = io::stdout();
+        // Progress is cosmetic, so a failed write isn't worth reporting
+        let _ = stdout
+            .write_all(sequence.as_bytes())
+            .and_then(|_| stdout.flush());
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.enabled {
+            Self::emit("\x1b]9;4;0;0\x07");
+        }
+    }
+}
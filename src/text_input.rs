@@ -0,0 +1,172 @@
+//! A one-line text prompt with a cursor, for typing in things like a color or the name of a
+//! profile.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Position, Rect},
+    text::Line,
+    widgets::{Block, Clear, Paragraph},
+};
+
+use crate::{
+    theme::{self, Role},
+    widgets::centered_rect,
+};
+
+/// Text being typed in, along with where the cursor is and why the text was rejected, if it
+/// was.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextInput {
+    value: String,
+    /// Byte offset of the cursor in `value`, always on a character boundary
+    cursor: usize,
+    /// Shown below the text until it's edited, e.g. why it couldn't be used
+    pub error: Option<String>,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The offset of the character before the cursor, if there is one.
+    fn previous(&self) -> Option<usize> {
+        self.value[..self.cursor]
+            .char_indices()
+            .last()
+            .map(|(i, _)| i)
+    }
+
+    /// The offset of the character after the cursor, if there is one.
+    fn next(&self) -> Option<usize> {
+        self.value[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+
+    /// The start of the word before the cursor, skipping any whitespace in between.
+    fn previous_word(&self) -> usize {
+        let before = self.value[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    fn delete(&mut self, start: usize, end: usize) {
+        self.value.replace_range(start..end, "");
+        self.cursor = start;
+    }
+
+    /// Handles a key press: typing inserts at the cursor, Backspace and Delete remove the
+    /// characters around it, the arrow keys, Home and End (or Ctrl-A and Ctrl-E) move it, and
+    /// Ctrl-W, Ctrl-U and Ctrl-K delete the word before it, everything before it and everything
+    /// after it.
+    ///
+    /// Returns whether the text was submitted with Enter or cancelled with Esc, once it's one
+    /// or the other.
+    pub fn on_key_event(&mut self, key: KeyEvent) -> Option<bool> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let before = self.value.clone();
+        match key.code {
+            KeyCode::Enter => return Some(true),
+            KeyCode::Esc => return Some(false),
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.value.len(),
+            KeyCode::Char('w') if ctrl => self.delete(self.previous_word(), self.cursor),
+            KeyCode::Char('u') if ctrl => self.delete(0, self.cursor),
+            KeyCode::Char('k') if ctrl => self.delete(self.cursor, self.value.len()),
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Char(c) => {
+                self.value.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace => {
+                if let Some(previous) = self.previous() {
+                    self.delete(previous, self.cursor);
+                }
+            }
+            KeyCode::Delete => {
+                if let Some(next) = self.next() {
+                    self.delete(self.cursor, next);
+                }
+            }
+            KeyCode::Left => self.cursor = self.previous().unwrap_or(self.cursor),
+            KeyCode::Right => self.cursor = self.next().unwrap_or(self.cursor),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.len(),
+            _ => {}
+        }
+        if self.value != before {
+            self.error = None;
+        }
+        None
+    }
+
+    /// Renders the prompt as a popup in the middle of `area`, scrolled so that the cursor is
+    /// visible.
+    pub fn render(&self, frame: &mut Frame, area: Rect, title: &str) {
+        let area = centered_rect(area, 40, 3);
+        let title = Line::styled(title, theme::style(Role::Title)).centered();
+        let mut block = Block::bordered().title(title);
+        if let Some(error) = &self.error {
+            block = block.title_bottom(Line::styled(
+                format!(" {} ", error),
+                theme::style(Role::Error),
+            ));
+        }
+        let inner = block.inner(area);
+        let column = self.value[..self.cursor].chars().count() as u16;
+        let scroll = column.saturating_sub(inner.width.saturating_sub(1));
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(self.value.as_str())
+                .style(theme::style(Role::Text))
+                .scroll((0, scroll))
+                .block(block),
+            area,
+        );
+        frame.set_cursor_position(Position::new(inner.x + column - scroll, inner.y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_text() {
+        let mut input = TextInput::new();
+        let mut press = |code, modifiers| input.on_key_event(KeyEvent::new(code, modifiers));
+        for c in "night lxé".chars() {
+            press(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        press(KeyCode::Left, KeyModifiers::NONE);
+        press(KeyCode::Backspace, KeyModifiers::NONE);
+        press(KeyCode::Char('i'), KeyModifiers::NONE);
+        press(KeyCode::Home, KeyModifiers::NONE);
+        press(KeyCode::Delete, KeyModifiers::NONE);
+        press(KeyCode::Char('N'), KeyModifiers::NONE);
+        assert_eq!(input.value(), "Night lié");
+        input.on_key_event(KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+        input.on_key_event(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(input.value(), "Night ");
+        input.on_key_event(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+        input.on_key_event(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL));
+        assert_eq!(input.value(), "");
+        assert_eq!(
+            input.on_key_event(KeyEvent::from(KeyCode::Enter)),
+            Some(true)
+        );
+        assert_eq!(
+            input.on_key_event(KeyEvent::from(KeyCode::Esc)),
+            Some(false)
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! The styles used by the TUI, adapted to how many colours the terminal supports.
+
+use std::{env, sync::OnceLock};
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// How many colours the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// 24-bit colour
+    TrueColor,
+    /// The 256-colour palette
+    Ansi256,
+    /// The 16 basic colours, as set by the terminal's colour scheme
+    Ansi16,
+    /// No colours at all, because of `NO_COLOR` or `TERM=dumb`
+    Mono,
+}
+
+impl ColorDepth {
+    /// Works out the colour depth from `NO_COLOR`, `COLORTERM` and `TERM`.
+    pub fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) || term == "dumb" {
+            return Self::Mono;
+        }
+        let color_term = env::var("COLORTERM").unwrap_or_default();
+        if color_term == "truecolor" || color_term == "24bit" {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// What a piece of the TUI is, which decides how it's styled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Panel and popup titles
+    Title,
+    /// Labels and table headers
+    Heading,
+    /// Ordinary text
+    Text,
+    /// Hints and other less important text
+    Dim,
+    /// Error messages
+    Error,
+    /// Things that need attention without being errors, e.g. differing values
+    Warning,
+    /// The highlighted row of a list
+    Selection,
+    /// The border of the pane that has the keyboard focus
+    Focus,
+    /// The parts of LED names that match the filter
+    Match,
+}
+
+/// A colour in each of the colour depths that have colours.
+struct Shade {
+    rgb: (u8, u8, u8),
+    indexed: u8,
+    basic: Color,
+}
+
+const BLUE: Shade = Shade {
+    rgb: (97, 175, 239),
+    indexed: 75,
+    basic: Color::Blue,
+};
+const RED: Shade = Shade {
+    rgb: (224, 108, 117),
+    indexed: 167,
+    basic: Color::Red,
+};
+const YELLOW: Shade = Shade {
+    rgb: (229, 192, 123),
+    indexed: 180,
+    basic: Color::Yellow,
+};
+const WHITE: Shade = Shade {
+    rgb: (220, 223, 228),
+    indexed: 253,
+    basic: Color::White,
+};
+const GREEN: Shade = Shade {
+    rgb: (152, 195, 121),
+    indexed: 114,
+    basic: Color::Green,
+};
+const CYAN: Shade = Shade {
+    rgb: (86, 182, 194),
+    indexed: 73,
+    basic: Color::Cyan,
+};
+const MAGENTA: Shade = Shade {
+    rgb: (198, 120, 221),
+    indexed: 176,
+    basic: Color::Magenta,
+};
+const ORANGE: Shade = Shade {
+    rgb: (209, 154, 102),
+    indexed: 173,
+    basic: Color::Yellow,
+};
+const SELECTION: Shade = Shade {
+    rgb: (40, 80, 140),
+    indexed: 25,
+    basic: Color::Blue,
+};
+
+/// Colours of the series in the brightness chart, in the order LEDs were added.
+const SERIES: [Shade; 6] = [
+    CYAN,
+    MAGENTA,
+    YELLOW,
+    GREEN,
+    RED,
+    Shade {
+        rgb: (97, 175, 239),
+        indexed: 75,
+        basic: Color::LightBlue,
+    },
+];
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Resolves [`Role`]s to styles for the terminal's colour depth.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    depth: ColorDepth,
+}
+
+impl Theme {
+    /// A theme for a colour depth, e.g. to style a one-off frame differently.
+    pub fn new(depth: ColorDepth) -> Self {
+        Self { depth }
+    }
+
+    /// The theme for the current terminal, detected once unless set with
+    /// [`Theme::set_color_depth`].
+    pub fn current() -> &'static Self {
+        THEME.get_or_init(|| Self::new(ColorDepth::detect()))
+    }
+
+    /// Uses a colour depth instead of detecting it. Does nothing once the theme has been used.
+    pub fn set_color_depth(depth: ColorDepth) {
+        let _ = THEME.set(Self::new(depth));
+    }
+
+    fn color(&self, shade: &Shade) -> Option<Color> {
+        match self.depth {
+            ColorDepth::TrueColor => Some(Color::Rgb(shade.rgb.0, shade.rgb.1, shade.rgb.2)),
+            ColorDepth::Ansi256 => Some(Color::Indexed(shade.indexed)),
+            ColorDepth::Ansi16 => Some(shade.basic),
+            ColorDepth::Mono => None,
+        }
+    }
+
+    fn fg(&self, shade: &Shade) -> Style {
+        self.color(shade)
+            .map_or_else(Style::new, |color| Style::new().fg(color))
+    }
+
+    /// The style of a part of the TUI.
+    pub fn style(&self, role: Role) -> Style {
+        match role {
+            Role::Title => self.fg(&BLUE).add_modifier(Modifier::BOLD),
+            Role::Heading => Style::new().add_modifier(Modifier::BOLD),
+            Role::Text => self.fg(&WHITE),
+            Role::Dim => Style::new().add_modifier(Modifier::DIM),
+            Role::Error => self.fg(&RED),
+            Role::Warning => match self.depth {
+                ColorDepth::Mono => Style::new().add_modifier(Modifier::UNDERLINED),
+                _ => self.fg(&YELLOW),
+            },
+            Role::Focus => self.fg(&BLUE).add_modifier(Modifier::BOLD),
+            Role::Match => match self.depth {
+                ColorDepth::Mono => Style::new().add_modifier(Modifier::UNDERLINED),
+                _ => self.fg(&YELLOW).add_modifier(Modifier::BOLD),
+            },
+            Role::Selection => match self.color(&SELECTION) {
+                Some(color) => Style::new().bg(color),
+                None => Style::new().add_modifier(Modifier::REVERSED),
+            },
+        }
+    }
+
+    /// A tint for the name of an LED of a color, as named in the color part of LED names (see
+    /// `include/dt-bindings/leds/common.h` in the kernel). Colors without a close match, like
+    /// `ir` or `multicolor`, aren't tinted.
+    pub fn led_color(&self, color: &str) -> Style {
+        let shade = match color {
+            "white" => &WHITE,
+            "red" => &RED,
+            "green" | "lime" => &GREEN,
+            "blue" => &BLUE,
+            "cyan" => &CYAN,
+            "amber" | "orange" => &ORANGE,
+            "yellow" => &YELLOW,
+            "violet" | "purple" | "pink" => &MAGENTA,
+            _ => return Style::new(),
+        };
+        self.fg(shade)
+    }
+
+    /// The style of the `index`th series in the brightness chart.
+    pub fn series(&self, index: usize) -> Style {
+        self.fg(&SERIES[index % SERIES.len()])
+    }
+}
+
+/// Shorthand for [`Theme::current`]'s style for a role.
+pub fn style(role: Role) -> Style {
+    Theme::current().style(role)
+}
+
+/// Shorthand for [`Theme::current`]'s tint for an LED color.
+pub fn led_color(color: &str) -> Style {
+    Theme::current().led_color(color)
+}
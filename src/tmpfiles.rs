@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use color_eyre::Result;
+
+use crate::{get_all_leds, glob, profiles::LedState};
+
+/// The `tmpfiles.d` line that writes `value` to an attribute file at boot.
+fn write_entry(path: &Path, value: &str) -> String {
+    let path = path.display().to_string();
+    let path = if path.contains(char::is_whitespace) {
+        format!("\"{}\"", path)
+    } else {
+        path
+    };
+    format!("w {} - - - - {}", path, value)
+}
+
+/// The `tmpfiles.d` lines that recreate an LED's state.
+///
+/// LEDs driven by a trigger only get the trigger written, since the trigger controls their
+/// brightness. Others get their trigger reset to `none` before the brightness is written.
+pub fn entries(state: &LedState, led_path: &Path, has_trigger: bool) -> Vec<String> {
+    match &state.trigger {
+        Some(trigger) => vec![write_entry(&led_path.join("trigger"), trigger)],
+        None => {
+            let mut entries = Vec::new();
+            if has_trigger {
+                entries.push(write_entry(&led_path.join("trigger"), "none"));
+            }
+            entries.push(write_entry(
+                &led_path.join("brightness"),
+                &state.brightness.to_string(),
+            ));
+            entries
+        }
+    }
+}
+
+/// Prints a `tmpfiles.d` snippet that restores the current state of the LEDs at boot.
+pub fn run(led_names: Vec<String>) -> Result<()> {
+    let leds = if led_names.is_empty() {
+        let mut leds = get_all_leds()?;
+        leds.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        leds
+    } else {
+        glob::expand(&led_names)?
+    };
+    println!("# LED state exported by Glimpse");
+    println!("# Install as /etc/tmpfiles.d/glimpse.conf to apply it at boot");
+    for mut led in leds {
+        let led_path = led.path();
+        let state = LedState::capture(&mut led)?;
+        let has_trigger = led_path.join("trigger").exists();
+        for entry in entries(&state, &led_path, has_trigger) {
+            println!("{}", entry);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,163 @@
+//! The settings some triggers add to an LED's directory, e.g. the blink timing of `timer`, and
+//! firing the `oneshot` trigger.
+
+use std::{fs, io, path::Path};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+};
+use thiserror::Error;
+
+use crate::{
+    LED,
+    theme::{self, Role},
+    widgets::centered_rect,
+};
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("invalid {attribute}: {value:?} is not {expected}")]
+    InvalidValue {
+        attribute: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("I/O error: {0}")]
+    IOError(#[from] io::Error),
+}
+
+/// The attributes a trigger adds that can be edited, in the order they're shown.
+pub fn settings_for(trigger: &str) -> &'static [&'static str] {
+    match trigger {
+        "timer" => &["delay_on", "delay_off"],
+        "oneshot" => &["delay_on", "delay_off", "invert"],
+        _ => &[],
+    }
+}
+
+/// Checks a value typed in for an attribute before it's written.
+fn validate(attribute: &'static str, value: &str) -> Result<(), SettingsError> {
+    let (valid, expected) = match attribute {
+        "invert" => (matches!(value, "0" | "1"), "0 or 1"),
+        _ => (value.parse::<u64>().is_ok(), "a number of milliseconds"),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(SettingsError::InvalidValue {
+            attribute,
+            value: value.to_string(),
+            expected,
+        })
+    }
+}
+
+/// Fires an LED using the `oneshot` trigger once: on for `delay_on`, then off for `delay_off`
+/// (or the other way round when inverted).
+///
+/// Fails with [`io::ErrorKind::NotFound`] if the LED uses a different trigger.
+pub fn shoot(led_path: &Path) -> io::Result<()> {
+    fs::write(led_path.join("shot"), "1")
+}
+
+/// A form for editing the settings of an LED's active trigger.
+#[derive(Debug, Clone)]
+pub struct SettingsForm {
+    /// File name of the LED
+    pub led: String,
+    pub trigger: String,
+    /// Attributes and the values typed in for them
+    fields: Vec<(&'static str, String)>,
+    focused: usize,
+}
+
+impl SettingsForm {
+    /// Reads the current settings of the LED's active trigger, or returns `None` if the trigger
+    /// has no settings that can be edited.
+    pub fn open(led: &LED) -> io::Result<Option<Self>> {
+        let Some(trigger) = led.triggers()?.and_then(|triggers| triggers.active) else {
+            return Ok(None);
+        };
+        let attributes = settings_for(&trigger);
+        if attributes.is_empty() {
+            return Ok(None);
+        }
+        let led_path = led.path();
+        let fields = attributes
+            .iter()
+            .map(|attribute| {
+                let value = fs::read_to_string(led_path.join(attribute))?;
+                Ok((*attribute, value.trim().to_string()))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Some(Self {
+            led: led.file_name.clone(),
+            trigger,
+            fields,
+            focused: 0,
+        }))
+    }
+
+    /// Moves the focus to the next field, or the previous one, wrapping around.
+    pub fn focus_next(&mut self, forward: bool) {
+        let offset = if forward { 1 } else { self.fields.len() - 1 };
+        self.focused = (self.focused + offset) % self.fields.len();
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.fields[self.focused].1.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.fields[self.focused].1.pop();
+    }
+
+    /// Validates every field, then writes them to the LED at `led_path` in order.
+    pub fn write(&self, led_path: &Path) -> Result<(), SettingsError> {
+        for (attribute, value) in &self.fields {
+            validate(attribute, value)?;
+        }
+        for (attribute, value) in &self.fields {
+            fs::write(led_path.join(attribute), value)?;
+        }
+        Ok(())
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let title = Line::styled(
+            format!("{} settings of {}", self.trigger, self.led),
+            theme::style(Role::Title),
+        )
+        .centered();
+        let mut lines: Vec<Line> = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, (attribute, value))| {
+                let line = Line::from(vec![
+                    Span::styled(format!("{:>10}: ", attribute), theme::style(Role::Heading)),
+                    Span::raw(value.as_str()),
+                ]);
+                if i == self.focused {
+                    line.patch_style(theme::style(Role::Selection))
+                } else {
+                    line
+                }
+            })
+            .collect();
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            "Tab next field, Enter save, Esc cancel",
+            theme::style(Role::Dim),
+        ));
+        let area = centered_rect(area, 50, lines.len() as u16 + 2);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! `glimpse setup-permissions`: a udev rule that lets a group change LEDs, so Glimpse works
+//! without root.
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    process::Command,
+};
+
+use color_eyre::{Result, eyre::eyre};
+
+/// Where `--install` puts the rule.
+pub const RULE_PATH: &str = "/etc/udev/rules.d/90-glimpse-leds.rules";
+/// The group given access if it exists and no other group is chosen.
+const DEFAULT_GROUP: &str = "leds";
+
+/// The udev rule giving `group` write access to the `brightness` and `trigger` of every LED,
+/// applied whenever an LED appears (including at boot).
+pub fn rule(group: &str) -> String {
+    format!(
+        "# Installed by glimpse setup-permissions: lets members of {group} change LEDs\n\
+         ACTION==\"add\", SUBSYSTEM==\"leds\", \
+         RUN+=\"/bin/chgrp {group} /sys%p/brightness /sys%p/trigger\", \
+         RUN+=\"/bin/chmod g+w /sys%p/brightness /sys%p/trigger\"\n"
+    )
+}
+
+/// Whether a group is listed in `/etc/group`.
+fn group_exists(group: &str) -> bool {
+    fs::read_to_string("/etc/group").is_ok_and(|groups| {
+        groups
+            .lines()
+            .any(|line| line.split(':').next() == Some(group))
+    })
+}
+
+/// The `leds` group if there is one, otherwise the user's primary group.
+fn default_group() -> Result<String> {
+    if group_exists(DEFAULT_GROUP) {
+        return Ok(DEFAULT_GROUP.to_string());
+    }
+    let output = Command::new("id").arg("-gn").output()?;
+    if !output.status.success() {
+        return Err(eyre!("couldn't find your group, choose one with --group"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Writes the rule to [`RULE_PATH`] and applies it to the LEDs that already exist.
+fn install(rule: &str) -> Result<()> {
+    fs::write(RULE_PATH, rule).map_err(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied => {
+            eyre!("not allowed to write {} (run as root)", RULE_PATH)
+        }
+        _ => e.into(),
+    })?;
+    println!("Wrote {}", RULE_PATH);
+    for args in [
+        &["control", "--reload"][..],
+        &["trigger", "--subsystem-match=leds", "--action=add"],
+    ] {
+        let status = Command::new("udevadm").args(args).status()?;
+        if !status.success() {
+            return Err(eyre!("udevadm {} exited with {}", args.join(" "), status));
+        }
+    }
+    println!("Applied the rule to the current LEDs");
+    Ok(())
+}
+
+/// Prints the udev rule, or with `install`, asks before installing it.
+pub fn run(group: Option<String>, install_rule: bool) -> Result<()> {
+    let group = match group {
+        Some(group) => group,
+        None => default_group()?,
+    };
+    if !group_exists(&group) {
+        return Err(eyre!("there is no group named {}", group));
+    }
+    let rule = rule(&group);
+    if !install_rule {
+        print!("{}", rule);
+        return Ok(());
+    }
+    print!("{}\nInstall this rule as {}? [y/N] ", rule, RULE_PATH);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y") {
+        println!("Not installed");
+        return Ok(());
+    }
+    if Path::new(RULE_PATH).exists() {
+        println!("Replacing the existing rule");
+    }
+    install(&rule)?;
+    println!(
+        "Members of {} can now change LEDs (after logging in again, if just added to it)",
+        group
+    );
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Virtual LEDs backed by the kernel's uleds driver, for trying out triggers and integrations
+//! without the hardware.
+
+use std::{
+    fs::OpenOptions,
+    io::{ErrorKind, Read, Write},
+};
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::output::{Output, Tone};
+
+const ULEDS_DEVICE: &str = "/dev/uleds";
+/// `LED_MAX_NAME_SIZE` from `linux/uleds.h`.
+const MAX_NAME_SIZE: usize = 64;
+/// Max brightness of virtual LEDs when none is given.
+pub const DEFAULT_MAX_BRIGHTNESS: u32 = 255;
+
+/// Encodes a `struct uleds_user_dev`: the NUL-padded name followed by the max brightness.
+fn user_dev(name: &str, max_brightness: u32) -> Result<Vec<u8>> {
+    if name.is_empty() || name.len() >= MAX_NAME_SIZE || name.contains(['/', '\0']) {
+        return Err(eyre!(
+            "LED names must be 1 to {} bytes long and can't contain '/'",
+            MAX_NAME_SIZE - 1
+        ));
+    }
+    let max_brightness =
+        i32::try_from(max_brightness).map_err(|_| eyre!("max brightness is too large"))?;
+    let mut dev = vec![0; MAX_NAME_SIZE];
+    dev[..name.len()].copy_from_slice(name.as_bytes());
+    dev.extend_from_slice(&max_brightness.to_ne_bytes());
+    Ok(dev)
+}
+
+/// Creates a virtual LED and prints its brightness whenever it changes.
+///
+/// The LED appears under `/sys/class/leds/<name>` and is removed again when Glimpse exits, as
+/// the kernel keeps it only while `/dev/uleds` is held open.
+pub fn run(name: String, max_brightness: u32) -> Result<()> {
+    let output = Output::detect();
+    let mut uleds = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(ULEDS_DEVICE)
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => eyre!("{} doesn't exist, try `modprobe uleds`", ULEDS_DEVICE),
+            _ => eyre!("can't open {}: {}", ULEDS_DEVICE, e),
+        })?;
+    uleds.write_all(&user_dev(&name, max_brightness)?)?;
+    println!(
+        "Created virtual LED {} (max brightness {}), press Ctrl-C to remove it",
+        output.paint(&name, Tone::Name),
+        max_brightness
+    );
+    let mut brightness = [0; 4];
+    loop {
+        uleds.read_exact(&mut brightness)?;
+        println!(
+            "{} brightness {}",
+            output.paint(&name, Tone::Name),
+            output.paint(&i32::from_ne_bytes(brightness).to_string(), Tone::Value)
+        );
+    }
+}
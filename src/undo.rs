@@ -0,0 +1,161 @@
+//! Undoing and redoing the brightness and trigger changes made in the TUI.
+//!
+//! Colors of multicolor LEDs, whether from the color prompt, a preset, the color temperature
+//! keys or the channel editor, aren't recorded: undoing has to be able to write the old value
+//! through the helper like any other, and the helper can only set brightnesses and triggers.
+
+use std::{fmt, io};
+
+use crate::{LED, helper::HelperRequest};
+
+/// How many steps can be undone before the oldest are forgotten.
+const MAX_UNDO_STEPS: usize = 100;
+
+/// A change to one attribute of one LED, with its value before and after.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Brightness {
+        led: String,
+        old: u32,
+        new: u32,
+    },
+    Trigger {
+        led: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl Edit {
+    /// File name of the changed LED.
+    pub fn led(&self) -> &str {
+        match self {
+            Self::Brightness { led, .. } | Self::Trigger { led, .. } => led,
+        }
+    }
+
+    /// The edits for setting the brightness of an LED. Turning an LED off also stops its
+    /// trigger, so that's recorded too.
+    pub fn brightness(led: &LED, old: u32, new: u32) -> Vec<Self> {
+        let mut edits = Vec::new();
+        if new == 0
+            && let Ok(Some(triggers)) = led.triggers()
+            && let Some(active) = triggers.active.filter(|active| active != "none")
+        {
+            edits.push(Self::Trigger {
+                led: led.file_name.clone(),
+                old: active,
+                new: "none".to_string(),
+            });
+        }
+        edits.push(Self::Brightness {
+            led: led.file_name.clone(),
+            old,
+            new,
+        });
+        edits
+    }
+
+    /// Writes the value from before the edit, or from after it when redoing.
+    pub fn apply(&self, led: &mut LED, undo: bool) -> io::Result<()> {
+        match self {
+            Self::Brightness { old, new, .. } => {
+                led.set_brightness(if undo { *old } else { *new })?;
+                let _ = led.refresh();
+                Ok(())
+            }
+            Self::Trigger { old, new, .. } => led.set_trigger(if undo { old } else { new }),
+        }
+    }
+
+    /// The request for the helper to write the value, if writing it directly isn't permitted.
+    pub fn helper_request(&self, undo: bool) -> HelperRequest {
+        match self {
+            Self::Brightness { led, old, new } => HelperRequest::Brightness {
+                led: led.clone(),
+                brightness: if undo { *old } else { *new },
+            },
+            Self::Trigger { led, old, new } => HelperRequest::Trigger {
+                led: led.clone(),
+                trigger: if undo { old } else { new }.clone(),
+            },
+        }
+    }
+}
+
+/// Edits are described as e.g. `input3::capslock brightness 0 → 1`.
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Brightness { led, old, new } => write!(f, "{} brightness {} → {}", led, old, new),
+            Self::Trigger { led, old, new } => write!(f, "{} trigger {} → {}", led, old, new),
+        }
+    }
+}
+
+/// The edits made by one action, which are undone together.
+pub type Step = Vec<Edit>;
+
+/// The steps that can be undone, and those that were undone and can be redone.
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    done: Vec<Step>,
+    undone: Vec<Step>,
+}
+
+impl UndoHistory {
+    /// Remembers a step so that it can be undone. Steps that were undone can't be redone
+    /// afterwards.
+    pub fn record(&mut self, step: Step) {
+        if step.is_empty() {
+            return;
+        }
+        self.done.push(step);
+        if self.done.len() > MAX_UNDO_STEPS {
+            self.done.remove(0);
+        }
+        self.undone.clear();
+    }
+
+    /// The latest step, which is moved to the ones that can be redone.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.done.pop()?;
+        self.undone.push(step.clone());
+        Some(step)
+    }
+
+    /// The latest undone step, which can be undone again afterwards.
+    pub fn redo(&mut self) -> Option<Step> {
+        let step = self.undone.pop()?;
+        self.done.push(step.clone());
+        Some(step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(new: u32) -> Step {
+        vec![Edit::Brightness {
+            led: "input3::capslock".to_string(),
+            old: new - 1,
+            new,
+        }]
+    }
+
+    #[test]
+    fn undoes_and_redoes() {
+        let mut history = UndoHistory::default();
+        history.record(step(1));
+        history.record(step(2));
+        assert_eq!(history.undo(), Some(step(2)));
+        assert_eq!(history.undo(), Some(step(1)));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), Some(step(1)));
+        // A new change can't be followed by redoing older ones
+        history.record(step(3));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(step(3)));
+    }
+}
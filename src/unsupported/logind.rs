@@ -0,0 +1,36 @@
+//! Stands in for the logind module off Linux, where there is no logind: backlights are always
+//! written through sysfs, and resumes are guessed from the clock.
+
+use std::{io, time::Duration};
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "logind is only available on Linux",
+    )
+}
+
+/// Whether sysfs can be written to without asking logind, which is always the case without a
+/// logind to ask.
+pub fn running_as_root() -> bool {
+    true
+}
+
+/// Fails, as there is no logind to ask.
+pub fn set_brightness(_subsystem: &str, _name: &str, _brightness: u32) -> io::Result<()> {
+    Err(unsupported())
+}
+
+/// Can't be subscribed to, as there is no logind to hear from.
+#[derive(Debug)]
+pub enum SleepWatcher {}
+
+impl SleepWatcher {
+    pub fn subscribe() -> io::Result<Self> {
+        Err(unsupported())
+    }
+
+    pub fn wait(&mut self, _timeout: Duration) -> io::Result<bool> {
+        match *self {}
+    }
+}
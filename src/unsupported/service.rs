@@ -0,0 +1,26 @@
+//! Stands in for the D-Bus service off Linux, where Glimpse can't connect to a bus.
+
+use std::time::Duration;
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::dbus::Bus;
+
+/// The well-known name the service would own.
+pub const SERVICE_NAME: &str = "dev.mmk21.Glimpse";
+
+/// Can't be started, as the bus can't be connected to.
+pub enum Service {}
+
+impl Service {
+    pub fn start(bus: Bus) -> Result<Self> {
+        Err(eyre!(
+            "can't offer the D-Bus service on the {} bus: D-Bus is only supported on Linux",
+            bus
+        ))
+    }
+
+    pub fn serve(&mut self, _timeout: Duration) -> Result<()> {
+        match *self {}
+    }
+}
@@ -0,0 +1,227 @@
+//! `glimpse watch`: printing a JSON line for every change to an LED, for status bars and scripts
+//! that would otherwise poll sysfs themselves.
+//!
+//! Each line looks like
+//! `{"timestamp":1760000000.250,"led":"input3::capslock","attribute":"brightness","old":0,"new":1}`.
+//! The attribute is `brightness`, `trigger` (`null` for LEDs without one), or `present` when an
+//! LED appears or disappears, e.g. as a keyboard is plugged in.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::Result;
+
+use crate::{LedBackend, SysfsBackend, glob, output::json_string};
+
+/// How often LEDs are read by `glimpse watch`, unless set with `--interval`.
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What's watched of an LED.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observed {
+    pub brightness: u32,
+    /// The active trigger, or `None` if the LED can't be driven by triggers
+    pub trigger: Option<String>,
+}
+
+/// One attribute of an LED changing between two reads, with the values as JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub led: String,
+    pub attribute: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl Change {
+    fn new(led: &str, attribute: &'static str, old: String, new: String) -> Self {
+        Self {
+            led: led.to_string(),
+            attribute,
+            old,
+            new,
+        }
+    }
+
+    /// The change as one line of NDJSON, stamped with the Unix time it was seen at.
+    pub fn to_json(&self, timestamp: SystemTime) -> String {
+        let unix_time = timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        format!(
+            "{{\"timestamp\":{:.3},\"led\":{},\"attribute\":\"{}\",\"old\":{},\"new\":{}}}",
+            unix_time,
+            json_string(&self.led),
+            self.attribute,
+            self.old,
+            self.new
+        )
+    }
+}
+
+/// Reads the LEDs whose names match any of the patterns, or all of them if there are none.
+///
+/// Whether an LED is present only depends on it being listed: an LED that's listed but can't be
+/// read keeps what was observed of it in `previous`, so that one failed read doesn't look like
+/// it was unplugged and plugged back in. It's only left out if it was never read.
+pub fn observe(
+    backend: &dyn LedBackend,
+    patterns: &[String],
+    previous: &BTreeMap<String, Observed>,
+) -> Result<BTreeMap<String, Observed>> {
+    let mut observed = BTreeMap::new();
+    for name in backend.led_names()? {
+        if !patterns.is_empty() && !patterns.iter().any(|pattern| glob::matches(pattern, &name)) {
+            continue;
+        }
+        let (Ok(brightness), Ok(triggers)) =
+            (backend.read_brightness(&name), backend.read_triggers(&name))
+        else {
+            if let Some(last) = previous.get(&name) {
+                observed.insert(name, last.clone());
+            }
+            continue;
+        };
+        let trigger = triggers.map(|triggers| triggers.active.unwrap_or_else(|| "none".into()));
+        observed.insert(
+            name,
+            Observed {
+                brightness,
+                trigger,
+            },
+        );
+    }
+    Ok(observed)
+}
+
+/// The changes between two reads of the LEDs, in order of LED name.
+pub fn changes(
+    before: &BTreeMap<String, Observed>,
+    after: &BTreeMap<String, Observed>,
+) -> Vec<Change> {
+    let trigger_json = |trigger: &Option<String>| {
+        trigger
+            .as_deref()
+            .map_or_else(|| "null".to_string(), json_string)
+    };
+    let mut changes = Vec::new();
+    for (led, old) in before {
+        let Some(new) = after.get(led) else {
+            changes.push(Change::new(led, "present", "true".into(), "false".into()));
+            continue;
+        };
+        if old.brightness != new.brightness {
+            changes.push(Change::new(
+                led,
+                "brightness",
+                old.brightness.to_string(),
+                new.brightness.to_string(),
+            ));
+        }
+        if old.trigger != new.trigger {
+            changes.push(Change::new(
+                led,
+                "trigger",
+                trigger_json(&old.trigger),
+                trigger_json(&new.trigger),
+            ));
+        }
+    }
+    for led in after.keys().filter(|led| !before.contains_key(*led)) {
+        changes.push(Change::new(led, "present", "false".into(), "true".into()));
+    }
+    changes.sort_by(|a, b| a.led.cmp(&b.led));
+    changes
+}
+
+/// Prints the changes to the LEDs matching the patterns until interrupted, or until nothing
+/// reads the output anymore.
+pub fn run(patterns: Vec<String>, interval: Duration) -> Result<()> {
+    let mut last = observe(&SysfsBackend, &patterns, &BTreeMap::new())?;
+    loop {
+        thread::sleep(interval);
+        let current = observe(&SysfsBackend, &patterns, &last)?;
+        let now = SystemTime::now();
+        let mut stdout = io::stdout().lock();
+        for change in changes(&last, &current) {
+            let written = writeln!(stdout, "{}", change.to_json(now)).and_then(|()| stdout.flush());
+            match written {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                result => result?,
+            }
+        }
+        last = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockBackend, backend::MockLed};
+
+    #[test]
+    fn reports_changes() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        backend.add_led("phy0-led", MockLed::new(1));
+        let before = observe(
+            &backend,
+            &["input*".to_string(), "phy0-led".to_string()],
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        backend.add_led(
+            "input3::capslock",
+            MockLed {
+                brightness: 1,
+                ..MockLed::new(1)
+            },
+        );
+        backend.write_trigger("input3::capslock", "timer").unwrap();
+        backend.remove_led("phy0-led");
+        backend.add_led("input5::numlock", MockLed::new(1));
+        let after = observe(&backend, &["input*".to_string()], &before).unwrap();
+        let changes = changes(&before, &after);
+        assert_eq!(
+            changes,
+            [
+                Change::new("input3::capslock", "brightness", "0".into(), "1".into()),
+                Change::new(
+                    "input3::capslock",
+                    "trigger",
+                    "\"none\"".into(),
+                    "\"timer\"".into()
+                ),
+                Change::new("input5::numlock", "present", "false".into(), "true".into()),
+                Change::new("phy0-led", "present", "true".into(), "false".into()),
+            ]
+        );
+        assert_eq!(
+            changes[0].to_json(UNIX_EPOCH + Duration::from_millis(1500)),
+            "{\"timestamp\":1.500,\"led\":\"input3::capslock\",\"attribute\":\"brightness\",\
+             \"old\":0,\"new\":1}"
+        );
+    }
+
+    #[test]
+    fn keeps_leds_that_fail_to_read() {
+        let backend = MockBackend::new();
+        backend.add_led("input3::capslock", MockLed::new(1));
+        let before = observe(&backend, &[], &BTreeMap::new()).unwrap();
+        let failing = MockLed {
+            brightness: 1,
+            error: Some(io::ErrorKind::TimedOut),
+            ..MockLed::new(1)
+        };
+        backend.add_led("input3::capslock", failing.clone());
+        backend.add_led("input5::numlock", failing);
+        let after = observe(&backend, &[], &before).unwrap();
+        assert_eq!(after, before);
+        assert!(changes(&before, &after).is_empty());
+    }
+}
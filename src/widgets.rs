@@ -0,0 +1,572 @@
+//! The LED list and detail panels as standalone ratatui widgets, so they can be embedded in
+//! other layouts.
+//!
+//! Both are [`StatefulWidget`]s: the list keeps its selection in an [`LedListState`], and the
+//! detail pane shows what an [`LedDetailState`] last read, so that rendering doesn't touch
+//! sysfs. Their colours follow [`Theme::current`](crate::theme::Theme::current).
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use glimpse::{
+//!     MockBackend,
+//!     backend::MockLed,
+//!     led::get_all_leds_from,
+//!     widgets::{LedDetailState, LedDetailWidget, LedListState, LedListWidget},
+//! };
+//! use ratatui::{
+//!     Terminal,
+//!     backend::TestBackend,
+//!     layout::{Constraint, Layout},
+//!     widgets::Block,
+//! };
+//!
+//! let backend = MockBackend::new();
+//! backend.add_led("input3::capslock", MockLed::new(1));
+//! let leds = get_all_leds_from(Arc::new(backend))?;
+//!
+//! let mut list_state = LedListState::default();
+//! list_state.select(Some(0));
+//! let mut detail_state = LedDetailState::default();
+//! detail_state.refresh(leds.first(), None);
+//!
+//! let mut terminal = Terminal::new(TestBackend::new(80, 10))?;
+//! terminal.draw(|frame| {
+//!     let [list, detail] = Layout::horizontal([Constraint::Length(30), Constraint::Fill(1)])
+//!         .areas(frame.area());
+//!     frame.render_stateful_widget(
+//!         LedListWidget::new(&leds).block(Block::bordered().title("LEDs")),
+//!         list,
+//!         &mut list_state,
+//!     );
+//!     frame.render_stateful_widget(
+//!         LedDetailWidget::default().block(Block::bordered()),
+//!         detail,
+//!         &mut detail_state,
+//!     );
+//! })?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{borrow::Cow, fs, io};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{
+        Block, LineGauge, List, ListItem, ListState, Paragraph, StatefulWidget, Widget, Wrap,
+    },
+};
+
+use crate::{
+    FailedLED, LED, LedKind, Triggers,
+    led::{SYS_CLASS_LEDS, brightness_to_percent, format_brightness},
+    remote,
+    theme::{self, Role},
+};
+
+/// An entry in the LED list.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SidebarRow {
+    /// A heading above the LEDs of one device or driver, or above the pinned LEDs
+    Group {
+        name: String,
+        /// How many LEDs are in the group, including hidden ones
+        leds: usize,
+        /// Whether the group's LEDs are hidden
+        collapsed: bool,
+        /// Whether the group is of pinned LEDs, which are shown by their full names
+        pinned: bool,
+    },
+    /// An LED, by index into the LEDs given to [`LedListWidget::new`]
+    LED(usize),
+}
+
+/// Splits text into spans, styling the characters at `positions` as matches.
+fn highlighted<'a>(text: &'a str, positions: &[usize]) -> Vec<Span<'a>> {
+    if positions.is_empty() {
+        return vec![Span::raw(text)];
+    }
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut in_match = false;
+    for (position, (byte, _)) in text.char_indices().enumerate() {
+        let is_match = positions.contains(&position);
+        if is_match != in_match {
+            spans.push(span_for(&text[start..byte], in_match));
+            start = byte;
+            in_match = is_match;
+        }
+    }
+    spans.push(span_for(&text[start..], in_match));
+    spans
+}
+
+fn span_for(text: &str, is_match: bool) -> Span<'_> {
+    if is_match {
+        Span::styled(text, theme::style(Role::Match))
+    } else {
+        Span::raw(text)
+    }
+}
+
+/// Selection state of an [`LedListWidget`].
+#[derive(Debug, Default, Clone)]
+pub struct LedListState {
+    list: ListState,
+}
+
+impl LedListState {
+    /// The index of the selected row.
+    pub fn selected(&self) -> Option<usize> {
+        self.list.selected()
+    }
+
+    /// Selects a row, or nothing with `None`.
+    pub fn select(&mut self, row: Option<usize>) {
+        self.list.select(row);
+    }
+
+    /// The index of the first row in view.
+    pub fn offset(&self) -> usize {
+        self.list.offset()
+    }
+}
+
+/// Finds the positions of the characters to highlight in an LED's name.
+type MatchPositions<'a> = dyn Fn(&str) -> Vec<usize> + 'a;
+/// Whether an LED is in some set, e.g. of marked or read-only LEDs.
+type LedPredicate<'a> = dyn Fn(&LED) -> bool + 'a;
+/// Finds the friendly name given to an LED.
+type Alias<'a> = dyn Fn(&LED) -> Option<&'a str> + 'a;
+
+/// A list of LEDs showing whether each is on and its brightness, optionally grouped under
+/// headings and with some LEDs marked.
+pub struct LedListWidget<'a> {
+    leds: &'a [LED],
+    failed: &'a [FailedLED],
+    rows: Cow<'a, [SidebarRow]>,
+    is_marked: Box<LedPredicate<'a>>,
+    is_read_only: Box<LedPredicate<'a>>,
+    is_hidden: Box<LedPredicate<'a>>,
+    is_checked: Option<Box<LedPredicate<'a>>>,
+    short_names: bool,
+    alias: Box<Alias<'a>>,
+    match_positions: Box<MatchPositions<'a>>,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> LedListWidget<'a> {
+    /// Lists all of the LEDs in order, without headings.
+    pub fn new(leds: &'a [LED]) -> Self {
+        Self {
+            leds,
+            failed: &[],
+            rows: (0..leds.len()).map(SidebarRow::LED).collect(),
+            is_marked: Box::new(|_| false),
+            is_read_only: Box::new(|_| false),
+            is_hidden: Box::new(|_| false),
+            is_checked: None,
+            short_names: false,
+            alias: Box::new(|_| None),
+            match_positions: Box::new(|_| Vec::new()),
+            block: None,
+        }
+    }
+
+    /// Shows these rows instead, e.g. to filter or group the LEDs.
+    pub fn rows(mut self, rows: impl Into<Cow<'a, [SidebarRow]>>) -> Self {
+        self.rows = rows.into();
+        self
+    }
+
+    /// Greys out LEDs that couldn't be read below the others, with the reason. They aren't
+    /// rows, so can't be selected.
+    pub fn failed(mut self, failed: &'a [FailedLED]) -> Self {
+        self.failed = failed;
+        self
+    }
+
+    /// Prefixes the LEDs for which `is_marked` returns true with `*`.
+    pub fn marked(mut self, is_marked: impl Fn(&LED) -> bool + 'a) -> Self {
+        self.is_marked = Box::new(is_marked);
+        self
+    }
+
+    /// Labels the LEDs for which `is_read_only` returns true as read-only.
+    pub fn read_only(mut self, is_read_only: impl Fn(&LED) -> bool + 'a) -> Self {
+        self.is_read_only = Box::new(is_read_only);
+        self
+    }
+
+    /// Puts a checkbox before each LED, checked for those for which `is_checked` returns true.
+    pub fn checkboxes(mut self, is_checked: impl Fn(&LED) -> bool + 'a) -> Self {
+        self.is_checked = Some(Box::new(is_checked));
+        self
+    }
+
+    /// Labels the LEDs for which `is_hidden` returns true as hidden, for when hidden LEDs are
+    /// listed anyway.
+    pub fn hidden(mut self, is_hidden: impl Fn(&LED) -> bool + 'a) -> Self {
+        self.is_hidden = Box::new(is_hidden);
+        self
+    }
+
+    /// Shows LEDs by their color and function only, for when they're grouped under their
+    /// device.
+    pub fn short_names(mut self, short_names: bool) -> Self {
+        self.short_names = short_names;
+        self
+    }
+
+    /// Shows LEDs for which `alias` returns a name by that name instead.
+    pub fn aliases(mut self, alias: impl Fn(&LED) -> Option<&'a str> + 'a) -> Self {
+        self.alias = Box::new(alias);
+        self
+    }
+
+    /// Highlights the characters of LED names at the positions `match_positions` returns, e.g.
+    /// the ones matching a search.
+    pub fn highlight(mut self, match_positions: impl Fn(&str) -> Vec<usize> + 'a) -> Self {
+        self.match_positions = Box::new(match_positions);
+        self
+    }
+
+    /// Draws the list inside a block, e.g. with a border and title.
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl StatefulWidget for LedListWidget<'_> {
+    type State = LedListState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let width = usize::from(
+            self.block
+                .as_ref()
+                .map_or(area, |block| block.inner(area))
+                .width,
+        );
+        let grouped = self
+            .rows
+            .iter()
+            .any(|row| matches!(row, SidebarRow::Group { .. }));
+        let mut in_pinned = false;
+        let items = self.rows.iter().map(|row| match row {
+            SidebarRow::Group {
+                name,
+                leds,
+                collapsed,
+                pinned,
+            } => {
+                in_pinned = *pinned;
+                ListItem::new(Line::styled(
+                    format!("{} {} ({})", if *collapsed { "▸" } else { "▾" }, name, leds),
+                    theme::style(Role::Heading).patch(theme::style(Role::Dim)),
+                ))
+            }
+            SidebarRow::LED(index) => {
+                let led = &self.leds[*index];
+                let indent = if grouped { "  " } else { "" };
+                let checkbox = match &self.is_checked {
+                    Some(is_checked) if is_checked(led) => "[x] ",
+                    Some(_) => "[ ] ",
+                    None => "",
+                };
+                let marker = if (self.is_marked)(led) { "* " } else { "" };
+                let name = if let Some(alias) = (self.alias)(led) {
+                    Cow::Borrowed(alias)
+                } else if self.short_names && !in_pinned {
+                    Cow::Owned(led.short_name())
+                } else {
+                    Cow::Borrowed(led.name.as_str())
+                };
+                // LEDs named with a color are tinted in it
+                let tint = led
+                    .color
+                    .as_deref()
+                    .map_or_else(Style::new, theme::led_color);
+                let state = if led.is_on { "● " } else { "○ " };
+                let mut spans = vec![Span::raw(format!(
+                    "{}{}{}{}",
+                    indent, checkbox, marker, state
+                ))];
+                spans.extend(
+                    highlighted(&name, &(self.match_positions)(&name))
+                        .into_iter()
+                        .map(|span| {
+                            Span::styled(span.content.into_owned(), tint.patch(span.style))
+                        }),
+                );
+                spans.extend([
+                    Span::raw(" "),
+                    Span::styled(
+                        format!("{}/{}", led.brightness, led.max_brightness),
+                        theme::style(Role::Dim),
+                    ),
+                ]);
+                if (self.is_read_only)(led) {
+                    spans.push(Span::styled(" read-only", theme::style(Role::Warning)));
+                }
+                if (self.is_hidden)(led) {
+                    spans.push(Span::styled(" hidden", theme::style(Role::Dim)));
+                }
+                // The percentage goes at the right edge, and is left out rather than cutting
+                // off the name or labels when the sidebar is narrow
+                let mut line = Line::from(spans);
+                let percent = format!(
+                    "{}%",
+                    brightness_to_percent(led.brightness, led.max_brightness)
+                );
+                let used = line.width() + 1 + percent.len();
+                if used <= width {
+                    line.push_span(Span::raw(" ".repeat(width - used + 1)));
+                    line.push_span(Span::styled(percent, theme::style(Role::Dim)));
+                }
+                ListItem::new(line)
+            }
+        });
+        let failed = self.failed.iter().map(|failed| {
+            ListItem::new(Line::styled(
+                format!("{} ({})", failed.file_name.replace("::", " "), failed.error),
+                theme::style(Role::Dim),
+            ))
+        });
+        let mut list = List::new(items.chain(failed))
+            .style(theme::style(Role::Text))
+            .highlight_style(theme::style(Role::Selection));
+        if let Some(block) = self.block {
+            list = list.block(block);
+        }
+        StatefulWidget::render(list, area, buf, &mut state.list);
+    }
+}
+
+/// What an [`LedDetailWidget`] shows, read from sysfs by [`LedDetailState::refresh`] so that
+/// rendering doesn't touch the filesystem.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LedDetailState {
+    /// `(label, value)` pairs, or empty when no LED is shown
+    pub fields: Vec<(&'static str, String)>,
+    /// File name and brightness of the LED the fields were read for
+    source: Option<(String, u32)>,
+    /// Brightness and maximum brightness of the LED, shown as a gauge above the fields
+    gauge: Option<(u32, u32)>,
+    /// The triggers as they were read, to notice when only the trigger changes
+    triggers: Option<Triggers>,
+}
+
+impl LedDetailState {
+    /// Reads the details of an LED, or clears them. The LED's alias, if it has one, is listed
+    /// above its name.
+    ///
+    /// Nothing is read if the details are of the same LED and its brightness hasn't changed
+    /// since, as brightness changes accompany nearly every other change. Trigger changes that
+    /// don't are picked up by [`LedDetailState::check_trigger`].
+    pub fn refresh(&mut self, led: Option<&LED>, alias: Option<&str>) {
+        let Some(led) = led else {
+            self.fields.clear();
+            self.source = None;
+            self.gauge = None;
+            return;
+        };
+        let source = (led.file_name.clone(), led.brightness);
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+        self.source = Some(source);
+        self.gauge = (led.max_brightness > 0).then_some((led.brightness, led.max_brightness));
+        let led_path = led.path();
+        let host = remote::split_host(&led.file_name);
+        let unreadable = |e: &dyn std::fmt::Display| format!("unreadable ({})", e);
+        let brightness = if host.is_some() {
+            // Only the brightness read over SSH is known
+            format_brightness(led.brightness, led.max_brightness)
+        } else {
+            fs::read_to_string(led_path.join("brightness")).map_or_else(
+                |e| unreadable(&e),
+                |b| match b.trim().parse() {
+                    Ok(b) => format!("{} ({}%)", b, brightness_to_percent(b, led.max_brightness)),
+                    Err(_) => b.trim().to_string(),
+                },
+            )
+        };
+        let max_brightness = led
+            .max_brightness()
+            .map_or_else(|e| unreadable(&e), |b| b.to_string());
+        let triggers = led.triggers();
+        self.triggers = triggers.as_ref().ok().cloned().flatten();
+        let (trigger, available_triggers) = match triggers {
+            Ok(Some(triggers)) => (
+                triggers.active.unwrap_or_else(|| "none".to_string()),
+                triggers.available.join(" "),
+            ),
+            Ok(None) => ("none".to_string(), "none".to_string()),
+            Err(e) => (unreadable(&e), unreadable(&e)),
+        };
+        // Virtual LEDs (e.g. those of triggers) have no device
+        let device = match fs::canonicalize(led_path.join("device")) {
+            _ if host.is_some() => "unknown".to_string(),
+            Ok(device) => device.display().to_string(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => "none".to_string(),
+            Err(e) => unreadable(&e),
+        };
+        let path = match host {
+            Some((host, file_name)) => format!("{}:{}/{}", host, SYS_CLASS_LEDS, file_name),
+            None => led_path.display().to_string(),
+        };
+        let mut fields = vec![
+            ("Name", led.name.clone()),
+            ("Path", path),
+            ("Brightness", brightness),
+            ("Max brightness", max_brightness),
+            ("Trigger", trigger),
+            ("Available triggers", available_triggers),
+            ("Device", device),
+            (
+                "Driver",
+                led.driver.clone().unwrap_or_else(|| "unknown".to_string()),
+            ),
+        ];
+        if led.is_backlight() {
+            // Backlights have no triggers, but report what the hardware is actually set to
+            // and how they're controlled (`raw`, `platform` or `firmware`)
+            let attribute = |name: &str| {
+                fs::read_to_string(led_path.join(name))
+                    .map_or_else(|e| unreadable(&e), |value| value.trim().to_string())
+            };
+            fields.splice(
+                4..6,
+                [
+                    ("Actual brightness", attribute("actual_brightness")),
+                    ("Type", attribute("type")),
+                ],
+            );
+        }
+        if let LedKind::Multicolor { channels } = &led.kind {
+            let intensities = led.intensities().map_or_else(
+                |e| unreadable(&e),
+                |intensities| {
+                    channels
+                        .iter()
+                        .zip(intensities)
+                        .map(|(channel, intensity)| format!("{} {}", channel, intensity))
+                        .collect::<Vec<_>>()
+                        .join("  ")
+                },
+            );
+            fields.insert(4, ("Channels", intensities));
+        }
+        if let Some(alias) = alias {
+            fields.insert(0, ("Alias", alias.to_string()));
+        }
+        self.fields = fields;
+    }
+
+    /// Reads the trigger of the LED again, and forgets the details if it changed so that the
+    /// next [`LedDetailState::refresh`] reads them again.
+    ///
+    /// Returns whether the trigger changed.
+    pub fn check_trigger(&mut self, led: &LED) -> bool {
+        let is_shown = self
+            .source
+            .as_ref()
+            .is_some_and(|(file_name, _)| *file_name == led.file_name);
+        if !is_shown || led.triggers().ok().flatten() == self.triggers {
+            return false;
+        }
+        self.source = None;
+        true
+    }
+}
+
+/// The details of one LED: a gauge of its brightness, then its name, path, brightness, trigger,
+/// device and driver.
+#[derive(Default)]
+pub struct LedDetailWidget<'a> {
+    block: Option<Block<'a>>,
+    scroll: usize,
+}
+
+impl<'a> LedDetailWidget<'a> {
+    /// Scrolls the first `scroll` fields out of view.
+    pub fn scroll(mut self, scroll: usize) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    /// Draws the details inside a block, e.g. with a border and title.
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl StatefulWidget for LedDetailWidget<'_> {
+    type State = LedDetailState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let lines: Vec<Line> = if state.fields.is_empty() {
+            vec![Line::styled("No LED selected", theme::style(Role::Dim))]
+        } else {
+            state
+                .fields
+                .iter()
+                .map(|(label, value)| {
+                    Line::from(vec![
+                        Span::styled(format!("{}: ", label), theme::style(Role::Heading)),
+                        value.into(),
+                    ])
+                })
+                .collect()
+        };
+        let mut area = area;
+        if let Some(block) = self.block {
+            let inner = block.inner(area);
+            block.render(area, buf);
+            area = inner;
+        }
+        // The gauge stays put while the fields scroll, with a blank line between them
+        if let Some((brightness, max_brightness)) = state.gauge
+            && !state.fields.is_empty()
+            && area.height > 2
+        {
+            let [gauge_area, _, fields_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Fill(1),
+            ])
+            .areas(area);
+            LineGauge::default()
+                .ratio((f64::from(brightness) / f64::from(max_brightness)).min(1.0))
+                .label(format!(
+                    "{} ",
+                    format_brightness(brightness, max_brightness)
+                ))
+                .filled_style(theme::style(Role::Focus))
+                .unfilled_style(theme::style(Role::Dim))
+                .render(gauge_area, buf);
+            area = fields_area;
+        }
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll.try_into().unwrap_or(u16::MAX), 0))
+            .render(area, buf);
+    }
+}
+
+/// A rectangle of the given size (in percent of the width and in rows) centred in `area`.
+pub fn centered_rect(area: Rect, percent_x: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Percentage(percent_x)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}